@@ -0,0 +1,449 @@
+//! Autonomous route registry and runner
+//!
+//! Routes are registered once with their metadata and starting pose, and the
+//! [`AutonRegistry`] takes care of the rest: a selector (on the screen, the
+//! controller, or anywhere else) picks a route with
+//! [`select`](AutonRegistry::select) or [`next`](AutonRegistry::next), and
+//! [`run`](AutonRegistry::run) sets up the tracking subsystem and runs it.
+//!
+//! ```ignore
+//! let autons = AutonRegistry::new().with_route(
+//!     Route::new("ring rush", Point2::new(-1500.0, -600.0), Angle::ZERO, |ctx| {
+//!         Box::pin(async move {
+//!             ctx.step("grab goal", drivetrain.action(forward(600.0))).await;
+//!             ctx.step("score", intake.run_for(Duration::from_secs(1))).await;
+//!         })
+//!     })
+//!     .with_alliance(Alliance::Red)
+//!     .with_expected_duration(Duration::from_secs(14)),
+//! );
+//! ```
+
+use core::{cell::RefCell, future::Future, pin::Pin, time::Duration};
+use std::time::Instant;
+
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+use nalgebra::Point2;
+use vexide::math::Angle;
+
+use crate::{
+    path_planner::Path,
+    subsystems::{drivetrain::ActionCanceller, tracking::TrackingSubsystem},
+    utils::alliance::AllianceContext,
+};
+
+pub mod dsl;
+pub mod skills;
+
+/// The alliance a route is written for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Alliance {
+    Red,
+    Blue,
+    /// The route works for either alliance.
+    #[default]
+    Any,
+}
+
+/// The side of the field a route starts on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Side {
+    Left,
+    Right,
+    /// The route works from either side.
+    #[default]
+    Any,
+}
+
+/// The length of the autonomous period of a match.
+pub const AUTON_BUDGET: Duration = Duration::from_secs(15);
+
+/// The length of a skills run.
+pub const SKILLS_BUDGET: Duration = Duration::from_secs(60);
+
+/// How much a [budgeted step](RouteContext::budgeted_step) matters to a
+/// route, which decides what happens to it when the route is short on time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepPriority {
+    /// The step always runs to the end, even over budget, e.g., scoring a
+    /// preload.
+    Critical,
+    /// The step is cut off when the budget runs out, and skipped if it
+    /// already has.
+    Normal,
+    /// Like [`Normal`](Self::Normal), but the step is skipped unless at
+    /// least this much of the budget is left, e.g., a last grab for a ring
+    /// which can't be scored in less time.
+    Optional(Duration),
+}
+
+/// The future returned by a route.
+pub type RouteFuture = Pin<Box<dyn Future<Output = ()>>>;
+
+/// Passed to a running route to time its steps.
+#[derive(Debug, Clone)]
+pub struct RouteContext {
+    name: &'static str,
+    start: Instant,
+    budget: Option<Duration>,
+    canceller: Option<ActionCanceller>,
+}
+
+impl RouteContext {
+    /// Runs a step of the route, logging how long it took.
+    pub async fn step<T>(&self, name: &str, step: impl Future<Output = T>) -> T {
+        let step_start = Instant::now();
+        let output = step.await;
+        log::info!(
+            "{}: step {:?} took {} ms (at {:.2}s)",
+            self.name,
+            name,
+            step_start.elapsed().as_millis(),
+            self.start.elapsed().as_secs_f64()
+        );
+        output
+    }
+
+    /// Runs a step of the route within the route's time budget, set with
+    /// [`Route::with_budget`], logging how long it took.
+    ///
+    /// Depending on `priority`, the step may be skipped or cut off when the
+    /// budget is running out, returning `None`. A step which is cut off is
+    /// dropped, and the running drivetrain action is cancelled if the
+    /// registry has a [canceller](AutonRegistry::with_canceller), so the
+    /// robot doesn't keep driving into the next step. Without a budget, this
+    /// is the same as [`step`](Self::step), which always runs steps to the
+    /// end.
+    pub async fn budgeted_step<T>(
+        &self,
+        name: &str,
+        priority: StepPriority,
+        step: impl Future<Output = T>,
+    ) -> Option<T> {
+        let Some(remaining) = self.remaining() else {
+            return Some(self.step(name, step).await);
+        };
+        let skip = match priority {
+            StepPriority::Critical => return Some(self.step(name, step).await),
+            StepPriority::Normal => remaining.is_zero(),
+            StepPriority::Optional(needs) => remaining < needs,
+        };
+        if skip {
+            log::warn!(
+                "{}: skipping step {:?} with {:.2}s of the budget left",
+                self.name,
+                name,
+                remaining.as_secs_f64()
+            );
+            return None;
+        }
+        let output = self
+            .step(name, crate::utils::sync::timeout(remaining, step))
+            .await;
+        if output.is_none() {
+            log::warn!(
+                "{}: step {:?} cut off at the end of the budget",
+                self.name,
+                name
+            );
+            if let Some(canceller) = &self.canceller {
+                canceller.cancel();
+            }
+        }
+        output
+    }
+
+    /// Returns how much of the route's time budget is left, or `None` if it
+    /// has no budget.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.budget
+            .map(|budget| budget.saturating_sub(self.start.elapsed()))
+    }
+
+    /// Returns how long the route has been running.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Returns the name of the running route.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// An autonomous route and its metadata.
+#[derive(Clone)]
+pub struct Route {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub alliance: Alliance,
+    pub side: Side,
+    /// How long the route is expected to take, used to warn about overruns
+    pub expected_duration: Option<Duration>,
+    /// How long the route may take, used to cut short
+    /// [budgeted steps](RouteContext::budgeted_step)
+    pub budget: Option<Duration>,
+    /// The starting position in mm, in the original coordinate system
+    pub initial_offset: Point2<f64>,
+    /// The starting heading, in the original coordinate system
+    pub initial_heading: Angle,
+    /// Whether the route runs mirrored; see
+    /// [`TrackingSubsystem::set_reverse`]
+    pub reverse: bool,
+    run: Rc<dyn Fn(RouteContext) -> RouteFuture>,
+    preview: Vec<(Rc<dyn Path>, Duration)>,
+}
+
+impl Route {
+    /// Creates a new route starting at the given pose.
+    pub fn new(
+        name: &'static str,
+        initial_offset: Point2<f64>,
+        initial_heading: Angle,
+        run: impl Fn(RouteContext) -> RouteFuture + 'static,
+    ) -> Self {
+        Self {
+            name,
+            description: "",
+            alliance: Alliance::Any,
+            side: Side::Any,
+            expected_duration: None,
+            budget: None,
+            initial_offset,
+            initial_heading,
+            reverse: false,
+            run: Rc::new(run),
+            preview: Vec::new(),
+        }
+    }
+
+    pub fn with_description(mut self, description: &'static str) -> Self {
+        self.description = description;
+        self
+    }
+
+    pub fn with_alliance(mut self, alliance: Alliance) -> Self {
+        self.alliance = alliance;
+        self
+    }
+
+    pub fn with_side(mut self, side: Side) -> Self {
+        self.side = side;
+        self
+    }
+
+    pub fn with_expected_duration(mut self, expected_duration: Duration) -> Self {
+        self.expected_duration = Some(expected_duration);
+        self
+    }
+
+    /// Sets how long the route may take, usually [`AUTON_BUDGET`] or
+    /// [`SKILLS_BUDGET`].
+    pub fn with_budget(mut self, budget: Duration) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    pub fn with_reverse(mut self, reverse: bool) -> Self {
+        self.reverse = reverse;
+        self
+    }
+
+    /// Adds a path the route drives, taking about `duration`, to its
+    /// [preview](crate::debug_render::AutonPreview). Paths are previewed in
+    /// the order they are added, in the original coordinate system.
+    pub fn with_preview_path(mut self, path: impl Path + 'static, duration: Duration) -> Self {
+        self.preview.push((Rc::new(path), duration));
+        self
+    }
+
+    /// Returns the paths added with
+    /// [`with_preview_path`](Self::with_preview_path) and how long each
+    /// takes.
+    pub fn preview(&self) -> &[(Rc<dyn Path>, Duration)] {
+        &self.preview
+    }
+}
+
+impl core::fmt::Debug for Route {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Route")
+            .field("name", &self.name)
+            .field("description", &self.description)
+            .field("alliance", &self.alliance)
+            .field("side", &self.side)
+            .field("expected_duration", &self.expected_duration)
+            .field("budget", &self.budget)
+            .field("initial_offset", &self.initial_offset)
+            .field("initial_heading", &self.initial_heading)
+            .field("reverse", &self.reverse)
+            .field("preview", &self.preview.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    routes: Vec<Route>,
+    selected: Option<usize>,
+    alliance: Option<AllianceContext>,
+    canceller: Option<ActionCanceller>,
+}
+
+impl Registry {
+    fn select_index(&mut self, index: usize) {
+        self.selected = Some(index);
+        if let Some(alliance) = &self.alliance {
+            alliance.set_mirrored(self.routes[index].reverse);
+        }
+    }
+}
+
+/// A registry of autonomous routes with a selected route.
+///
+/// Clones share the same routes and selection, so a selector can hold one
+/// clone while the competition code holds another.
+#[derive(Debug, Clone, Default)]
+pub struct AutonRegistry {
+    inner: Rc<RefCell<Registry>>,
+}
+
+impl AutonRegistry {
+    /// Creates an empty registry with no route selected.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shares the given alliance context, e.g., the one from
+    /// [`TrackingSubsystem::alliance`], and mirrors it to match the selected
+    /// route whenever the selection changes.
+    ///
+    /// This lets mirrored subsystems and the field view show the selected
+    /// route's side before the match starts.
+    pub fn with_alliance(self, alliance: AllianceContext) -> Self {
+        {
+            let mut inner = self.inner.borrow_mut();
+            inner.alliance = Some(alliance);
+            if let Some(index) = inner.selected {
+                inner.select_index(index);
+            }
+        }
+        self
+    }
+
+    /// Cancels the drivetrain's running action with `canceller`, from
+    /// [`Drivetrain::canceller`], whenever a
+    /// [budgeted step](RouteContext::budgeted_step) is cut off.
+    ///
+    /// [`Drivetrain::canceller`]: crate::subsystems::drivetrain::Drivetrain::canceller
+    pub fn with_canceller(self, canceller: ActionCanceller) -> Self {
+        self.inner.borrow_mut().canceller = Some(canceller);
+        self
+    }
+
+    /// Registers a route. The first registered route is selected by default.
+    pub fn with_route(self, route: Route) -> Self {
+        self.register(route);
+        self
+    }
+
+    /// Registers a route. The first registered route is selected by default.
+    pub fn register(&self, route: Route) {
+        let mut inner = self.inner.borrow_mut();
+        inner.routes.push(route);
+        if inner.selected.is_none() {
+            inner.select_index(0);
+        }
+    }
+
+    /// Returns every registered route.
+    pub fn routes(&self) -> Vec<Route> {
+        self.inner.borrow().routes.clone()
+    }
+
+    /// Selects the route with the given name. Returns whether it exists.
+    pub fn select(&self, name: &str) -> bool {
+        let mut inner = self.inner.borrow_mut();
+        match inner.routes.iter().position(|route| route.name == name) {
+            Some(index) => {
+                inner.select_index(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Selects the next route, wrapping around, and returns it.
+    pub fn next(&self) -> Option<Route> {
+        self.step_selection(1)
+    }
+
+    /// Selects the previous route, wrapping around, and returns it.
+    pub fn previous(&self) -> Option<Route> {
+        self.step_selection(-1)
+    }
+
+    fn step_selection(&self, step: isize) -> Option<Route> {
+        let mut inner = self.inner.borrow_mut();
+        let len = inner.routes.len() as isize;
+        if len == 0 {
+            return None;
+        }
+        let index = (inner.selected.unwrap_or(0) as isize + step).rem_euclid(len) as usize;
+        inner.select_index(index);
+        Some(inner.routes[index].clone())
+    }
+
+    /// Returns the selected route, if any.
+    pub fn selected(&self) -> Option<Route> {
+        let inner = self.inner.borrow();
+        inner.selected.map(|index| inner.routes[index].clone())
+    }
+
+    /// Runs the selected route.
+    ///
+    /// The tracking subsystem's reverse flag and pose are set from the route
+    /// before it starts, and the total time is logged when it finishes, with a
+    /// warning if it took longer than expected. Does nothing but log an error
+    /// if no route is selected.
+    pub async fn run(&self, tracking: &mut TrackingSubsystem) {
+        let Some(route) = self.selected() else {
+            log::error!("No autonomous route selected");
+            return;
+        };
+        log::info!(
+            "Running autonomous route {:?} ({:?}, {:?}{})",
+            route.name,
+            route.alliance,
+            route.side,
+            if route.reverse { ", reversed" } else { "" }
+        );
+        // Reverse first, so that the initial pose is in the original
+        // coordinate system
+        tracking.set_reverse(route.reverse);
+        tracking.set_current(route.initial_offset, route.initial_heading);
+
+        let context = RouteContext {
+            name: route.name,
+            start: Instant::now(),
+            budget: route.budget,
+            canceller: self.inner.borrow().canceller.clone(),
+        };
+        (route.run)(context.clone()).await;
+
+        let elapsed = context.elapsed();
+        match route.expected_duration {
+            Some(expected) if elapsed > expected => log::warn!(
+                "Route {:?} took {:.2}s, over the expected {:.2}s",
+                route.name,
+                elapsed.as_secs_f64(),
+                expected.as_secs_f64()
+            ),
+            _ => log::info!(
+                "Route {:?} finished in {:.2}s",
+                route.name,
+                elapsed.as_secs_f64()
+            ),
+        }
+    }
+}
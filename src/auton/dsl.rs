@@ -0,0 +1,220 @@
+//! A declarative syntax for the steps of a route
+//!
+//! Most routes are a list of drivetrain actions and subsystem commands, each
+//! awaited in turn. [`route!`](crate::route) writes them out with the
+//! drivetrain and action config given once, and runs each as a
+//! [step](super::RouteContext::step), so every step is logged and timed the
+//! same way:
+//!
+//! ```ignore
+//! Route::new("ring rush", Point2::new(-1500.0, -600.0), Angle::ZERO, move |ctx| {
+//!     let drivetrain = drivetrain.clone();
+//!     let intake = intake.clone();
+//!     Box::pin(async move {
+//!         route! { ctx, drivetrain.borrow_mut(), config;
+//!             drive_to(-900.0, -600.0);
+//!             turn_to(deg 90.0);
+//!             parallel { intake.run_for(Duration::from_secs(2)), forward(300.0) };
+//!             wait(Duration::from_millis(250));
+//!             follow(to_ladder());
+//!         }
+//!     })
+//! })
+//! ```
+//!
+//! The first line gives the [`RouteContext`](super::RouteContext), an
+//! expression for the drivetrain, evaluated once per step, and the
+//! [`ActionConfig`](crate::subsystems::drivetrain::actions::config::ActionConfig).
+//! The drivetrain may be borrowed from a `RefCell`, since it is only borrowed
+//! while starting an action. Each following statement, separated by
+//! semicolons, is one of:
+//!
+//! - `set_pose(x, y, heading)` sets the tracking pose, with the heading an
+//!   [`Angle`](vexide::math::Angle), or in degrees as `deg 90.0`
+//! - `drive_to(x, y)` runs a
+//!   [`DriveToPointAction`](crate::subsystems::drivetrain::actions::DriveToPointAction)
+//! - `turn_to(heading)` runs a
+//!   [`RotationAction`](crate::subsystems::drivetrain::actions::RotationAction)
+//!   to an `Angle`, or to `deg 90.0`
+//! - `forward(distance)` runs a
+//!   [`ForwardAction`](crate::subsystems::drivetrain::actions::ForwardAction)
+//! - `follow(path)` runs a
+//!   [`PurePursuitAction`](crate::subsystems::drivetrain::actions::PurePursuitAction)
+//! - `wait(duration)` sleeps
+//! - `parallel { a, b, ... }` runs any of these, except `set_pose`, at the same
+//!   time until all of them finish
+//! - any other expression is awaited as a step of its own, e.g.,
+//!   `intake.run_for(Duration::from_secs(1))`
+
+/// Items used by the expansion of [`route!`](crate::route).
+#[doc(hidden)]
+pub mod __private {
+    use alloc::boxed::Box;
+    use core::{future::Future, pin::Pin};
+
+    pub use crate::utils::sync::join_all;
+    pub use log;
+    pub use nalgebra::Point2;
+    pub use vexide::{math::Angle, time::sleep};
+
+    /// Boxes a future for [`join_all`], discarding its output.
+    pub fn boxed<F: Future + 'static>(future: F) -> Pin<Box<dyn Future<Output = ()>>> {
+        Box::pin(async move {
+            future.await;
+        })
+    }
+}
+
+/// Runs the steps of a route. See the [module
+/// documentation](crate::auton::dsl).
+#[macro_export]
+macro_rules! route {
+    ($ctx:expr, $drivetrain:expr, $config:expr; $($steps:tt)*) => {{
+        $crate::__route_steps!(($ctx, $drivetrain, $config) $($steps)*);
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __route_steps {
+    ($env:tt) => {};
+    ($env:tt set_pose($x:expr, $y:expr, deg $heading:expr) $(; $($rest:tt)*)?) => {
+        $crate::__route_steps!(
+            $env set_pose(
+                $x,
+                $y,
+                $crate::auton::dsl::__private::Angle::from_degrees($heading)
+            );
+            $($($rest)*)?
+        );
+    };
+    (($ctx:expr, $drivetrain:expr, $config:expr) set_pose($x:expr, $y:expr, $heading:expr) $(; $($rest:tt)*)?) => {
+        {
+            let offset = $crate::auton::dsl::__private::Point2::new($x, $y);
+            let heading: $crate::auton::dsl::__private::Angle = $heading;
+            $crate::auton::dsl::__private::log::info!(
+                "{}: set pose to {:?}, {:.1}°",
+                $ctx.name(),
+                offset,
+                heading.as_degrees()
+            );
+            let mut tracking = $drivetrain.tracking().clone();
+            tracking.set_current(offset, heading);
+        }
+        $crate::__route_steps!(($ctx, $drivetrain, $config) $($($rest)*)?);
+    };
+    ($env:tt parallel { $($items:tt)* } $(; $($rest:tt)*)?) => {
+        $crate::__route_run!(
+            $env
+            concat!("parallel { ", stringify!($($items)*), " }"),
+            $crate::auton::dsl::__private::join_all(
+                $crate::__route_parallel!($env [] $($items)*)
+            )
+        );
+        $crate::__route_steps!($env $($($rest)*)?);
+    };
+    ($env:tt $command:ident($($args:tt)*) $(; $($rest:tt)*)?) => {
+        $crate::__route_run!(
+            $env
+            stringify!($command($($args)*)),
+            $crate::__route_future!($env $command($($args)*))
+        );
+        $crate::__route_steps!($env $($($rest)*)?);
+    };
+    ($env:tt $step:expr $(; $($rest:tt)*)?) => {
+        $crate::__route_run!($env stringify!($step), $step);
+        $crate::__route_steps!($env $($($rest)*)?);
+    };
+}
+
+/// Expands to an array of the boxed futures of the items of `parallel`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __route_parallel {
+    ($env:tt [$($futures:tt)*]) => {
+        [$($futures)*]
+    };
+    ($env:tt [$($futures:tt)*] $command:ident($($args:tt)*) $(, $($rest:tt)*)?) => {
+        $crate::__route_parallel!(
+            $env
+            [
+                $($futures)*
+                $crate::auton::dsl::__private::boxed(
+                    $crate::__route_future!($env $command($($args)*))
+                ),
+            ]
+            $($($rest)*)?
+        )
+    };
+    ($env:tt [$($futures:tt)*] $item:expr $(, $($rest:tt)*)?) => {
+        $crate::__route_parallel!(
+            $env
+            [$($futures)* $crate::auton::dsl::__private::boxed($item),]
+            $($($rest)*)?
+        )
+    };
+}
+
+/// Expands to the future of a step, starting its action if it has one.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __route_future {
+    (($ctx:expr, $drivetrain:expr, $config:expr) drive_to($x:expr, $y:expr $(,)?)) => {{
+        // Start the action in its own statement, so the drivetrain isn't
+        // borrowed while it runs
+        let future = $drivetrain.action(
+            $crate::subsystems::drivetrain::actions::DriveToPointAction::new(
+                $crate::auton::dsl::__private::Point2::new($x, $y),
+                $config,
+            ),
+        );
+        future
+    }};
+    (($ctx:expr, $drivetrain:expr, $config:expr) turn_to(deg $heading:expr)) => {{
+        let future = $drivetrain.action(
+            $crate::subsystems::drivetrain::actions::RotationAction::new(
+                $crate::auton::dsl::__private::Angle::from_degrees($heading).as_radians(),
+                $config,
+            ),
+        );
+        future
+    }};
+    (($ctx:expr, $drivetrain:expr, $config:expr) turn_to($heading:expr)) => {{
+        let heading: $crate::auton::dsl::__private::Angle = $heading;
+        let future = $drivetrain.action(
+            $crate::subsystems::drivetrain::actions::RotationAction::new(
+                heading.as_radians(),
+                $config,
+            ),
+        );
+        future
+    }};
+    (($ctx:expr, $drivetrain:expr, $config:expr) forward($distance:expr)) => {{
+        let future = $drivetrain.action(
+            $crate::subsystems::drivetrain::actions::ForwardAction::new($distance, $config),
+        );
+        future
+    }};
+    (($ctx:expr, $drivetrain:expr, $config:expr) follow($path:expr)) => {{
+        let future = $drivetrain.action(
+            $crate::subsystems::drivetrain::actions::PurePursuitAction::new($path, None, $config),
+        );
+        future
+    }};
+    ($env:tt wait($duration:expr)) => {
+        $crate::auton::dsl::__private::sleep($duration)
+    };
+    ($env:tt $step:expr) => {
+        $step
+    };
+}
+
+/// Runs a future as a step of the route.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __route_run {
+    (($ctx:expr, $drivetrain:expr, $config:expr) $name:expr, $future:expr) => {{
+        let future = $future;
+        $ctx.step($name, future).await;
+    }};
+}
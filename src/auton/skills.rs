@@ -0,0 +1,255 @@
+//! Skills runs split into checked phases
+//!
+//! A skills run is long enough for odometry to drift and for one missed
+//! element to cost the rest of the run. A [`SkillsRun`] chains [`Phase`]s,
+//! and after each one compares the tracked pose against where the phase
+//! should have ended. If they are too far apart, the reset of the phase (or
+//! of the run), e.g., a wall or GPS correction, is applied before the next
+//! phase starts. Phases which wouldn't fit in the time left are replaced by
+//! their substitute, or skipped:
+//!
+//! ```ignore
+//! let skills = SkillsRun::new(tracking.clone())
+//!     .with_reset(move |tracking| walls.correct_pose(tracking, FieldWall::LEFT).is_some())
+//!     .with_phase(
+//!         Phase::new("first goal", |ctx| Box::pin(async move { /* ... */ }))
+//!             .with_checkpoint(Point2::new(-1200.0, 600.0), Angle::QUARTER_TURN)
+//!             .with_expected_duration(Duration::from_secs(12)),
+//!     )
+//!     .with_phase(
+//!         Phase::new("hang", |ctx| Box::pin(async move { /* ... */ }))
+//!             .with_expected_duration(Duration::from_secs(8))
+//!             .with_substitute(Phase::new("park", |ctx| Box::pin(async move { /* ... */ }))),
+//!     );
+//! autons.register(skills.into_route("skills", Point2::new(-1500.0, 0.0), Angle::ZERO));
+//! ```
+
+use core::time::Duration;
+
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+use nalgebra::Point2;
+use vexide::math::Angle;
+
+use crate::{subsystems::tracking::TrackingSubsystem, utils::angle};
+
+use super::{Route, RouteContext, RouteFuture, SKILLS_BUDGET};
+
+/// A reset of the tracking pose from an absolute reference. Returns whether
+/// the pose was reset.
+type Reset = Rc<dyn Fn(&mut TrackingSubsystem) -> bool>;
+
+/// One phase of a [`SkillsRun`].
+#[derive(Clone)]
+pub struct Phase {
+    pub name: &'static str,
+    /// Where the phase should end, as a position in mm and a heading
+    pub checkpoint: Option<(Point2<f64>, Angle)>,
+    /// How long the phase is expected to take, used to decide whether it
+    /// fits in the time left
+    pub expected_duration: Option<Duration>,
+    run: Rc<dyn Fn(RouteContext) -> RouteFuture>,
+    reset: Option<Reset>,
+    substitute: Option<Box<Phase>>,
+}
+
+impl Phase {
+    pub fn new(name: &'static str, run: impl Fn(RouteContext) -> RouteFuture + 'static) -> Self {
+        Self {
+            name,
+            checkpoint: None,
+            expected_duration: None,
+            run: Rc::new(run),
+            reset: None,
+            substitute: None,
+        }
+    }
+
+    /// Checks the tracked pose against `offset` and `heading` after the
+    /// phase.
+    pub fn with_checkpoint(mut self, offset: Point2<f64>, heading: Angle) -> Self {
+        self.checkpoint = Some((offset, heading));
+        self
+    }
+
+    pub fn with_expected_duration(mut self, expected_duration: Duration) -> Self {
+        self.expected_duration = Some(expected_duration);
+        self
+    }
+
+    /// Resets the pose with `reset` instead of the run's reset if it drifted
+    /// from the checkpoint, e.g., against the wall the phase ends at.
+    pub fn with_reset(mut self, reset: impl Fn(&mut TrackingSubsystem) -> bool + 'static) -> Self {
+        self.reset = Some(Rc::new(reset));
+        self
+    }
+
+    /// Runs `substitute` instead of this phase if this one wouldn't fit in
+    /// the time left, e.g., a park instead of a hang.
+    pub fn with_substitute(mut self, substitute: Phase) -> Self {
+        self.substitute = Some(Box::new(substitute));
+        self
+    }
+
+    /// Returns whether the phase fits in `remaining`. Phases without an
+    /// expected duration always fit.
+    fn fits(&self, remaining: Duration) -> bool {
+        self.expected_duration
+            .is_none_or(|expected| expected <= remaining)
+    }
+}
+
+impl core::fmt::Debug for Phase {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Phase")
+            .field("name", &self.name)
+            .field("checkpoint", &self.checkpoint)
+            .field("expected_duration", &self.expected_duration)
+            .field("substitute", &self.substitute)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Runs the phases of a skills run in order, checking the pose between them.
+/// See the [module documentation](self).
+#[derive(Clone)]
+pub struct SkillsRun {
+    tracking: TrackingSubsystem,
+    phases: Vec<Phase>,
+    reset: Option<Reset>,
+    max_drift: f64,
+    max_heading_drift: Angle,
+    time_limit: Duration,
+}
+
+impl SkillsRun {
+    /// Creates an empty run with a 60 s time limit, which resets the pose
+    /// when it drifts more than 50 mm or 5° from a checkpoint.
+    pub fn new(tracking: TrackingSubsystem) -> Self {
+        Self {
+            tracking,
+            phases: Vec::new(),
+            reset: None,
+            max_drift: 50.0,
+            max_heading_drift: Angle::from_degrees(5.0),
+            time_limit: SKILLS_BUDGET,
+        }
+    }
+
+    pub fn with_phase(mut self, phase: Phase) -> Self {
+        self.phases.push(phase);
+        self
+    }
+
+    /// Resets the pose with `reset` after phases which drifted from their
+    /// checkpoint and have no reset of their own.
+    pub fn with_reset(mut self, reset: impl Fn(&mut TrackingSubsystem) -> bool + 'static) -> Self {
+        self.reset = Some(Rc::new(reset));
+        self
+    }
+
+    /// Sets how far, in mm, and how much the heading may drift from a
+    /// checkpoint before the pose is reset.
+    pub fn with_max_drift(mut self, max_drift: f64, max_heading_drift: Angle) -> Self {
+        self.max_drift = max_drift;
+        self.max_heading_drift = max_heading_drift;
+        self
+    }
+
+    pub fn with_time_limit(mut self, time_limit: Duration) -> Self {
+        self.time_limit = time_limit;
+        self
+    }
+
+    /// Wraps the run in a [`Route`] starting at the given pose, to register
+    /// it with an [`AutonRegistry`](super::AutonRegistry).
+    pub fn into_route(
+        self,
+        name: &'static str,
+        initial_offset: Point2<f64>,
+        initial_heading: Angle,
+    ) -> Route {
+        let time_limit = self.time_limit;
+        let run = Rc::new(self);
+        Route::new(name, initial_offset, initial_heading, move |context| {
+            let run = run.clone();
+            Box::pin(async move { run.run(context).await })
+        })
+        .with_expected_duration(time_limit)
+        .with_budget(time_limit)
+    }
+
+    /// Runs the phases in order.
+    pub async fn run(&self, context: RouteContext) {
+        for phase in &self.phases {
+            let remaining = self.time_limit.saturating_sub(context.elapsed());
+            let phase = if phase.fits(remaining) {
+                phase
+            } else if let Some(substitute) = phase
+                .substitute
+                .as_deref()
+                .filter(|substitute| substitute.fits(remaining))
+            {
+                log::warn!(
+                    "{}: {:.2}s left, substituting {:?} for {:?}",
+                    context.name(),
+                    remaining.as_secs_f64(),
+                    substitute.name,
+                    phase.name
+                );
+                substitute
+            } else {
+                log::warn!(
+                    "{}: {:.2}s left, skipping {:?}",
+                    context.name(),
+                    remaining.as_secs_f64(),
+                    phase.name
+                );
+                continue;
+            };
+
+            context.step(phase.name, (phase.run)(context.clone())).await;
+            self.check(phase);
+        }
+    }
+
+    /// Compares the pose against the checkpoint of `phase`, and resets it if
+    /// it drifted too far.
+    fn check(&self, phase: &Phase) {
+        let Some((offset, heading)) = phase.checkpoint else {
+            return;
+        };
+        let current = self.tracking.current();
+        let drift = nalgebra::distance(&current.offset, &offset);
+        let heading_drift = angle::shortest_error(heading, current.heading);
+        log::info!(
+            "Checkpoint {:?}: {:.0} mm and {:.1}° off",
+            phase.name,
+            drift,
+            heading_drift.as_degrees()
+        );
+        if drift <= self.max_drift
+            && heading_drift.as_radians().abs() <= self.max_heading_drift.as_radians()
+        {
+            return;
+        }
+        match phase.reset.as_ref().or(self.reset.as_ref()) {
+            Some(reset) => {
+                if !reset(&mut self.tracking.clone()) {
+                    log::warn!("Checkpoint {:?}: drifted, but the reset failed", phase.name);
+                }
+            }
+            None => log::warn!("Checkpoint {:?}: drifted, with no reset", phase.name),
+        }
+    }
+}
+
+impl core::fmt::Debug for SkillsRun {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SkillsRun")
+            .field("phases", &self.phases)
+            .field("max_drift", &self.max_drift)
+            .field("max_heading_drift", &self.max_heading_drift)
+            .field("time_limit", &self.time_limit)
+            .finish_non_exhaustive()
+    }
+}
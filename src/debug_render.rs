@@ -1,36 +1,118 @@
 use alloc::boxed::Box;
 use alloc::format;
+use alloc::string::String;
 use alloc::vec::Vec;
-use embedded_graphics::image::Image;
-use embedded_graphics::mono_font::MonoTextStyleBuilder;
+use core::time::Duration;
+use std::time::Instant;
+
+use embedded_graphics::mono_font::iso_8859_1::FONT_6X10;
+use embedded_graphics::mono_font::{MonoTextStyle, MonoTextStyleBuilder};
 use embedded_graphics::pixelcolor::Rgb888;
 use embedded_graphics::prelude::*;
-use embedded_graphics::primitives::{Circle, Line, PrimitiveStyleBuilder, StyledDrawable};
+use embedded_graphics::primitives::{
+    Circle, Line, PrimitiveStyleBuilder, Rectangle, StyledDrawable,
+};
 use embedded_graphics::text::{Text, TextStyleBuilder};
 use nalgebra::{Point2, Vector2};
-use vexide::devices::display::{self};
+use vexide::display;
 
 use crate::path_planner::Path;
 
-const FIELD_SIZE: f64 = 240.0;
-const FIELD_ORIGIN: Point2<f64> = Point2::new(FIELD_SIZE / 2.0, FIELD_SIZE / 2.0);
-/// Field scale multiplier to convert from mm to pixels
-const FIELD_SCALE: f64 = FIELD_SIZE / (600.0 * 6.0);
+mod checklist;
+pub mod comparison;
+mod console;
+mod driver;
+pub mod field;
+pub mod graph;
+mod heatmap;
+pub mod pages;
+mod preview;
+mod touch;
+
+pub use checklist::ChecklistPage;
+pub use comparison::RouteComparison;
+pub use console::LogConsole;
+pub use driver::DisplayDriver;
+pub use field::{FieldConfig, FieldRotation};
+pub use graph::Graph;
+pub use heatmap::Heatmap;
+pub use pages::{Page, PagedDisplay, TextPage};
+pub use preview::AutonPreview;
+pub use touch::FieldTouchMode;
+
+/// Y coordinate of the top of the graph area in the side panel
+const GRAPH_TOP: f64 = 72.0;
+/// Spacing around graphs in the side panel, in pixels
+const GRAPH_MARGIN: u32 = 4;
 
+/// A point drawn on the field, e.g., the pure pursuit lookahead point or a
+/// detected game element.
 pub struct DebugRenderMark {
-    pub point: Point2<i32>,
+    /// The position of the mark on the field in mm
+    pub point: Point2<f64>,
     pub color: Rgb888,
     size: u32,
+    /// Text drawn next to the mark
+    pub label: Option<String>,
+    /// How long the mark is shown for before it is removed, or `None` to show
+    /// it until it is removed manually
+    pub ttl: Option<Duration>,
+    /// Marks on higher layers are drawn on top of marks on lower layers
+    pub layer: i32,
+    /// When the mark was first rendered, used for the TTL
+    shown_at: Option<Instant>,
 }
 
 impl Default for DebugRenderMark {
     fn default() -> Self {
         Self {
-            point: Point2::new(0, 0),
+            point: Point2::new(0.0, 0.0),
             color: Rgb888::new(255, 0, 0),
             size: 2,
+            label: None,
+            ttl: None,
+            layer: 0,
+            shown_at: None,
+        }
+    }
+}
+
+impl DebugRenderMark {
+    /// Creates a new mark at the given point on the field.
+    pub fn new(point: Point2<f64>) -> Self {
+        Self {
+            point,
+            ..Default::default()
         }
     }
+
+    pub fn with_color(mut self, color: Rgb888) -> Self {
+        self.color = color;
+        self
+    }
+    pub fn with_size(mut self, size: u32) -> Self {
+        self.size = size;
+        self
+    }
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+    pub fn with_layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    /// Returns whether the mark's TTL has elapsed.
+    fn expired(&self) -> bool {
+        self.ttl
+            .zip(self.shown_at)
+            .is_some_and(|(ttl, shown_at)| shown_at.elapsed() >= ttl)
+    }
 }
 
 trait Point2Ext {
@@ -42,83 +124,327 @@ impl Point2Ext for Point2<f64> {
         Point::new(self.x as i32, self.y as i32)
     }
 }
-impl Point2Ext for Point2<i32> {
-    fn to_point(self) -> Point {
-        Point::new(self.x, self.y)
-    }
-}
 
 pub struct DebugRender {
-    display: vexide_embedded_graphics::DisplayDriver,
-    field_bmp:
-        tinybmp::Bmp<'static, <vexide_embedded_graphics::DisplayDriver as DrawTarget>::Color>,
+    /// The display, or `None` if the renderer is shown as a page of a
+    /// [`PagedDisplay`]
+    display: Option<DisplayDriver>,
+    field: FieldConfig,
+    field_bmp: Option<tinybmp::Bmp<'static, <DisplayDriver as DrawTarget>::Color>>,
+    paths: Vec<Box<dyn Path>>,
+    comparisons: Vec<RouteComparison>,
+    selected_comparison: Option<usize>,
+    touch_mode: FieldTouchMode,
+    /// The press count of the touchscreen when it was last checked
+    last_press_count: Option<i32>,
+    /// Whether the background layer was drawn without graphs
+    background_graphs_empty: bool,
+    /// Whether the background layer must be redrawn
+    background_stale: bool,
 
-    pub paths: Vec<Box<dyn Path>>,
     pub marks: Vec<DebugRenderMark>,
+    /// Graphs drawn stacked in the side panel, top to bottom
+    pub graphs: Vec<Graph>,
+    /// Where the robot has been, drawn over the field under the marks
+    pub heatmap: Option<Heatmap>,
 }
 
 impl DebugRender {
+    /// Creates a new renderer showing the full High Stakes field.
     pub fn new(display: display::Display) -> Self {
+        Self::with_field(display, FieldConfig::default())
+    }
+
+    /// Creates a new renderer with the given field configuration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the field image is not a valid BMP image.
+    pub fn with_field(display: display::Display, field: FieldConfig) -> Self {
         Self {
-            display: vexide_embedded_graphics::DisplayDriver::new(display),
-            field_bmp: tinybmp::Bmp::from_slice(include_bytes!("../assets/field.bmp")).unwrap(),
+            display: Some(DisplayDriver::new(display)),
+            ..Self::page(field)
+        }
+    }
 
+    /// Creates a new renderer to be shown as a page of a [`PagedDisplay`],
+    /// instead of on its own. The field should be small enough to fit above
+    /// the [tab bar](pages::TAB_BAR_HEIGHT).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the field image is not a valid BMP image.
+    pub fn page(field: FieldConfig) -> Self {
+        Self {
+            display: None,
+            field_bmp: field
+                .image
+                .map(|image| tinybmp::Bmp::from_slice(image).expect("invalid field image")),
+            field,
             paths: Vec::new(),
+            comparisons: Vec::new(),
+            selected_comparison: None,
+            touch_mode: FieldTouchMode::Off,
+            last_press_count: None,
+            background_graphs_empty: true,
+            background_stale: true,
+
             marks: Vec::new(),
+            graphs: Vec::new(),
+            heatmap: None,
+        }
+    }
+
+    /// Returns the field configuration.
+    pub fn field(&self) -> &FieldConfig {
+        &self.field
+    }
+
+    /// Returns the paths drawn on the field.
+    pub fn paths(&self) -> &[Box<dyn Path>] {
+        &self.paths
+    }
+
+    /// Adds a path to draw on the field.
+    pub fn add_path(&mut self, path: Box<dyn Path>) {
+        self.paths.push(path);
+        self.invalidate();
+    }
+
+    /// Removes all paths from the field.
+    pub fn clear_paths(&mut self) {
+        self.paths.clear();
+        self.invalidate();
+    }
+
+    /// Adds a comparison of a route's planned paths and actual trajectory,
+    /// replacing any other comparison of the same route. It is only drawn
+    /// once selected with [`select_comparison`](Self::select_comparison).
+    pub fn add_comparison(&mut self, comparison: RouteComparison) {
+        match self
+            .comparisons
+            .iter()
+            .position(|existing| existing.route == comparison.route)
+        {
+            Some(index) => {
+                self.comparisons[index] = comparison;
+                if self.selected_comparison == Some(index) {
+                    self.invalidate();
+                }
+            }
+            None => self.comparisons.push(comparison),
+        }
+    }
+
+    /// Draws the comparison of the given route on the field, instead of any
+    /// other. Returns whether there is one.
+    pub fn select_comparison(&mut self, route: &str) -> bool {
+        let index = self
+            .comparisons
+            .iter()
+            .position(|comparison| comparison.route == route);
+        if index != self.selected_comparison {
+            self.selected_comparison = index;
+            self.invalidate();
+        }
+        index.is_some()
+    }
+
+    /// Stops drawing a comparison on the field.
+    pub fn clear_comparison(&mut self) {
+        if self.selected_comparison.take().is_some() {
+            self.invalidate();
         }
     }
 
+    /// Returns the comparison drawn on the field, if any.
+    pub fn selected_comparison(&self) -> Option<&RouteComparison> {
+        self.selected_comparison
+            .map(|index| &self.comparisons[index])
+    }
+
+    /// Sets what tapping the field view does. It is checked on every call to
+    /// [`render`](Self::render).
+    pub fn set_touch_mode(&mut self, mode: FieldTouchMode) {
+        self.touch_mode = mode;
+    }
+
+    /// Adds a mark to draw on the field.
+    pub fn add_mark(&mut self, mark: DebugRenderMark) {
+        self.marks.push(mark);
+    }
+
+    /// Removes all marks on the given layer.
+    pub fn clear_layer(&mut self, layer: i32) {
+        self.marks.retain(|mark| mark.layer != layer);
+    }
+
+    /// Forces the background layer (the field image, paths, and label) to be
+    /// redrawn on the next call to [`render`](Self::render).
+    pub fn invalidate(&mut self) {
+        self.background_stale = true;
+    }
+
     /// Renders the field and overlays the paths and marks
     /// This function should be called in a loop to update the display
+    ///
+    /// The field image, paths, and label only change when paths are added or
+    /// removed, so they are drawn once into a cached background layer. Each
+    /// call only restores that layer and redraws the marks and graphs.
+    ///
+    /// Does nothing if the renderer is shown as a page of a [`PagedDisplay`],
+    /// which renders it instead.
     pub fn render(&mut self) {
-        self.display.clear(Rgb888::BLACK).unwrap();
+        let Some(mut display) = self.display.take() else {
+            return;
+        };
+        self.handle_touch(&display);
+        let bounds = display.bounding_box();
+        self.draw_frame(&mut display, bounds);
+        display.render();
+        self.display = Some(display);
+    }
 
-        let image = Image::with_center(&self.field_bmp, FIELD_ORIGIN.to_point());
-        image.draw(&mut self.display).unwrap();
+    /// Draws the field view and the side panel inside `bounds`.
+    fn draw_frame(&mut self, display: &mut DisplayDriver, bounds: Rectangle) {
+        let graphs_empty = self.graphs.is_empty();
+        if self.background_stale
+            || graphs_empty != self.background_graphs_empty
+            || !display.restore_background()
+        {
+            self.draw_background(display, bounds);
+            display.save_background();
+            self.background_graphs_empty = graphs_empty;
+            self.background_stale = false;
+        }
 
-        // Draw the paths
-        for path in &self.paths {
-            let mut last_point = path.evaluate(0.0);
-            let dt = 0.01;
-            let mut t = 0.0;
-            let style = PrimitiveStyleBuilder::new()
-                .stroke_color(Rgb888::new(255, 0, 0))
-                .stroke_width(2)
-                .build();
-            while t <= 1.0 {
-                let current_point = path.evaluate(t);
-                let line = Line::new(
-                    (FIELD_ORIGIN + (Vector2::from(last_point) * FIELD_SCALE)).to_point(),
-                    (FIELD_ORIGIN + (Vector2::from(current_point) * FIELD_SCALE)).to_point(),
-                );
-                line.draw_styled(&style, &mut self.display).unwrap();
-                last_point = current_point;
-                t += dt;
-            }
+        if let Some(heatmap) = &self.heatmap {
+            heatmap.draw(display, &self.field).unwrap();
         }
 
-        // Draw the marks
+        // Drop expired marks, starting the TTL of newly added ones
+        let now = Instant::now();
+        for mark in &mut self.marks {
+            mark.shown_at.get_or_insert(now);
+        }
+        self.marks.retain(|mark| !mark.expired());
+        // Draw the marks from the bottom layer up. The sort is stable, so marks
+        // on the same layer keep the order they were added in.
+        self.marks.sort_by_key(|mark| mark.layer);
         for mark in &self.marks {
-            let circle = Circle::with_center(
-                Point {
-                    x: mark.point.x,
-                    y: mark.point.y,
-                },
-                mark.size,
-            )
-            .into_styled(PrimitiveStyleBuilder::new().fill_color(mark.color).build());
-            circle.draw(&mut self.display).unwrap();
+            let center = self.field.to_screen(mark.point);
+            let circle = Circle::with_center(center, mark.size)
+                .into_styled(PrimitiveStyleBuilder::new().fill_color(mark.color).build());
+            circle.draw(display).unwrap();
+            if let Some(label) = &mark.label {
+                Text::with_baseline(
+                    label,
+                    center + Point::new(mark.size as i32 / 2 + 2, 0),
+                    MonoTextStyle::new(&FONT_6X10, mark.color),
+                    embedded_graphics::text::Baseline::Middle,
+                )
+                .draw(display)
+                .unwrap();
+            }
+        }
+
+        // Draw the graphs, splitting the space under the label evenly. If the
+        // field leaves no room beside it, or there are too many graphs to fit
+        // under the label, they are skipped.
+        if !self.graphs.is_empty() {
+            let field_size = self.field.size;
+            let panel_width = bounds
+                .size
+                .width
+                .saturating_sub(field_size as u32 + 2 * GRAPH_MARGIN);
+            let slot_height =
+                bounds.size.height.saturating_sub(GRAPH_TOP as u32) / self.graphs.len() as u32;
+            let graph_height = slot_height.saturating_sub(GRAPH_MARGIN);
+            if panel_width > 0 && graph_height > 0 {
+                for (i, graph) in self.graphs.iter().enumerate() {
+                    let bounds = Rectangle::new(
+                        Point::new(
+                            field_size as i32 + GRAPH_MARGIN as i32,
+                            GRAPH_TOP as i32 + (i as u32 * slot_height) as i32,
+                        ),
+                        Size::new(panel_width, graph_height),
+                    );
+                    graph.draw(display, bounds).unwrap();
+                }
+            }
+        }
+    }
+
+    /// Passes a new tap on the field view, if any, to the touch mode.
+    fn handle_touch(&mut self, display: &DisplayDriver) {
+        let touch = display.touch_status();
+        let is_new = self
+            .last_press_count
+            .replace(touch.press_count)
+            .is_some_and(|last| last != touch.press_count);
+        if is_new {
+            self.tap(Point::new(touch.point.x as i32, touch.point.y as i32));
         }
+    }
 
-        let text = format!(
-            "libdoxa v{}\ndebug renderer\nhigh stakes 2024-25",
-            env!("CARGO_PKG_VERSION")
+    /// Passes a tap at `point` on the screen to the touch mode if it is on
+    /// the field view, and marks where it was.
+    fn tap(&mut self, point: Point) {
+        if matches!(self.touch_mode, FieldTouchMode::Off) {
+            return;
+        }
+        let Some(point) = self.field.from_screen(point) else {
+            return;
+        };
+        self.touch_mode.tap(point);
+        self.add_mark(
+            DebugRenderMark::new(point)
+                .with_color(Rgb888::new(255, 255, 0))
+                .with_size(6)
+                .with_ttl(Duration::from_secs(1)),
         );
+    }
+
+    /// Draws the static parts of the display: the field image, the paths, and
+    /// the label.
+    fn draw_background(&mut self, display: &mut DisplayDriver, bounds: Rectangle) {
+        display.fill_solid(&bounds, Rgb888::BLACK).unwrap();
+
+        if let Some(bmp) = &self.field_bmp {
+            draw_field_image(display, &self.field, bmp).unwrap();
+        }
+
+        // Draw the paths
+        for path in &self.paths {
+            draw_path(display, &self.field, path.as_ref(), Rgb888::new(255, 0, 0)).unwrap();
+        }
+        if let Some(index) = self.selected_comparison {
+            self.comparisons[index].draw(display, &self.field).unwrap();
+        }
+
+        let mut text = format!(
+            "libdoxa v{}\ndebug renderer\n{}",
+            env!("CARGO_PKG_VERSION"),
+            self.field.name
+        );
+        if let Some(comparison) = self.selected_comparison() {
+            text.push_str(&format!("\n{}", comparison.route));
+            if let Some((_, deviation)) = comparison.max_deviation() {
+                text.push_str(&format!("\nmax {:.0} mm", deviation));
+            }
+        }
+        let field_size = self.field.size;
+        let display_size = bounds.size;
         let label_text = Text::with_text_style(
             &text,
             Point2::new(
-                FIELD_SIZE + (self.display.bounding_box().size.width as f64 - FIELD_SIZE) / 2.0,
-                self.display.bounding_box().size.height as f64 / 2.0 - 18.0,
+                field_size + (display_size.width as f64 - field_size) / 2.0,
+                if self.graphs.is_empty() {
+                    display_size.height as f64 / 2.0 - 18.0
+                } else {
+                    // Move the label to the top of the panel to make room
+                    GRAPH_TOP / 2.0 - 18.0
+                },
             )
             .to_point(),
             MonoTextStyleBuilder::new()
@@ -132,8 +458,57 @@ impl DebugRender {
                 .build(),
         );
 
-        label_text.draw(&mut self.display).unwrap();
+        label_text.draw(display).unwrap();
+    }
+}
+
+impl Page for DebugRender {
+    fn title(&self) -> &'static str {
+        "Field"
+    }
+
+    fn draw(&mut self, display: &mut DisplayDriver, bounds: Rectangle) {
+        self.draw_frame(display, bounds);
+    }
+
+    fn on_tap(&mut self, point: Point) {
+        self.tap(point);
+    }
+}
+
+/// Draws the field image `bmp` on the field view.
+fn draw_field_image<D: DrawTarget<Color = Rgb888>>(
+    target: &mut D,
+    field: &FieldConfig,
+    bmp: &tinybmp::Bmp<'static, Rgb888>,
+) -> Result<(), D::Error> {
+    let image_size = Vector2::new(bmp.size().width as f64, bmp.size().height as f64);
+    target.draw_iter(
+        bmp.pixels()
+            .map(|Pixel(point, color)| Pixel(field.image_to_screen(point, image_size), color)),
+    )
+}
 
-        self.display.render();
+/// Draws `path` on the field view as a polyline.
+fn draw_path<D: DrawTarget<Color = Rgb888>>(
+    target: &mut D,
+    field: &FieldConfig,
+    path: &dyn Path,
+    color: Rgb888,
+) -> Result<(), D::Error> {
+    let mut last_point = path.evaluate(0.0);
+    let dt = 0.01;
+    let mut t = 0.0;
+    let style = PrimitiveStyleBuilder::new()
+        .stroke_color(color)
+        .stroke_width(2)
+        .build();
+    while t <= 1.0 {
+        let current_point = path.evaluate(t);
+        let line = Line::new(field.to_screen(last_point), field.to_screen(current_point));
+        line.draw_styled(&style, target)?;
+        last_point = current_point;
+        t += dt;
     }
+    Ok(())
 }
@@ -0,0 +1,102 @@
+use core::time::Duration;
+
+use alloc::format;
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, iso_8859_1::FONT_6X10},
+    pixelcolor::Rgb888,
+    prelude::*,
+    primitives::Rectangle,
+    text::{Baseline, Text},
+};
+
+use crate::subsystems::system_check::{CheckStatus, SystemCheck, SystemCheckReport};
+
+use super::{DisplayDriver, Page};
+
+const PASS_COLOR: Rgb888 = Rgb888::new(0, 220, 0);
+const WARN_COLOR: Rgb888 = Rgb888::new(255, 200, 0);
+const FAIL_COLOR: Rgb888 = Rgb888::new(255, 60, 60);
+
+/// A [page](Page) showing the results of a [`SystemCheck`] as a checklist.
+///
+/// The checks are run when the page is first shown, and again whenever it is
+/// tapped, but not every frame, since some checks fire pneumatics.
+#[derive(Debug)]
+pub struct ChecklistPage {
+    checks: SystemCheck,
+    report: Option<SystemCheckReport>,
+}
+
+impl ChecklistPage {
+    /// Creates a page for the checks of `checks`.
+    pub fn new(checks: SystemCheck) -> Self {
+        Self {
+            checks,
+            report: None,
+        }
+    }
+
+    /// Returns the result of the last run, if the checks have been run.
+    pub fn report(&self) -> Option<&SystemCheckReport> {
+        self.report.as_ref()
+    }
+}
+
+impl Page for ChecklistPage {
+    fn title(&self) -> &'static str {
+        "Check"
+    }
+
+    fn draw(&mut self, display: &mut DisplayDriver, bounds: Rectangle) {
+        let checks = &self.checks;
+        let report = self.report.get_or_insert_with(|| {
+            let report = checks.system_check();
+            report.log();
+            report
+        });
+
+        display.fill_solid(&bounds, Rgb888::BLACK).unwrap();
+        let (summary, color) = if report.passed() {
+            ("ALL CHECKS PASSED", PASS_COLOR)
+        } else {
+            ("CHECKS FAILED", FAIL_COLOR)
+        };
+        Text::with_baseline(
+            &format!("{} (tap to rerun)", summary),
+            bounds.top_left + Point::new(4, 4),
+            MonoTextStyle::new(&FONT_6X10, color),
+            Baseline::Top,
+        )
+        .draw(display)
+        .unwrap();
+
+        let mut position = bounds.top_left + Point::new(4, 20);
+        for (result, line) in report.results.iter().zip(report.lines()) {
+            if position.y + 10 > bounds.top_left.y + bounds.size.height as i32 {
+                break;
+            }
+            let color = match result.status {
+                CheckStatus::Pass => PASS_COLOR,
+                CheckStatus::Warn(_) => WARN_COLOR,
+                CheckStatus::Fail(_) => FAIL_COLOR,
+            };
+            Text::with_baseline(
+                &line,
+                position,
+                MonoTextStyle::new(&FONT_6X10, color),
+                Baseline::Top,
+            )
+            .draw(display)
+            .unwrap();
+            position.y += 12;
+        }
+    }
+
+    fn on_tap(&mut self, _point: Point) {
+        self.report = None;
+    }
+
+    fn frame_interval(&self) -> Duration {
+        Duration::from_millis(250)
+    }
+}
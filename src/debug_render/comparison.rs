@@ -0,0 +1,147 @@
+use alloc::{boxed::Box, vec::Vec};
+use embedded_graphics::{
+    pixelcolor::Rgb888,
+    prelude::*,
+    primitives::{Circle, Line, PrimitiveStyle, PrimitiveStyleBuilder},
+};
+use nalgebra::Point2;
+
+use crate::{path_planner::Path, subsystems::tracking::PoseTrace};
+
+use super::field::FieldConfig;
+
+/// The color of the planned paths.
+const PLANNED_COLOR: Rgb888 = Rgb888::new(80, 160, 255);
+/// The color of the actual trajectory where it is close to the plan.
+const ACTUAL_COLOR: Rgb888 = Rgb888::new(0, 220, 0);
+/// The color of the actual trajectory where it strayed from the plan.
+const DEVIATION_COLOR: Rgb888 = Rgb888::new(255, 60, 0);
+
+/// The planned paths of an autonomous route and the trajectory the robot
+/// actually drove, drawn over each other on the field by
+/// [`DebugRender`](super::DebugRender).
+///
+/// Parts of the trajectory further than the threshold from every planned
+/// path are highlighted, and the worst point is circled, so divergence is
+/// visible right after the run:
+///
+/// ```ignore
+/// let trace = PoseTrace::load("/skills.csv")?;
+/// render.add_comparison(RouteComparison::from_trace("skills", skills_paths(), &trace));
+/// render.select_comparison("skills");
+/// ```
+pub struct RouteComparison {
+    /// The name of the route, as in [`Route::name`](crate::auton::Route::name)
+    pub route: &'static str,
+    planned: Vec<Box<dyn Path>>,
+    /// The actual positions and their distance from the closest planned path
+    actual: Vec<(Point2<f64>, f64)>,
+    threshold: f64,
+}
+
+impl core::fmt::Debug for RouteComparison {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("RouteComparison")
+            .field("route", &self.route)
+            .field("planned", &self.planned.len())
+            .field("actual", &self.actual.len())
+            .field("threshold", &self.threshold)
+            .finish()
+    }
+}
+
+impl RouteComparison {
+    /// Compares the `actual` positions against the `planned` paths of
+    /// `route`, highlighting deviations of more than 50 mm.
+    ///
+    /// Each position is compared against the whole of every path, so this
+    /// should be done once after the run rather than while driving.
+    pub fn new(
+        route: &'static str,
+        planned: Vec<Box<dyn Path>>,
+        actual: impl IntoIterator<Item = Point2<f64>>,
+    ) -> Self {
+        let actual = actual
+            .into_iter()
+            .map(|point| {
+                let deviation = planned
+                    .iter()
+                    .map(|path| {
+                        nalgebra::distance(&path.evaluate(path.closest_point_global(point)), &point)
+                    })
+                    .fold(f64::INFINITY, f64::min);
+                (point, deviation)
+            })
+            .collect();
+        Self {
+            route,
+            planned,
+            actual,
+            threshold: 50.0,
+        }
+    }
+
+    /// Compares the positions recorded in `trace` against the `planned`
+    /// paths of `route`.
+    pub fn from_trace(route: &'static str, planned: Vec<Box<dyn Path>>, trace: &PoseTrace) -> Self {
+        Self::new(
+            route,
+            planned,
+            trace.samples().iter().map(|sample| sample.offset),
+        )
+    }
+
+    /// Sets the distance from the plan, in mm, past which the trajectory is
+    /// highlighted.
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Returns the largest distance from the plan, in mm, and where it was,
+    /// or `None` if there is no trajectory.
+    pub fn max_deviation(&self) -> Option<(Point2<f64>, f64)> {
+        self.actual
+            .iter()
+            .copied()
+            .filter(|(_, deviation)| deviation.is_finite())
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Draws the planned paths, then the trajectory over them.
+    pub(crate) fn draw<D: DrawTarget<Color = Rgb888>>(
+        &self,
+        target: &mut D,
+        field: &FieldConfig,
+    ) -> Result<(), D::Error> {
+        for path in &self.planned {
+            super::draw_path(target, field, path.as_ref(), PLANNED_COLOR)?;
+        }
+        for pair in self.actual.windows(2) {
+            let deviated = pair[0].1 > self.threshold || pair[1].1 > self.threshold;
+            Line::new(field.to_screen(pair[0].0), field.to_screen(pair[1].0))
+                .into_styled(PrimitiveStyle::with_stroke(
+                    if deviated {
+                        DEVIATION_COLOR
+                    } else {
+                        ACTUAL_COLOR
+                    },
+                    if deviated { 2 } else { 1 },
+                ))
+                .draw(target)?;
+        }
+        if let Some((point, deviation)) = self.max_deviation()
+            && deviation > self.threshold
+        {
+            Circle::with_center(field.to_screen(point), 9)
+                .into_styled(
+                    PrimitiveStyleBuilder::new()
+                        .stroke_color(DEVIATION_COLOR)
+                        .stroke_width(1)
+                        .build(),
+                )
+                .draw(target)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+use alloc::{string::String, vec::Vec};
+use core::time::Duration;
+
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, iso_8859_1::FONT_6X10},
+    pixelcolor::Rgb888,
+    prelude::*,
+    primitives::Rectangle,
+    text::{Baseline, Text},
+};
+use log::Level;
+
+use crate::utils::logger;
+
+use super::{DisplayDriver, Page};
+
+/// The height of a line of the console, in pixels.
+const LINE_HEIGHT: u32 = 11;
+/// The width of a character of the console, in pixels.
+const CHAR_WIDTH: u32 = 6;
+
+fn level_color(level: Level) -> Rgb888 {
+    match level {
+        Level::Error => Rgb888::new(255, 70, 70),
+        Level::Warn => Rgb888::new(255, 200, 0),
+        Level::Info => Rgb888::WHITE,
+        Level::Debug => Rgb888::new(120, 180, 255),
+        Level::Trace => Rgb888::new(140, 140, 140),
+    }
+}
+
+/// A [page](Page) mirroring the most recent log lines, colored by level, so
+/// the log can be read at the field without the SD card.
+///
+/// The console follows new lines as they are logged. Tapping the top half
+/// scrolls back through older lines, freezing the console, and tapping the
+/// bottom half scrolls forwards again until it follows the log once more.
+/// Only the lines kept by [`logger::recent_records`] can be shown.
+#[derive(Debug, Default)]
+pub struct LogConsole {
+    /// How many lines the console is scrolled back from the newest
+    scroll: usize,
+    /// The lines shown while scrolled back, so that they don't move as new
+    /// lines are logged
+    frozen: Option<Vec<(Level, String)>>,
+    /// How many lines fitted on the page when it was last drawn
+    visible: usize,
+}
+
+impl LogConsole {
+    /// Creates a new console following the log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Splits the log lines into lines that fit in `columns` characters.
+    fn wrapped(records: &[(Level, String)], columns: usize) -> Vec<(Level, String)> {
+        let columns = columns.max(1);
+        let mut lines = Vec::new();
+        for (level, line) in records {
+            let chars: Vec<char> = line.chars().collect();
+            for chunk in chars.chunks(columns) {
+                lines.push((*level, chunk.iter().collect()));
+            }
+        }
+        lines
+    }
+}
+
+impl Page for LogConsole {
+    fn title(&self) -> &'static str {
+        "Log"
+    }
+
+    fn draw(&mut self, display: &mut DisplayDriver, bounds: Rectangle) {
+        display.fill_solid(&bounds, Rgb888::BLACK).unwrap();
+
+        let columns = ((bounds.size.width - 4) / CHAR_WIDTH) as usize;
+        self.visible = (bounds.size.height / LINE_HEIGHT) as usize;
+        let records = match &self.frozen {
+            Some(records) => records.clone(),
+            None => logger::recent_records(usize::MAX),
+        };
+        let lines = Self::wrapped(&records, columns);
+        self.scroll = self.scroll.min(lines.len().saturating_sub(self.visible));
+        if self.scroll == 0 {
+            // Follow the log again once scrolled back to the newest line
+            self.frozen = None;
+        }
+        let end = lines.len() - self.scroll;
+        let start = end.saturating_sub(self.visible);
+
+        let mut position = bounds.top_left + Point::new(2, 0);
+        for (level, line) in &lines[start..end] {
+            Text::with_baseline(
+                line,
+                position,
+                MonoTextStyle::new(&FONT_6X10, level_color(*level)),
+                Baseline::Top,
+            )
+            .draw(display)
+            .unwrap();
+            position.y += LINE_HEIGHT as i32;
+        }
+
+        if self.scroll > 0 {
+            let marker = alloc::format!("-{}", self.scroll);
+            Text::with_baseline(
+                &marker,
+                Point::new(
+                    bounds.top_left.x + bounds.size.width as i32
+                        - (marker.len() as u32 * CHAR_WIDTH) as i32
+                        - 2,
+                    bounds.top_left.y,
+                ),
+                MonoTextStyle::new(&FONT_6X10, Rgb888::new(0, 220, 220)),
+                Baseline::Top,
+            )
+            .draw(display)
+            .unwrap();
+        }
+    }
+
+    fn on_tap(&mut self, point: Point) {
+        let step = (self.visible / 2).max(1);
+        // Taps are in display coordinates, and pages start at the top
+        if point.y < (self.visible as u32 * LINE_HEIGHT / 2) as i32 {
+            if self.frozen.is_none() {
+                self.frozen = Some(logger::recent_records(usize::MAX));
+            }
+            self.scroll += step;
+        } else {
+            self.scroll = self.scroll.saturating_sub(step);
+        }
+    }
+
+    fn frame_interval(&self) -> Duration {
+        Duration::from_millis(100)
+    }
+}
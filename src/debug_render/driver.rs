@@ -0,0 +1,121 @@
+use alloc::{vec, vec::Vec};
+use core::convert::Infallible;
+
+use embedded_graphics::{pixelcolor::Rgb888, prelude::*, primitives::Rectangle};
+use vexide::{
+    color::Color,
+    display::{Display, Rect, RenderMode, TouchEvent},
+};
+
+const WIDTH: usize = Display::HORIZONTAL_RESOLUTION as usize;
+const HEIGHT: usize = Display::VERTICAL_RESOLUTION as usize;
+
+/// An `embedded-graphics` draw target for the Brain display.
+///
+/// Drawing only touches an in-memory framebuffer. Nothing is shown until
+/// [`render`](Self::render) copies the framebuffer to the display, so a whole
+/// frame costs a single SDK call instead of one per primitive.
+///
+/// The framebuffer can be saved as a background layer, which is much cheaper
+/// to restore at the start of a frame than redrawing whatever is static.
+pub struct DisplayDriver {
+    display: Display,
+    buffer: Vec<Color>,
+    background: Vec<Color>,
+}
+
+impl DisplayDriver {
+    pub fn new(mut display: Display) -> Self {
+        display.set_render_mode(RenderMode::DoubleBuffered);
+        Self {
+            display,
+            buffer: vec![Color::new(0, 0, 0); WIDTH * HEIGHT],
+            background: Vec::new(),
+        }
+    }
+
+    /// Saves the current contents of the framebuffer as the background layer.
+    pub fn save_background(&mut self) {
+        self.background.clear();
+        self.background.extend_from_slice(&self.buffer);
+    }
+
+    /// Replaces the contents of the framebuffer with the background layer.
+    ///
+    /// Returns `false`, leaving the framebuffer untouched, if no background
+    /// has been saved.
+    pub fn restore_background(&mut self) -> bool {
+        if self.background.is_empty() {
+            false
+        } else {
+            self.buffer.copy_from_slice(&self.background);
+            true
+        }
+    }
+
+    /// Discards the saved background layer.
+    pub fn clear_background(&mut self) {
+        self.background.clear();
+    }
+
+    /// Copies the framebuffer to the display and flips the display's double
+    /// buffer.
+    pub fn render(&mut self) {
+        self.display.draw_buffer(
+            Rect::from_dimensions([0, 0], WIDTH as u16, HEIGHT as u16),
+            &self.buffer,
+        );
+        self.display.render();
+    }
+
+    /// Returns the last recorded state of the touchscreen.
+    pub fn touch_status(&self) -> TouchEvent {
+        self.display.touch_status()
+    }
+}
+
+impl OriginDimensions for DisplayDriver {
+    fn size(&self) -> Size {
+        Size::new(WIDTH as u32, HEIGHT as u32)
+    }
+}
+
+impl DrawTarget for DisplayDriver {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if point.x >= 0
+                && point.y >= 0
+                && (point.x as usize) < WIDTH
+                && (point.y as usize) < HEIGHT
+            {
+                self.buffer[point.y as usize * WIDTH + point.x as usize] =
+                    Color::new(color.r(), color.g(), color.b());
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+        let color = Color::new(color.r(), color.g(), color.b());
+        for y in area.rows() {
+            let row = y as usize * WIDTH;
+            for x in area.columns() {
+                self.buffer[row + x as usize] = color;
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.buffer
+            .fill(Color::new(color.r(), color.g(), color.b()));
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use embedded_graphics::prelude::Point;
+use nalgebra::{Point2, Vector2};
+
+/// The High Stakes (2024-25) field image, drawn at 240x240 pixels.
+pub const HIGH_STAKES_FIELD: &[u8] = include_bytes!("../../assets/field.bmp");
+
+/// Clockwise rotation of the field view on the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FieldRotation {
+    /// +x is right and +y is up.
+    #[default]
+    None,
+    /// +x is down and +y is right.
+    Clockwise90,
+    /// +x is left and +y is down.
+    Clockwise180,
+    /// +x is up and +y is left.
+    Clockwise270,
+}
+
+impl FieldRotation {
+    /// Rotates a vector in screen space (y down) clockwise by this rotation.
+    fn rotate(self, v: Vector2<f64>) -> Vector2<f64> {
+        match self {
+            FieldRotation::None => v,
+            FieldRotation::Clockwise90 => Vector2::new(-v.y, v.x),
+            FieldRotation::Clockwise180 => -v,
+            FieldRotation::Clockwise270 => Vector2::new(v.y, -v.x),
+        }
+    }
+
+    /// Undoes [`rotate`](Self::rotate).
+    fn unrotate(self, v: Vector2<f64>) -> Vector2<f64> {
+        match self {
+            FieldRotation::None => v,
+            FieldRotation::Clockwise90 => Vector2::new(v.y, -v.x),
+            FieldRotation::Clockwise180 => -v,
+            FieldRotation::Clockwise270 => Vector2::new(-v.y, v.x),
+        }
+    }
+}
+
+/// Configuration of the field view drawn by
+/// [`DebugRender`](super::DebugRender).
+///
+/// The field view is a `size` by `size` pixel square in the top-left corner
+/// of the display. The side panel takes up the rest of the display.
+#[derive(Debug, Clone, Copy)]
+pub struct FieldConfig {
+    /// A BMP image drawn centered in the field view, or `None` to draw no
+    /// image. The image is drawn 1:1, so it should already be `size` pixels
+    /// wide and match `scale`.
+    pub image: Option<&'static [u8]>,
+    /// The name of the field, shown in the side panel.
+    pub name: &'static str,
+    /// The width and height of the field view in pixels.
+    pub size: f64,
+    /// The scale of the field view in pixels per mm.
+    pub scale: f64,
+    /// The point on the field, in mm, drawn at the center of the field view.
+    pub center: Point2<f64>,
+    /// The rotation of the field view, including the image.
+    pub rotation: FieldRotation,
+    /// Whether the field view, including the image, is mirrored over the x
+    /// axis. This matches [`TrackingSubsystem::set_reverse`] and is useful to
+    /// show the other alliance's side.
+    ///
+    /// [`TrackingSubsystem::set_reverse`]: crate::subsystems::tracking::TrackingSubsystem::set_reverse
+    pub mirrored: bool,
+}
+
+impl Default for FieldConfig {
+    /// The full High Stakes field, with the image.
+    fn default() -> Self {
+        Self {
+            image: Some(HIGH_STAKES_FIELD),
+            name: "high stakes 2024-25",
+            size: 240.0,
+            scale: 240.0 / (600.0 * 6.0),
+            center: Point2::origin(),
+            rotation: FieldRotation::None,
+            mirrored: false,
+        }
+    }
+}
+
+impl FieldConfig {
+    /// Converts a point on the field in mm to a point on the screen.
+    pub fn to_screen(&self, point: Point2<f64>) -> Point {
+        let mut v = point - self.center;
+        if self.mirrored {
+            v.y = -v.y;
+        }
+        // The screen's y axis points down
+        let v = self.rotation.rotate(Vector2::new(v.x, -v.y) * self.scale);
+        Point::new(
+            (self.size / 2.0 + v.x) as i32,
+            (self.size / 2.0 + v.y) as i32,
+        )
+    }
+
+    /// Converts a point on the screen to a point on the field in mm, or
+    /// `None` if it is outside the field view.
+    pub fn from_screen(&self, point: Point) -> Option<Point2<f64>> {
+        if point.x < 0 || point.y < 0 || point.x as f64 >= self.size || point.y as f64 >= self.size
+        {
+            return None;
+        }
+        let v = self.rotation.unrotate(Vector2::new(
+            point.x as f64 - self.size / 2.0,
+            point.y as f64 - self.size / 2.0,
+        )) / self.scale;
+        let mut v = Vector2::new(v.x, -v.y);
+        if self.mirrored {
+            v.y = -v.y;
+        }
+        Some(self.center + v)
+    }
+
+    /// Converts a pixel of the field image, whose size is `image_size`, to a
+    /// point on the screen.
+    pub(crate) fn image_to_screen(&self, pixel: Point, image_size: Vector2<f64>) -> Point {
+        let mut v = Vector2::new(pixel.x as f64, pixel.y as f64) - image_size / 2.0;
+        if self.mirrored {
+            v.y = -v.y;
+        }
+        let v = self.rotation.rotate(v);
+        Point::new(
+            (self.size / 2.0 + v.x) as i32,
+            (self.size / 2.0 + v.y) as i32,
+        )
+    }
+
+    pub fn with_image(mut self, image: Option<&'static [u8]>) -> Self {
+        self.image = image;
+        self
+    }
+    pub fn with_name(mut self, name: &'static str) -> Self {
+        self.name = name;
+        self
+    }
+    pub fn with_size(mut self, size: f64) -> Self {
+        self.size = size;
+        self
+    }
+    pub fn with_scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+    pub fn with_center(mut self, center: Point2<f64>) -> Self {
+        self.center = center;
+        self
+    }
+    pub fn with_rotation(mut self, rotation: FieldRotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+    pub fn with_mirrored(mut self, mirrored: bool) -> Self {
+        self.mirrored = mirrored;
+        self
+    }
+}
@@ -0,0 +1,127 @@
+use core::cell::RefCell;
+
+use alloc::{collections::VecDeque, format, rc::Rc};
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, iso_8859_1::FONT_6X10},
+    pixelcolor::Rgb888,
+    prelude::*,
+    primitives::{Line, PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+};
+
+#[derive(Debug)]
+struct GraphData {
+    samples: VecDeque<f64>,
+    capacity: usize,
+}
+
+/// A small time-series graph of the most recent samples of a value.
+///
+/// Samples are kept in a ring buffer: once `capacity` samples have been
+/// pushed, each new sample replaces the oldest one. The vertical axis is
+/// scaled symmetrically around zero so that steady-state error and
+/// oscillation around the setpoint are easy to see.
+///
+/// Graphs are cheap to clone and clones share the same samples, so one clone
+/// can be given to [`DebugRender`](super::DebugRender) while another is
+/// written to by, e.g., the drivetrain.
+#[derive(Debug, Clone)]
+pub struct Graph {
+    label: &'static str,
+    color: Rgb888,
+    data: Rc<RefCell<GraphData>>,
+}
+
+impl Graph {
+    /// Creates a new, empty graph holding at most `capacity` samples.
+    pub fn new(label: &'static str, capacity: usize, color: Rgb888) -> Self {
+        assert!(capacity >= 2, "Graph must hold at least two samples");
+        Self {
+            label,
+            color,
+            data: Rc::new(RefCell::new(GraphData {
+                samples: VecDeque::with_capacity(capacity),
+                capacity,
+            })),
+        }
+    }
+
+    /// Adds a sample to the graph, discarding the oldest sample if the graph
+    /// is full.
+    pub fn push(&self, sample: f64) {
+        let mut data = self.data.borrow_mut();
+        if data.samples.len() == data.capacity {
+            data.samples.pop_front();
+        }
+        data.samples.push_back(sample);
+    }
+
+    /// Removes all samples from the graph.
+    pub fn clear(&self) {
+        self.data.borrow_mut().samples.clear();
+    }
+
+    /// Returns the most recently pushed sample.
+    pub fn latest(&self) -> Option<f64> {
+        self.data.borrow().samples.back().copied()
+    }
+
+    /// Draws the graph, its label, and the latest value inside `bounds`.
+    pub fn draw<D: DrawTarget<Color = Rgb888>>(
+        &self,
+        target: &mut D,
+        bounds: Rectangle,
+    ) -> Result<(), D::Error> {
+        let data = self.data.borrow();
+        let axis_style = PrimitiveStyle::with_stroke(Rgb888::new(80, 80, 80), 1);
+        bounds.into_styled(axis_style).draw(target)?;
+
+        let height = bounds.size.height as i32;
+        let width = bounds.size.width as i32;
+        let middle = bounds.top_left.y + height / 2;
+        Line::new(
+            Point::new(bounds.top_left.x, middle),
+            Point::new(bounds.top_left.x + width - 1, middle),
+        )
+        .into_styled(axis_style)
+        .draw(target)?;
+
+        // Scale symmetrically so zero is always the middle line
+        let max = data
+            .samples
+            .iter()
+            .fold(f64::EPSILON, |max, sample| max.max(sample.abs()));
+        let scale = (height / 2 - 1) as f64 / max;
+        let step = (width - 1) as f64 / (data.capacity - 1) as f64;
+        let to_point = |i: usize, sample: f64| {
+            Point::new(
+                bounds.top_left.x + (i as f64 * step) as i32,
+                middle - (sample * scale) as i32,
+            )
+        };
+        let line_style = PrimitiveStyle::with_stroke(self.color, 1);
+        for (i, (a, b)) in data
+            .samples
+            .iter()
+            .zip(data.samples.iter().skip(1))
+            .enumerate()
+        {
+            Line::new(to_point(i, *a), to_point(i + 1, *b))
+                .into_styled(line_style)
+                .draw(target)?;
+        }
+
+        let text = match data.samples.back() {
+            Some(latest) => format!("{} {:.2} (\u{00B1}{:.2})", self.label, latest, max),
+            None => format!("{} -", self.label),
+        };
+        Text::with_baseline(
+            &text,
+            bounds.top_left + Point::new(2, 1),
+            MonoTextStyle::new(&FONT_6X10, self.color),
+            Baseline::Top,
+        )
+        .draw(target)?;
+        Ok(())
+    }
+}
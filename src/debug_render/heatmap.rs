@@ -0,0 +1,140 @@
+use core::cell::RefCell;
+
+use alloc::{collections::BTreeMap, rc::Rc};
+use embedded_graphics::{
+    pixelcolor::Rgb888,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+};
+use nalgebra::{Point2, Vector2};
+
+use crate::subsystems::tracking::PoseTrace;
+
+use super::field::FieldConfig;
+
+/// A record of where the robot has been, drawn over the field view by
+/// [`DebugRender`](super::DebugRender).
+///
+/// The field is divided into square cells, and each recorded position adds
+/// one to its cell. Recording at a fixed rate makes the intensity of a cell
+/// how long the robot spent there, so a skills run shows which areas it
+/// covered, and the same route drifting further each run shows up as a
+/// smeared trail:
+///
+/// ```ignore
+/// let heatmap = Heatmap::new(100.0);
+/// render.heatmap = Some(heatmap.clone());
+/// loop {
+///     heatmap.record(tracking.current().offset);
+///     sleep(Duration::from_millis(100)).await;
+/// }
+/// ```
+///
+/// Heatmaps are cheap to clone and clones share the same cells, like
+/// [`Graph`](super::Graph)s.
+#[derive(Debug, Clone)]
+pub struct Heatmap {
+    cell_size: f64,
+    cells: Rc<RefCell<BTreeMap<(i32, i32), u32>>>,
+}
+
+impl Heatmap {
+    /// Creates a new, empty heatmap with cells `cell_size` mm wide.
+    pub fn new(cell_size: f64) -> Self {
+        assert!(cell_size > 0.0, "Heatmap cell size must be positive");
+        Self {
+            cell_size,
+            cells: Rc::new(RefCell::new(BTreeMap::new())),
+        }
+    }
+
+    /// Creates a heatmap of the positions recorded in `trace`.
+    pub fn from_trace(trace: &PoseTrace, cell_size: f64) -> Self {
+        let heatmap = Self::new(cell_size);
+        for sample in trace.samples() {
+            heatmap.record(sample.offset);
+        }
+        heatmap
+    }
+
+    /// Adds one to the cell containing `point`.
+    pub fn record(&self, point: Point2<f64>) {
+        let cell = self.cell(point);
+        *self.cells.borrow_mut().entry(cell).or_insert(0) += 1;
+    }
+
+    /// Removes every recorded position.
+    pub fn clear(&self) {
+        self.cells.borrow_mut().clear();
+    }
+
+    /// Returns how many positions were recorded in the busiest cell.
+    pub fn max(&self) -> u32 {
+        self.cells.borrow().values().copied().max().unwrap_or(0)
+    }
+
+    /// Returns how many positions were recorded in the cell containing
+    /// `point`.
+    pub fn count(&self, point: Point2<f64>) -> u32 {
+        let cell = self.cell(point);
+        self.cells.borrow().get(&cell).copied().unwrap_or(0)
+    }
+
+    fn cell(&self, point: Point2<f64>) -> (i32, i32) {
+        (
+            (point.x / self.cell_size).floor() as i32,
+            (point.y / self.cell_size).floor() as i32,
+        )
+    }
+
+    /// Draws every visited cell, from blue for the least visited to red for
+    /// the most.
+    pub(crate) fn draw<D: DrawTarget<Color = Rgb888>>(
+        &self,
+        target: &mut D,
+        field: &FieldConfig,
+    ) -> Result<(), D::Error> {
+        let max = self.max();
+        if max == 0 {
+            return Ok(());
+        }
+        // Keep cells off the field view out of the side panel
+        let mut target = target.clipped(&Rectangle::new(
+            Point::zero(),
+            Size::new(field.size as u32, field.size as u32),
+        ));
+        for (&(column, row), &count) in self.cells.borrow().iter() {
+            let corner = Point2::new(column as f64, row as f64) * self.cell_size;
+            // The field view may be rotated or mirrored, so take the screen
+            // rectangle spanned by opposite corners
+            let rectangle = Rectangle::with_corners(
+                field.to_screen(corner),
+                field.to_screen(corner + Vector2::repeat(self.cell_size)),
+            );
+            rectangle
+                .into_styled(PrimitiveStyle::with_fill(heat_color(
+                    count as f64 / max as f64,
+                )))
+                .draw(&mut target)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the color for an intensity from 0 to 1.
+fn heat_color(intensity: f64) -> Rgb888 {
+    let intensity = intensity.clamp(0.0, 1.0);
+    if intensity < 0.5 {
+        // Blue to yellow
+        let t = intensity * 2.0;
+        Rgb888::new(
+            (255.0 * t) as u8,
+            (255.0 * t) as u8,
+            (255.0 * (1.0 - t)) as u8,
+        )
+    } else {
+        // Yellow to red
+        let t = (intensity - 0.5) * 2.0;
+        Rgb888::new(255, (255.0 * (1.0 - t)) as u8, 0)
+    }
+}
@@ -0,0 +1,354 @@
+use alloc::{boxed::Box, rc::Rc, string::String, vec::Vec};
+use core::{cell::RefCell, time::Duration};
+use std::time::Instant;
+
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, iso_8859_1::FONT_6X10},
+    pixelcolor::Rgb888,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+};
+use vexide::display;
+
+use crate::utils::profiling;
+
+use super::DisplayDriver;
+
+/// The height of the tab bar along the bottom of the display, in pixels.
+pub const TAB_BAR_HEIGHT: u32 = 20;
+
+/// How far, in pixels, a touch must move sideways to count as a swipe.
+const SWIPE_DISTANCE: i32 = 80;
+/// How far, in pixels, a touch may move while still counting as a tap.
+const TAP_SLOP: i32 = 20;
+
+const TAB_COLOR: Rgb888 = Rgb888::new(40, 40, 40);
+const SELECTED_TAB_COLOR: Rgb888 = Rgb888::new(0, 90, 200);
+
+/// One screen of a [`PagedDisplay`], e.g., the field view, telemetry, or the
+/// log.
+pub trait Page {
+    /// The name shown on the page's tab. It should be short, since the tabs
+    /// share the width of the display.
+    fn title(&self) -> &'static str;
+
+    /// Draws the page inside `bounds`, which excludes the tab bar.
+    ///
+    /// The background layer of `display` belongs to the shown page, and is
+    /// cleared whenever the page is switched.
+    fn draw(&mut self, display: &mut DisplayDriver, bounds: Rectangle);
+
+    /// Handles a tap at `point`, in display coordinates, inside the page.
+    fn on_tap(&mut self, point: Point) {
+        _ = point;
+    }
+
+    /// How long to wait between frames of the page.
+    fn frame_interval(&self) -> Duration {
+        Duration::from_millis(50)
+    }
+
+    /// How long drawing a frame of the page should take at most. Frames
+    /// which take longer delay the next frame by the overrun.
+    fn budget(&self) -> Duration {
+        Duration::from_millis(10)
+    }
+}
+
+impl<P: Page> Page for Rc<RefCell<P>> {
+    fn title(&self) -> &'static str {
+        self.borrow().title()
+    }
+
+    fn draw(&mut self, display: &mut DisplayDriver, bounds: Rectangle) {
+        self.borrow_mut().draw(display, bounds);
+    }
+
+    fn on_tap(&mut self, point: Point) {
+        self.borrow_mut().on_tap(point);
+    }
+
+    fn frame_interval(&self) -> Duration {
+        self.borrow().frame_interval()
+    }
+
+    fn budget(&self) -> Duration {
+        self.borrow().budget()
+    }
+}
+
+/// A page showing lines of text, refreshed every frame, e.g., telemetry or
+/// tuning values.
+pub struct TextPage {
+    title: &'static str,
+    lines: Box<dyn FnMut() -> Vec<String>>,
+}
+
+impl core::fmt::Debug for TextPage {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TextPage")
+            .field("title", &self.title)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TextPage {
+    /// Creates a page showing the lines returned by `lines`.
+    pub fn new(title: &'static str, lines: impl FnMut() -> Vec<String> + 'static) -> Self {
+        Self {
+            title,
+            lines: Box::new(lines),
+        }
+    }
+}
+
+impl Page for TextPage {
+    fn title(&self) -> &'static str {
+        self.title
+    }
+
+    fn draw(&mut self, display: &mut DisplayDriver, bounds: Rectangle) {
+        display.fill_solid(&bounds, Rgb888::BLACK).unwrap();
+        let style = MonoTextStyle::new(&FONT_6X10, Rgb888::WHITE);
+        let mut position = bounds.top_left + Point::new(4, 4);
+        for line in (self.lines)() {
+            if position.y + 10 > bounds.top_left.y + bounds.size.height as i32 {
+                break;
+            }
+            Text::with_baseline(&line, position, style, Baseline::Top)
+                .draw(display)
+                .unwrap();
+            position.y += 12;
+        }
+    }
+
+    fn frame_interval(&self) -> Duration {
+        Duration::from_millis(100)
+    }
+}
+
+/// The Brain display split into [pages](Page), with a tab bar along the
+/// bottom.
+///
+/// Tapping a tab or swiping sideways switches pages, and other taps are
+/// passed to the shown page. Only the shown page is drawn, at its own frame
+/// rate, so several screen features can share the display:
+///
+/// ```ignore
+/// let render = Rc::new(RefCell::new(DebugRender::page(
+///     FieldConfig::default().with_size(240.0 - TAB_BAR_HEIGHT as f64),
+/// )));
+/// let mut screen = PagedDisplay::new(peripherals.display)
+///     .with_page(render.clone())
+///     .with_page(TextPage::new("Drive", move || drivetrain_lines()));
+/// loop {
+///     screen.render();
+///     sleep(Duration::from_millis(10)).await;
+/// }
+/// ```
+pub struct PagedDisplay {
+    display: DisplayDriver,
+    pages: Vec<Box<dyn Page>>,
+    current: usize,
+    /// When the shown page should next be drawn, or `None` to draw it now
+    next_frame: Option<Instant>,
+
+    /// The press and release counts of the touchscreen when it was last
+    /// checked
+    last_counts: Option<(i32, i32)>,
+    /// Where the current touch started
+    press_start: Option<Point>,
+}
+
+impl core::fmt::Debug for PagedDisplay {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PagedDisplay")
+            .field(
+                "pages",
+                &self
+                    .pages
+                    .iter()
+                    .map(|page| page.title())
+                    .collect::<Vec<_>>(),
+            )
+            .field("current", &self.current)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PagedDisplay {
+    /// Creates a new paged display with no pages.
+    pub fn new(display: display::Display) -> Self {
+        Self {
+            display: DisplayDriver::new(display),
+            pages: Vec::new(),
+            current: 0,
+            next_frame: None,
+            last_counts: None,
+            press_start: None,
+        }
+    }
+
+    /// Adds a page after the existing ones.
+    pub fn with_page(mut self, page: impl Page + 'static) -> Self {
+        self.add_page(Box::new(page));
+        self
+    }
+
+    /// Adds a page after the existing ones.
+    pub fn add_page(&mut self, page: Box<dyn Page>) {
+        self.pages.push(page);
+    }
+
+    /// Returns the title of the shown page, or `None` if there are no pages.
+    pub fn current_page(&self) -> Option<&'static str> {
+        self.pages.get(self.current).map(|page| page.title())
+    }
+
+    /// Shows the first page with the given title. Returns whether there is
+    /// one.
+    pub fn select_page(&mut self, title: &str) -> bool {
+        match self.pages.iter().position(|page| page.title() == title) {
+            Some(index) => {
+                self.show(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Shows the next page, wrapping around to the first.
+    pub fn next_page(&mut self) {
+        if !self.pages.is_empty() {
+            self.show((self.current + 1) % self.pages.len());
+        }
+    }
+
+    /// Shows the previous page, wrapping around to the last.
+    pub fn previous_page(&mut self) {
+        if !self.pages.is_empty() {
+            self.show((self.current + self.pages.len() - 1) % self.pages.len());
+        }
+    }
+
+    fn show(&mut self, index: usize) {
+        if index != self.current {
+            self.current = index;
+            self.display.clear_background();
+        }
+        self.next_frame = None;
+    }
+
+    /// Handles touches, and draws the shown page and the tab bar if its next
+    /// frame is due.
+    /// This function should be called in a loop to update the display
+    pub fn render(&mut self) {
+        self.handle_touch();
+
+        let now = Instant::now();
+        if self.next_frame.is_some_and(|next_frame| now < next_frame) {
+            return;
+        }
+        let size = self.display.bounding_box().size;
+        let bounds = Rectangle::new(
+            Point::zero(),
+            Size::new(size.width, size.height - TAB_BAR_HEIGHT),
+        );
+        let Some(page) = self.pages.get_mut(self.current) else {
+            self.display.clear(Rgb888::BLACK).unwrap();
+            self.display.render();
+            self.next_frame = Some(now + Duration::from_millis(100));
+            return;
+        };
+
+        let budget = page.budget();
+        {
+            let _scope = profiling::scope_with_budget(page.title(), budget);
+            page.draw(&mut self.display, bounds);
+        }
+        let overrun = now.elapsed().saturating_sub(budget);
+        self.next_frame = Some(now + page.frame_interval() + overrun);
+
+        self.draw_tab_bar();
+        self.display.render();
+    }
+
+    fn draw_tab_bar(&mut self) {
+        let size = self.display.bounding_box().size;
+        let top = (size.height - TAB_BAR_HEIGHT) as i32;
+        let tab_width = size.width / self.pages.len().max(1) as u32;
+        let text_style = TextStyleBuilder::new()
+            .alignment(Alignment::Center)
+            .baseline(Baseline::Middle)
+            .build();
+        for (i, page) in self.pages.iter().enumerate() {
+            let tab = Rectangle::new(
+                Point::new(i as i32 * tab_width as i32, top),
+                Size::new(tab_width - 1, TAB_BAR_HEIGHT),
+            );
+            tab.into_styled(PrimitiveStyle::with_fill(if i == self.current {
+                SELECTED_TAB_COLOR
+            } else {
+                TAB_COLOR
+            }))
+            .draw(&mut self.display)
+            .unwrap();
+            Text::with_text_style(
+                page.title(),
+                tab.center(),
+                MonoTextStyle::new(&FONT_6X10, Rgb888::WHITE),
+                text_style,
+            )
+            .draw(&mut self.display)
+            .unwrap();
+        }
+    }
+
+    /// Turns new presses and releases of the touchscreen into taps and
+    /// swipes.
+    fn handle_touch(&mut self) {
+        let touch = self.display.touch_status();
+        let point = Point::new(touch.point.x as i32, touch.point.y as i32);
+        let counts = (touch.press_count, touch.release_count);
+        let Some((last_presses, last_releases)) = self.last_counts.replace(counts) else {
+            return;
+        };
+        if counts.0 != last_presses {
+            self.press_start = Some(point);
+        }
+        if counts.1 == last_releases {
+            return;
+        }
+        let Some(start) = self.press_start.take() else {
+            return;
+        };
+
+        let moved = point - start;
+        if moved.x.abs() >= SWIPE_DISTANCE && moved.x.abs() > moved.y.abs() {
+            // Swiping left moves to the page on the right, like turning a page
+            if moved.x < 0 {
+                self.next_page();
+            } else {
+                self.previous_page();
+            }
+        } else if moved.x.abs() <= TAP_SLOP && moved.y.abs() <= TAP_SLOP {
+            self.tap(start);
+        }
+    }
+
+    fn tap(&mut self, point: Point) {
+        let size = self.display.bounding_box().size;
+        if point.y >= (size.height - TAB_BAR_HEIGHT) as i32 {
+            let tab_width = size.width / self.pages.len().max(1) as u32;
+            let index = point.x.max(0) as usize / tab_width.max(1) as usize;
+            if index < self.pages.len() {
+                self.show(index);
+            }
+        } else if let Some(page) = self.pages.get_mut(self.current) {
+            page.on_tap(point);
+            // Show the result of the tap straight away
+            self.next_frame = None;
+        }
+    }
+}
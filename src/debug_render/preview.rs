@@ -0,0 +1,262 @@
+use core::time::Duration;
+use std::time::Instant;
+
+use alloc::format;
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, iso_8859_1::FONT_6X10},
+    pixelcolor::Rgb888,
+    prelude::*,
+    primitives::{Circle, Line, PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+};
+use nalgebra::{Point2, Vector2};
+
+use crate::{
+    auton::{AutonRegistry, Route},
+    utils::health::MotorHealth,
+};
+
+use super::{DisplayDriver, Page, field::FieldConfig};
+
+/// How long the robot stays at the end of the route before the animation
+/// starts again.
+const END_PAUSE: Duration = Duration::from_secs(1);
+
+/// The radius of the robot marker, in mm.
+const ROBOT_RADIUS: f64 = 200.0;
+
+const PATH_COLOR: Rgb888 = Rgb888::new(255, 0, 0);
+const ROBOT_COLOR: Rgb888 = Rgb888::new(0, 220, 0);
+const HOT_COLOR: Rgb888 = Rgb888::new(255, 60, 60);
+
+/// A [page](Page) animating the selected autonomous route of an
+/// [`AutonRegistry`] on the field, so the drive team can check they picked
+/// the right route and side while queueing.
+///
+/// The paths added with [`Route::with_preview_path`] are drawn on the field,
+/// mirrored if the route is, and a robot marker sweeps along each in turn,
+/// taking as long as the route would. The side panel shows the route's
+/// metadata and how far through it the animation is, and, with
+/// [`with_health`](Self::with_health), a warning if the drivetrain is
+/// running hot. Tapping the page
+/// restarts the animation.
+///
+/// ```ignore
+/// let screen = PagedDisplay::new(peripherals.display)
+///     .with_page(AutonPreview::new(autons.clone(), FieldConfig::default().with_size(220.0)));
+/// ```
+pub struct AutonPreview {
+    autons: AutonRegistry,
+    field: FieldConfig,
+    field_bmp: Option<tinybmp::Bmp<'static, Rgb888>>,
+    /// The route and mirroring the background layer was drawn for
+    drawn: Option<(&'static str, bool)>,
+    started: Instant,
+    health: Option<MotorHealth>,
+}
+
+impl core::fmt::Debug for AutonPreview {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("AutonPreview")
+            .field("autons", &self.autons)
+            .field("field", &self.field)
+            .field("drawn", &self.drawn)
+            .field("health", &self.health)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AutonPreview {
+    /// Creates a preview of the route selected in `autons`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the field image is not a valid BMP image.
+    pub fn new(autons: AutonRegistry, field: FieldConfig) -> Self {
+        Self {
+            autons,
+            field_bmp: field
+                .image
+                .map(|image| tinybmp::Bmp::from_slice(image).expect("invalid field image")),
+            field,
+            drawn: None,
+            started: Instant::now(),
+            health: None,
+        }
+    }
+
+    /// Warns under the route when a drive motor of `health` is likely to
+    /// limit its current within about a match, so a less drive-intensive
+    /// route can be picked.
+    pub fn with_health(mut self, health: MotorHealth) -> Self {
+        self.health = Some(health);
+        self
+    }
+
+    /// Returns the field view for `route`, mirrored if the route is.
+    fn route_field(&self, route: &Route) -> FieldConfig {
+        FieldConfig {
+            mirrored: self.field.mirrored != route.reverse,
+            ..self.field
+        }
+    }
+
+    /// Draws the field image, the route's paths, and its metadata.
+    fn draw_background(&self, display: &mut DisplayDriver, bounds: Rectangle, route: &Route) {
+        let field = self.route_field(route);
+        display.fill_solid(&bounds, Rgb888::BLACK).unwrap();
+        if let Some(bmp) = &self.field_bmp {
+            super::draw_field_image(display, &field, bmp).unwrap();
+        }
+        for (path, _) in route.preview() {
+            super::draw_path(display, &field, path.as_ref(), PATH_COLOR).unwrap();
+        }
+
+        let mut text = format!(
+            "{}\n{:?} {:?}{}",
+            route.name,
+            route.alliance,
+            route.side,
+            if route.reverse { " (mirrored)" } else { "" }
+        );
+        if !route.description.is_empty() {
+            text.push('\n');
+            text.push_str(route.description);
+        }
+        if route.preview().is_empty() {
+            text.push_str("\nno preview paths");
+        }
+        Text::with_baseline(
+            &text,
+            Point::new(field.size as i32 + 6, bounds.top_left.y + 6),
+            MonoTextStyle::new(&FONT_6X10, Rgb888::WHITE),
+            Baseline::Top,
+        )
+        .draw(display)
+        .unwrap();
+    }
+
+    /// Returns where the robot is `elapsed` into the preview of `route`, its
+    /// heading in radians, and the index of the path it is on, or `None`
+    /// before the first path.
+    fn robot_at(route: &Route, elapsed: Duration) -> (Point2<f64>, f64, Option<usize>) {
+        let mut remaining = elapsed;
+        for (index, (path, duration)) in route.preview().iter().enumerate() {
+            if remaining <= *duration || index == route.preview().len() - 1 {
+                let t = if duration.is_zero() {
+                    1.0
+                } else {
+                    (remaining.as_secs_f64() / duration.as_secs_f64()).min(1.0)
+                };
+                return (path.evaluate(t), path.evaluate_angle(t), Some(index));
+            }
+            remaining -= *duration;
+        }
+        (
+            route.initial_offset,
+            route.initial_heading.as_radians(),
+            None,
+        )
+    }
+}
+
+impl Page for AutonPreview {
+    fn title(&self) -> &'static str {
+        "Preview"
+    }
+
+    fn draw(&mut self, display: &mut DisplayDriver, bounds: Rectangle) {
+        let Some(route) = self.autons.selected() else {
+            display.fill_solid(&bounds, Rgb888::BLACK).unwrap();
+            Text::with_baseline(
+                "No autonomous route selected",
+                bounds.top_left + Point::new(6, 6),
+                MonoTextStyle::new(&FONT_6X10, Rgb888::WHITE),
+                Baseline::Top,
+            )
+            .draw(display)
+            .unwrap();
+            self.drawn = None;
+            return;
+        };
+
+        let key = (route.name, route.reverse);
+        if self.drawn != Some(key) || !display.restore_background() {
+            if self.drawn != Some(key) {
+                self.started = Instant::now();
+            }
+            self.draw_background(display, bounds, &route);
+            display.save_background();
+            self.drawn = Some(key);
+        }
+
+        let total: Duration = route.preview().iter().map(|(_, duration)| *duration).sum();
+        let mut elapsed = self.started.elapsed();
+        if elapsed > total + END_PAUSE {
+            self.started = Instant::now();
+            elapsed = Duration::ZERO;
+        }
+        let (position, heading, index) = Self::robot_at(&route, elapsed.min(total));
+
+        let field = self.route_field(&route);
+        let center = field.to_screen(position);
+        let radius = (ROBOT_RADIUS * field.scale).max(2.0);
+        Circle::with_center(center, (radius * 2.0) as u32)
+            .into_styled(PrimitiveStyle::with_stroke(ROBOT_COLOR, 2))
+            .draw(display)
+            .unwrap();
+        let front = position + Vector2::new(heading.cos(), heading.sin()) * ROBOT_RADIUS;
+        Line::new(center, field.to_screen(front))
+            .into_styled(PrimitiveStyle::with_stroke(ROBOT_COLOR, 2))
+            .draw(display)
+            .unwrap();
+
+        let mut progress = format!(
+            "{:.1} / {:.1} s",
+            elapsed.min(total).as_secs_f64(),
+            total.as_secs_f64()
+        );
+        if let Some(index) = index {
+            progress.push_str(&format!(
+                "\npath {} of {}",
+                index + 1,
+                route.preview().len()
+            ));
+        }
+        if let Some(expected) = route.expected_duration {
+            progress.push_str(&format!("\nexpected {:.1} s", expected.as_secs_f64()));
+        }
+        if let Some(warning) = self
+            .health
+            .as_ref()
+            .and_then(|health| health.drive_warning_text())
+        {
+            Text::with_baseline(
+                &format!("HOT: {}", warning),
+                Point::new(
+                    field.size as i32 + 6,
+                    bounds.top_left.y + bounds.size.height as i32 - 56,
+                ),
+                MonoTextStyle::new(&FONT_6X10, HOT_COLOR),
+                Baseline::Top,
+            )
+            .draw(display)
+            .unwrap();
+        }
+        Text::with_baseline(
+            &progress,
+            Point::new(
+                field.size as i32 + 6,
+                bounds.top_left.y + bounds.size.height as i32 - 40,
+            ),
+            MonoTextStyle::new(&FONT_6X10, Rgb888::new(0, 220, 220)),
+            Baseline::Top,
+        )
+        .draw(display)
+        .unwrap();
+    }
+
+    fn on_tap(&mut self, _point: Point) {
+        self.started = Instant::now();
+    }
+}
@@ -0,0 +1,67 @@
+use alloc::boxed::Box;
+use nalgebra::Point2;
+
+use crate::subsystems::tracking::TrackingSubsystem;
+
+/// What tapping the field view of a [`DebugRender`](super::DebugRender)
+/// does.
+///
+/// Tapping where the robot actually is sanity checks odometry without
+/// changing code, and tapping where it should go makes quick movement tests
+/// possible:
+///
+/// ```ignore
+/// // Sets the tracking position, keeping the heading
+/// render.set_touch_mode(FieldTouchMode::SetPose(tracking.clone()));
+///
+/// // Drives to the tapped point
+/// let drivetrain = Rc::new(RefCell::new(drivetrain));
+/// render.set_touch_mode(FieldTouchMode::test(move |point| {
+///     _ = drivetrain.borrow_mut().action(DriveToPointAction::new(point, config));
+/// }));
+/// ```
+#[derive(Default)]
+pub enum FieldTouchMode {
+    /// Tapping does nothing.
+    #[default]
+    Off,
+    /// Tapping sets the tracking position to the tapped point, keeping the
+    /// heading.
+    SetPose(TrackingSubsystem),
+    /// Tapping calls the callback with the tapped point in mm, e.g., to start
+    /// a drive-to-point action.
+    Test(Box<dyn FnMut(Point2<f64>)>),
+}
+
+impl FieldTouchMode {
+    /// Calls `callback` with each tapped point.
+    pub fn test(callback: impl FnMut(Point2<f64>) + 'static) -> Self {
+        Self::Test(Box::new(callback))
+    }
+
+    /// Handles a tap at `point` on the field.
+    pub(crate) fn tap(&mut self, point: Point2<f64>) {
+        match self {
+            FieldTouchMode::Off => {}
+            FieldTouchMode::SetPose(tracking) => {
+                let heading = tracking.current().heading;
+                log::info!("Touch: set pose to {:?}", point);
+                tracking.set_current(point, heading);
+            }
+            FieldTouchMode::Test(callback) => {
+                log::info!("Touch: tapped {:?}", point);
+                callback(point);
+            }
+        }
+    }
+}
+
+impl core::fmt::Debug for FieldTouchMode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Off => write!(f, "Off"),
+            Self::SetPose(_) => write!(f, "SetPose"),
+            Self::Test(_) => write!(f, "Test"),
+        }
+    }
+}
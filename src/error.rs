@@ -0,0 +1,88 @@
+//! The crate-wide error type
+//!
+//! Each module has its own error type, e.g., [`HangError`] or
+//! [`ConfigError`]. Fallible operations on subsystems return a [`DoxaError`]
+//! instead, which wraps all of them, so a routine can use `?` across
+//! subsystems and decide in one place how to handle a failure:
+//!
+//! ```ignore
+//! async fn score(lift: &mut LiftSubsystem, clamp: &mut PneumaticSubsystem<1>) -> Result<(), DoxaError> {
+//!     clamp.extend()?;
+//!     lift.move_to_named("score")?.await;
+//!     clamp.retract()
+//! }
+//! ```
+//!
+//! [`DoxaError`] also works with
+//! [`expect_report`](crate::utils::unwrap_expect_report::UnwrapExpectReportExt::expect_report),
+//! for callers which would rather carry on: disconnected devices are
+//! reported once, and configuration errors panic.
+
+use alloc::{string::String, vec::Vec};
+
+use snafu::Snafu;
+use vexide::smart::PortError;
+
+use crate::{
+    motorgroup::DoxaMotorGroupError,
+    path_planner::trajectory::TrajectoryError,
+    subsystems::{drivetrain::actions::config::ActionConfigError, hang::HangError},
+    utils::{
+        config::ConfigError, motion_profile::MotionProfileError, settling::TolerancesError,
+        unwrap_expect_report::DeviceError,
+    },
+};
+
+#[derive(Debug, Snafu)]
+pub enum DoxaError {
+    #[snafu(display("Device error: {}", source), context(false))]
+    Port { source: PortError },
+    #[snafu(display("Error(s) in motor group: {:?}", errors))]
+    MotorGroup { errors: Vec<PortError> },
+    #[snafu(display("I/O error: {}", source), context(false))]
+    Io { source: std::io::Error },
+    #[snafu(display("{}", source), context(false))]
+    Config { source: ConfigError },
+    #[snafu(display("{}", source), context(false))]
+    ActionConfig { source: ActionConfigError },
+    #[snafu(display("Invalid tolerances: {}", source), context(false))]
+    Tolerances { source: TolerancesError },
+    #[snafu(display("Invalid motion profile: {}", source), context(false))]
+    MotionProfile { source: MotionProfileError },
+    #[snafu(display("{}", source), context(false))]
+    Hang { source: HangError },
+    #[snafu(display("Invalid trajectory: {}", source), context(false))]
+    Trajectory { source: TrajectoryError },
+    #[snafu(display("No {} named {:?}", kind, name))]
+    NotFound { kind: &'static str, name: String },
+}
+
+impl<T> From<DoxaMotorGroupError<T>> for DoxaError {
+    fn from(error: DoxaMotorGroupError<T>) -> Self {
+        Self::MotorGroup {
+            errors: error.errors,
+        }
+    }
+}
+
+impl DeviceError for DoxaError {
+    fn port_error(&self) -> Option<PortError> {
+        match self {
+            Self::Port { source } => Some(*source),
+            Self::MotorGroup { errors } => errors.first().copied(),
+            _ => None,
+        }
+    }
+
+    fn is_configuration_error(&self) -> bool {
+        matches!(
+            self,
+            Self::Config { .. }
+                | Self::ActionConfig { .. }
+                | Self::Tolerances { .. }
+                | Self::MotionProfile { .. }
+                | Self::NotFound { .. }
+                | Self::Trajectory { .. }
+        )
+    }
+}
@@ -1,6 +1,8 @@
 extern crate alloc;
 
-// pub mod debug_render;
+pub mod auton;
+pub mod debug_render;
+pub mod error;
 pub mod motorgroup;
 pub mod path_planner;
 pub mod subsystems;
@@ -1 +1,917 @@
+//! Motor groups with health telemetry
+//!
+//! [`DoxaMotorGroup`] controls several motors as one, like
+//! [`vexide_motorgroup::MotorGroup`], but also keeps track of the health of
+//! each motor: temperatures, currents, how far the motors' positions disagree
+//! (a sign of a slipping gear or a broken shaft), and which motors have been
+//! disconnected. Errors from every motor are aggregated into a single
+//! [`DoxaMotorGroupError`], which works with
+//! [`expect_report`](crate::utils::unwrap_expect_report::UnwrapExpectReportExt::expect_report)
+//! so that a disconnected motor is logged once instead of every loop.
+//!
+//! Clones share the same motors, so a group can be given to a subsystem and
+//! still be inspected from elsewhere, e.g., a HUD.
+//!
+//! If a motor disconnects mid-match, a group in continue-on-failure mode (see
+//! [`DoxaMotorGroup::with_continue_on_failure`]) carries on with the motors
+//! it has left instead of reporting an error on every call, and emits a
+//! single [`MotorGroupEvent`].
+//!
+//! A group can tell when it is stalled, i.e., driven but not moving, with
+//! [`DoxaMotorGroup::is_stalled`], e.g., to clear a jammed intake.
+//!
+//! [`DoxaMotorGroup::hold_position`] holds a mechanism where it is, e.g., an
+//! arm holding a stake, using the motors' built-in position controllers.
+//!
+//! Voltages can be shaped before they reach the motors, with a slew rate
+//! limit, a deadband, and a minimum output to overcome static friction; see
+//! [`DoxaMotorGroup::with_slew_rate`].
+//!
+//! Each motor can be reversed, and the group can have an external gear ratio,
+//! so that positions and velocities are those of the mechanism rather than
+//! of the motors:
+//!
+//! ```ignore
+//! // Two motors facing each other driving a 36:12 arm
+//! let arm = DoxaMotorGroup::new(vec![left_arm, right_arm])
+//!     .with_reversed(right_arm_port)?
+//!     .with_gear_ratio(12.0 / 36.0);
+//! ```
+
+use core::{cell::RefCell, time::Duration};
+use std::time::Instant;
+
+use alloc::{boxed::Box, rc::Rc, string::ToString, vec::Vec};
+use vexide::{
+    math::Angle,
+    smart::{
+        PortError, SmartDevice,
+        motor::{BrakeMode, Gearset, Motor},
+    },
+};
+
 pub use vexide_motorgroup::{MotorGroup, MotorGroupError};
+
+use crate::{
+    error::DoxaError,
+    utils::{
+        filters::{Ema, Filter},
+        logger::native_log,
+        ticker::LOOP_PERIOD,
+        unwrap_expect_report::UnwrapExpectReportExt,
+    },
+};
+
+/// The EMA alpha used to smooth currents for stall detection
+const STALL_CURRENT_SMOOTHING: f64 = 0.3;
+
+/// Errors from one or more motors in a [`DoxaMotorGroup`].
+///
+/// For getters, `result` is the value computed from the motors which didn't
+/// fail, or `None` if they all failed.
+#[derive(Debug)]
+pub struct DoxaMotorGroupError<T = ()> {
+    pub errors: Vec<PortError>,
+    pub result: Option<T>,
+}
+
+impl<T> core::fmt::Display for DoxaMotorGroupError<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "error(s) in motor group: {:?}", self.errors)
+    }
+}
+
+impl<T: core::fmt::Debug> core::error::Error for DoxaMotorGroupError<T> {}
+
+/// The result of a getter on a [`DoxaMotorGroup`].
+pub type GetterResult<T> = Result<T, DoxaMotorGroupError<T>>;
+
+/// A snapshot of the health of one motor in a [`DoxaMotorGroup`].
+///
+/// Readings are `None` if the motor couldn't be read. Like the group's
+/// getters, `position` and `velocity` are in mechanism space, i.e., corrected
+/// for the motor's direction and the group's gear ratio.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotorTelemetry {
+    pub port: u8,
+    pub connected: bool,
+    /// Temperature in °C
+    pub temperature: Option<f64>,
+    /// Current in amps
+    pub current: Option<f64>,
+    pub position: Option<Angle>,
+    /// Velocity in RPM
+    pub velocity: Option<f64>,
+}
+
+/// A change in the health of a [`DoxaMotorGroup`].
+///
+/// Disconnects and reconnects are only emitted in continue-on-failure mode,
+/// and stalls only for thresholds given to
+/// [`with_stall_detection`](DoxaMotorGroup::with_stall_detection).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MotorGroupEvent {
+    /// The motor on `port` disconnected and is no longer used. `remaining`
+    /// motors are still connected.
+    Disconnected { port: u8, remaining: usize },
+    /// The motor on `port` reconnected and is used again.
+    Reconnected { port: u8 },
+    /// The group has been stalled past `threshold` for long enough.
+    Stalled { threshold: StallThreshold },
+    /// The group is no longer stalled past `threshold`.
+    StallCleared { threshold: StallThreshold },
+}
+
+/// When a [`DoxaMotorGroup`] counts as stalled: driven with at least
+/// `voltage` volts, but moving slower than `velocity` RPM and, if set,
+/// drawing at least `current` amps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StallThreshold {
+    pub voltage: f64,
+    /// Velocity of the mechanism in RPM
+    pub velocity: f64,
+    pub current: Option<f64>,
+}
+
+impl StallThreshold {
+    /// Creates a new stall threshold without a current condition.
+    pub fn new(voltage: f64, velocity: f64) -> Self {
+        Self {
+            voltage,
+            velocity,
+            current: None,
+        }
+    }
+
+    /// Also requires at least `current` amps to count as stalled, which tells
+    /// a jam apart from a mechanism which is just slow to start. The current
+    /// is smoothed so that a single spike doesn't count.
+    pub fn with_current(mut self, current: f64) -> Self {
+        self.current = Some(current);
+        self
+    }
+}
+
+#[derive(Debug)]
+struct StallTimer {
+    threshold: StallThreshold,
+    /// When the group was first seen stalled past the threshold
+    since: Option<Instant>,
+    /// How long to be stalled before emitting an event, if events are wanted
+    report_after: Option<Duration>,
+    reported: bool,
+}
+
+struct Inner {
+    motors: Vec<Motor>,
+    /// Whether each motor in `motors` is reversed
+    reversed: Vec<bool>,
+    /// Whether each motor in `motors` has been found disconnected, in
+    /// continue-on-failure mode
+    failed: Vec<bool>,
+    /// Mechanism turns per motor turn
+    ratio: f64,
+    continue_on_failure: bool,
+    failover_current_limit: Option<f64>,
+    event_handlers: Vec<Box<dyn FnMut(MotorGroupEvent)>>,
+    /// Volts per second
+    slew_rate: Option<f64>,
+    deadband: f64,
+    min_output: f64,
+    /// The last voltage set and when, for slew rate limiting
+    last_voltage: f64,
+    last_voltage_time: Option<Instant>,
+    stall_timers: Vec<StallTimer>,
+    /// Smooths current spikes for stall detection
+    stall_current: Ema,
+    /// The mechanism position being held, if any
+    held: Option<Angle>,
+}
+
+impl core::fmt::Debug for Inner {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Inner")
+            .field("motors", &self.motors)
+            .field("reversed", &self.reversed)
+            .field("failed", &self.failed)
+            .field("ratio", &self.ratio)
+            .field("continue_on_failure", &self.continue_on_failure)
+            .field("slew_rate", &self.slew_rate)
+            .field("deadband", &self.deadband)
+            .field("min_output", &self.min_output)
+            .field("stall_timers", &self.stall_timers)
+            .field("held", &self.held)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Inner {
+    fn sign(&self, index: usize) -> f64 {
+        if self.reversed[index] { -1.0 } else { 1.0 }
+    }
+
+    /// Returns whether the motor at `index` is left out of reads and writes.
+    ///
+    /// Failed motors are left out unless every motor has failed, in which
+    /// case the errors are reported as usual.
+    fn skipped(&self, index: usize) -> bool {
+        self.failed[index] && !self.failed.iter().all(|&failed| failed)
+    }
+
+    /// Returns each motor which isn't skipped with the sign which converts
+    /// its readings and outputs to and from the mechanism's direction.
+    fn signed(&self) -> impl Iterator<Item = (&Motor, f64)> {
+        self.motors
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !self.skipped(*index))
+            .map(|(index, motor)| (motor, self.sign(index)))
+    }
+
+    /// Applies the deadband, minimum output, and slew rate to `volts`.
+    fn shape(&mut self, volts: f64) -> f64 {
+        let mut volts = if volts == 0.0 || volts.abs() < self.deadband {
+            0.0
+        } else {
+            volts.signum() * volts.abs().max(self.min_output)
+        };
+        let now = Instant::now();
+        if let Some(slew_rate) = self.slew_rate {
+            // The first voltage ramps from 0 V, as if it had been set a loop
+            // ago
+            let elapsed = self
+                .last_voltage_time
+                .map_or(LOOP_PERIOD, |last_time| now.duration_since(last_time));
+            let max_change = slew_rate * elapsed.as_secs_f64();
+            volts = volts.clamp(
+                self.last_voltage - max_change,
+                self.last_voltage + max_change,
+            );
+        }
+        self.last_voltage = volts;
+        self.last_voltage_time = Some(now);
+        volts
+    }
+
+    /// Resets the output state after something other than a voltage was set:
+    /// the slew rate limit restarts from zero and any hold ends.
+    fn reset_output(&mut self) {
+        self.last_voltage = 0.0;
+        self.last_voltage_time = None;
+        self.held = None;
+    }
+
+    /// Updates which motors have failed and which stall timers are running,
+    /// returning the resulting events.
+    fn check_health(&mut self) -> Vec<MotorGroupEvent> {
+        let mut events = Vec::new();
+        if self.continue_on_failure {
+            self.check_failures(&mut events);
+        }
+        self.check_stalls(&mut events);
+        events
+    }
+
+    /// Returns the average voltage, velocity, and current of the motors which
+    /// aren't skipped, or `None` if none of them could be read.
+    fn stall_readings(&self) -> Option<(f64, f64, f64)> {
+        let mut voltage = 0.0;
+        let mut velocity = 0.0;
+        let mut current = 0.0;
+        let mut count = 0.0;
+        for (motor, sign) in self.signed() {
+            let (Ok(motor_voltage), Ok(motor_velocity), Ok(motor_current)) =
+                (motor.voltage(), motor.velocity(), motor.current())
+            else {
+                continue;
+            };
+            voltage += motor_voltage * sign;
+            velocity += motor_velocity * sign * self.ratio;
+            current += motor_current;
+            count += 1.0;
+        }
+        (count > 0.0).then(|| (voltage / count, velocity / count, current / count))
+    }
+
+    fn check_stalls(&mut self, events: &mut Vec<MotorGroupEvent>) {
+        if self.stall_timers.is_empty() {
+            return;
+        }
+        let readings = self.stall_readings();
+        let readings = match readings {
+            Some((voltage, velocity, current)) => {
+                Some((voltage, velocity, self.stall_current.update(current)))
+            }
+            None => {
+                self.stall_current.reset();
+                None
+            }
+        };
+        for timer in &mut self.stall_timers {
+            let threshold = timer.threshold;
+            // Motors which can't be read don't count as stalled
+            let stalled = readings.is_some_and(|(voltage, velocity, current)| {
+                voltage.abs() >= threshold.voltage
+                    && velocity.abs() < threshold.velocity
+                    && threshold
+                        .current
+                        .is_none_or(|threshold| current >= threshold)
+            });
+            if !stalled {
+                timer.since = None;
+                if timer.reported {
+                    timer.reported = false;
+                    native_log!(
+                        info,
+                        "Motor group no longer stalled",
+                        "Motor group no longer stalled"
+                    );
+                    events.push(MotorGroupEvent::StallCleared { threshold });
+                }
+                continue;
+            }
+            let since = *timer.since.get_or_insert_with(Instant::now);
+            if let Some(report_after) = timer.report_after
+                && !timer.reported
+                && since.elapsed() >= report_after
+            {
+                timer.reported = true;
+                log::warn!("Motor group stalled: {:?}", threshold);
+                events.push(MotorGroupEvent::Stalled { threshold });
+            }
+        }
+    }
+
+    fn check_failures(&mut self, events: &mut Vec<MotorGroupEvent>) {
+        let mut disconnected = false;
+        for index in 0..self.motors.len() {
+            let connected = self.motors[index].is_connected();
+            if connected != self.failed[index] {
+                // No change since the last check
+                continue;
+            }
+            self.failed[index] = !connected;
+            let port = self.motors[index].port_number();
+            if connected {
+                native_log!(
+                    info,
+                    "Motor on port {=u8} reconnected",
+                    "Motor on port {} reconnected",
+                    port
+                );
+                events.push(MotorGroupEvent::Reconnected { port });
+            } else {
+                let remaining = self.failed.iter().filter(|&&failed| !failed).count();
+                native_log!(
+                    error,
+                    "Motor on port {=u8} disconnected; continuing with {=usize} motor(s)",
+                    "Motor on port {} disconnected; continuing with {} motor(s)",
+                    port,
+                    remaining
+                );
+                events.push(MotorGroupEvent::Disconnected { port, remaining });
+                disconnected = true;
+            }
+        }
+        if let Some(limit) = self.failover_current_limit
+            && disconnected
+        {
+            for (motor, failed) in self.motors.iter_mut().zip(&self.failed) {
+                if !failed {
+                    motor
+                        .set_current_limit(limit)
+                        .expect_report("failed to set failover current limit");
+                }
+            }
+        }
+    }
+}
+
+/// A group of motors controlled together, with per-motor telemetry.
+///
+/// Getters average over the motors which could be read, and setters are
+/// applied to every motor even if some of them fail. Positions and velocities,
+/// both read and set, are in mechanism space: see
+/// [`with_reversed`](Self::with_reversed) and
+/// [`with_gear_ratio`](Self::with_gear_ratio).
+#[derive(Debug, Clone)]
+pub struct DoxaMotorGroup {
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl DoxaMotorGroup {
+    /// Creates a new motor group.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `motors` is empty.
+    pub fn new(motors: Vec<Motor>) -> Self {
+        assert!(
+            !motors.is_empty(),
+            "Cannot create a motor group with no motors"
+        );
+        Self {
+            inner: Rc::new(RefCell::new(Inner {
+                reversed: alloc::vec![false; motors.len()],
+                failed: alloc::vec![false; motors.len()],
+                motors,
+                ratio: 1.0,
+                continue_on_failure: false,
+                failover_current_limit: None,
+                event_handlers: Vec::new(),
+                slew_rate: None,
+                deadband: 0.0,
+                min_output: 0.0,
+                last_voltage: 0.0,
+                last_voltage_time: None,
+                stall_timers: Vec::new(),
+                stall_current: Ema::new(STALL_CURRENT_SMOOTHING),
+                held: None,
+            })),
+        }
+    }
+
+    /// Reverses the motor on the given port, relative to its configured
+    /// [`Direction`](vexide::math::Direction).
+    ///
+    /// Fails if no motor in the group is on `port`.
+    pub fn with_reversed(self, port: u8) -> Result<Self, DoxaError> {
+        {
+            let mut inner = self.inner.borrow_mut();
+            let index = inner
+                .motors
+                .iter()
+                .position(|motor| motor.port_number() == port)
+                .ok_or_else(|| DoxaError::NotFound {
+                    kind: "motor on port",
+                    name: port.to_string(),
+                })?;
+            inner.reversed[index] = !inner.reversed[index];
+        }
+        Ok(self)
+    }
+
+    /// Sets the external gear ratio, in mechanism turns per motor turn.
+    ///
+    /// For example, a 12-tooth gear on the motors driving a 36-tooth gear on
+    /// the mechanism has a ratio of `12.0 / 36.0`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ratio` is zero or not finite.
+    pub fn with_gear_ratio(self, ratio: f64) -> Self {
+        assert!(
+            ratio.is_finite() && ratio != 0.0,
+            "Invalid motor group gear ratio: {}",
+            ratio
+        );
+        self.inner.borrow_mut().ratio = ratio;
+        self
+    }
+
+    /// Keeps the group running when a motor disconnects.
+    ///
+    /// Disconnected motors are left out of reads and writes until they
+    /// reconnect, so that, e.g., odometry from drive motors keeps using the
+    /// motors which are left instead of reporting errors. Each disconnect and
+    /// reconnect is logged once and emitted as a [`MotorGroupEvent`]. If every
+    /// motor disconnects, errors are reported as usual.
+    pub fn with_continue_on_failure(self) -> Self {
+        self.inner.borrow_mut().continue_on_failure = true;
+        self
+    }
+
+    /// Keeps the group running when a motor disconnects, like
+    /// [`with_continue_on_failure`](Self::with_continue_on_failure), and
+    /// raises the current limit of the remaining motors to `limit` amps to
+    /// make up for the lost one.
+    ///
+    /// The raised limit is kept even if the motor reconnects.
+    pub fn with_failover_current_limit(self, limit: f64) -> Self {
+        self.inner.borrow_mut().failover_current_limit = Some(limit);
+        self.with_continue_on_failure()
+    }
+
+    /// Calls `handler` with every [`MotorGroupEvent`].
+    pub fn with_event_handler(self, handler: impl FnMut(MotorGroupEvent) + 'static) -> Self {
+        self.inner
+            .borrow_mut()
+            .event_handlers
+            .push(Box::new(handler));
+        self
+    }
+
+    /// Emits [`MotorGroupEvent::Stalled`] once the group has been stalled past
+    /// `threshold` for `duration`, and [`MotorGroupEvent::StallCleared`] once
+    /// it no longer is.
+    ///
+    /// Like [`is_stalled`](Self::is_stalled), this is checked whenever the
+    /// group is read or written.
+    pub fn with_stall_detection(self, threshold: StallThreshold, duration: Duration) -> Self {
+        self.inner.borrow_mut().stall_timers.push(StallTimer {
+            threshold,
+            since: None,
+            report_after: Some(duration),
+            reported: false,
+        });
+        self
+    }
+
+    /// Returns whether the group has been stalled past `threshold` for at
+    /// least `duration`.
+    ///
+    /// The first call with a threshold starts checking it, and from then on
+    /// it is checked whenever the group is read or written, which subsystems
+    /// do every loop. Stalls are judged from the voltage the motors are
+    /// actually driven with, so this works with every kind of output.
+    pub fn is_stalled(&self, threshold: StallThreshold, duration: Duration) -> bool {
+        {
+            let mut inner = self.inner.borrow_mut();
+            if !inner
+                .stall_timers
+                .iter()
+                .any(|timer| timer.threshold == threshold)
+            {
+                inner.stall_timers.push(StallTimer {
+                    threshold,
+                    since: None,
+                    report_after: None,
+                    reported: false,
+                });
+            }
+        }
+        self.check_health();
+        self.inner
+            .borrow()
+            .stall_timers
+            .iter()
+            .find(|timer| timer.threshold == threshold)
+            .and_then(|timer| timer.since)
+            .is_some_and(|since| since.elapsed() >= duration)
+    }
+
+    /// Checks which motors have failed and which are stalled, and emits any
+    /// resulting events.
+    fn check_health(&self) {
+        let events = self.inner.borrow_mut().check_health();
+        if events.is_empty() {
+            return;
+        }
+        // The handlers are taken out so that they can use the group
+        let mut handlers = core::mem::take(&mut self.inner.borrow_mut().event_handlers);
+        for event in events {
+            for handler in &mut handlers {
+                handler(event);
+            }
+        }
+        let mut inner = self.inner.borrow_mut();
+        handlers.append(&mut inner.event_handlers);
+        inner.event_handlers = handlers;
+    }
+
+    /// Limits how quickly the voltage set with
+    /// [`set_voltage`](Self::set_voltage) may change, in volts per second.
+    ///
+    /// This protects gearboxes and keeps mechanisms from jerking, like the
+    /// drivetrain's acceleration limit. Setting anything other than a voltage
+    /// resets the limit, so the next voltage ramps up from zero.
+    pub fn with_slew_rate(self, slew_rate: f64) -> Self {
+        self.inner.borrow_mut().slew_rate = Some(slew_rate);
+        self
+    }
+
+    /// Treats voltages smaller than `deadband` in magnitude as zero, e.g., to
+    /// ignore joystick drift.
+    pub fn with_deadband(self, deadband: f64) -> Self {
+        self.inner.borrow_mut().deadband = deadband;
+        self
+    }
+
+    /// Raises non-zero voltages to at least `min_output` in magnitude, so
+    /// that small outputs overcome static friction instead of stalling.
+    pub fn with_min_output(self, min_output: f64) -> Self {
+        self.inner.borrow_mut().min_output = min_output;
+        self
+    }
+
+    /// Returns the external gear ratio, in mechanism turns per motor turn.
+    pub fn gear_ratio(&self) -> f64 {
+        self.inner.borrow().ratio
+    }
+
+    /// Returns whether the motor on the given port is reversed, or `None` if
+    /// no motor in the group is on `port`.
+    pub fn is_reversed(&self, port: u8) -> Option<bool> {
+        let inner = self.inner.borrow();
+        inner
+            .motors
+            .iter()
+            .position(|motor| motor.port_number() == port)
+            .map(|index| inner.reversed[index])
+    }
+
+    /// Applies `write` to every motor which isn't skipped with its sign (see
+    /// [`Inner::signed`]) and the gear ratio.
+    fn write(
+        &mut self,
+        mut write: impl FnMut(&mut Motor, f64, f64) -> Result<(), PortError>,
+    ) -> Result<(), DoxaMotorGroupError> {
+        self.check_health();
+        let mut inner = self.inner.borrow_mut();
+        let ratio = inner.ratio;
+        let mut errors = Vec::new();
+        for index in 0..inner.motors.len() {
+            if inner.skipped(index) {
+                continue;
+            }
+            let sign = inner.sign(index);
+            if let Err(error) = write(&mut inner.motors[index], sign, ratio) {
+                errors.push(error);
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(DoxaMotorGroupError {
+                errors,
+                result: Some(()),
+            })
+        }
+    }
+
+    /// Averages `read` over every motor which could be read. `read` is given
+    /// each motor with its sign and the gear ratio.
+    fn average(
+        &self,
+        read: impl Fn(&Motor, f64, f64) -> Result<f64, PortError>,
+    ) -> GetterResult<f64> {
+        self.check_health();
+        let inner = self.inner.borrow();
+        let mut errors = Vec::new();
+        let mut sum = 0.0;
+        let mut count = 0;
+        for (motor, sign) in inner.signed() {
+            match read(motor, sign, inner.ratio) {
+                Ok(value) => {
+                    sum += value;
+                    count += 1;
+                }
+                Err(error) => errors.push(error),
+            }
+        }
+        let result = (count > 0).then(|| sum / count as f64);
+        match (errors.is_empty(), result) {
+            (true, Some(result)) => Ok(result),
+            _ => Err(DoxaMotorGroupError { errors, result }),
+        }
+    }
+
+    /// Sets the voltage of every motor, in the mechanism's direction.
+    ///
+    /// The voltage is shaped by the deadband, minimum output, and slew rate,
+    /// if any are configured.
+    pub fn set_voltage(&mut self, volts: f64) -> Result<(), DoxaMotorGroupError> {
+        let volts = {
+            let mut inner = self.inner.borrow_mut();
+            inner.held = None;
+            inner.shape(volts)
+        };
+        self.write(|motor, sign, _| motor.set_voltage(volts * sign))
+    }
+
+    /// Sets the velocity of the mechanism in RPM, using the built-in velocity
+    /// controller of every motor.
+    pub fn set_velocity(&mut self, rpm: i32) -> Result<(), DoxaMotorGroupError> {
+        self.inner.borrow_mut().reset_output();
+        self.write(|motor, sign, ratio| motor.set_velocity(to_motor_rpm(rpm, sign, ratio)))
+    }
+
+    /// Sets the position target of the mechanism, using the built-in position
+    /// controller of every motor. `velocity` is the mechanism's RPM.
+    pub fn set_position_target(
+        &mut self,
+        position: Angle,
+        velocity: i32,
+    ) -> Result<(), DoxaMotorGroupError> {
+        self.inner.borrow_mut().reset_output();
+        // The velocity is a speed limit, so it's always positive regardless of
+        // the direction of the motor or gearing
+        self.write(|motor, sign, ratio| {
+            motor.set_position_target(
+                position * (sign / ratio),
+                to_motor_rpm(velocity.abs(), 1.0, ratio.abs()),
+            )
+        })
+    }
+
+    /// Stops every motor with the given brake mode.
+    pub fn brake(&mut self, mode: BrakeMode) -> Result<(), DoxaMotorGroupError> {
+        self.inner.borrow_mut().reset_output();
+        self.write(|motor, _, _| motor.brake(mode))
+    }
+
+    pub fn set_voltage_limit(&mut self, limit: f64) -> Result<(), DoxaMotorGroupError> {
+        self.write(|motor, _, _| motor.set_voltage_limit(limit))
+    }
+
+    pub fn set_current_limit(&mut self, limit: f64) -> Result<(), DoxaMotorGroupError> {
+        self.write(|motor, _, _| motor.set_current_limit(limit))
+    }
+
+    /// Sets the current position of the mechanism.
+    ///
+    /// If the group is holding its position, it keeps holding where it is.
+    pub fn set_position(&mut self, position: Angle) -> Result<(), DoxaMotorGroupError> {
+        self.write(|motor, sign, ratio| motor.set_position(position * (sign / ratio)))?;
+        self.rehold()
+    }
+
+    /// Sets the current position of the mechanism to zero.
+    ///
+    /// If the group is holding its position, it keeps holding where it is.
+    pub fn reset_position(&mut self) -> Result<(), DoxaMotorGroupError> {
+        self.write(|motor, _, _| motor.reset_position())?;
+        self.rehold()
+    }
+
+    /// Holds the mechanism at its current position until anything else is
+    /// set, or [`release`](Self::release) is called.
+    ///
+    /// Each motor holds its own current position with its built-in position
+    /// controller, so motors which disagree slightly don't fight each other,
+    /// and no task is needed to keep holding.
+    pub fn hold_position(&mut self) -> Result<(), DoxaMotorGroupError> {
+        let held = self
+            .position()
+            .unwrap_or_else(|err| err.result.unwrap_or_default());
+        self.inner.borrow_mut().reset_output();
+        self.write(|motor, _, _| {
+            let position = motor.position()?;
+            let rpm = motor
+                .gearset()
+                .map_or(Gearset::MAX_GREEN_RPM, |gearset| gearset.max_rpm());
+            motor.set_position_target(position, rpm as i32)
+        })?;
+        log::debug!("Holding motor group at {:.1}°", held.as_degrees());
+        self.inner.borrow_mut().held = Some(held);
+        Ok(())
+    }
+
+    /// Latches the hold again after the motors' positions were changed.
+    fn rehold(&mut self) -> Result<(), DoxaMotorGroupError> {
+        if self.is_holding() {
+            self.hold_position()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Stops holding the mechanism's position, letting it coast.
+    pub fn release(&mut self) -> Result<(), DoxaMotorGroupError> {
+        self.brake(BrakeMode::Coast)
+    }
+
+    /// Returns whether the group is holding a position set with
+    /// [`hold_position`](Self::hold_position).
+    pub fn is_holding(&self) -> bool {
+        self.inner.borrow().held.is_some()
+    }
+
+    /// Returns the mechanism position being held, if any.
+    pub fn held_position(&self) -> Option<Angle> {
+        self.inner.borrow().held
+    }
+
+    /// Returns the average position of the mechanism.
+    pub fn position(&self) -> GetterResult<Angle> {
+        self.average(|motor, sign, ratio| {
+            motor
+                .position()
+                .map(|position| position.as_radians() * sign * ratio)
+        })
+        .map(Angle::from_radians)
+        .map_err(|err| DoxaMotorGroupError {
+            errors: err.errors,
+            result: err.result.map(Angle::from_radians),
+        })
+    }
+
+    /// Returns the average velocity of the mechanism in RPM.
+    pub fn velocity(&self) -> GetterResult<f64> {
+        self.average(|motor, sign, ratio| motor.velocity().map(|velocity| velocity * sign * ratio))
+    }
+
+    /// Returns the average current of the motors in amps.
+    pub fn current(&self) -> GetterResult<f64> {
+        self.average(|motor, _, _| motor.current())
+    }
+
+    /// Returns the average temperature of the motors in °C.
+    pub fn temperature(&self) -> GetterResult<f64> {
+        self.average(|motor, _, _| motor.temperature())
+    }
+
+    /// Returns the average voltage of the motors, in the mechanism's
+    /// direction.
+    pub fn voltage(&self) -> GetterResult<f64> {
+        self.average(|motor, sign, _| motor.voltage().map(|voltage| voltage * sign))
+    }
+
+    /// Returns the average power of the motors in watts.
+    pub fn power(&self) -> GetterResult<f64> {
+        self.average(|motor, _, _| motor.power())
+    }
+
+    /// Returns the average torque of the motors in Nm.
+    pub fn torque(&self) -> GetterResult<f64> {
+        self.average(|motor, _, _| motor.torque())
+    }
+
+    /// Returns the number of motors in the group.
+    pub fn len(&self) -> usize {
+        self.inner.borrow().motors.len()
+    }
+
+    /// Always returns `false`, since a group can't be empty.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Returns a snapshot of the health of each motor.
+    pub fn telemetry(&self) -> Vec<MotorTelemetry> {
+        let inner = self.inner.borrow();
+        inner
+            .motors
+            .iter()
+            .enumerate()
+            .map(|(index, motor)| (motor, inner.sign(index)))
+            .map(|(motor, sign)| MotorTelemetry {
+                port: motor.port_number(),
+                connected: motor.is_connected(),
+                temperature: motor.temperature().ok(),
+                current: motor.current().ok(),
+                position: motor
+                    .position()
+                    .ok()
+                    .map(|position| position * (sign * inner.ratio)),
+                velocity: motor
+                    .velocity()
+                    .ok()
+                    .map(|velocity| velocity * sign * inner.ratio),
+            })
+            .collect()
+    }
+
+    /// Returns the ports of the motors which are disconnected.
+    ///
+    /// Unlike [`MotorGroupEvent`]s, this reflects the motors' current state
+    /// whether or not the group is in continue-on-failure mode.
+    pub fn disconnected(&self) -> Vec<u8> {
+        self.inner
+            .borrow()
+            .motors
+            .iter()
+            .filter(|motor| !motor.is_connected())
+            .map(|motor| motor.port_number())
+            .collect()
+    }
+
+    /// Returns the port and temperature of the hottest motor, or `None` if no
+    /// motor could be read.
+    pub fn hottest(&self) -> Option<(u8, f64)> {
+        self.telemetry()
+            .into_iter()
+            .filter_map(|motor| Some((motor.port, motor.temperature?)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Returns the difference between the highest and lowest motor
+    /// positions, or `None` if fewer than two motors could be read.
+    ///
+    /// Motors geared together should stay close together, so a growing
+    /// disagreement means that a gear is slipping or a shaft has broken.
+    pub fn position_disagreement(&self) -> Option<Angle> {
+        let positions: Vec<f64> = self
+            .telemetry()
+            .into_iter()
+            .filter_map(|motor| motor.position.map(|position| position.as_radians()))
+            .collect();
+        if positions.len() < 2 {
+            return None;
+        }
+        let max = positions.iter().copied().fold(f64::MIN, f64::max);
+        let min = positions.iter().copied().fold(f64::MAX, f64::min);
+        Some(Angle::from_radians(max - min))
+    }
+}
+
+/// Converts a mechanism RPM to the RPM of a motor with the given sign.
+fn to_motor_rpm(rpm: i32, sign: f64, ratio: f64) -> i32 {
+    (rpm as f64 * sign / ratio).round() as i32
+}
+
+impl From<Vec<Motor>> for DoxaMotorGroup {
+    fn from(motors: Vec<Motor>) -> Self {
+        Self::new(motors)
+    }
+}
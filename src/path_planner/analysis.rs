@@ -0,0 +1,114 @@
+//! Cross-track error analysis
+//!
+//! Whether a route got worse after a tuning change is hard to tell by
+//! watching the robot. A [`CrossTrackReport`] measures how far a recorded
+//! run strayed from the planned path, so it can be compared between runs:
+//!
+//! ```ignore
+//! let trace = PoseTrace::load("/trace.csv")?;
+//! if let Some(report) = CrossTrackReport::from_trace(&path, &trace) {
+//!     report.log("skills route");
+//! }
+//! ```
+//!
+//! To measure every run of an action instead, wrap it in an
+//! [`AnalyzedAction`](crate::subsystems::drivetrain::actions::AnalyzedAction).
+
+use nalgebra::Point2;
+
+use crate::subsystems::tracking::PoseTrace;
+
+use super::Path;
+
+/// How far a run strayed from its path. See the
+/// [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CrossTrackReport {
+    /// The number of poses measured
+    pub samples: usize,
+    /// The largest distance from the path, in mm
+    pub max: f64,
+    /// The distance along the path, in mm, of the point closest to the pose
+    /// with the largest error
+    pub max_at: f64,
+    /// The root mean square distance from the path, in mm
+    pub rms: f64,
+    /// The distance from the path at the last pose, in mm
+    pub last: f64,
+}
+
+impl CrossTrackReport {
+    /// Measures the distance of each of `points` from `path`.
+    ///
+    /// Each point is compared to the closest point on the whole path, so this
+    /// evaluates the path a few hundred times per point. Returns `None` if
+    /// there are no points.
+    pub fn from_points(
+        path: &(impl Path + ?Sized),
+        points: impl IntoIterator<Item = Point2<f64>>,
+    ) -> Option<Self> {
+        let mut stats = CrossTrackStats::default();
+        for point in points {
+            let t = path.closest_point_global(point);
+            stats.add(t, nalgebra::distance(&path.evaluate(t), &point));
+        }
+        stats.report(path)
+    }
+
+    /// Measures the distance of each sample in `trace` from `path`. Returns
+    /// `None` if the trace is empty.
+    pub fn from_trace(path: &(impl Path + ?Sized), trace: &PoseTrace) -> Option<Self> {
+        Self::from_points(path, trace.samples().iter().map(|sample| sample.offset))
+    }
+
+    /// Logs a one-line summary of the report, labeled with `name`.
+    pub fn log(&self, name: &str) {
+        log::info!(
+            "Cross-track: {}: max {:.1}mm at {:.0}mm, RMS {:.1}mm, final {:.1}mm ({} samples)",
+            name,
+            self.max,
+            self.max_at,
+            self.rms,
+            self.last,
+            self.samples
+        );
+    }
+}
+
+/// The running totals behind a [`CrossTrackReport`], so errors can be added
+/// one at a time as the robot drives.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct CrossTrackStats {
+    samples: usize,
+    sum_squares: f64,
+    /// The largest error and the t of the closest point to it
+    max: (f64, f64),
+    last: f64,
+}
+
+impl CrossTrackStats {
+    /// Adds a pose which was `error` mm from the point at `t` on the path.
+    pub(crate) fn add(&mut self, t: f64, error: f64) {
+        if self.samples == 0 || error > self.max.0 {
+            self.max = (error, t);
+        }
+        self.sum_squares += error * error;
+        self.last = error;
+        self.samples += 1;
+    }
+
+    /// Returns the report for the errors added so far, or `None` if there
+    /// are none.
+    pub(crate) fn report(&self, path: &(impl Path + ?Sized)) -> Option<CrossTrackReport> {
+        if self.samples == 0 {
+            return None;
+        }
+        Some(CrossTrackReport {
+            samples: self.samples,
+            max: self.max.0,
+            max_at: path.length_until(self.max.1),
+            rms: (self.sum_squares / self.samples as f64).sqrt(),
+            last: self.last,
+        })
+    }
+}
@@ -46,14 +46,9 @@ impl CompoundPath {
             total_length,
         }
     }
-}
 
-impl Path for CompoundPath {
-    fn length_until(&self, t: f64) -> f64 {
-        // If t is 1.0, we are at the end of the last path
-        if t == 1.0 {
-            return self.total_length;
-        }
+    /// Returns the index of the path containing `t`, and `t` along that path.
+    fn locate(&self, t: f64) -> (usize, f64) {
         // Find which path t is in
         let path = (self.paths.len() as f64 * t).floor() as isize;
         // If path is less than 0, use the first path. If it's more than the
@@ -66,7 +61,17 @@ impl Path for CompoundPath {
             path as usize
         };
         // Find t along that path
-        let local_t = (t - path as f64 * self.path_t) / self.path_t;
+        (path, (t - path as f64 * self.path_t) / self.path_t)
+    }
+}
+
+impl Path for CompoundPath {
+    fn length_until(&self, t: f64) -> f64 {
+        // If t is 1.0, we are at the end of the last path
+        if t == 1.0 {
+            return self.total_length;
+        }
+        let (path, local_t) = self.locate(t);
         self.paths[path].length_until(local_t)
             + if path > 0 {
                 self.lengths[path - 1]
@@ -75,41 +80,19 @@ impl Path for CompoundPath {
             }
     }
 
-    // TODO: move shared logic with evaluate_angle to a helper function
-
     fn evaluate(&self, t: f64) -> Point2<f64> {
-        // Find which path t is in
-        let path = (self.paths.len() as f64 * t).floor() as isize;
-        // If path is less than 0, use the first path. If it's more than the
-        // number of paths, use the last path.
-        let path = if path < 0 {
-            0
-        } else if path >= self.paths.len() as isize {
-            self.paths.len() - 1
-        } else {
-            path as usize
-        };
-        // Find t along that path
-        let local_t = (t - path as f64 * self.path_t) / self.path_t;
+        let (path, local_t) = self.locate(t);
         // Evaluate the path at the given t
         self.paths[path].evaluate(local_t)
     }
 
     fn evaluate_angle(&self, t: f64) -> f64 {
-        // Find which path t is in
-        let path = (self.paths.len() as f64 * t).floor() as isize;
-        // If path is less than 0, use the first path. If it's more than the
-        // number of paths, use the last path.
-        let path = if path < 0 {
-            0
-        } else if path >= self.paths.len() as isize {
-            self.paths.len() - 1
-        } else {
-            path as usize
-        };
-        // Find t along that path
-        let local_t = (t - path as f64 * self.path_t) / self.path_t;
+        let (path, local_t) = self.locate(t);
         // Evaluate the path at the given t
         self.paths[path].evaluate_angle(local_t)
     }
+
+    fn segment(&self, t: f64) -> usize {
+        self.locate(t).0
+    }
 }
@@ -14,6 +14,8 @@
 use nalgebra::Point2;
 use vexide::math::Angle;
 
+use crate::utils::{math, units::Millimeters};
+
 use super::Path;
 
 #[rustfmt::skip]
@@ -29,6 +31,9 @@ const CURVE_FITTING_MATRIX: nalgebra::Matrix4<f64> = nalgebra::Matrix4::new(
     // 3.0, 2.0, 1.0, 0.0
 );
 
+/// How many segments the path is split into to integrate its length
+const LENGTH_SEGMENTS: usize = 8;
+
 #[derive(Debug, Clone, Copy)]
 struct Cubic {
     pub a: f64,
@@ -76,11 +81,13 @@ impl CubicParametricPath {
     pub fn new(
         start_point: Point2<f64>,
         start_angle: Angle,
-        start_easing: f64,
+        start_easing: impl Into<Millimeters>,
         end_point: Point2<f64>,
         end_angle: Angle,
-        end_easing: f64,
+        end_easing: impl Into<Millimeters>,
     ) -> Self {
+        let start_easing = start_easing.into().0;
+        let end_easing = end_easing.into().0;
         let x = Cubic::from_endpoints(
             start_point.x,
             end_point.x,
@@ -136,27 +143,16 @@ impl Path for CubicParametricPath {
     }
 
     fn length_until(&self, max_t: f64) -> f64 {
-        // TODO(@rh0820): #1 implement length calculation using calculus
-        // for now, just using a small step size
-        let dt = 0.001;
-        let mut length = 0.0;
-        let mut last_point = self.evaluate(0.0);
-        let mut t = dt;
-        if max_t >= 0.0 {
-            while t <= max_t {
-                let point = self.evaluate(t);
-                length += nalgebra::distance(&last_point, &point);
-                last_point = point;
-                t += dt;
-            }
-        } else {
-            while t >= max_t {
-                let point = self.evaluate(t);
-                length -= nalgebra::distance(&last_point, &point);
-                last_point = point;
-                t -= dt;
-            }
-        }
-        length
+        // The arc length is the integral of the speed along the curve
+        math::integrate(
+            |t| {
+                self.x
+                    .evaluate_derivative(t)
+                    .hypot(self.y.evaluate_derivative(t))
+            },
+            0.0,
+            max_t,
+            LENGTH_SEGMENTS,
+        )
     }
 }
@@ -0,0 +1,287 @@
+//! Planning around obstacles on an occupancy grid
+//!
+//! An [`OccupancyGrid`] divides a rectangle of the field into square cells,
+//! each either free or blocked. [`plan`](OccupancyGrid::plan) finds the
+//! shortest route between two points through free cells with A*, then pulls
+//! it taut so that it only turns at the corners of obstacles:
+//!
+//! ```ignore
+//! let mut grid = OccupancyGrid::new(Point2::new(-1800.0, -1800.0), Point2::new(1800.0, 1800.0), 50.0);
+//! // Grow obstacles by the robot's radius, so the robot's center can pass
+//! // anywhere that's free
+//! grid.block_circle(Point2::new(0.0, 0.0), 200.0 + ROBOT_RADIUS);
+//! let waypoints = grid.plan(start, goal);
+//! ```
+
+use core::cmp::Ordering;
+
+use alloc::{collections::BinaryHeap, vec::Vec};
+use nalgebra::Point2;
+
+/// The steps to the eight neighbours of a cell, as (column, row) offsets.
+const NEIGHBOURS: [(isize, isize); 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
+
+/// A cell waiting to be expanded by A*, ordered so that the lowest estimated
+/// total cost comes out of the heap first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct OpenCell {
+    estimate: f64,
+    cell: usize,
+}
+
+impl Eq for OpenCell {}
+
+impl PartialOrd for OpenCell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenCell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+/// A map of which parts of the field are blocked. See the
+/// [module documentation](self).
+#[derive(Debug, Clone, PartialEq)]
+pub struct OccupancyGrid {
+    /// The corner of the first cell, with the smallest coordinates
+    origin: Point2<f64>,
+    cell_size: f64,
+    columns: usize,
+    rows: usize,
+    blocked: Vec<bool>,
+}
+
+impl OccupancyGrid {
+    /// Creates an empty grid covering the rectangle from `min` to `max`, with
+    /// cells `cell_size` mm wide.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cell_size` isn't positive or `max` isn't above and to the
+    /// right of `min`.
+    pub fn new(min: Point2<f64>, max: Point2<f64>, cell_size: f64) -> Self {
+        assert!(cell_size > 0.0, "OccupancyGrid cell size must be positive");
+        assert!(
+            max.x > min.x && max.y > min.y,
+            "OccupancyGrid must cover a non-empty rectangle"
+        );
+        let columns = ((max.x - min.x) / cell_size).ceil() as usize;
+        let rows = ((max.y - min.y) / cell_size).ceil() as usize;
+        Self {
+            origin: min,
+            cell_size,
+            columns,
+            rows,
+            blocked: alloc::vec![false; columns * rows],
+        }
+    }
+
+    /// Returns the width of a cell in mm.
+    pub fn cell_size(&self) -> f64 {
+        self.cell_size
+    }
+
+    /// Blocks every cell whose center is within `radius` of `center`.
+    pub fn block_circle(&mut self, center: Point2<f64>, radius: f64) {
+        self.block_where(
+            center - nalgebra::Vector2::repeat(radius),
+            center + nalgebra::Vector2::repeat(radius),
+            |point| nalgebra::distance(&point, &center) <= radius,
+        );
+    }
+
+    /// Blocks every cell whose center is inside the rectangle from `min` to
+    /// `max`.
+    pub fn block_rectangle(&mut self, min: Point2<f64>, max: Point2<f64>) {
+        self.block_where(min, max, |_| true);
+    }
+
+    /// Frees every cell.
+    pub fn clear(&mut self) {
+        self.blocked.fill(false);
+    }
+
+    /// Returns whether `point` is in a blocked cell. Points outside the grid
+    /// are blocked.
+    pub fn is_blocked(&self, point: Point2<f64>) -> bool {
+        self.cell_at(point)
+            .is_none_or(|(column, row)| self.blocked[self.index(column, row)])
+    }
+
+    /// Returns whether the straight line from `start` to `end` only crosses
+    /// free cells.
+    pub fn is_line_free(&self, start: Point2<f64>, end: Point2<f64>) -> bool {
+        let steps = (nalgebra::distance(&start, &end) / (self.cell_size / 2.0)).ceil() as usize;
+        (0..=steps.max(1)).all(|step| {
+            let t = step as f64 / steps.max(1) as f64;
+            !self.is_blocked(start + (end - start) * t)
+        })
+    }
+
+    /// Finds the shortest route from `start` to `goal` through free cells.
+    ///
+    /// Returns the corners of the route, from `start` to `goal`, or `None` if
+    /// `goal` is blocked or can't be reached. The route may leave `start`
+    /// even if it is blocked, so that the robot can escape after being pushed
+    /// into an obstacle.
+    pub fn plan(&self, start: Point2<f64>, goal: Point2<f64>) -> Option<Vec<Point2<f64>>> {
+        let (start_column, start_row) = self.cell_at(start)?;
+        let (goal_column, goal_row) = self.cell_at(goal)?;
+        let start_cell = self.index(start_column, start_row);
+        let goal_cell = self.index(goal_column, goal_row);
+        if self.blocked[goal_cell] {
+            return None;
+        }
+
+        let heuristic = |cell: usize| {
+            let (column, row) = (cell % self.columns, cell / self.columns);
+            (column.abs_diff(goal_column) as f64).hypot(row.abs_diff(goal_row) as f64)
+        };
+        let mut cost = alloc::vec![f64::INFINITY; self.blocked.len()];
+        let mut came_from = alloc::vec![usize::MAX; self.blocked.len()];
+        let mut open = BinaryHeap::new();
+        cost[start_cell] = 0.0;
+        open.push(OpenCell {
+            estimate: heuristic(start_cell),
+            cell: start_cell,
+        });
+
+        while let Some(OpenCell { estimate, cell }) = open.pop() {
+            if cell == goal_cell {
+                break;
+            }
+            if estimate > cost[cell] + heuristic(cell) {
+                // A cheaper way here was already expanded
+                continue;
+            }
+            let (column, row) = (cell % self.columns, cell / self.columns);
+            for (step_column, step_row) in NEIGHBOURS {
+                let (Some(next_column), Some(next_row)) = (
+                    column.checked_add_signed(step_column),
+                    row.checked_add_signed(step_row),
+                ) else {
+                    continue;
+                };
+                if next_column >= self.columns || next_row >= self.rows {
+                    continue;
+                }
+                let next = self.index(next_column, next_row);
+                // Don't cut the corners of blocked cells diagonally
+                if self.blocked[next]
+                    || self.blocked[self.index(next_column, row)]
+                    || self.blocked[self.index(column, next_row)]
+                {
+                    continue;
+                }
+                let next_cost = cost[cell] + (step_column as f64).hypot(step_row as f64);
+                if next_cost < cost[next] {
+                    cost[next] = next_cost;
+                    came_from[next] = cell;
+                    open.push(OpenCell {
+                        estimate: next_cost + heuristic(next),
+                        cell: next,
+                    });
+                }
+            }
+        }
+        if cost[goal_cell].is_infinite() {
+            return None;
+        }
+
+        let mut cells = Vec::from([goal_cell]);
+        while let Some(&cell) = cells.last()
+            && cell != start_cell
+        {
+            cells.push(came_from[cell]);
+        }
+        let mut route: Vec<Point2<f64>> = cells
+            .iter()
+            .rev()
+            .map(|&cell| self.center(cell % self.columns, cell / self.columns))
+            .collect();
+        // Use the exact endpoints instead of the centers of their cells
+        route[0] = start;
+        let last = route.len() - 1;
+        route[last] = goal;
+        Some(self.pull_taut(&route))
+    }
+
+    /// Removes the points of `route` which can be skipped with a straight
+    /// line through free cells.
+    fn pull_taut(&self, route: &[Point2<f64>]) -> Vec<Point2<f64>> {
+        let mut corners = Vec::from([route[0]]);
+        let mut anchor = 0;
+        while anchor < route.len() - 1 {
+            // The furthest point visible from the anchor; the next point is
+            // always visible, since neighbouring cells are free
+            let next = (anchor + 1..route.len())
+                .rev()
+                .find(|&index| self.is_line_free(route[anchor], route[index]))
+                .unwrap_or(anchor + 1);
+            corners.push(route[next]);
+            anchor = next;
+        }
+        corners
+    }
+
+    fn block_where(
+        &mut self,
+        min: Point2<f64>,
+        max: Point2<f64>,
+        predicate: impl Fn(Point2<f64>) -> bool,
+    ) {
+        let first_column = ((min.x - self.origin.x) / self.cell_size).floor().max(0.0) as usize;
+        let first_row = ((min.y - self.origin.y) / self.cell_size).floor().max(0.0) as usize;
+        let last_column = ((max.x - self.origin.x) / self.cell_size).ceil().max(0.0) as usize;
+        let last_row = ((max.y - self.origin.y) / self.cell_size).ceil().max(0.0) as usize;
+        for row in first_row..last_row.min(self.rows) {
+            for column in first_column..last_column.min(self.columns) {
+                let center = self.center(column, row);
+                if center.x >= min.x
+                    && center.x <= max.x
+                    && center.y >= min.y
+                    && center.y <= max.y
+                    && predicate(center)
+                {
+                    let index = self.index(column, row);
+                    self.blocked[index] = true;
+                }
+            }
+        }
+    }
+
+    fn cell_at(&self, point: Point2<f64>) -> Option<(usize, usize)> {
+        let column = ((point.x - self.origin.x) / self.cell_size).floor();
+        let row = ((point.y - self.origin.y) / self.cell_size).floor();
+        if column < 0.0 || row < 0.0 {
+            return None;
+        }
+        let (column, row) = (column as usize, row as usize);
+        (column < self.columns && row < self.rows).then_some((column, row))
+    }
+
+    fn center(&self, column: usize, row: usize) -> Point2<f64> {
+        self.origin
+            + nalgebra::Vector2::new(
+                (column as f64 + 0.5) * self.cell_size,
+                (row as f64 + 0.5) * self.cell_size,
+            )
+    }
+
+    fn index(&self, column: usize, row: usize) -> usize {
+        row * self.columns + column
+    }
+}
@@ -0,0 +1,56 @@
+use nalgebra::Point2;
+
+use crate::{path_planner::Path, utils::alliance::AllianceContext};
+
+/// A path which is mirrored over the central line whenever its
+/// [`AllianceContext`] is mirrored.
+///
+/// Drivetrain actions don't need this, since the tracking subsystem already
+/// reports the pose in the original coordinate system when reversed. It is
+/// for paths which must be in field coordinates, such as paths drawn on the
+/// field view.
+#[derive(Debug)]
+pub struct MirroredPath<P: Path> {
+    path: P,
+    alliance: AllianceContext,
+}
+
+impl<P: Path> MirroredPath<P> {
+    /// Wraps `path`, which is written in the original coordinate system.
+    pub fn new(path: P, alliance: AllianceContext) -> Self {
+        Self { path, alliance }
+    }
+
+    /// Returns the unmirrored path.
+    pub fn inner(&self) -> &P {
+        &self.path
+    }
+}
+
+impl<P: Path> Path for MirroredPath<P> {
+    fn length_until(&self, t: f64) -> f64 {
+        // Mirroring doesn't change lengths
+        self.path.length_until(t)
+    }
+
+    fn evaluate(&self, t: f64) -> Point2<f64> {
+        self.alliance.point(self.path.evaluate(t))
+    }
+
+    fn evaluate_angle(&self, t: f64) -> f64 {
+        let angle = self.path.evaluate_angle(t);
+        if self.alliance.is_mirrored() {
+            -angle
+        } else {
+            angle
+        }
+    }
+
+    fn length(&self) -> f64 {
+        self.path.length()
+    }
+
+    fn segment(&self, t: f64) -> usize {
+        self.path.segment(t)
+    }
+}
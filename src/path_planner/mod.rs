@@ -2,8 +2,29 @@ use core::fmt::Debug;
 
 use nalgebra::Point2;
 
+pub mod analysis;
 pub mod compound;
 pub mod cubic_parametric;
+pub mod grid;
+pub mod mirrored;
+pub mod sampled;
+pub mod trajectory;
+
+/// How close to the radius, in mm, a point found by
+/// [`Path::point_on_radius`] must be by default.
+pub const DEFAULT_RADIUS_TOLERANCE: f64 = 3.0;
+
+/// The step of the coarse scan in [`Path::point_on_radius`].
+const COARSE_STEP: f64 = 0.01;
+
+/// The number of bisection steps used to refine a crossing in
+/// [`Path::point_on_radius`], enough for a precision of about 1e-8 in t.
+const REFINE_ITERATIONS: usize = 20;
+
+/// The number of samples used to refine the closest candidate in
+/// [`Path::point_on_radius`] when there is no crossing, and in
+/// [`Path::closest_point_global`].
+const REFINE_SAMPLES: usize = 40;
 
 pub trait Path: Debug {
     /// Returns the length of the path from t=0 to t=`t`. This is calculated as
@@ -24,6 +45,16 @@ pub trait Path: Debug {
         (p2.y - p1.y).atan2(p2.x - p1.x)
     }
 
+    /// Returns the index of the segment of the path containing `t`.
+    ///
+    /// Paths joined from several others, like
+    /// [`CompoundPath`](compound::CompoundPath), have one segment per path.
+    /// The default implementation returns 0, for a single segment.
+    fn segment(&self, t: f64) -> usize {
+        _ = t;
+        0
+    }
+
     /// Returns the total length of the path.
     ///
     /// Typically, unless there is a more efficient implementation, the default
@@ -32,43 +63,77 @@ pub trait Path: Debug {
         self.length_until(1.0)
     }
 
-    /// Finds a point on the path that is approximately `radius` distance from
-    /// the given `point`.
+    /// Finds the first point at or after `initial_t` where the path leaves
+    /// the circle of `radius` around `point`.
+    ///
+    /// Points behind `initial_t` are never returned, so a pure pursuit
+    /// controller never aims backwards. If the path ends inside the circle,
+    /// the end of the path is returned. Otherwise, if the path never reaches
+    /// the circle, the point closest to it is returned if it is within
+    /// `tolerance` of the radius, which defaults to
+    /// [`DEFAULT_RADIUS_TOLERANCE`].
+    ///
+    /// The default implementation scans forward in coarse steps, then refines
+    /// the crossing by bisection.
     fn point_on_radius(
         &self,
         point: Point2<f64>,
         radius: f64,
         initial_t: Option<f64>,
+        tolerance: Option<f64>,
     ) -> Option<f64> {
-        let dt = 0.001;
-        let mut t = initial_t.unwrap_or(0.0);
-        let mut closest_t = t;
-        let mut closest_distance = f64::MAX;
-        // let mut last_distance = self.evaluate(t).distance(&pose);
-        while t <= 1.0 {
-            let current_point = self.evaluate(t);
-            let distance = nalgebra::distance(&current_point, &point);
-            if (distance - radius).abs() < closest_distance {
-                closest_distance = (distance - radius).abs();
-                closest_t = t;
+        let start = initial_t.unwrap_or(0.0).clamp(0.0, 1.0);
+        let tolerance = tolerance.unwrap_or(DEFAULT_RADIUS_TOLERANCE);
+        // Negative inside the circle, positive outside
+        let error = |t: f64| nalgebra::distance(&self.evaluate(t), &point) - radius;
+
+        let mut last_t = start;
+        let mut last_error = error(start);
+        let mut best = (start, last_error.abs());
+        while last_t < 1.0 {
+            let t = (last_t + COARSE_STEP).min(1.0);
+            let error_t = error(t);
+            if last_error < 0.0 && error_t >= 0.0 {
+                // The path leaves the circle in this step
+                let (mut inside, mut outside) = (last_t, t);
+                for _ in 0..REFINE_ITERATIONS {
+                    let middle = (inside + outside) / 2.0;
+                    if error(middle) < 0.0 {
+                        inside = middle;
+                    } else {
+                        outside = middle;
+                    }
+                }
+                return Some((inside + outside) / 2.0);
+            }
+            if error_t.abs() < best.1 {
+                best = (t, error_t.abs());
+            }
+            last_t = t;
+            last_error = error_t;
+        }
+        if last_error < 0.0 {
+            return Some(1.0);
+        }
+
+        // Refine around the closest coarse sample
+        let from = (best.0 - COARSE_STEP).max(start);
+        let to = (best.0 + COARSE_STEP).min(1.0);
+        for sample in 0..=REFINE_SAMPLES {
+            let t = from + (to - from) * sample as f64 / REFINE_SAMPLES as f64;
+            let error_t = error(t).abs();
+            if error_t < best.1 {
+                best = (t, error_t);
             }
-            // Seems to not work
-            // TODO: we don't want to skip a path segment so we should check
-            // whether the last point is much farther along the path than the previous
-            // if distance > last_distance {
-            //     break;
-            // }
-            // last_distance = distance;
-            t += dt;
         }
-        if closest_distance < 3.0 {
-            Some(closest_t)
+        if best.1 < tolerance {
+            Some(best.0)
         } else {
             log::error!(
                 "Path: No point on path found within radius {} of point {:?}. Closest point was {} away",
                 radius,
                 point,
-                closest_distance
+                best.1
             );
             None
         }
@@ -121,4 +186,34 @@ pub trait Path: Debug {
         }
         closest_t
     }
+
+    /// Finds the closest point on the whole path to the given `point`.
+    ///
+    /// Unlike [`closest_point`](Self::closest_point), this can't get stuck in
+    /// a local minimum, e.g., on the wrong bend of an S-curve, but it
+    /// evaluates the path a few hundred times, so it is better used now and
+    /// then to check the local search. The default implementation scans the
+    /// path in coarse steps, then refines around the closest sample.
+    fn closest_point_global(&self, point: Point2<f64>) -> f64 {
+        let distance = |t: f64| nalgebra::distance(&self.evaluate(t), &point);
+        let steps = (1.0 / COARSE_STEP).round() as usize;
+        let mut best = (0.0, f64::MAX);
+        for step in 0..=steps {
+            let t = step as f64 / steps as f64;
+            let distance_t = distance(t);
+            if distance_t < best.1 {
+                best = (t, distance_t);
+            }
+        }
+        let from = (best.0 - COARSE_STEP).max(0.0);
+        let to = (best.0 + COARSE_STEP).min(1.0);
+        for sample in 0..=REFINE_SAMPLES {
+            let t = from + (to - from) * sample as f64 / REFINE_SAMPLES as f64;
+            let distance_t = distance(t);
+            if distance_t < best.1 {
+                best = (t, distance_t);
+            }
+        }
+        best.0
+    }
 }
@@ -0,0 +1,162 @@
+use alloc::vec::Vec;
+use nalgebra::Point2;
+
+use crate::{
+    path_planner::Path,
+    utils::{geometry, units::Millimeters},
+};
+
+/// A path through a list of sampled points, connected by straight segments.
+///
+/// The path is parametrized by arc length, so `t = 0.5` is halfway along the
+/// path by distance. This is useful for following paths which were recorded
+/// rather than planned, such as a pose trace from a practice run.
+#[derive(Debug, Clone)]
+pub struct SampledPath {
+    points: Vec<Point2<f64>>,
+    /// Cumulative length of the path up to each point
+    lengths: Vec<f64>,
+}
+
+impl SampledPath {
+    /// Creates a new path through the given points.
+    ///
+    /// # Panics
+    ///
+    /// Panics if fewer than two points are given.
+    pub fn new(points: Vec<Point2<f64>>) -> Self {
+        assert!(
+            points.len() >= 2,
+            "SampledPath must contain at least two points"
+        );
+        let mut lengths = Vec::with_capacity(points.len());
+        let mut total = 0.0;
+        lengths.push(0.0);
+        for pair in points.windows(2) {
+            total += nalgebra::distance(&pair[0], &pair[1]);
+            lengths.push(total);
+        }
+        Self { points, lengths }
+    }
+
+    /// Creates a new path through the given points, smoothed with a centered
+    /// moving average over `window` points and thinned so that consecutive
+    /// points are at least `spacing` mm apart.
+    ///
+    /// The first and last points are kept as-is so that the path still starts
+    /// and ends where the samples do.
+    pub fn smoothed(
+        points: &[Point2<f64>],
+        window: usize,
+        spacing: impl Into<Millimeters>,
+    ) -> Self {
+        let spacing = spacing.into().0;
+        let half = window / 2;
+        let mut smoothed: Vec<Point2<f64>> = Vec::with_capacity(points.len());
+        for i in 0..points.len() {
+            let point = if i == 0 || i == points.len() - 1 {
+                points[i]
+            } else {
+                let start = i.saturating_sub(half);
+                let end = (i + half + 1).min(points.len());
+                let sum = points[start..end]
+                    .iter()
+                    .fold(nalgebra::Vector2::zeros(), |sum, point| sum + point.coords);
+                Point2::from(sum / (end - start) as f64)
+            };
+            let is_last = i == points.len() - 1;
+            match smoothed.last() {
+                Some(last) if !is_last && nalgebra::distance(last, &point) < spacing => {}
+                _ => smoothed.push(point),
+            }
+        }
+        if smoothed.len() < 2 {
+            smoothed = Vec::from([points[0], points[points.len() - 1]]);
+        }
+        Self::new(smoothed)
+    }
+
+    /// Returns the points the path passes through.
+    pub fn points(&self) -> &[Point2<f64>] {
+        &self.points
+    }
+
+    /// Returns the index of the segment containing the point `distance` mm
+    /// along the path, and how far along that segment the point is from 0 to
+    /// 1.
+    fn segment_at(&self, distance: f64) -> (usize, f64) {
+        let segment = match self
+            .lengths
+            .binary_search_by(|length| length.total_cmp(&distance))
+        {
+            Ok(index) | Err(index) => index.saturating_sub(1),
+        }
+        .min(self.points.len() - 2);
+        let segment_length = self.lengths[segment + 1] - self.lengths[segment];
+        let local = if segment_length > 0.0 {
+            (distance - self.lengths[segment]) / segment_length
+        } else {
+            0.0
+        };
+        (segment, local.clamp(0.0, 1.0))
+    }
+}
+
+impl Path for SampledPath {
+    fn length_until(&self, t: f64) -> f64 {
+        self.length() * t.clamp(0.0, 1.0)
+    }
+
+    fn evaluate(&self, t: f64) -> Point2<f64> {
+        let (segment, local) = self.segment_at(self.length() * t.clamp(0.0, 1.0));
+        self.points[segment] + (self.points[segment + 1] - self.points[segment]) * local
+    }
+
+    fn evaluate_angle(&self, t: f64) -> f64 {
+        let (segment, _) = self.segment_at(self.length() * t.clamp(0.0, 1.0));
+        let direction = self.points[segment + 1] - self.points[segment];
+        direction.y.atan2(direction.x)
+    }
+
+    fn length(&self) -> f64 {
+        self.lengths[self.lengths.len() - 1]
+    }
+
+    /// Finds the first point at or after `initial_t` where the path crosses
+    /// the circle of `radius` around `point`, exactly rather than by sampling,
+    /// so `tolerance` is unused. If there is none and the path ends inside
+    /// the circle, the end of the path is returned.
+    fn point_on_radius(
+        &self,
+        point: Point2<f64>,
+        radius: f64,
+        initial_t: Option<f64>,
+        _tolerance: Option<f64>,
+    ) -> Option<f64> {
+        let start = self.length() * initial_t.unwrap_or(0.0).clamp(0.0, 1.0);
+        let (first, _) = self.segment_at(start);
+        for segment in first..self.points.len() - 1 {
+            let segment_length = self.lengths[segment + 1] - self.lengths[segment];
+            for local in geometry::circle_segment_intersections(
+                point,
+                radius,
+                self.points[segment],
+                self.points[segment + 1],
+            ) {
+                let distance = self.lengths[segment] + local * segment_length;
+                if distance >= start {
+                    return Some(distance / self.length());
+                }
+            }
+        }
+        if nalgebra::distance(&self.points[self.points.len() - 1], &point) < radius {
+            return Some(1.0);
+        }
+        log::error!(
+            "Path: No point on path found within radius {} of point {:?}",
+            radius,
+            point
+        );
+        None
+    }
+}
@@ -0,0 +1,293 @@
+//! Time-parameterized trajectories
+//!
+//! A [`Path`](super::Path) only says where to go. A [`Trajectory`] also says
+//! when to be there, and how fast to be going: a list of timestamped states
+//! with a pose and velocities, as produced by a trajectory optimizer.
+//!
+//! Trajectories can be imported from the desktop tools FRC teams use:
+//!
+//! - [Choreo](Trajectory::load_choreo) `.traj` files, both the current format
+//!   (`trajectory.samples` with `t`, `vl`/`vr` or `vx`/`vy`, and `omega`) and
+//!   the older one (`samples` with `timestamp`, `velocityX`/`velocityY`, and
+//!   `angularVelocity`).
+//! - [PathPlanner](Trajectory::load_path_planner) trajectories exported as
+//!   WPILib trajectory JSON, an array of states with `time`, `velocity`,
+//!   `curvature`, and a `pose`.
+//!
+//! Both tools work in meters and radians with the origin in a corner of the
+//! field. Imported trajectories are converted to mm, but otherwise kept in
+//! the tool's frame, so they should be planned with the same origin as the
+//! robot's tracking.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::time::Duration;
+
+use nalgebra::Point2;
+use snafu::Snafu;
+use vexide::math::Angle;
+
+use crate::{
+    path_planner::sampled::SampledPath,
+    utils::{
+        angle,
+        json::{JsonError, JsonValue},
+    },
+};
+
+/// Millimeters in a meter, the unit of imported trajectories.
+const MM_PER_METER: f64 = 1000.0;
+
+#[derive(Debug, Snafu)]
+pub enum TrajectoryError {
+    #[snafu(display("Failed to read {}: {}", path, source))]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[snafu(display("{}: {}", path, source))]
+    Json { path: String, source: JsonError },
+    #[snafu(display("{}: {}", path, message))]
+    Format { path: String, message: String },
+    #[snafu(display("Trajectory has no states"))]
+    NoStates,
+    #[snafu(display("Trajectory state {} is earlier than the one before it", index))]
+    OutOfOrder { index: usize },
+}
+
+/// A state of a [`Trajectory`] at one point in time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrajectoryState {
+    /// Time since the start of the trajectory
+    pub time: Duration,
+    pub position: Point2<f64>,
+    pub heading: Angle,
+    /// Forward velocity in mm/s
+    pub velocity: f64,
+    /// Angular velocity in rad/s, counterclockwise-positive
+    pub angular_velocity: f64,
+}
+
+/// A time-parameterized trajectory. See the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct Trajectory {
+    states: Vec<TrajectoryState>,
+}
+
+impl Trajectory {
+    /// Creates a trajectory from states in order of time.
+    ///
+    /// Returns an error if no states are given, or if they aren't in order of
+    /// time.
+    pub fn new(states: Vec<TrajectoryState>) -> Result<Self, TrajectoryError> {
+        if states.is_empty() {
+            return Err(TrajectoryError::NoStates);
+        }
+        if let Some(index) = states
+            .windows(2)
+            .position(|pair| pair[0].time > pair[1].time)
+        {
+            return Err(TrajectoryError::OutOfOrder { index: index + 1 });
+        }
+        Ok(Self { states })
+    }
+
+    /// Returns the states of the trajectory.
+    pub fn states(&self) -> &[TrajectoryState] {
+        &self.states
+    }
+
+    /// Returns the time of the last state.
+    pub fn duration(&self) -> Duration {
+        self.states[self.states.len() - 1].time
+    }
+
+    /// Returns the state at `time`, interpolated between the states around
+    /// it. Times before the start or after the end return the first or last
+    /// state.
+    pub fn sample(&self, time: Duration) -> TrajectoryState {
+        let next = self.states.partition_point(|state| state.time <= time);
+        if next == 0 {
+            return self.states[0];
+        }
+        if next == self.states.len() {
+            return self.states[next - 1];
+        }
+        let (from, to) = (&self.states[next - 1], &self.states[next]);
+        let t = (time - from.time).as_secs_f64() / (to.time - from.time).as_secs_f64();
+        let lerp = |a: f64, b: f64| a + (b - a) * t;
+        TrajectoryState {
+            time,
+            position: from.position + (to.position - from.position) * t,
+            heading: angle::lerp(from.heading, to.heading, t),
+            velocity: lerp(from.velocity, to.velocity),
+            angular_velocity: lerp(from.angular_velocity, to.angular_velocity),
+        }
+    }
+
+    /// Returns the path the trajectory follows, for followers which don't use
+    /// time, like pure pursuit. Returns `None` if the trajectory doesn't
+    /// move.
+    pub fn to_path(&self) -> Option<SampledPath> {
+        let mut points: Vec<Point2<f64>> = Vec::with_capacity(self.states.len());
+        for state in &self.states {
+            if points.last() != Some(&state.position) {
+                points.push(state.position);
+            }
+        }
+        (points.len() >= 2).then(|| SampledPath::new(points))
+    }
+
+    /// Loads a Choreo `.traj` file.
+    pub fn load_choreo(path: &str) -> Result<Self, TrajectoryError> {
+        Self::load(path, Self::parse_choreo)
+    }
+
+    /// Loads a PathPlanner trajectory exported as WPILib trajectory JSON.
+    pub fn load_path_planner(path: &str) -> Result<Self, TrajectoryError> {
+        Self::load(path, Self::parse_path_planner)
+    }
+
+    fn load(
+        path: &str,
+        parse: fn(&str, &str) -> Result<Self, TrajectoryError>,
+    ) -> Result<Self, TrajectoryError> {
+        let text = std::fs::read_to_string(path).map_err(|source| TrajectoryError::Io {
+            path: path.to_string(),
+            source,
+        })?;
+        let trajectory = parse(path, &text)?;
+        log::info!(
+            "Loaded {} trajectory states ({:?}) from {}",
+            trajectory.states.len(),
+            trajectory.duration(),
+            path
+        );
+        Ok(trajectory)
+    }
+
+    /// Parses the text of a Choreo `.traj` file. `path` is only used in error
+    /// messages.
+    pub fn parse_choreo(path: &str, text: &str) -> Result<Self, TrajectoryError> {
+        let root = parse_json(path, text)?;
+        let samples = root
+            .get("trajectory")
+            .and_then(|trajectory| trajectory.get("samples"))
+            .or_else(|| root.get("samples"))
+            .and_then(JsonValue::as_array)
+            .ok_or_else(|| format_error(path, "no samples"))?;
+
+        let states = samples
+            .iter()
+            .enumerate()
+            .map(|(index, sample)| {
+                let field = |names: &[&str]| field(path, index, sample, names);
+                let heading = field(&["heading"])?;
+                let velocity = match (sample.get("vl"), sample.get("vr")) {
+                    // Differential samples have wheel velocities
+                    (Some(_), Some(_)) => (field(&["vl"])? + field(&["vr"])?) / 2.0,
+                    // Swerve samples have field-relative velocities, so take
+                    // the component along the heading
+                    _ => {
+                        field(&["vx", "velocityX"])? * heading.cos()
+                            + field(&["vy", "velocityY"])? * heading.sin()
+                    }
+                };
+                Ok(TrajectoryState {
+                    time: seconds(path, index, field(&["t", "timestamp"])?)?,
+                    position: Point2::new(field(&["x"])?, field(&["y"])?) * MM_PER_METER,
+                    heading: Angle::from_radians(heading),
+                    velocity: velocity * MM_PER_METER,
+                    angular_velocity: field(&["omega", "angularVelocity"])?,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        from_states(path, states)
+    }
+
+    /// Parses the text of a WPILib trajectory JSON file, as exported by
+    /// PathPlanner. `path` is only used in error messages.
+    pub fn parse_path_planner(path: &str, text: &str) -> Result<Self, TrajectoryError> {
+        let root = parse_json(path, text)?;
+        let samples = root
+            .as_array()
+            .or_else(|| root.get("states").and_then(JsonValue::as_array))
+            .ok_or_else(|| format_error(path, "expected an array of states"))?;
+
+        let states = samples
+            .iter()
+            .enumerate()
+            .map(|(index, sample)| {
+                let pose = sample
+                    .get("pose")
+                    .ok_or_else(|| format_error(path, &format!("state {}: no pose", index)))?;
+                let translation = pose.get("translation").unwrap_or(&JsonValue::Null);
+                let rotation = pose.get("rotation").unwrap_or(&JsonValue::Null);
+                let velocity = field(path, index, sample, &["velocity"])?;
+                Ok(TrajectoryState {
+                    time: seconds(path, index, field(path, index, sample, &["time"])?)?,
+                    position: Point2::new(
+                        field(path, index, translation, &["x"])?,
+                        field(path, index, translation, &["y"])?,
+                    ) * MM_PER_METER,
+                    heading: Angle::from_radians(field(path, index, rotation, &["radians"])?),
+                    velocity: velocity * MM_PER_METER,
+                    // Curvature is in rad/m, so this is already in rad/s
+                    angular_velocity: velocity * field(path, index, sample, &["curvature"])?,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        from_states(path, states)
+    }
+}
+
+fn format_error(path: &str, message: &str) -> TrajectoryError {
+    TrajectoryError::Format {
+        path: path.to_string(),
+        message: message.to_string(),
+    }
+}
+
+fn parse_json(path: &str, text: &str) -> Result<JsonValue, TrajectoryError> {
+    JsonValue::parse(text).map_err(|source| TrajectoryError::Json {
+        path: path.to_string(),
+        source,
+    })
+}
+
+/// Returns the first of `names` which `value` has as a number.
+fn field(
+    path: &str,
+    index: usize,
+    value: &JsonValue,
+    names: &[&str],
+) -> Result<f64, TrajectoryError> {
+    names
+        .iter()
+        .find_map(|name| value.get(name))
+        .and_then(JsonValue::as_f64)
+        .ok_or_else(|| format_error(path, &format!("state {}: no number {:?}", index, names[0])))
+}
+
+/// Converts a timestamp to a duration, clamping negative times to zero.
+/// Fails if the time isn't finite or too large for a [`Duration`].
+fn seconds(path: &str, index: usize, seconds: f64) -> Result<Duration, TrajectoryError> {
+    let error = || format_error(path, &format!("state {}: invalid time {}", index, seconds));
+    if !seconds.is_finite() {
+        return Err(error());
+    }
+    Duration::try_from_secs_f64(seconds.max(0.0)).map_err(|_| error())
+}
+
+fn from_states(path: &str, states: Vec<TrajectoryState>) -> Result<Trajectory, TrajectoryError> {
+    Trajectory::new(states).map_err(|err| match err {
+        TrajectoryError::NoStates => format_error(path, "no states"),
+        TrajectoryError::OutOfOrder { index } => format_error(
+            path,
+            &format!("state {} is earlier than the one before it", index),
+        ),
+        err => err,
+    })
+}
@@ -0,0 +1,195 @@
+use core::{cell::RefCell, future::Future, time::Duration};
+
+use alloc::{boxed::Box, rc::Rc};
+use vexide::{adi::digital::AdiDigitalIn, math::Angle};
+
+use crate::{
+    motorgroup::{DoxaMotorGroup, StallThreshold},
+    utils::{
+        profiling,
+        ticker::{LOOP_PERIOD, Ticker},
+        traits::HasRotation,
+        unwrap_expect_report::UnwrapExpectReportExt as _,
+    },
+};
+
+/// How a [`CatapultSubsystem`] knows that it is cocked.
+pub enum CockedSensor {
+    /// A limit switch which is pressed while the catapult is cocked.
+    LimitSwitch(AdiDigitalIn),
+    /// A rotation sensor on the catapult's drive axle.
+    ///
+    /// The catapult is cocked while the sensor's position, wrapped to one
+    /// revolution, is between `cocked` and `cocked + window`. This works for
+    /// slip-gear and ratchet mechanisms which turn continuously in one
+    /// direction.
+    Rotation {
+        sensor: Box<dyn HasRotation>,
+        cocked: Angle,
+        window: Angle,
+    },
+}
+
+impl CockedSensor {
+    fn is_cocked(&self) -> bool {
+        match self {
+            CockedSensor::LimitSwitch(switch) => switch.is_high().unwrap_or(false),
+            CockedSensor::Rotation {
+                sensor,
+                cocked,
+                window,
+            } => (sensor.position() - *cocked).wrapped_full() < *window,
+        }
+    }
+}
+
+/// The state of a [`CatapultSubsystem`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CatapultState {
+    /// The catapult is being driven down to the cocked position.
+    Cocking,
+    /// The catapult is cocked and ready to fire.
+    Ready,
+    /// The catapult is being driven past the cocked position to release.
+    Firing,
+    /// The motors stalled while cocking or firing and were stopped. Call
+    /// [`CatapultSubsystem::reload`] to try again.
+    Stalled,
+}
+
+#[derive(Debug)]
+struct CatapultInner {
+    state: CatapultState,
+    /// The number of times the catapult has released since it was created
+    shots: u32,
+}
+
+/// A catapult or puncher driven by a motor group until a sensor reports that
+/// it is cocked.
+///
+/// The mechanism is cocked automatically as soon as it is created and after
+/// every shot. [`fire`](Self::fire) releases it, and [`ready`](Self::ready)
+/// waits until it is cocked again.
+///
+/// If the motors stay below `stall_velocity` RPM for longer than
+/// `stall_duration` while driving, they are stopped to protect them and the
+/// catapult enters [`CatapultState::Stalled`].
+#[derive(Debug, Clone)]
+pub struct CatapultSubsystem {
+    inner: Rc<RefCell<CatapultInner>>,
+    _task: Rc<vexide::task::Task<()>>,
+}
+
+impl CatapultSubsystem {
+    /// Creates a new catapult subsystem and starts cocking it.
+    ///
+    /// `voltage` is the voltage used to cock and fire the catapult.
+    pub fn new(
+        mut motors: DoxaMotorGroup,
+        sensor: CockedSensor,
+        voltage: f64,
+        stall_velocity: f64,
+        stall_duration: Duration,
+    ) -> Self {
+        let inner = Rc::new(RefCell::new(CatapultInner {
+            state: CatapultState::Cocking,
+            shots: 0,
+        }));
+        // Half the driving voltage, so that the stall timer stops as soon as
+        // the motors are stopped
+        let stall_threshold = StallThreshold::new(voltage.abs() / 2.0, stall_velocity);
+        Self {
+            inner: inner.clone(),
+            _task: Rc::new(vexide::task::spawn(async move {
+                let mut ticker = Ticker::new(LOOP_PERIOD);
+                loop {
+                    let scope = profiling::scope("catapult");
+                    {
+                        let mut inner = inner.borrow_mut();
+                        let cocked = sensor.is_cocked();
+                        match inner.state {
+                            CatapultState::Cocking if cocked => {
+                                inner.state = CatapultState::Ready;
+                            }
+                            CatapultState::Firing if !cocked => {
+                                // Released; immediately start reloading
+                                inner.shots += 1;
+                                inner.state = CatapultState::Cocking;
+                            }
+                            _ => {}
+                        }
+
+                        let driving =
+                            matches!(inner.state, CatapultState::Cocking | CatapultState::Firing);
+                        if driving {
+                            motors
+                                .set_voltage(voltage)
+                                .expect_report("failed to set catapult voltage");
+                            if motors.is_stalled(stall_threshold, stall_duration) {
+                                log::error!(
+                                    "Catapult stalled while {:?}; stopping motors",
+                                    inner.state
+                                );
+                                inner.state = CatapultState::Stalled;
+                            }
+                        } else {
+                            motors
+                                .set_voltage(0.0)
+                                .expect_report("failed to stop catapult");
+                        }
+                    }
+                    drop(scope);
+                    ticker.tick().await;
+                }
+            })),
+        }
+    }
+
+    /// Fires the catapult if it is ready. Returns whether it was fired.
+    ///
+    /// The catapult re-cocks automatically after it is released.
+    pub fn fire(&mut self) -> bool {
+        let mut inner = self.inner.borrow_mut();
+        if inner.state == CatapultState::Ready {
+            inner.state = CatapultState::Firing;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Starts cocking the catapult again after it stalled.
+    ///
+    /// Does nothing unless the catapult is [`CatapultState::Stalled`].
+    pub fn reload(&mut self) {
+        let mut inner = self.inner.borrow_mut();
+        if inner.state == CatapultState::Stalled {
+            inner.state = CatapultState::Cocking;
+        }
+    }
+
+    /// Returns the current state of the catapult.
+    pub fn state(&self) -> CatapultState {
+        self.inner.borrow().state
+    }
+
+    /// Returns whether the catapult is cocked and ready to fire.
+    pub fn is_ready(&self) -> bool {
+        self.state() == CatapultState::Ready
+    }
+
+    /// Waits until the catapult is cocked and ready to fire.
+    pub fn ready(&self) -> impl Future<Output = ()> + 'static {
+        let catapult = self.clone();
+        async move {
+            while !catapult.is_ready() {
+                vexide::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+    }
+
+    /// Returns how many times the catapult has been fired.
+    pub fn shots(&self) -> u32 {
+        self.inner.borrow().shots
+    }
+}
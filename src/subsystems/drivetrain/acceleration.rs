@@ -0,0 +1,149 @@
+//! Acceleration limits for the drivetrain output
+//!
+//! The drivetrain limits how fast RPM targets change with the chassis's
+//! [`max_acceleration`](super::ChassisConfig::max_acceleration). An action
+//! wrapped in a [`LimitedAction`] can override that, and limit how fast
+//! voltages change, with an [`AccelerationLimit`], e.g., to carry a mobile goal
+//! gently in the same routine as a fast rush:
+//!
+//! ```ignore
+//! let limit = AccelerationLimit::new()
+//!     .with_voltage(Ramp::new(24.0, 48.0))
+//!     .with_rpm(Ramp::new(600.0, 1200.0));
+//! drivetrain.action(LimitedAction::new(ForwardAction::new(600.0, config), limit)).await;
+//! ```
+//!
+//! Turning can be limited separately from driving forwards, by limiting how
+//! fast the difference between the sides changes. This keeps fast turns from
+//! snapping the heading and flinging a clamped goal, without slowing down
+//! straight drives:
+//!
+//! ```ignore
+//! // Every action, in RPM/s of half the difference between the sides
+//! drivetrain.set_angular_acceleration(Some(Ramp::new(400.0, 800.0)));
+//! // Or only while carrying a goal
+//! let limit = AccelerationLimit::new().with_angular_voltage(Ramp::symmetric(20.0));
+//! ```
+//!
+//! [`LimitedAction`]: super::actions::LimitedAction
+
+use core::time::Duration;
+
+/// How fast an output may speed up and slow down, in its units per second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ramp {
+    /// The limit while the output grows in magnitude
+    pub acceleration: f64,
+    /// The limit while the output shrinks in magnitude or changes sign
+    pub deceleration: f64,
+}
+
+impl Ramp {
+    /// Creates a ramp with separate acceleration and deceleration limits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either limit is negative or NaN.
+    pub fn new(acceleration: f64, deceleration: f64) -> Self {
+        assert!(
+            acceleration >= 0.0 && deceleration >= 0.0,
+            "Invalid ramp limits: {}, {}",
+            acceleration,
+            deceleration
+        );
+        Self {
+            acceleration,
+            deceleration,
+        }
+    }
+
+    /// Creates a ramp with the same limit both ways.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limit` is negative or NaN.
+    pub fn symmetric(limit: f64) -> Self {
+        Self::new(limit, limit)
+    }
+
+    /// Returns `target`, limited to the change allowed from `last` in `dt`.
+    pub fn step(&self, last: f64, target: f64, dt: Duration) -> f64 {
+        let speeding_up = target.abs() > last.abs() && (last == 0.0 || target * last > 0.0);
+        let rate = if speeding_up {
+            self.acceleration
+        } else {
+            self.deceleration
+        };
+        let max_step = rate * dt.as_secs_f64();
+        // Not clamp, which panics if the fields were set to a negative or NaN
+        // rate
+        target.max(last - max_step).min(last + max_step)
+    }
+
+    /// Returns the `left` and `right` targets with the change in their
+    /// turning part, half their difference, limited from `last`. The
+    /// forward part, their mean, is unchanged.
+    pub fn step_angular(
+        &self,
+        last: (f64, f64),
+        left: f64,
+        right: f64,
+        dt: Duration,
+    ) -> (f64, f64) {
+        let linear = (left + right) / 2.0;
+        let angular = self.step((last.0 - last.1) / 2.0, (left - right) / 2.0, dt);
+        (linear + angular, linear - angular)
+    }
+}
+
+/// Acceleration limits for an action's output, by unit. See the
+/// [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct AccelerationLimit {
+    /// The limit on voltage outputs, in volts per second, or `None` for no
+    /// limit
+    pub voltage: Option<Ramp>,
+    /// The limit on RPM outputs, in RPM per second, or `None` for the
+    /// drivetrain's `max_acceleration`
+    pub rpm: Option<Ramp>,
+    /// The limit on the turning part of voltage outputs, half the difference
+    /// between the sides, in volts per second, or `None` for no limit
+    pub angular_voltage: Option<Ramp>,
+    /// The limit on the turning part of RPM outputs, in RPM per second, or
+    /// `None` for the drivetrain's
+    /// [angular acceleration](super::Drivetrain::set_angular_acceleration)
+    pub angular_rpm: Option<Ramp>,
+}
+
+impl AccelerationLimit {
+    /// Creates a limit which changes nothing from the drivetrain's defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits how fast voltage outputs change.
+    pub fn with_voltage(mut self, ramp: Ramp) -> Self {
+        self.voltage = Some(ramp);
+        self
+    }
+
+    /// Limits how fast RPM outputs change, instead of the drivetrain's
+    /// `max_acceleration`.
+    pub fn with_rpm(mut self, ramp: Ramp) -> Self {
+        self.rpm = Some(ramp);
+        self
+    }
+
+    /// Limits how fast the turning part of voltage outputs changes.
+    pub fn with_angular_voltage(mut self, ramp: Ramp) -> Self {
+        self.angular_voltage = Some(ramp);
+        self
+    }
+
+    /// Limits how fast the turning part of RPM outputs changes, instead of
+    /// the drivetrain's angular acceleration.
+    pub fn with_angular_rpm(mut self, ramp: Ramp) -> Self {
+        self.angular_rpm = Some(ramp);
+        self
+    }
+}
@@ -1,16 +1,27 @@
 use core::fmt::Debug;
 
+use vexide::math::Angle;
+
 use crate::subsystems::tracking::TrackingData;
 
+mod acquire;
+mod align_to_wall;
+mod analyzed;
+mod any;
 mod boomerang;
 pub mod config;
 mod drive_to_point;
 mod forward;
 mod lazy;
+mod limited;
+mod navigate;
+mod obstacle;
 mod pure_pursuit;
+mod replay;
 mod rotation;
 mod seeking;
 mod turn_to_point;
+mod velocity;
 mod voltage;
 
 /// A drivetrain action.
@@ -22,6 +33,53 @@ mod voltage;
 pub trait Action: Debug {
     /// Updates the action and returns the desired drivetrain output.
     fn update(&mut self, context: ActionContext) -> Option<super::DrivetrainPair>;
+
+    /// Returns the error and output of the action's primary controller as of
+    /// the last call to [`update`](Self::update), if the action has one.
+    ///
+    /// This is used for debugging and tuning, e.g., to plot the error over
+    /// time. The default implementation returns `None`.
+    fn telemetry(&self) -> Option<ActionTelemetry> {
+        None
+    }
+
+    /// Called when the drivetrain overrides the action's output, e.g., when
+    /// the robot starts to tip. The default implementation does nothing.
+    fn on_event(&mut self, event: ActionEvent) {
+        _ = event;
+    }
+
+    /// Returns the acceleration limit to apply to the action's output instead
+    /// of the drivetrain's. The default implementation returns `None`. See
+    /// [`LimitedAction`].
+    fn acceleration_limit(&self) -> Option<super::acceleration::AccelerationLimit> {
+        None
+    }
+    /// Returns the name of the action, for logs and traces. The default
+    /// implementation returns the type name.
+    fn name(&self) -> &'static str {
+        core::any::type_name::<Self>()
+    }
+}
+
+/// Something the drivetrain did to the running action.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ActionEvent {
+    /// The robot tipped past the [`TipGuard`](super::tip_guard::TipGuard)
+    /// threshold, so the drivetrain is overriding the action's output.
+    Tipping { pitch: Angle, roll: Angle },
+    /// The robot is level again, so the action's output is used again.
+    Recovered,
+}
+
+/// A snapshot of an action's primary controller.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ActionTelemetry {
+    /// The error fed into the controller, in mm for linear controllers and
+    /// radians for angular controllers.
+    pub error: f64,
+    /// The output of the controller.
+    pub output: f64,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -29,12 +87,21 @@ pub struct ActionContext {
     pub data: TrackingData,
 }
 
+pub use acquire::AcquireAction;
+pub use align_to_wall::AlignToWallAction;
+pub use analyzed::AnalyzedAction;
+pub use any::AnyAction;
 pub use boomerang::BoomerangAction;
-pub use drive_to_point::DriveToPointAction;
+pub use drive_to_point::{ApproachDirection, DriveToPointAction};
 pub use forward::ForwardAction;
 pub use lazy::LazyAction;
+pub use limited::LimitedAction;
+pub use navigate::NavigateAction;
+pub use obstacle::ObstacleStop;
 pub use pure_pursuit::PurePursuitAction;
+pub use replay::ReplayAction;
 pub use rotation::RotationAction;
 pub use seeking::SeekingAction;
 pub use turn_to_point::TurnToPointAction;
+pub use velocity::VelocityOutput;
 pub use voltage::VoltageAction;
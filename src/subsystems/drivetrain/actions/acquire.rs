@@ -0,0 +1,206 @@
+use core::{fmt::Debug, time::Duration};
+use std::time::Instant;
+
+use alloc::boxed::Box;
+use nalgebra::Point2;
+
+use crate::subsystems::{
+    drivetrain::DrivetrainPair,
+    vision::{LocalizedObject, VisionSubsystem},
+};
+
+use super::{SeekingAction, config::ActionConfig};
+
+/// An action that drives to the nearest game element seen by a
+/// [`VisionSubsystem`] and intakes it.
+///
+/// The action seeks the nearest element with the given label, turns the
+/// intake on once within the intake distance, and finishes as soon as the
+/// acquired sensor reports the element. While driving, it follows the element
+/// if it moves, and re-targets the next nearest element if it disappears for
+/// longer than the lost timeout. It gives up, turning the intake off, if
+/// nothing has been acquired before the timeout.
+pub struct AcquireAction {
+    vision: VisionSubsystem,
+    label: &'static str,
+    config: ActionConfig,
+    intake: Box<dyn FnMut(bool)>,
+    acquired: Box<dyn FnMut() -> bool>,
+
+    intake_distance: f64,
+    retarget_distance: f64,
+    lost_timeout: Duration,
+    timeout: Duration,
+
+    target: Option<Point2<f64>>,
+    last_seen: Option<Instant>,
+    seeking: Option<SeekingAction>,
+    intaking: bool,
+    start: Option<Instant>,
+}
+
+impl Debug for AcquireAction {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("AcquireAction")
+            .field("label", &self.label)
+            .field("target", &self.target)
+            .field("seeking", &self.seeking)
+            .field("intaking", &self.intaking)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AcquireAction {
+    /// Creates a new acquire action.
+    ///
+    /// `intake` is called with `true` to start the intake and `false` to stop
+    /// it, and `acquired` should return whether the element has been intaken,
+    /// e.g., from a distance or optical sensor.
+    pub fn new(
+        vision: VisionSubsystem,
+        label: &'static str,
+        config: ActionConfig,
+        intake: impl FnMut(bool) + 'static,
+        acquired: impl FnMut() -> bool + 'static,
+    ) -> Self {
+        Self {
+            vision,
+            label,
+            config,
+            intake: Box::new(intake),
+            acquired: Box::new(acquired),
+            intake_distance: 400.0,
+            retarget_distance: 100.0,
+            lost_timeout: Duration::from_millis(300),
+            timeout: Duration::from_secs(3),
+            target: None,
+            last_seen: None,
+            seeking: None,
+            intaking: false,
+            start: None,
+        }
+    }
+
+    /// Sets the distance in mm from the element at which the intake starts.
+    pub fn with_intake_distance(mut self, intake_distance: f64) -> Self {
+        self.intake_distance = intake_distance;
+        self
+    }
+
+    /// Sets how far in mm a sighting can be from the current target and still
+    /// be considered the same element.
+    pub fn with_retarget_distance(mut self, retarget_distance: f64) -> Self {
+        self.retarget_distance = retarget_distance;
+        self
+    }
+
+    /// Sets how long the target can go unseen before another element is
+    /// targeted.
+    pub fn with_lost_timeout(mut self, lost_timeout: Duration) -> Self {
+        self.lost_timeout = lost_timeout;
+        self
+    }
+
+    /// Sets how long to try before giving up.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn set_intake(&mut self, intaking: bool) {
+        if self.intaking != intaking {
+            self.intaking = intaking;
+            (self.intake)(intaking);
+        }
+    }
+
+    /// Picks the sighting to drive to: the current target if it is still
+    /// seen, even if it moved, or otherwise the nearest element once the
+    /// current target has been lost for long enough.
+    fn select_target(&mut self) -> Option<LocalizedObject> {
+        let objects = self.vision.objects_labeled(self.label);
+        if let Some(target) = self.target {
+            let tracked = objects
+                .iter()
+                .filter(|object| {
+                    nalgebra::distance(&object.position, &target) < self.retarget_distance
+                })
+                .min_by(|a, b| {
+                    nalgebra::distance(&a.position, &target)
+                        .total_cmp(&nalgebra::distance(&b.position, &target))
+                });
+            if let Some(tracked) = tracked {
+                return Some(*tracked);
+            }
+            if self
+                .last_seen
+                .is_some_and(|last_seen| last_seen.elapsed() < self.lost_timeout)
+            {
+                return None;
+            }
+        }
+        objects
+            .into_iter()
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+}
+
+impl super::Action for AcquireAction {
+    fn update(&mut self, context: super::ActionContext) -> Option<DrivetrainPair> {
+        let start = *self.start.get_or_insert_with(Instant::now);
+        if (self.acquired)() {
+            log::info!(
+                "Acquired {:?} after {} ms",
+                self.label,
+                start.elapsed().as_millis()
+            );
+            self.set_intake(false);
+            return None;
+        }
+        if start.elapsed() > self.timeout {
+            log::warn!("Gave up acquiring {:?}", self.label);
+            self.set_intake(false);
+            return None;
+        }
+
+        if let Some(sighting) = self.select_target() {
+            let moved = self.target.is_none_or(|target| {
+                nalgebra::distance(&sighting.position, &target) > self.retarget_distance / 2.0
+            });
+            if self.target.is_some() && moved {
+                log::debug!("Re-targeting {:?} at {:?}", self.label, sighting.position);
+            }
+            if moved || self.seeking.is_none() {
+                self.seeking = Some(SeekingAction::new(sighting.position, self.config));
+            }
+            self.target = Some(sighting.position);
+            self.last_seen = Some(Instant::now());
+        }
+
+        let Some(target) = self.target else {
+            // Nothing seen yet
+            return Some(DrivetrainPair::new_voltage(0.0, 0.0));
+        };
+        if nalgebra::distance(&context.data.offset, &target) < self.intake_distance {
+            self.set_intake(true);
+        }
+        match self
+            .seeking
+            .as_mut()
+            .and_then(|seeking| seeking.update(context))
+        {
+            Some(output) => Some(output),
+            None => {
+                // Arrived, but not acquired yet: wait for the intake
+                self.seeking = None;
+                Some(DrivetrainPair::new_voltage(0.0, 0.0))
+            }
+        }
+    }
+
+    fn telemetry(&self) -> Option<super::ActionTelemetry> {
+        self.seeking
+            .as_ref()
+            .and_then(|seeking| seeking.telemetry())
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{
+    subsystems::{drivetrain::DrivetrainPair, wall::WallSensors},
+    utils::{controllers::PidController, settling::Tolerances},
+};
+
+use super::config::ActionConfig;
+
+/// An action that drives square to a wall, a certain distance away, using
+/// [`WallSensors`].
+///
+/// The action ignores the tracking pose and works purely from the distance
+/// sensors. It stops driving while the wall can't be seen, and finishes once
+/// both the distance and angle have settled, or the linear timeout elapses.
+#[derive(Debug)]
+pub struct AlignToWallAction {
+    sensors: WallSensors,
+    distance: f64,
+    linear_pid: PidController,
+    angular_pid: PidController,
+    linear_tolerances: Tolerances,
+    angular_tolerances: Tolerances,
+    last_measurement: Option<(f64, f64)>,
+    telemetry: Option<super::ActionTelemetry>,
+}
+
+impl AlignToWallAction {
+    /// Creates a new action which drives to `distance` mm from the wall.
+    pub fn new(sensors: WallSensors, distance: f64, config: ActionConfig) -> Self {
+        Self {
+            sensors,
+            distance,
+            linear_pid: config.linear_pid(0.0),
+            angular_pid: config.turn_pid(0.0),
+            linear_tolerances: config.linear_tolerances(),
+            angular_tolerances: config.turn_tolerances(),
+            last_measurement: None,
+            telemetry: None,
+        }
+    }
+}
+
+impl super::Action for AlignToWallAction {
+    fn update(&mut self, context: super::ActionContext) -> Option<DrivetrainPair> {
+        let Some(measurement) = self.sensors.measurement() else {
+            self.last_measurement = None;
+            // Still check the tolerances so that the timeout applies
+            if self.linear_tolerances.check(f64::INFINITY, 0.0) {
+                log::warn!("Timed out waiting to see the wall");
+                return None;
+            }
+            return Some(DrivetrainPair::new_voltage(0.0, 0.0));
+        };
+        let error_distance = measurement.distance - self.distance;
+        let error_angular = -measurement.angle.as_radians();
+
+        // Velocities from the change in the measurement, since the tracking
+        // velocity isn't relative to the wall
+        let dt = context.data.dt.as_secs_f64();
+        let (linear_velocity, angular_velocity) = match self.last_measurement {
+            Some((distance, angle)) if dt > 0.0 => (
+                (measurement.distance - distance) / dt,
+                (measurement.angle.as_radians() - angle) / dt,
+            ),
+            _ => (0.0, 0.0),
+        };
+        self.last_measurement = Some((measurement.distance, measurement.angle.as_radians()));
+
+        let linear_settled = self
+            .linear_tolerances
+            .check(error_distance, linear_velocity);
+        let angular_settled = self
+            .angular_tolerances
+            .check(error_angular, angular_velocity);
+        if linear_settled && angular_settled {
+            return None;
+        }
+
+        // Driving forwards closes the distance if the sensors face forwards
+        let direction = if self.sensors.face().cos() >= 0.0 {
+            1.0
+        } else {
+            -1.0
+        };
+        let output_linear = self.linear_pid.next_control_output(-error_distance).output * direction;
+        let output_angular = self.angular_pid.next_control_output(error_angular).output;
+        self.telemetry = Some(super::ActionTelemetry {
+            error: error_distance,
+            output: output_linear,
+        });
+
+        Some(DrivetrainPair::new_voltage(
+            output_linear - output_angular,
+            output_linear + output_angular,
+        ))
+    }
+
+    fn telemetry(&self) -> Option<super::ActionTelemetry> {
+        self.telemetry
+    }
+}
@@ -0,0 +1,87 @@
+use crate::{
+    path_planner::{Path, analysis::CrossTrackStats},
+    subsystems::drivetrain::{DrivetrainPair, acceleration::AccelerationLimit},
+};
+
+use super::Action;
+
+/// Runs an action and logs how far the robot strayed from `path` once it
+/// ends, whether it finished or was cancelled. See
+/// [`analysis`](crate::path_planner::analysis).
+///
+/// Each update measures the pose against the path near the closest point
+/// from the last update, rather than searching the whole path, so the
+/// analysis costs the control loop a few path evaluations per update and
+/// nothing when the action ends. A robot which strays far enough to be closer
+/// to a different part of the path may be measured against the wrong part.
+#[derive(Debug)]
+pub struct AnalyzedAction<T: Action, P: Path> {
+    action: T,
+    path: P,
+    /// The t of the closest point on the path at the last update
+    closest_t: f64,
+    stats: CrossTrackStats,
+    reported: bool,
+}
+
+impl<T: Action, P: Path> AnalyzedAction<T, P> {
+    /// Wraps `action`, which is expected to follow `path`.
+    pub fn new(action: T, path: P) -> Self {
+        Self {
+            action,
+            path,
+            closest_t: 0.0,
+            stats: CrossTrackStats::default(),
+            reported: false,
+        }
+    }
+
+    fn report(&mut self) {
+        if self.reported {
+            return;
+        }
+        self.reported = true;
+        if let Some(report) = self.stats.report(&self.path) {
+            report.log(self.action.name());
+        }
+    }
+}
+
+impl<T: Action, P: Path> Action for AnalyzedAction<T, P> {
+    fn update(&mut self, context: super::ActionContext) -> Option<DrivetrainPair> {
+        if !self.reported {
+            let point = context.data.offset;
+            self.closest_t = self.path.closest_point(point, Some(self.closest_t), None);
+            let error = nalgebra::distance(&self.path.evaluate(self.closest_t), &point);
+            self.stats.add(self.closest_t, error);
+        }
+        let output = self.action.update(context);
+        if output.is_none() {
+            self.report();
+        }
+        output
+    }
+
+    fn telemetry(&self) -> Option<super::ActionTelemetry> {
+        self.action.telemetry()
+    }
+
+    fn on_event(&mut self, event: super::ActionEvent) {
+        self.action.on_event(event);
+    }
+
+    fn acceleration_limit(&self) -> Option<AccelerationLimit> {
+        self.action.acceleration_limit()
+    }
+
+    fn name(&self) -> &'static str {
+        self.action.name()
+    }
+}
+
+impl<T: Action, P: Path> Drop for AnalyzedAction<T, P> {
+    fn drop(&mut self) {
+        // Cancelled before finishing
+        self.report();
+    }
+}
@@ -0,0 +1,133 @@
+use alloc::boxed::Box;
+
+use crate::{
+    path_planner::Path,
+    subsystems::drivetrain::{DrivetrainPair, acceleration::AccelerationLimit},
+};
+
+use super::{
+    AcquireAction, Action, ActionContext, ActionEvent, ActionTelemetry, AlignToWallAction,
+    AnalyzedAction, BoomerangAction, DriveToPointAction, ForwardAction, LazyAction, LimitedAction,
+    NavigateAction, PurePursuitAction, ReplayAction, RotationAction, SeekingAction,
+    TurnToPointAction, VoltageAction,
+};
+
+macro_rules! any_action {
+    ($($variant:ident($action:ty)),* $(,)?) => {
+        /// Any of the built-in actions, or a boxed custom action.
+        ///
+        /// The drivetrain stores its current action as an `AnyAction`, so
+        /// that starting a built-in action doesn't allocate. Every built-in
+        /// action converts into one with [`From`]. Generic actions, like
+        /// [`PurePursuitAction`], and custom actions are boxed.
+        // Boxing the large variants would allocate, which is what this is
+        // for avoiding; there is only ever one of these in the slot
+        #[allow(clippy::large_enum_variant)]
+        #[derive(Debug)]
+        pub enum AnyAction {
+            $($variant($action),)*
+            Boxed(Box<dyn Action>),
+        }
+
+        impl Action for AnyAction {
+            fn update(&mut self, context: ActionContext) -> Option<DrivetrainPair> {
+                match self {
+                    $(Self::$variant(action) => action.update(context),)*
+                    Self::Boxed(action) => action.update(context),
+                }
+            }
+
+            fn telemetry(&self) -> Option<ActionTelemetry> {
+                match self {
+                    $(Self::$variant(action) => action.telemetry(),)*
+                    Self::Boxed(action) => action.telemetry(),
+                }
+            }
+
+            fn on_event(&mut self, event: ActionEvent) {
+                match self {
+                    $(Self::$variant(action) => action.on_event(event),)*
+                    Self::Boxed(action) => action.on_event(event),
+                }
+            }
+
+            fn acceleration_limit(&self) -> Option<AccelerationLimit> {
+                match self {
+                    $(Self::$variant(action) => action.acceleration_limit(),)*
+                    Self::Boxed(action) => action.acceleration_limit(),
+                }
+            }
+
+            fn name(&self) -> &'static str {
+                match self {
+                    $(Self::$variant(action) => action.name(),)*
+                    Self::Boxed(action) => action.name(),
+                }
+            }
+        }
+
+        $(
+            impl From<$action> for AnyAction {
+                fn from(action: $action) -> Self {
+                    Self::$variant(action)
+                }
+            }
+        )*
+    };
+}
+
+any_action!(
+    Acquire(AcquireAction),
+    AlignToWall(AlignToWallAction),
+    Boomerang(BoomerangAction),
+    DriveToPoint(DriveToPointAction),
+    Forward(ForwardAction),
+    Replay(ReplayAction),
+    Rotation(RotationAction),
+    Seeking(SeekingAction),
+    TurnToPoint(TurnToPointAction),
+    Voltage(VoltageAction),
+);
+
+impl AnyAction {
+    /// Boxes a custom action.
+    pub fn boxed(action: impl Action + 'static) -> Self {
+        Self::Boxed(Box::new(action))
+    }
+}
+
+impl From<Box<dyn Action>> for AnyAction {
+    fn from(action: Box<dyn Action>) -> Self {
+        Self::Boxed(action)
+    }
+}
+
+impl<T: Path + 'static> From<PurePursuitAction<T>> for AnyAction {
+    fn from(action: PurePursuitAction<T>) -> Self {
+        Self::boxed(action)
+    }
+}
+
+impl From<NavigateAction> for AnyAction {
+    fn from(action: NavigateAction) -> Self {
+        Self::boxed(action)
+    }
+}
+
+impl<T: Action + 'static> From<LazyAction<T>> for AnyAction {
+    fn from(action: LazyAction<T>) -> Self {
+        Self::boxed(action)
+    }
+}
+
+impl<T: Action + 'static> From<LimitedAction<T>> for AnyAction {
+    fn from(action: LimitedAction<T>) -> Self {
+        Self::boxed(action)
+    }
+}
+
+impl<T: Action + 'static, P: Path + 'static> From<AnalyzedAction<T, P>> for AnyAction {
+    fn from(action: AnalyzedAction<T, P>) -> Self {
+        Self::boxed(action)
+    }
+}
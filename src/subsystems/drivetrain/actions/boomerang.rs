@@ -1,13 +1,25 @@
 use nalgebra::{Point2, Vector2};
-use pid::Pid;
 use vexide::math::Angle;
 
-use crate::{subsystems::drivetrain::DrivetrainPair, utils::settling::Tolerances};
+use crate::{
+    subsystems::drivetrain::DrivetrainPair,
+    utils::{
+        angle,
+        controllers::PidController,
+        settling::{SettleReason, Tolerances},
+    },
+};
+
+use super::{RotationAction, config::ActionConfig};
 
 // Inspired by https://github.com/vexide/evian/blob/2c07838519f335f2308d7d1b869cb62363f635fb/packages/evian-motion/src/seeking/boomerang.rs
 
 /// An action that moves the drivetrain to a target point using a boomerang
 /// approach.
+///
+/// If [`ActionConfig::boomerang_heading_tolerance`] is set, the action turns
+/// in place once it reaches the point, until it is also within that
+/// tolerance of the target heading.
 #[derive(Debug, Clone, Copy)]
 pub struct BoomerangAction {
     target_point: Point2<f64>,
@@ -25,16 +37,20 @@ pub struct BoomerangAction {
 
     tolerances: Tolerances,
 
-    linear_pid: Pid<f64>,
-    angular_pid: Pid<f64>,
+    linear_pid: PidController,
+    angular_pid: PidController,
+
+    velocity_output: Option<super::VelocityOutput>,
+
+    config: ActionConfig,
+    /// Turns to the target heading once the point is reached
+    final_turn: Option<RotationAction>,
+
+    telemetry: Option<super::ActionTelemetry>,
 }
 
 impl BoomerangAction {
-    pub fn new(
-        target_point: Point2<f64>,
-        target_heading: Angle,
-        config: super::config::ActionConfig,
-    ) -> Self {
+    pub fn new(target_point: Point2<f64>, target_heading: Angle, config: ActionConfig) -> Self {
         Self {
             target_point,
             target_heading,
@@ -44,6 +60,10 @@ impl BoomerangAction {
             linear_pid: config.linear_pid(0.0),
             angular_pid: config.turn_pid(0.0),
             reverse: false,
+            velocity_output: None,
+            config,
+            final_turn: None,
+            telemetry: None,
         }
     }
 
@@ -55,10 +75,33 @@ impl BoomerangAction {
         self.reverse = true;
         self
     }
+
+    /// Outputs wheel RPM targets instead of voltages. See [`VelocityOutput`].
+    ///
+    /// [`VelocityOutput`]: super::VelocityOutput
+    pub fn with_velocity_output(mut self, velocity_output: super::VelocityOutput) -> Self {
+        self.velocity_output = Some(velocity_output);
+        self
+    }
+}
+
+impl BoomerangAction {
+    /// Converts an output to RPM if a velocity output is set.
+    fn output(&self, output: DrivetrainPair) -> DrivetrainPair {
+        match &self.velocity_output {
+            Some(velocity_output) => velocity_output.convert(output),
+            None => output,
+        }
+    }
 }
 
 impl super::Action for BoomerangAction {
     fn update(&mut self, context: super::ActionContext) -> Option<DrivetrainPair> {
+        if let Some(turn) = &mut self.final_turn {
+            let output = turn.update(context)?;
+            return Some(self.output(output));
+        }
+
         // Carrot -- what we're currently aiming for
         // We want to aim for a point ahead of the target point in the direction
         // of the target heading, scaled by the distance to the target point.
@@ -72,17 +115,18 @@ impl super::Action for BoomerangAction {
         };
 
         let local_target = carrot - context.data.offset;
-        let angle_to_target = Angle::from_radians(local_target.y.atan2(local_target.x));
+        let angle_to_target = angle::angle_to(context.data.offset, carrot);
 
         // Compute the angular angle
-        let error_angular = (angle_to_target
-            - if self.reverse {
+        let error_angular = angle::shortest_error(
+            angle_to_target,
+            if self.reverse {
                 // If we're reversed, we want to face backwards
                 context.data.heading + Angle::HALF_TURN
             } else {
                 context.data.heading
-            })
-        .wrapped_half();
+            },
+        );
         let (error_distance, close) = {
             // Find the "straight-line" distance to the target point
             // See subsystems::tracking::tracking_data for more information about
@@ -95,11 +139,34 @@ impl super::Action for BoomerangAction {
         };
 
         // Check tolerances
-        if self
+        match self
             .tolerances
-            .check(error_distance, context.data.linear_velocity())
+            .check_reason(error_distance, context.data.linear_velocity())
         {
-            return None;
+            Some(SettleReason::InTolerance) if self.config.boomerang_heading_tolerance > 0.0 => {
+                // The back of the robot faces the target heading when reversed
+                let target_heading = if self.reverse {
+                    self.target_heading + Angle::HALF_TURN
+                } else {
+                    self.target_heading
+                };
+                let heading_error =
+                    angle::shortest_error(target_heading, context.data.heading).as_radians();
+                if heading_error.abs() <= self.config.boomerang_heading_tolerance {
+                    return None;
+                }
+                log::debug!(
+                    "Boomerang: arrived {:.1}° off the target heading; turning",
+                    heading_error.to_degrees()
+                );
+                let config = self
+                    .config
+                    .with_turn_error_tolerance(self.config.boomerang_heading_tolerance);
+                self.final_turn = Some(RotationAction::new(target_heading.as_radians(), config));
+                return self.update(context);
+            }
+            Some(_) => return None,
+            None => {}
         }
 
         let output_angular = if close {
@@ -116,11 +183,23 @@ impl super::Action for BoomerangAction {
             * error_angular.cos().max(0.0)
             // If reversed, invert the linear output to drive backwards
             * if self.reverse { -1.0 } else { 1.0 };
+        self.telemetry = Some(super::ActionTelemetry {
+            error: error_distance,
+            output: output_linear,
+        });
 
-        Some(DrivetrainPair {
+        let output = DrivetrainPair {
             left: output_linear - output_angular,
             right: output_linear + output_angular,
             units: crate::subsystems::drivetrain::drivetrain_pair::DrivetrainUnits::Voltage,
-        })
+        };
+        Some(self.output(output))
+    }
+
+    fn telemetry(&self) -> Option<super::ActionTelemetry> {
+        match &self.final_turn {
+            Some(turn) => turn.telemetry(),
+            None => self.telemetry,
+        }
     }
 }
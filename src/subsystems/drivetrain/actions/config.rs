@@ -1,9 +1,34 @@
 use core::time::Duration;
 
-use pid::Pid;
+use snafu::Snafu;
 
-use crate::utils::settling::Tolerances;
+use crate::{
+    path_planner::DEFAULT_RADIUS_TOLERANCE,
+    subsystems::drivetrain::ChassisConfig,
+    utils::{
+        controllers::PidController,
+        settling::{Tolerances, TolerancesError},
+        units::{Millimeters, MillimetersPerSecond, RadiansPerSecond},
+    },
+};
 
+/// The maximum output of the controllers, in volts.
+const MAX_VOLTAGE: f64 = 12.0;
+
+/// The gains, limits, and tolerances shared by the drivetrain actions.
+///
+/// Linear errors are in mm, turn errors are in radians, and every output is
+/// in volts. Rather than filling in every field, start from a preset and
+/// adjust it with the builder methods:
+///
+/// - [`ActionConfig::default`] is a reasonable starting point for a typical
+///   chassis.
+/// - [`ActionConfig::conservative`] is slower and more precise.
+/// - [`ActionConfig::aggressive`] is faster and gives up sooner.
+/// - [`ActionConfig::from_chassis`] scales the gains to the top speed of a
+///   chassis.
+///
+/// Use [`ActionConfig::validate`] to catch mistakes before the robot moves.
 #[derive(Clone, Debug, Copy)]
 pub struct ActionConfig {
     pub linear_kp: f64,
@@ -13,6 +38,12 @@ pub struct ActionConfig {
     pub linear_kd: f64,
     pub linear_kd_limit: f64,
     pub linear_limit: f64,
+    /// The error within which the linear integral accumulates, or 0.0 to
+    /// always accumulate
+    pub linear_integral_zone: f64,
+    /// The smallest linear output while outside the error tolerance, to
+    /// overcome static friction, or 0.0 for none
+    pub linear_min_output: f64,
 
     pub turn_kp: f64,
     pub turn_kp_limit: f64,
@@ -21,6 +52,12 @@ pub struct ActionConfig {
     pub turn_kd: f64,
     pub turn_kd_limit: f64,
     pub turn_limit: f64,
+    /// The error within which the turn integral accumulates, or 0.0 to
+    /// always accumulate
+    pub turn_integral_zone: f64,
+    /// The smallest turn output while outside the error tolerance, to
+    /// overcome static friction, or 0.0 for none
+    pub turn_min_output: f64,
 
     pub pursuit_turn_kp: f64,
     pub pursuit_turn_kp_limit: f64,
@@ -30,60 +67,354 @@ pub struct ActionConfig {
     pub pursuit_turn_kd_limit: f64,
     pub pursuit_turn_limit: f64,
     pub pursuit_lookahead: f64,
+    /// How close to the lookahead distance the target point must be when the
+    /// robot is too far from the path for the lookahead circle to cross it
+    pub pursuit_radius_tolerance: f64,
 
     pub boomerang_lead: f64,
     pub boomerang_close: f64,
+    /// The heading error, in radians, which boomerang actions must also be
+    /// within to settle, or 0.0 to only settle on the distance
+    pub boomerang_heading_tolerance: f64,
 
     pub linear_error_tolerance: f64,
+    /// The speed, in mm/s, below which the robot counts as stopped for linear
+    /// actions
     pub linear_velocity_tolerance: f64,
     pub linear_tolerance_duration: Duration,
     pub linear_timeout: Duration,
+    /// How much the linear error must improve by within
+    /// `linear_progress_window`, or 0.0 to never give up early
+    pub linear_min_progress: f64,
+    pub linear_progress_window: Duration,
 
     pub turn_error_tolerance: f64,
+    /// The angular velocity from tracking, in rad/s, below which the robot
+    /// counts as stopped for turns
     pub turn_velocity_tolerance: f64,
     pub turn_tolerance_duration: Duration,
     pub turn_timeout: Duration,
+    /// How much the turn error must improve by within
+    /// `turn_progress_window`, or 0.0 to never give up early
+    pub turn_min_progress: f64,
+    pub turn_progress_window: Duration,
+}
+
+/// An [`ActionConfig`] which can't work as intended.
+#[derive(Debug, Clone, Copy, PartialEq, Snafu)]
+pub enum ActionConfigError {
+    #[snafu(display("The {} must be positive, got {}", name, value))]
+    NotPositive { name: &'static str, value: f64 },
+    #[snafu(display("The {} must not be negative, got {}", name, value))]
+    Negative { name: &'static str, value: f64 },
+    #[snafu(display("The boomerang lead must be in (0, 1], got {}", lead))]
+    BoomerangLead { lead: f64 },
+    #[snafu(display(
+        "The {} integral zone ({}) is within the error tolerance ({}), so the integral can never act",
+        name,
+        zone,
+        tolerance
+    ))]
+    IntegralZoneInsideTolerance {
+        name: &'static str,
+        zone: f64,
+        tolerance: f64,
+    },
+    #[snafu(display("Invalid {} tolerances: {}", name, source))]
+    Tolerances {
+        name: &'static str,
+        source: TolerancesError,
+    },
+}
+
+impl Default for ActionConfig {
+    /// Returns gains and tolerances which work passably on a typical chassis,
+    /// i.e., 600 RPM on 3.25" wheels with a 300 mm track width. Tune from
+    /// here, or use [`ActionConfig::from_chassis`].
+    fn default() -> Self {
+        Self {
+            linear_kp: 0.05,
+            linear_kp_limit: MAX_VOLTAGE,
+            linear_ki: 0.0,
+            linear_ki_limit: 2.0,
+            linear_kd: 0.3,
+            linear_kd_limit: MAX_VOLTAGE,
+            linear_limit: MAX_VOLTAGE,
+            linear_integral_zone: 0.0,
+            linear_min_output: 0.0,
+
+            turn_kp: 8.0,
+            turn_kp_limit: MAX_VOLTAGE,
+            turn_ki: 0.0,
+            turn_ki_limit: 2.0,
+            turn_kd: 40.0,
+            turn_kd_limit: MAX_VOLTAGE,
+            turn_limit: MAX_VOLTAGE,
+            turn_integral_zone: 0.0,
+            turn_min_output: 0.0,
+
+            pursuit_turn_kp: 6.0,
+            pursuit_turn_kp_limit: MAX_VOLTAGE,
+            pursuit_turn_ki: 0.0,
+            pursuit_turn_ki_limit: 2.0,
+            pursuit_turn_kd: 20.0,
+            pursuit_turn_kd_limit: MAX_VOLTAGE,
+            pursuit_turn_limit: MAX_VOLTAGE,
+            pursuit_lookahead: 250.0,
+            pursuit_radius_tolerance: DEFAULT_RADIUS_TOLERANCE,
+
+            boomerang_lead: 0.6,
+            boomerang_close: 75.0,
+            boomerang_heading_tolerance: 0.0,
+
+            linear_error_tolerance: 10.0,
+            linear_velocity_tolerance: 20.0,
+            linear_tolerance_duration: Duration::from_millis(100),
+            linear_timeout: Duration::from_secs(3),
+            linear_min_progress: 0.0,
+            linear_progress_window: Duration::ZERO,
+
+            turn_error_tolerance: 1.0_f64.to_radians(),
+            turn_velocity_tolerance: 0.1,
+            turn_tolerance_duration: Duration::from_millis(100),
+            turn_timeout: Duration::from_secs(2),
+            turn_min_progress: 0.0,
+            turn_progress_window: Duration::ZERO,
+        }
+    }
 }
 
 impl ActionConfig {
-    pub fn linear_pid(&self, setpoint: f64) -> Pid<f64> {
-        let mut pid = Pid::new(setpoint, self.linear_limit);
+    /// Returns the [default](ActionConfig::default) config at two thirds of
+    /// the voltage, with tighter tolerances and more time to meet them.
+    ///
+    /// This suits skills runs and scoring moves which must be precise more
+    /// than they must be fast.
+    pub fn conservative() -> Self {
+        let default = Self::default();
+        let voltage = MAX_VOLTAGE * 2.0 / 3.0;
+        Self {
+            linear_limit: voltage,
+            turn_limit: voltage,
+            pursuit_turn_limit: voltage,
+            linear_error_tolerance: 5.0,
+            linear_velocity_tolerance: 10.0,
+            linear_tolerance_duration: Duration::from_millis(200),
+            linear_timeout: Duration::from_secs(5),
+            turn_error_tolerance: 0.5_f64.to_radians(),
+            turn_velocity_tolerance: 0.05,
+            turn_tolerance_duration: Duration::from_millis(200),
+            turn_timeout: Duration::from_secs(3),
+            ..default
+        }
+    }
+
+    /// Returns the [default](ActionConfig::default) config with stiffer
+    /// gains, looser tolerances, and shorter timeouts. Actions also give up
+    /// once they stop making progress, rather than waiting for the timeout.
+    ///
+    /// This suits match autonomous routines, where time matters more than
+    /// the last few millimeters.
+    pub fn aggressive() -> Self {
+        let default = Self::default();
+        Self {
+            linear_kp: default.linear_kp * 1.5,
+            turn_kp: default.turn_kp * 1.5,
+            pursuit_turn_kp: default.pursuit_turn_kp * 1.5,
+            pursuit_lookahead: 350.0,
+            linear_error_tolerance: 20.0,
+            linear_velocity_tolerance: 50.0,
+            linear_tolerance_duration: Duration::from_millis(50),
+            linear_timeout: Duration::from_millis(1500),
+            linear_min_progress: 5.0,
+            linear_progress_window: Duration::from_millis(250),
+            turn_error_tolerance: 2.0_f64.to_radians(),
+            turn_velocity_tolerance: 0.2,
+            turn_tolerance_duration: Duration::from_millis(50),
+            turn_timeout: Duration::from_secs(1),
+            turn_min_progress: 1.0_f64.to_radians(),
+            turn_progress_window: Duration::from_millis(250),
+            ..default
+        }
+    }
+
+    /// Returns a config with gains scaled to the top speed of a chassis.
+    ///
+    /// The proportional gains saturate at the distance or angle the chassis
+    /// covers in a quarter of a second at top speed, and the derivative gains
+    /// oppose a quarter of the voltage at top speed. The tolerances are
+    /// otherwise the [default](ActionConfig::default) ones.
+    pub fn from_chassis(chassis: &ChassisConfig) -> Self {
+        // Per loop, for the derivative gains
+        const LOOP: f64 = 0.01;
+        const SATURATION_TIME: f64 = 0.25;
+
+        let default = Self::default();
+        let linear_speed = chassis.max_speed().0;
+        let turn_speed = chassis.max_turn_rate().0;
+        if !(linear_speed > 0.0 && turn_speed.is_finite() && turn_speed > 0.0) {
+            log::warn!("Chassis has no top speed; using the default config");
+            return default;
+        }
+
+        let linear_kp = MAX_VOLTAGE / (linear_speed * SATURATION_TIME);
+        let linear_kd = MAX_VOLTAGE / 4.0 / (linear_speed * LOOP);
+        let turn_kp = MAX_VOLTAGE / (turn_speed * SATURATION_TIME);
+        let turn_kd = MAX_VOLTAGE / 4.0 / (turn_speed * LOOP);
+        Self {
+            linear_kp,
+            linear_kd,
+            turn_kp,
+            turn_kd,
+            pursuit_turn_kp: turn_kp * 0.75,
+            pursuit_turn_kd: turn_kd / 2.0,
+            // Slower than 2% of top speed counts as stopped
+            linear_velocity_tolerance: linear_speed * 0.02,
+            turn_velocity_tolerance: turn_speed * 0.02,
+            ..default
+        }
+    }
+
+    /// Checks that every limit is positive, no gain is negative, and the
+    /// tolerances can settle.
+    ///
+    /// Integral zones must also be larger than the error tolerances, or the
+    /// integral would only act once the action had already settled.
+    pub fn validate(&self) -> Result<(), ActionConfigError> {
+        for (name, value) in [
+            ("linear limit", self.linear_limit),
+            ("linear kP limit", self.linear_kp_limit),
+            ("linear kI limit", self.linear_ki_limit),
+            ("linear kD limit", self.linear_kd_limit),
+            ("turn limit", self.turn_limit),
+            ("turn kP limit", self.turn_kp_limit),
+            ("turn kI limit", self.turn_ki_limit),
+            ("turn kD limit", self.turn_kd_limit),
+            ("pursuit turn limit", self.pursuit_turn_limit),
+            ("pursuit turn kP limit", self.pursuit_turn_kp_limit),
+            ("pursuit turn kI limit", self.pursuit_turn_ki_limit),
+            ("pursuit turn kD limit", self.pursuit_turn_kd_limit),
+            ("pursuit lookahead", self.pursuit_lookahead),
+            ("pursuit radius tolerance", self.pursuit_radius_tolerance),
+        ] {
+            if value <= 0.0 || value.is_nan() {
+                return Err(ActionConfigError::NotPositive { name, value });
+            }
+        }
+        for (name, value) in [
+            ("linear kP", self.linear_kp),
+            ("linear kI", self.linear_ki),
+            ("linear kD", self.linear_kd),
+            ("linear integral zone", self.linear_integral_zone),
+            ("linear minimum output", self.linear_min_output),
+            ("turn kP", self.turn_kp),
+            ("turn kI", self.turn_ki),
+            ("turn kD", self.turn_kd),
+            ("turn integral zone", self.turn_integral_zone),
+            ("turn minimum output", self.turn_min_output),
+            ("pursuit turn kP", self.pursuit_turn_kp),
+            ("pursuit turn kI", self.pursuit_turn_ki),
+            ("pursuit turn kD", self.pursuit_turn_kd),
+            ("boomerang close distance", self.boomerang_close),
+            (
+                "boomerang heading tolerance",
+                self.boomerang_heading_tolerance,
+            ),
+            ("linear minimum progress", self.linear_min_progress),
+            ("turn minimum progress", self.turn_min_progress),
+        ] {
+            if value < 0.0 || value.is_nan() {
+                return Err(ActionConfigError::Negative { name, value });
+            }
+        }
+        if !(self.boomerang_lead > 0.0 && self.boomerang_lead <= 1.0) {
+            return Err(ActionConfigError::BoomerangLead {
+                lead: self.boomerang_lead,
+            });
+        }
+        for (name, zone, tolerance) in [
+            (
+                "linear",
+                self.linear_integral_zone,
+                self.linear_error_tolerance,
+            ),
+            ("turn", self.turn_integral_zone, self.turn_error_tolerance),
+        ] {
+            if zone > 0.0 && zone <= tolerance {
+                return Err(ActionConfigError::IntegralZoneInsideTolerance {
+                    name,
+                    zone,
+                    tolerance,
+                });
+            }
+        }
+        for (name, tolerances) in [
+            ("linear", self.linear_tolerances()),
+            ("turn", self.turn_tolerances()),
+        ] {
+            tolerances
+                .validate()
+                .map_err(|source| ActionConfigError::Tolerances { name, source })?;
+        }
+        Ok(())
+    }
+
+    /// Returns the linear PID controller. Its integral is cleared on
+    /// overshoot and only accumulates within the integral zone, if set.
+    pub fn linear_pid(&self, setpoint: f64) -> PidController {
+        let mut pid = PidController::new(setpoint, self.linear_limit);
         pid.p(self.linear_kp, self.linear_kp_limit);
         pid.i(self.linear_ki, self.linear_ki_limit);
         pid.d(self.linear_kd, self.linear_kd_limit);
-        pid
+        with_integral_zone(pid, self.linear_integral_zone)
     }
 
-    pub fn turn_pid(&self, setpoint: f64) -> Pid<f64> {
-        let mut pid = Pid::new(setpoint, self.turn_limit);
+    /// Returns the turn PID controller. Its integral is cleared on overshoot
+    /// and only accumulates within the integral zone, if set.
+    pub fn turn_pid(&self, setpoint: f64) -> PidController {
+        let mut pid = PidController::new(setpoint, self.turn_limit);
         pid.p(self.turn_kp, self.turn_kp_limit);
         pid.i(self.turn_ki, self.turn_ki_limit);
         pid.d(self.turn_kd, self.turn_kd_limit);
-        pid
+        with_integral_zone(pid, self.turn_integral_zone)
     }
 
-    pub fn pursuit_turn_pid(&self, setpoint: f64) -> Pid<f64> {
-        let mut pid = Pid::new(setpoint, self.pursuit_turn_limit);
+    pub fn pursuit_turn_pid(&self, setpoint: f64) -> PidController {
+        let mut pid = PidController::new(setpoint, self.pursuit_turn_limit);
         pid.p(self.pursuit_turn_kp, self.pursuit_turn_kp_limit);
         pid.i(self.pursuit_turn_ki, self.pursuit_turn_ki_limit);
         pid.d(self.pursuit_turn_kd, self.pursuit_turn_kd_limit);
         pid
     }
 
+    /// Returns the linear tolerances. These also settle once the error stops
+    /// improving, if a minimum progress is set.
     pub fn linear_tolerances(&self) -> Tolerances {
-        Tolerances::new()
+        let tolerances = Tolerances::new()
             .error_tolerance(self.linear_error_tolerance)
             .tolerance_duration(self.linear_tolerance_duration)
             .velocity_tolerance(self.linear_velocity_tolerance)
-            .timeout(self.linear_timeout)
+            .timeout(self.linear_timeout);
+        with_progress(
+            tolerances,
+            self.linear_min_progress,
+            self.linear_progress_window,
+        )
     }
 
+    /// Returns the turn tolerances. These also settle once the error stops
+    /// improving, if a minimum progress is set.
     pub fn turn_tolerances(&self) -> Tolerances {
-        Tolerances::new()
+        let tolerances = Tolerances::new()
             .error_tolerance(self.turn_error_tolerance)
             .tolerance_duration(self.turn_tolerance_duration)
             .velocity_tolerance(self.turn_velocity_tolerance)
-            .timeout(self.turn_timeout)
+            .timeout(self.turn_timeout);
+        with_progress(
+            tolerances,
+            self.turn_min_progress,
+            self.turn_progress_window,
+        )
     }
 
     pub fn with_boomerang_lead(mut self, lead: f64) -> Self {
@@ -91,6 +422,13 @@ impl ActionConfig {
         self
     }
 
+    /// Makes boomerang actions turn in place to within `tolerance` radians of
+    /// the target heading once they reach the point, before settling.
+    pub fn with_boomerang_heading_tolerance(mut self, tolerance: f64) -> Self {
+        self.boomerang_heading_tolerance = tolerance;
+        self
+    }
+
     // #region: Builder
     pub fn with_linear_kp(mut self, linear_kp: f64) -> Self {
         self.linear_kp = linear_kp;
@@ -120,6 +458,14 @@ impl ActionConfig {
         self.linear_limit = linear_limit;
         self
     }
+    pub fn with_linear_integral_zone(mut self, linear_integral_zone: f64) -> Self {
+        self.linear_integral_zone = linear_integral_zone;
+        self
+    }
+    pub fn with_linear_min_output(mut self, linear_min_output: f64) -> Self {
+        self.linear_min_output = linear_min_output;
+        self
+    }
     pub fn with_turn_kp(mut self, turn_kp: f64) -> Self {
         self.turn_kp = turn_kp;
         self
@@ -148,6 +494,14 @@ impl ActionConfig {
         self.turn_limit = turn_limit;
         self
     }
+    pub fn with_turn_integral_zone(mut self, turn_integral_zone: f64) -> Self {
+        self.turn_integral_zone = turn_integral_zone;
+        self
+    }
+    pub fn with_turn_min_output(mut self, turn_min_output: f64) -> Self {
+        self.turn_min_output = turn_min_output;
+        self
+    }
     pub fn with_pursuit_turn_kp(mut self, pursuit_turn_kp: f64) -> Self {
         self.pursuit_turn_kp = pursuit_turn_kp;
         self
@@ -176,16 +530,29 @@ impl ActionConfig {
         self.pursuit_turn_limit = pursuit_turn_limit;
         self
     }
-    pub fn with_pursuit_lookahead(mut self, pursuit_lookahead: f64) -> Self {
-        self.pursuit_lookahead = pursuit_lookahead;
+    pub fn with_pursuit_lookahead(mut self, pursuit_lookahead: impl Into<Millimeters>) -> Self {
+        self.pursuit_lookahead = pursuit_lookahead.into().0;
+        self
+    }
+    pub fn with_pursuit_radius_tolerance(
+        mut self,
+        pursuit_radius_tolerance: impl Into<Millimeters>,
+    ) -> Self {
+        self.pursuit_radius_tolerance = pursuit_radius_tolerance.into().0;
         self
     }
-    pub fn with_linear_error_tolerance(mut self, linear_error_tolerance: f64) -> Self {
-        self.linear_error_tolerance = linear_error_tolerance;
+    pub fn with_linear_error_tolerance(
+        mut self,
+        linear_error_tolerance: impl Into<Millimeters>,
+    ) -> Self {
+        self.linear_error_tolerance = linear_error_tolerance.into().0;
         self
     }
-    pub fn with_linear_velocity_tolerance(mut self, linear_velocity_tolerance: f64) -> Self {
-        self.linear_velocity_tolerance = linear_velocity_tolerance;
+    pub fn with_linear_velocity_tolerance(
+        mut self,
+        linear_velocity_tolerance: impl Into<MillimetersPerSecond>,
+    ) -> Self {
+        self.linear_velocity_tolerance = linear_velocity_tolerance.into().0;
         self
     }
     pub fn with_linear_tolerance_duration(mut self, linear_tolerance_duration: Duration) -> Self {
@@ -196,12 +563,24 @@ impl ActionConfig {
         self.linear_timeout = linear_timeout;
         self
     }
+    pub fn with_linear_progress(
+        mut self,
+        min_progress: impl Into<Millimeters>,
+        window: Duration,
+    ) -> Self {
+        self.linear_min_progress = min_progress.into().0;
+        self.linear_progress_window = window;
+        self
+    }
     pub fn with_turn_error_tolerance(mut self, turn_error_tolerance: f64) -> Self {
         self.turn_error_tolerance = turn_error_tolerance;
         self
     }
-    pub fn with_turn_velocity_tolerance(mut self, turn_velocity_tolerance: f64) -> Self {
-        self.turn_velocity_tolerance = turn_velocity_tolerance;
+    pub fn with_turn_velocity_tolerance(
+        mut self,
+        turn_velocity_tolerance: impl Into<RadiansPerSecond>,
+    ) -> Self {
+        self.turn_velocity_tolerance = turn_velocity_tolerance.into().0;
         self
     }
     pub fn with_turn_tolerance_duration(mut self, turn_tolerance_duration: Duration) -> Self {
@@ -212,5 +591,47 @@ impl ActionConfig {
         self.turn_timeout = turn_timeout;
         self
     }
+    pub fn with_turn_progress(mut self, min_progress: f64, window: Duration) -> Self {
+        self.turn_min_progress = min_progress;
+        self.turn_progress_window = window;
+        self
+    }
     // #endregion: Builder
 }
+
+/// Applies an integral zone from the config, where 0.0 means no zone.
+fn with_integral_zone(pid: PidController, zone: f64) -> PidController {
+    if zone > 0.0 {
+        pid.with_integral_zone(zone)
+    } else {
+        pid
+    }
+}
+
+/// Raises a nonzero `output` to at least `min_output` in magnitude while
+/// `error` is outside the error tolerance of `tolerances`, so that the robot
+/// doesn't stall just short of the target.
+pub(crate) fn with_min_output(
+    output: f64,
+    min_output: f64,
+    error: f64,
+    tolerances: &Tolerances,
+) -> f64 {
+    let outside = tolerances
+        .error_tolerance
+        .is_none_or(|tolerance| error.abs() >= tolerance);
+    if outside && output != 0.0 {
+        output.signum() * output.abs().max(min_output)
+    } else {
+        output
+    }
+}
+
+/// Applies a no-progress condition from the config, where 0.0 means none.
+fn with_progress(mut tolerances: Tolerances, min_progress: f64, window: Duration) -> Tolerances {
+    if min_progress > 0.0 {
+        tolerances.no_progress(min_progress, window)
+    } else {
+        tolerances
+    }
+}
@@ -1,9 +1,22 @@
 use nalgebra::Point2;
+use vexide::math::Angle;
 
 use super::BoomerangAction;
 use super::config::ActionConfig;
 use super::{Action, ActionContext, turn_to_point::TurnToPointAction};
-use crate::subsystems::drivetrain::DrivetrainPair;
+use crate::{subsystems::drivetrain::DrivetrainPair, utils::angle};
+
+/// Which way round the robot drives to the point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ApproachDirection {
+    /// Drives front first.
+    #[default]
+    Forward,
+    /// Drives back first.
+    Reverse,
+    /// Drives whichever way round needs the smaller turn to start.
+    Auto,
+}
 
 /// An action that drives the robot to a specific point.
 ///
@@ -13,7 +26,7 @@ use crate::subsystems::drivetrain::DrivetrainPair;
 #[derive(Debug)]
 pub struct DriveToPointAction {
     target: Point2<f64>,
-    reverse: bool,
+    direction: ApproachDirection,
     state: DriveToPointState,
     config: super::config::ActionConfig,
     turn_config: super::config::ActionConfig,
@@ -32,7 +45,7 @@ impl DriveToPointAction {
     pub fn new(target: Point2<f64>, config: ActionConfig) -> Self {
         Self {
             target,
-            reverse: false,
+            direction: ApproachDirection::Forward,
             state: DriveToPointState::NotStarted,
             config,
             turn_config: config,
@@ -44,20 +57,49 @@ impl DriveToPointAction {
         self
     }
 
-    pub fn reversed(mut self) -> Self {
-        self.reverse = true;
+    /// Drives to the point back first. Shorthand for
+    /// [`with_direction(ApproachDirection::Reverse)`](Self::with_direction).
+    pub fn reversed(self) -> Self {
+        self.with_direction(ApproachDirection::Reverse)
+    }
+
+    /// Sets which way round the robot drives to the point. The choice is made
+    /// once, when the action starts, and used for both the turn and the drive.
+    pub fn with_direction(mut self, direction: ApproachDirection) -> Self {
+        self.direction = direction;
         self
     }
+
+    /// Returns whether the robot drives back first. Always `false` for
+    /// [`ApproachDirection::Auto`] before the action starts.
+    fn reverse(&self) -> bool {
+        self.direction == ApproachDirection::Reverse
+    }
 }
 
 impl Action for DriveToPointAction {
     fn update(&mut self, context: ActionContext) -> Option<DrivetrainPair> {
         match &mut self.state {
             DriveToPointState::NotStarted => {
+                if self.direction == ApproachDirection::Auto {
+                    let angle_to_target = angle::angle_to(context.data.offset, self.target);
+                    let forward_error =
+                        angle::shortest_error(angle_to_target, context.data.heading);
+                    self.direction =
+                        if forward_error.as_radians().abs() > core::f64::consts::FRAC_PI_2 {
+                            ApproachDirection::Reverse
+                        } else {
+                            ApproachDirection::Forward
+                        };
+                    log::debug!("Drive to point: approaching {:?}", self.direction);
+                }
                 // Transition to the turning state
                 let turn = TurnToPointAction::new(self.target, self.turn_config);
-                self.state =
-                    DriveToPointState::Turning(if self.reverse { turn.reversed() } else { turn });
+                self.state = DriveToPointState::Turning(if self.reverse() {
+                    turn.reversed()
+                } else {
+                    turn
+                });
                 self.update(context)
             }
             DriveToPointState::Turning(turn_action) => {
@@ -65,9 +107,15 @@ impl Action for DriveToPointAction {
                     return Some(voltage);
                 }
                 // Transition to driving action
-                let boomerang =
-                    BoomerangAction::new(self.target, context.data.heading, self.config);
-                self.state = DriveToPointState::Driving(if self.reverse {
+                // A reversed boomerang aims the back of the robot along the
+                // target heading, which is the way the robot now drives
+                let target_heading = if self.reverse() {
+                    context.data.heading + Angle::HALF_TURN
+                } else {
+                    context.data.heading
+                };
+                let boomerang = BoomerangAction::new(self.target, target_heading, self.config);
+                self.state = DriveToPointState::Driving(if self.reverse() {
                     boomerang.reversed()
                 } else {
                     boomerang
@@ -85,4 +133,12 @@ impl Action for DriveToPointAction {
             DriveToPointState::Done => None,
         }
     }
+
+    fn telemetry(&self) -> Option<super::ActionTelemetry> {
+        match &self.state {
+            DriveToPointState::Turning(turn_action) => turn_action.telemetry(),
+            DriveToPointState::Driving(forward_action) => forward_action.telemetry(),
+            DriveToPointState::NotStarted | DriveToPointState::Done => None,
+        }
+    }
 }
@@ -1,20 +1,34 @@
-use nalgebra::Point2;
-use pid::Pid;
+use nalgebra::{Point2, Vector2};
+use vexide::math::Angle;
 
-use crate::{subsystems::drivetrain::DrivetrainPair, utils::settling};
+use crate::{
+    subsystems::drivetrain::DrivetrainPair,
+    utils::{controllers::PidController, settling},
+};
 
-use super::config::ActionConfig;
+use super::{
+    ObstacleStop,
+    config::{ActionConfig, with_min_output},
+    obstacle::Obstacle,
+};
 
 /// An action that drives the robot forward a certain distance.
 ///
 /// This action uses a PID controller to drive the robot forward a certain
-/// distance.
+/// distance. By default, the distance is the straight line from where the
+/// action started, so being pushed sideways counts as progress; see
+/// [`with_heading_projection`](Self::with_heading_projection).
 #[derive(Debug)]
 pub struct ForwardAction {
-    controller: Pid<f64>,
+    controller: PidController,
     tolerances: settling::Tolerances,
     setpoint: f64,
+    min_output: f64,
     initial_point: Option<Point2<f64>>,
+    initial_heading: Angle,
+    project: bool,
+    obstacle_stop: Option<ObstacleStop>,
+    telemetry: Option<super::ActionTelemetry>,
 }
 
 impl ForwardAction {
@@ -23,11 +37,31 @@ impl ForwardAction {
             controller: config.linear_pid(0.0),
             tolerances: config.linear_tolerances(),
             setpoint: distance,
+            min_output: config.linear_min_output,
             initial_point: None,
+            initial_heading: Angle::ZERO,
+            project: false,
+            obstacle_stop: None,
+            telemetry: None,
         }
     }
 
-    pub fn controller(&mut self) -> &mut Pid<f64> {
+    /// Pauses while driving forwards if `stop` sees an obstacle, and gives up
+    /// if it doesn't clear in time.
+    pub fn with_obstacle_stop(mut self, stop: ObstacleStop) -> Self {
+        self.obstacle_stop = Some(stop);
+        self
+    }
+
+    /// Measures the distance along the heading the action started at,
+    /// instead of in a straight line, so that being pushed sideways or
+    /// drifting onto an arc doesn't count as progress.
+    pub fn with_heading_projection(mut self) -> Self {
+        self.project = true;
+        self
+    }
+
+    pub fn controller(&mut self) -> &mut PidController {
         &mut self.controller
     }
 
@@ -41,23 +75,53 @@ impl super::Action for ForwardAction {
         &mut self,
         context: super::ActionContext,
     ) -> Option<crate::subsystems::drivetrain::DrivetrainPair> {
+        if self.setpoint > 0.0
+            && let Some(stop) = &mut self.obstacle_stop
+        {
+            match stop.check() {
+                Obstacle::Clear => {}
+                Obstacle::Blocked => return Some(ObstacleStop::PAUSED),
+                Obstacle::TimedOut => return None,
+            }
+        }
         if self.initial_point.is_none() {
             self.initial_point = Some(context.data.offset);
+            self.initial_heading = context.data.heading;
         }
 
         let travelled = context.data.offset - self.initial_point.unwrap();
-        let mut distance = travelled.norm();
-        if self.controller.setpoint < 0.0 {
-            // If we are going backwards, invert the distance
-            distance *= -1.0;
-        }
+        let distance = if self.project {
+            // Only count movement along the initial heading. This is already
+            // negative when going backwards.
+            travelled.dot(&Vector2::new(
+                self.initial_heading.cos(),
+                self.initial_heading.sin(),
+            ))
+        } else {
+            let mut distance = travelled.norm();
+            if self.controller.gains().setpoint < 0.0 {
+                // If we are going backwards, invert the distance
+                distance *= -1.0;
+            }
+            distance
+        };
         let error = self.setpoint - distance;
         if self.tolerances.check(error, context.data.linear_velocity()) {
             return None;
         }
 
-        let output = self.controller.next_control_output(-error).output;
+        let output = with_min_output(
+            self.controller.next_control_output(-error).output,
+            self.min_output,
+            error,
+            &self.tolerances,
+        );
+        self.telemetry = Some(super::ActionTelemetry { error, output });
 
         Some(DrivetrainPair::from(output))
     }
+
+    fn telemetry(&self) -> Option<super::ActionTelemetry> {
+        self.telemetry
+    }
 }
@@ -2,7 +2,10 @@ use core::fmt::Debug;
 
 use alloc::boxed::Box;
 
-use crate::subsystems::{drivetrain::DrivetrainPair, tracking::TrackingData};
+use crate::subsystems::{
+    drivetrain::{DrivetrainPair, acceleration::AccelerationLimit},
+    tracking::TrackingData,
+};
 
 use super::Action;
 
@@ -42,4 +45,27 @@ impl<T: Action> super::Action for LazyAction<T> {
             None
         }
     }
+
+    fn telemetry(&self) -> Option<super::ActionTelemetry> {
+        self.action.as_ref().and_then(|action| action.telemetry())
+    }
+
+    fn on_event(&mut self, event: super::ActionEvent) {
+        if let Some(action) = &mut self.action {
+            action.on_event(event);
+        }
+    }
+
+    fn acceleration_limit(&self) -> Option<AccelerationLimit> {
+        self.action
+            .as_ref()
+            .and_then(|action| action.acceleration_limit())
+    }
+
+    fn name(&self) -> &'static str {
+        match &self.action {
+            Some(action) => action.name(),
+            None => core::any::type_name::<Self>(),
+        }
+    }
 }
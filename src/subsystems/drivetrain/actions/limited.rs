@@ -0,0 +1,39 @@
+use crate::subsystems::drivetrain::{DrivetrainPair, acceleration::AccelerationLimit};
+
+use super::Action;
+
+/// Runs an action with its own acceleration limit. See
+/// [`acceleration`](crate::subsystems::drivetrain::acceleration).
+#[derive(Debug)]
+pub struct LimitedAction<T: Action> {
+    action: T,
+    limit: AccelerationLimit,
+}
+
+impl<T: Action> LimitedAction<T> {
+    pub fn new(action: T, limit: AccelerationLimit) -> Self {
+        Self { action, limit }
+    }
+}
+
+impl<T: Action> Action for LimitedAction<T> {
+    fn update(&mut self, context: super::ActionContext) -> Option<DrivetrainPair> {
+        self.action.update(context)
+    }
+
+    fn telemetry(&self) -> Option<super::ActionTelemetry> {
+        self.action.telemetry()
+    }
+
+    fn on_event(&mut self, event: super::ActionEvent) {
+        self.action.on_event(event);
+    }
+
+    fn acceleration_limit(&self) -> Option<AccelerationLimit> {
+        Some(self.limit)
+    }
+
+    fn name(&self) -> &'static str {
+        self.action.name()
+    }
+}
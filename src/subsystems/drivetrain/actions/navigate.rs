@@ -0,0 +1,225 @@
+use core::fmt::Debug;
+
+use alloc::{boxed::Box, vec::Vec};
+use nalgebra::Point2;
+use vexide::math::Angle;
+
+use crate::{
+    path_planner::{grid::OccupancyGrid, sampled::SampledPath},
+    subsystems::drivetrain::DrivetrainPair,
+    utils::geometry,
+};
+
+use super::{Action, PurePursuitAction, RotationAction, config::ActionConfig};
+
+/// How many updates pass between polls of the obstacles.
+const OBSTACLE_POLL_INTERVAL: u32 = 10;
+
+/// The spacing, in mm, of the points of the smoothed path.
+const PATH_SPACING: f64 = 50.0;
+
+/// The window of the moving average which rounds the corners of the planned
+/// route, in points of [`PATH_SPACING`].
+const SMOOTHING_WINDOW: usize = 5;
+
+/// An action that drives to a pose around obstacles, replanning if new ones
+/// block the way.
+///
+/// The route is planned on an [`OccupancyGrid`] of the fixed obstacles, plus
+/// circles around the moving ones from
+/// [`with_obstacles`](Self::with_obstacles), e.g., other robots seen by a
+/// [`VisionSubsystem`](crate::subsystems::vision::VisionSubsystem). Its
+/// corners are rounded with [`SampledPath::smoothed`], and it is followed with
+/// a [`PurePursuitAction`], before turning to the target heading. Obstacles
+/// should be grown by the robot's radius and a margin for the rounded corners.
+///
+/// ```ignore
+/// let robots = vision.clone();
+/// drivetrain
+///     .action(
+///         NavigateAction::new(grid, Point2::new(600.0, 1200.0), Some(Angle::QUARTER_TURN), config)
+///             .with_obstacles(
+///                 move || robots.objects_labeled("robot").iter().map(|robot| robot.position).collect(),
+///                 400.0,
+///             ),
+///     )
+///     .await;
+/// ```
+///
+/// The action gives up if there is no route to the target.
+pub struct NavigateAction {
+    grid: OccupancyGrid,
+    target: Point2<f64>,
+    heading: Option<Angle>,
+    config: ActionConfig,
+    obstacles: Option<Box<dyn FnMut() -> Vec<Point2<f64>>>>,
+    obstacle_radius: f64,
+
+    /// The obstacles the current route was planned around
+    planned_around: Vec<Point2<f64>>,
+    route: Option<SampledPath>,
+    pursuit: Option<PurePursuitAction<SampledPath>>,
+    final_turn: Option<RotationAction>,
+    updates: u32,
+}
+
+impl Debug for NavigateAction {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("NavigateAction")
+            .field("target", &self.target)
+            .field("heading", &self.heading)
+            .field("planned_around", &self.planned_around)
+            .field("pursuit", &self.pursuit)
+            .field("final_turn", &self.final_turn)
+            .finish_non_exhaustive()
+    }
+}
+
+impl NavigateAction {
+    /// Creates a new navigate action to `target`, around the blocked cells of
+    /// `grid`, then turning to `heading` if given.
+    pub fn new(
+        grid: OccupancyGrid,
+        target: Point2<f64>,
+        heading: Option<Angle>,
+        config: ActionConfig,
+    ) -> Self {
+        Self {
+            grid,
+            target,
+            heading,
+            config,
+            obstacles: None,
+            obstacle_radius: 0.0,
+            planned_around: Vec::new(),
+            route: None,
+            pursuit: None,
+            final_turn: None,
+            updates: 0,
+        }
+    }
+
+    /// Avoids circles of `radius` mm around the points returned by
+    /// `obstacles`, which is polled every 100 ms. The route is replanned
+    /// whenever an obstacle it wasn't planned around comes within `radius` of
+    /// it.
+    pub fn with_obstacles(
+        mut self,
+        obstacles: impl FnMut() -> Vec<Point2<f64>> + 'static,
+        radius: f64,
+    ) -> Self {
+        self.obstacles = Some(Box::new(obstacles));
+        self.obstacle_radius = radius;
+        self
+    }
+
+    /// Plans a route from `start` around the current obstacles and starts
+    /// following it. Returns `false` if there is no route.
+    fn plan(&mut self, start: Point2<f64>) -> bool {
+        let mut grid = self.grid.clone();
+        for obstacle in &self.planned_around {
+            grid.block_circle(*obstacle, self.obstacle_radius);
+        }
+        let Some(corners) = grid.plan(start, self.target) else {
+            log::error!(
+                "Navigate: no route from {:?} to {:?} around {} obstacles",
+                start,
+                self.target,
+                self.planned_around.len()
+            );
+            return false;
+        };
+        log::debug!(
+            "Navigate: planned a route with {} corners around {} obstacles",
+            corners.len(),
+            self.planned_around.len()
+        );
+
+        // Resample the corners, so the moving average only rounds them off
+        let mut points = Vec::from([corners[0]]);
+        for pair in corners.windows(2) {
+            let steps = (nalgebra::distance(&pair[0], &pair[1]) / PATH_SPACING).ceil() as usize;
+            for step in 1..=steps.max(1) {
+                points.push(geometry::point_on_segment(
+                    pair[0],
+                    pair[1],
+                    step as f64 / steps.max(1) as f64,
+                ));
+            }
+        }
+        let route = SampledPath::smoothed(&points, SMOOTHING_WINDOW, PATH_SPACING);
+        self.pursuit = Some(PurePursuitAction::new(route.clone(), None, self.config));
+        self.route = Some(route);
+        true
+    }
+
+    /// Polls the obstacles, and returns whether any which the route wasn't
+    /// planned around are in the way of it.
+    fn route_blocked(&mut self) -> bool {
+        let Some(obstacles) = &mut self.obstacles else {
+            return false;
+        };
+        let obstacles = obstacles();
+        let blocked = self.route.as_ref().is_some_and(|route| {
+            obstacles.iter().any(|obstacle| {
+                let known = self
+                    .planned_around
+                    .iter()
+                    .any(|planned| nalgebra::distance(planned, obstacle) < self.grid.cell_size());
+                !known
+                    && route.points().windows(2).any(|pair| {
+                        geometry::distance_to_segment(*obstacle, pair[0], pair[1])
+                            < self.obstacle_radius
+                    })
+            })
+        });
+        if blocked || self.route.is_none() {
+            self.planned_around = obstacles;
+        }
+        blocked
+    }
+}
+
+impl Action for NavigateAction {
+    fn update(&mut self, context: super::ActionContext) -> Option<DrivetrainPair> {
+        if let Some(turn) = &mut self.final_turn {
+            return turn.update(context);
+        }
+
+        let poll = self.updates.is_multiple_of(OBSTACLE_POLL_INTERVAL);
+        self.updates += 1;
+        if self.route.is_none() {
+            self.route_blocked();
+            if !self.plan(context.data.offset) {
+                return None;
+            }
+        } else if poll && self.route_blocked() {
+            log::info!("Navigate: route blocked, replanning");
+            if !self.plan(context.data.offset) {
+                return None;
+            }
+        }
+
+        if let Some(output) = self
+            .pursuit
+            .as_mut()
+            .and_then(|pursuit| pursuit.update(context))
+        {
+            return Some(output);
+        }
+        let heading = self.heading?;
+        self.pursuit = None;
+        let turn = self
+            .final_turn
+            .insert(RotationAction::new(heading.as_radians(), self.config));
+        turn.update(context)
+    }
+
+    fn telemetry(&self) -> Option<super::ActionTelemetry> {
+        match (&self.final_turn, &self.pursuit) {
+            (Some(turn), _) => turn.telemetry(),
+            (None, Some(pursuit)) => pursuit.telemetry(),
+            (None, None) => None,
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use alloc::rc::Rc;
+use core::time::Duration;
+use std::time::Instant;
+
+use vexide::smart::distance::DistanceSensor;
+
+use crate::{
+    subsystems::drivetrain::{DrivetrainPair, drivetrain_pair::DrivetrainUnits},
+    utils::{logger::native_log, unwrap_expect_report::UnwrapExpectReportExt},
+};
+
+/// What an [`ObstacleStop`] saw ahead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Obstacle {
+    Clear,
+    /// Something is too close, so the action should pause
+    Blocked,
+    /// Something has been too close for longer than the timeout, so the action
+    /// should give up
+    TimedOut,
+}
+
+/// Pauses an action while a forward-facing distance sensor sees something
+/// closer than a threshold, such as an opponent in autonomous, and aborts it
+/// if the obstacle doesn't clear in time.
+///
+/// Clones share the sensor, so one stop can be created at startup and cloned
+/// into each action with `with_obstacle_stop`. Objects are only checked while
+/// the action drives forwards. An action's timeout keeps running while it is
+/// paused.
+#[derive(Debug, Clone)]
+pub struct ObstacleStop {
+    sensor: Rc<DistanceSensor>,
+    threshold: f64,
+    timeout: Duration,
+    blocked_since: Option<Instant>,
+}
+
+impl ObstacleStop {
+    /// Creates a stop which pauses while an object is closer than
+    /// `threshold` mm, and aborts after it has been for `timeout`.
+    pub fn new(sensor: DistanceSensor, threshold: f64, timeout: Duration) -> Self {
+        Self {
+            sensor: Rc::new(sensor),
+            threshold,
+            timeout,
+            blocked_since: None,
+        }
+    }
+
+    /// The output of a paused action.
+    pub(crate) const PAUSED: DrivetrainPair = DrivetrainPair {
+        left: 0.0,
+        right: 0.0,
+        units: DrivetrainUnits::Voltage,
+    };
+
+    /// Reads the sensor. A sensor which can't be read is treated as clear, so
+    /// that a disconnected sensor doesn't stop autonomous.
+    pub(crate) fn check(&mut self) -> Obstacle {
+        let distance = self
+            .sensor
+            .object()
+            .expect_report("failed to read obstacle distance sensor")
+            .flatten()
+            .map(|object| object.distance as f64);
+        match (distance, self.blocked_since) {
+            (Some(distance), None) if distance < self.threshold => {
+                native_log!(
+                    warn,
+                    "Obstacle {=f64} mm ahead; pausing",
+                    "Obstacle {:.0} mm ahead; pausing",
+                    distance
+                );
+                self.blocked_since = Some(Instant::now());
+                Obstacle::Blocked
+            }
+            (Some(distance), Some(since)) if distance < self.threshold => {
+                if since.elapsed() > self.timeout {
+                    log::warn!(
+                        "Obstacle {:.0} mm ahead for {:?}; aborting",
+                        distance,
+                        self.timeout
+                    );
+                    Obstacle::TimedOut
+                } else {
+                    Obstacle::Blocked
+                }
+            }
+            (_, Some(since)) => {
+                log::info!("Obstacle cleared after {:?}; resuming", since.elapsed());
+                self.blocked_since = None;
+                Obstacle::Clear
+            }
+            (_, None) => Obstacle::Clear,
+        }
+    }
+}
@@ -1,13 +1,52 @@
+use alloc::{boxed::Box, collections::BTreeMap, vec::Vec};
 use nalgebra::Point2;
-use pid::Pid;
 use vexide::math::Angle;
 
 use crate::{
-    path_planner::Path, subsystems::drivetrain::DrivetrainPair, utils::settling::Tolerances,
+    path_planner::Path,
+    subsystems::drivetrain::{DrivetrainPair, curvature::CurvatureDrive},
+    utils::{angle, controllers::PidController, logger::native_log, settling::Tolerances},
 };
 
-use super::{BoomerangAction, config::ActionConfig};
+use super::{
+    BoomerangAction, ObstacleStop, VelocityOutput, config::ActionConfig, obstacle::Obstacle,
+};
+
+/// How many updates pass between checks of the local closest point search
+/// against a search of the whole path.
+const GLOBAL_SEARCH_INTERVAL: u32 = 25;
+
+/// How far along the path, in mm, the closest point may move in one update
+/// before the whole path is searched. The robot can't move this far in 10 ms.
+const MAX_PROGRESS_JUMP: f64 = 100.0;
+
+/// How much closer, in mm, the closest point on the whole path must be than
+/// the local one to re-localize on the path.
+const RELOCALIZE_MARGIN: f64 = 10.0;
+
+/// A callback for when the robot reaches a segment of the path.
+struct SegmentMarker {
+    segment: usize,
+    callback: Box<dyn FnMut()>,
+    fired: bool,
+}
+
+impl core::fmt::Debug for SegmentMarker {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SegmentMarker")
+            .field("segment", &self.segment)
+            .field("fired", &self.fired)
+            .finish_non_exhaustive()
+    }
+}
 
+/// An action that follows a path with pure pursuit.
+///
+/// A [`CompoundPath`](crate::path_planner::compound::CompoundPath) is followed
+/// in one go, without stopping at the joins. Each of its segments can have its
+/// own speed cap with [`with_segment_speed_cap`](Self::with_segment_speed_cap),
+/// and callbacks can run as the robot reaches a segment with
+/// [`on_segment`](Self::on_segment).
 #[derive(Debug)]
 pub struct PurePursuitAction<T: Path> {
     // Cached values
@@ -19,15 +58,25 @@ pub struct PurePursuitAction<T: Path> {
     // State
     settled: bool,
     last_t: f64,
+    updates: u32,
+    segment: usize,
     final_seeking: Option<BoomerangAction>,
+    telemetry: Option<super::ActionTelemetry>,
 
     // PIDs
-    rotational_pid: Pid<f64>,
-    linear_pid: Pid<f64>,
+    rotational_pid: PidController,
+    linear_pid: PidController,
 
     // Configuration
     path: T,
     disable_seeking_distance: f64,
+    always_search_globally: bool,
+    obstacle_stop: Option<ObstacleStop>,
+    /// The curvature mixer and the track width in mm
+    curvature_drive: Option<(CurvatureDrive, f64)>,
+    velocity_output: Option<VelocityOutput>,
+    segment_speed_caps: BTreeMap<usize, f64>,
+    segment_markers: Vec<SegmentMarker>,
     linear_tolerances: Tolerances,
     reverse: bool,
     config: ActionConfig,
@@ -40,12 +89,21 @@ impl<T: Path> PurePursuitAction<T> {
             end_point: path.evaluate(1.0),
             path_total,
             disable_seeking_distance: disable_seeking_distance.unwrap_or(0.0),
+            always_search_globally: false,
+            obstacle_stop: None,
+            curvature_drive: None,
+            velocity_output: None,
+            segment_speed_caps: BTreeMap::new(),
+            segment_markers: Vec::new(),
             target_point: path.evaluate(0.0),
             linear_pid: config.linear_pid(0.0),
             path,
             last_t: 0.0,
+            updates: 0,
+            segment: 0,
             settled: false,
             final_seeking: None,
+            telemetry: None,
             lookahead: config.pursuit_lookahead,
             rotational_pid: config.pursuit_turn_pid(0.0),
             linear_tolerances: config.linear_tolerances(),
@@ -63,6 +121,124 @@ impl<T: Path> PurePursuitAction<T> {
         self.reverse = true;
         self
     }
+
+    /// Checks the closest point on the path against a search of the whole
+    /// path on every update, rather than now and then.
+    ///
+    /// This costs a few hundred path evaluations per update, but never loses
+    /// track of progress on paths which double back on themselves.
+    pub fn with_global_search(mut self) -> Self {
+        self.always_search_globally = true;
+        self
+    }
+
+    /// Pauses while driving forwards if `stop` sees an obstacle, and gives up
+    /// if it doesn't clear in time.
+    pub fn with_obstacle_stop(mut self, stop: ObstacleStop) -> Self {
+        self.obstacle_stop = Some(stop);
+        self
+    }
+
+    /// Mixes the output with `drive` instead of adding a turning PID output,
+    /// so the robot drives the arc through the target point.
+    ///
+    /// This follows gentle, arc-heavy paths more smoothly. The turning PID is
+    /// still used when the linear output is low enough to quick-turn.
+    /// `track_width` is the distance between the left and right wheels in mm.
+    pub fn with_curvature_drive(mut self, drive: CurvatureDrive, track_width: f64) -> Self {
+        self.curvature_drive = Some((drive, track_width));
+        self
+    }
+
+    /// Outputs wheel RPM targets instead of voltages, including while seeking
+    /// the end of the path. See [`VelocityOutput`].
+    ///
+    /// A [curvature drive](Self::with_curvature_drive) should then output
+    /// voltages, which are converted in the same way.
+    pub fn with_velocity_output(mut self, velocity_output: VelocityOutput) -> Self {
+        self.velocity_output = Some(velocity_output);
+        self
+    }
+
+    /// Limits the linear output while the robot is on `segment` of the path
+    /// (see [`Path::segment`]), e.g., to slow down for a tight turn or before
+    /// a pickup.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is negative or NaN.
+    pub fn with_segment_speed_cap(mut self, segment: usize, max: f64) -> Self {
+        assert!(max >= 0.0, "Invalid segment speed cap: {}", max);
+        self.segment_speed_caps.insert(segment, max);
+        self
+    }
+
+    /// Calls `callback` once, when the robot first reaches `segment` of the
+    /// path (see [`Path::segment`]), e.g., to start an intake at a join.
+    pub fn on_segment(mut self, segment: usize, callback: impl FnMut() + 'static) -> Self {
+        self.segment_markers.push(SegmentMarker {
+            segment,
+            callback: Box::new(callback),
+            fired: false,
+        });
+        self
+    }
+
+    /// Tracks which segment of the path the robot is on, and calls the
+    /// callbacks of any segments it has reached.
+    fn update_segment(&mut self, t: f64) {
+        let segment = self.path.segment(t);
+        if segment != self.segment {
+            native_log!(
+                debug,
+                "Pure pursuit: segment {=usize} -> {=usize}",
+                "Pure pursuit: segment {} -> {}",
+                self.segment,
+                segment
+            );
+            self.segment = segment;
+        }
+        for marker in &mut self.segment_markers {
+            if !marker.fired && marker.segment <= segment {
+                marker.fired = true;
+                (marker.callback)();
+            }
+        }
+    }
+
+    /// Finds the closest point on the path to `point`, searching near the
+    /// last one, and re-localizes on the path if a search of the whole path
+    /// finds a much closer point.
+    ///
+    /// The whole path is searched periodically, and whenever the local
+    /// search jumps further than the robot could have moved.
+    fn closest_t(&mut self, point: Point2<f64>) -> f64 {
+        let local_t = self.path.closest_point(point, Some(self.last_t), Some(0.1));
+        self.updates += 1;
+        let jumped = (self.path.length_until(local_t) - self.path.length_until(self.last_t)).abs()
+            > MAX_PROGRESS_JUMP;
+        if !(self.always_search_globally
+            || jumped
+            || self.updates.is_multiple_of(GLOBAL_SEARCH_INTERVAL))
+        {
+            return local_t;
+        }
+        let global_t = self.path.closest_point_global(point);
+        let local_distance = nalgebra::distance(&self.path.evaluate(local_t), &point);
+        let global_distance = nalgebra::distance(&self.path.evaluate(global_t), &point);
+        if global_distance + RELOCALIZE_MARGIN < local_distance {
+            log::warn!(
+                "Pure pursuit: re-localized on path from t={:.3} ({:.0} mm away) to t={:.3} ({:.0} mm away)",
+                local_t,
+                local_distance,
+                global_t,
+                global_distance
+            );
+            global_t
+        } else {
+            local_t
+        }
+    }
 }
 
 impl<T: Path> super::Action for PurePursuitAction<T> {
@@ -71,14 +247,21 @@ impl<T: Path> super::Action for PurePursuitAction<T> {
         if self.settled {
             return None;
         }
-        if let Some(mut action) = self.final_seeking {
+        if !self.reverse
+            && let Some(stop) = &mut self.obstacle_stop
+        {
+            match stop.check() {
+                Obstacle::Clear => {}
+                Obstacle::Blocked => return Some(ObstacleStop::PAUSED),
+                Obstacle::TimedOut => return None,
+            }
+        }
+        if let Some(action) = &mut self.final_seeking {
             // If we are in final seeking mode, just run that action
             action.update(context)
         } else {
             // Find the closest point on the path to the current pose
-            let current_t =
-                self.path
-                    .closest_point(context.data.offset, Some(self.last_t), Some(0.1));
+            let current_t = self.closest_t(context.data.offset);
             // Find how far along the path we are
             let path_distance = self.path.length_until(current_t);
             // Calculate the distance and velocity to the end of the path
@@ -90,17 +273,22 @@ impl<T: Path> super::Action for PurePursuitAction<T> {
                 return None;
             }
             self.last_t = current_t;
+            self.update_segment(current_t);
 
             // If we're within the disable seeking distance, let's just start seeking
             // the end of the path
             if nalgebra::distance(&self.target_point, &context.data.offset)
                 < self.disable_seeking_distance
             {
-                self.final_seeking = Some(BoomerangAction::new(
+                let seeking = BoomerangAction::new(
                     self.end_point,
                     Angle::from_radians(self.path.evaluate_angle(1.0)),
                     self.config,
-                ));
+                );
+                self.final_seeking = Some(match self.velocity_output {
+                    Some(velocity_output) => seeking.with_velocity_output(velocity_output),
+                    None => seeking,
+                });
                 return self.final_seeking.as_mut().unwrap().update(context);
             }
 
@@ -108,10 +296,12 @@ impl<T: Path> super::Action for PurePursuitAction<T> {
             // With pure pursuit, we find the intersection of the path and a circle
             // with radius equal to the lookahead distance
             // Calculate the angle to the target point
-            if let Some(target_t) =
-                self.path
-                    .point_on_radius(context.data.offset, self.lookahead, Some(current_t))
-            {
+            if let Some(target_t) = self.path.point_on_radius(
+                context.data.offset,
+                self.lookahead,
+                Some(current_t),
+                Some(self.config.pursuit_radius_tolerance),
+            ) {
                 self.target_point = self.path.evaluate(target_t);
                 #[cfg(feature = "unsafe_debug_render")]
                 if matches!(
@@ -146,17 +336,16 @@ impl<T: Path> super::Action for PurePursuitAction<T> {
             }
 
             // Calculate the closest angular error to the target point
-            let angular_error = (Angle::from_radians(
-                (self.target_point.y - context.data.offset.y)
-                    .atan2(self.target_point.x - context.data.offset.x),
-            ) - (context.data.heading
-                + if self.reverse {
-                    // Reverse the heading by 180 degrees if we're reversed
-                    Angle::HALF_TURN
-                } else {
-                    Angle::ZERO
-                }))
-            .wrapped_half();
+            let angular_error = angle::shortest_error(
+                angle::angle_to(context.data.offset, self.target_point),
+                context.data.heading
+                    + if self.reverse {
+                        // Reverse the heading by 180 degrees if we're reversed
+                        Angle::HALF_TURN
+                    } else {
+                        Angle::ZERO
+                    },
+            );
 
             // Calculate the rotational voltage
             let rotational_voltage = self
@@ -167,9 +356,44 @@ impl<T: Path> super::Action for PurePursuitAction<T> {
                 .output;
 
             // Calculate the linear part of the differential drive
-            let linear_voltage = self.linear_pid.next_control_output(-linear_error).output
+            let mut linear_voltage = self.linear_pid.next_control_output(-linear_error).output
                 // scalar to reduce speed on turns. more info in boomerang action
                 * angular_error.cos().max(0.0);
+            if let Some(cap) = self.segment_speed_caps.get(&self.segment) {
+                linear_voltage = linear_voltage.clamp(-cap, *cap);
+            }
+            self.telemetry = Some(super::ActionTelemetry {
+                error: linear_error,
+                output: linear_voltage,
+            });
+
+            if let Some((drive, track_width)) = &self.curvature_drive {
+                let throttle = linear_voltage / drive.max();
+                let curvature = if drive.is_quick_turn(throttle) {
+                    rotational_voltage / drive.max()
+                } else {
+                    // The arc through the target point has a curvature of
+                    // 2 sin(error) / distance, which the mixer expects scaled
+                    // by half the track width
+                    let distance = nalgebra::distance(&context.data.offset, &self.target_point);
+                    if distance > 0.0 {
+                        angular_error.sin() * track_width / distance
+                    } else {
+                        0.0
+                    }
+                };
+                let output = drive.mix(throttle, curvature);
+                return Some(match &self.velocity_output {
+                    Some(velocity_output) => velocity_output.convert(output),
+                    None => output,
+                });
+            }
+            if let Some(velocity_output) = &self.velocity_output {
+                return Some(velocity_output.convert(DrivetrainPair::new_voltage(
+                    linear_voltage - rotational_voltage,
+                    linear_voltage + rotational_voltage,
+                )));
+            }
 
             Some(DrivetrainPair {
                 left: linear_voltage - rotational_voltage,
@@ -178,4 +402,11 @@ impl<T: Path> super::Action for PurePursuitAction<T> {
             })
         }
     }
+
+    fn telemetry(&self) -> Option<super::ActionTelemetry> {
+        match &self.final_seeking {
+            Some(action) => action.telemetry(),
+            None => self.telemetry,
+        }
+    }
 }
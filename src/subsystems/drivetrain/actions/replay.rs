@@ -0,0 +1,46 @@
+use core::time::Duration;
+use std::time::Instant;
+
+use alloc::vec::Vec;
+
+use crate::subsystems::drivetrain::DrivetrainPair;
+
+/// An action that replays recorded drivetrain outputs open-loop.
+///
+/// Each output is applied from its timestamp until the next one, and the
+/// action finishes after the last output. Since nothing corrects for drift,
+/// this works best for short sequences; for longer ones, follow a path made
+/// from the recorded poses instead (see
+/// [`PoseTrace::to_path`](crate::subsystems::tracking::PoseTrace::to_path)).
+#[derive(Debug)]
+pub struct ReplayAction {
+    outputs: Vec<(Duration, DrivetrainPair)>,
+    start: Option<Instant>,
+    index: usize,
+}
+
+impl ReplayAction {
+    /// Creates a new replay action from outputs paired with their time since
+    /// the start of the recording, in ascending order of time.
+    pub fn new(outputs: Vec<(Duration, DrivetrainPair)>) -> Self {
+        Self {
+            outputs,
+            start: None,
+            index: 0,
+        }
+    }
+}
+
+impl super::Action for ReplayAction {
+    fn update(&mut self, _context: super::ActionContext) -> Option<DrivetrainPair> {
+        let elapsed = self.start.get_or_insert_with(Instant::now).elapsed();
+        let (last_time, _) = self.outputs.last()?;
+        if elapsed > *last_time {
+            return None;
+        }
+        while self.index + 1 < self.outputs.len() && self.outputs[self.index + 1].0 <= elapsed {
+            self.index += 1;
+        }
+        Some(self.outputs[self.index].1)
+    }
+}
@@ -1,18 +1,20 @@
-use crate::utils::settling;
-use pid::Pid;
 use vexide::math::Angle;
 
-use super::config::ActionConfig;
+use crate::utils::{angle, controllers::PidController, settling};
+
+use super::config::{ActionConfig, with_min_output};
 
 /// An action that rotates the drivetrain to a specific absolute heading.
 ///
 /// This action uses a PID controller to rotate the drivetrain to a target
 /// heading.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct RotationAction {
-    controller: Pid<f64>,
+    controller: PidController,
     setpoint: f64,
+    min_output: f64,
     tolerances: settling::Tolerances,
+    telemetry: Option<super::ActionTelemetry>,
 }
 
 impl RotationAction {
@@ -20,11 +22,13 @@ impl RotationAction {
         Self {
             controller: config.turn_pid(0.0),
             setpoint: target_radians,
+            min_output: config.turn_min_output,
             tolerances: config.turn_tolerances(),
+            telemetry: None,
         }
     }
 
-    pub fn controller(&mut self) -> &mut Pid<f64> {
+    pub fn controller(&mut self) -> &mut PidController {
         &mut self.controller
     }
 
@@ -39,8 +43,7 @@ impl super::Action for RotationAction {
         context: super::ActionContext,
     ) -> Option<crate::subsystems::drivetrain::DrivetrainPair> {
         // Calculate the shortest angular error
-        let error = (Angle::from_radians(self.setpoint) - context.data.heading)
-            .wrapped_half()
+        let error = angle::shortest_error(Angle::from_radians(self.setpoint), context.data.heading)
             .as_radians();
         log::trace!(
             "Rotation: {:.3} --> {:.3} (error: {:.3})",
@@ -56,11 +59,21 @@ impl super::Action for RotationAction {
             return None;
         }
 
-        let output = self.controller.next_control_output(error).output;
+        let output = with_min_output(
+            self.controller.next_control_output(error).output,
+            self.min_output,
+            error,
+            &self.tolerances,
+        );
+        self.telemetry = Some(super::ActionTelemetry { error, output });
 
         // Apply the output as a voltage pair for rotation
         Some(crate::subsystems::drivetrain::DrivetrainPair::new_voltage(
             -output, output,
         ))
     }
+
+    fn telemetry(&self) -> Option<super::ActionTelemetry> {
+        self.telemetry
+    }
 }
@@ -1,8 +1,10 @@
 use nalgebra::{Point2, Vector2};
-use pid::Pid;
 use vexide::math::Angle;
 
-use crate::{subsystems::drivetrain::DrivetrainPair, utils::settling::Tolerances};
+use crate::{
+    subsystems::drivetrain::DrivetrainPair,
+    utils::{angle, controllers::PidController, settling::Tolerances},
+};
 
 #[derive(Debug, Clone, Copy)]
 pub struct SeekingAction {
@@ -16,9 +18,14 @@ pub struct SeekingAction {
     reverse: bool,
 
     tolerances: Tolerances,
+    min_output: f64,
 
-    linear_pid: Pid<f64>,
-    angular_pid: Pid<f64>,
+    linear_pid: PidController,
+    angular_pid: PidController,
+
+    velocity_output: Option<super::VelocityOutput>,
+
+    telemetry: Option<super::ActionTelemetry>,
 }
 
 impl SeekingAction {
@@ -27,9 +34,12 @@ impl SeekingAction {
             target_point,
             close: config.boomerang_close,
             tolerances: config.linear_tolerances(),
+            min_output: config.linear_min_output,
             linear_pid: config.linear_pid(0.0),
             angular_pid: config.turn_pid(0.0),
             reverse: false,
+            velocity_output: None,
+            telemetry: None,
         }
     }
 
@@ -41,22 +51,31 @@ impl SeekingAction {
         self.reverse = true;
         self
     }
+
+    /// Outputs wheel RPM targets instead of voltages. See [`VelocityOutput`].
+    ///
+    /// [`VelocityOutput`]: super::VelocityOutput
+    pub fn with_velocity_output(mut self, velocity_output: super::VelocityOutput) -> Self {
+        self.velocity_output = Some(velocity_output);
+        self
+    }
 }
 
 impl super::Action for SeekingAction {
     fn update(&mut self, context: super::ActionContext) -> Option<DrivetrainPair> {
         let local_target = self.target_point - context.data.offset;
-        let angle_to_target = Angle::from_radians(local_target.y.atan2(local_target.x));
+        let angle_to_target = angle::angle_to(context.data.offset, self.target_point);
 
         // Compute the angular angle
-        let error_angular = (angle_to_target
-            - if self.reverse {
+        let error_angular = angle::shortest_error(
+            angle_to_target,
+            if self.reverse {
                 // If we're reversed, we want to face backwards
                 context.data.heading + Angle::HALF_TURN
             } else {
                 context.data.heading
-            })
-        .wrapped_half();
+            },
+        );
         let (error_distance, close) = {
             // Find the "straight-line" distance to the target point
             // See subsystems::tracking::tracking_data for more information about
@@ -84,17 +103,34 @@ impl super::Action for SeekingAction {
                 .next_control_output(error_angular.as_radians())
                 .output
         };
-        let output_linear = self.linear_pid.next_control_output(-error_distance).output
+        let output_linear = super::config::with_min_output(
+            self.linear_pid.next_control_output(-error_distance).output,
+            self.min_output,
+            error_distance,
+            &self.tolerances,
+        )
             // If the angular error is more than 90 degrees (|cos(angle) < 0|),
             // stop moving forward
             * error_angular.cos().max(0.0)
             // If reversed, invert the linear output to drive backwards
             * if self.reverse { -1.0 } else { 1.0 };
+        self.telemetry = Some(super::ActionTelemetry {
+            error: error_distance,
+            output: output_linear,
+        });
 
-        Some(DrivetrainPair {
+        let output = DrivetrainPair {
             left: output_linear - output_angular,
             right: output_linear + output_angular,
             units: crate::subsystems::drivetrain::drivetrain_pair::DrivetrainUnits::Voltage,
+        };
+        Some(match &self.velocity_output {
+            Some(velocity_output) => velocity_output.convert(output),
+            None => output,
         })
     }
+
+    fn telemetry(&self) -> Option<super::ActionTelemetry> {
+        self.telemetry
+    }
 }
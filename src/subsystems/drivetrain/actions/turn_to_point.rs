@@ -1,12 +1,9 @@
-use core::f64::consts::PI;
-
 use nalgebra::Point2;
+use vexide::math::Angle;
 
-use super::{RotationAction, config::ActionConfig};
+use crate::utils::angle;
 
-fn angle_between_points(from: &Point2<f64>, to: &Point2<f64>) -> f64 {
-    (to.y - from.y).atan2(to.x - from.x)
-}
+use super::{RotationAction, config::ActionConfig};
 
 /// An action that turns the robot to face a point.
 ///
@@ -42,8 +39,13 @@ impl super::Action for TurnToPointAction {
         context: super::ActionContext,
     ) -> Option<crate::subsystems::drivetrain::DrivetrainPair> {
         if self.action.is_none() {
-            let target_heading = angle_between_points(&context.data.offset, &self.target)
-                + if self.reverse { PI } else { 0.0 };
+            let target_heading = (angle::angle_to(context.data.offset, self.target)
+                + if self.reverse {
+                    Angle::HALF_TURN
+                } else {
+                    Angle::ZERO
+                })
+            .as_radians();
             log::debug!(
                 "Turn to point: {:.2} -> {:.2} ({:.2} rad)",
                 context.data.offset,
@@ -55,4 +57,8 @@ impl super::Action for TurnToPointAction {
 
         self.action.as_mut().unwrap().update(context)
     }
+
+    fn telemetry(&self) -> Option<super::ActionTelemetry> {
+        self.action.as_ref().and_then(|action| action.telemetry())
+    }
 }
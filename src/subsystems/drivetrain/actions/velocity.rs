@@ -0,0 +1,56 @@
+use crate::{
+    subsystems::drivetrain::{ChassisConfig, DrivetrainPair, drivetrain_pair::DrivetrainUnits},
+    utils::units::{Millimeters, MillimetersPerSecond},
+};
+
+/// The voltage which drives the chassis at its free speed.
+const MAX_VOLTAGE: f64 = 12.0;
+
+/// Turns the voltage output of an action into wheel RPM targets.
+///
+/// The controllers' voltages are divided by a kV feedforward gain to get the
+/// speed each side of the chassis should drive at, which is converted to the
+/// RPM of its wheels. The motors' built-in velocity controllers then hold that
+/// speed as the battery sags, and the drivetrain's acceleration limit applies.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VelocityOutput {
+    /// Volts per mm/s of wheel speed
+    kv: f64,
+    wheel_circumference: f64,
+}
+
+impl VelocityOutput {
+    /// Creates an output with a gain of `kv` volts per mm/s of wheel speed.
+    pub fn new(kv: f64, wheel_circumference: impl Into<Millimeters>) -> Self {
+        Self {
+            kv,
+            wheel_circumference: wheel_circumference.into().0,
+        }
+    }
+
+    /// Creates an output where 12 volts drives the wheels at the chassis's
+    /// [top speed](ChassisConfig::max_speed).
+    pub fn from_chassis(chassis: &ChassisConfig) -> Self {
+        Self {
+            kv: MAX_VOLTAGE / chassis.max_speed().0,
+            wheel_circumference: chassis.wheel_circumference,
+        }
+    }
+
+    /// Returns the wheel speed which `voltage` is expected to drive at.
+    pub fn speed(&self, voltage: f64) -> MillimetersPerSecond {
+        MillimetersPerSecond(voltage / self.kv)
+    }
+
+    /// Converts a voltage output to RPM. Outputs already in RPM are returned
+    /// as they are.
+    pub fn convert(&self, output: DrivetrainPair) -> DrivetrainPair {
+        match output.units {
+            DrivetrainUnits::Voltage => DrivetrainPair::new_rpm(
+                self.speed(output.left).0 / self.wheel_circumference * 60.0,
+                self.speed(output.right).0 / self.wheel_circumference * 60.0,
+            ),
+            DrivetrainUnits::RPM => output,
+        }
+    }
+}
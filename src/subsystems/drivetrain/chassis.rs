@@ -0,0 +1,90 @@
+//! The physical description of a drivetrain
+//!
+//! The same few numbers about a chassis are needed all over: the drive
+//! encoders need the track width and wheel size to track, odometry needs the
+//! tracking wheels' offsets, the action gains
+//! scale with the top speed, and the slip filter needs the acceleration
+//! limit. Passing them to each constructor separately lets them drift out of
+//! sync when the robot changes, so they are kept together in a
+//! [`ChassisConfig`]:
+//!
+//! ```ignore
+//! const CHASSIS: ChassisConfig = ChassisConfig {
+//!     track_width: 290.0,
+//!     wheel_circumference: 3.25 * 25.4 * PI,
+//!     max_rpm: 450.0,
+//!     mass: 6.5,
+//!     max_acceleration: 3000.0,
+//!     parallel_offset: 0.0,
+//!     perpendicular_offset: -50.0,
+//! };
+//!
+//! let tracking = TrackingSubsystem::drive_encoders(left.clone(), right.clone(), &CHASSIS, imu)
+//!     .with_slip_filter(SlipFilter::for_chassis(&CHASSIS));
+//! let drivetrain = Drivetrain::new(left, right, 12.0, tracking, &CHASSIS);
+//! let config = ActionConfig::from_chassis(&CHASSIS);
+//! ```
+//!
+//! RPMs are those of the wheels, as reported by the drivetrain's motor
+//! groups, so any gearing between the motors and the wheels is set on the
+//! groups with
+//! [`DoxaMotorGroup::with_gear_ratio`](crate::motorgroup::DoxaMotorGroup::with_gear_ratio).
+//!
+//! The numbers can be overridden from the `[chassis]` section of the [config
+//! file](crate::utils::config::ConfigFile::chassis), and changed at runtime
+//! through a [`ConfigStore`](crate::utils::config_store::ConfigStore).
+
+use crate::utils::units::{MillimetersPerSecond, RadiansPerSecond};
+
+/// The physical description of a drivetrain. See the [module
+/// documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChassisConfig {
+    /// The distance between the left and right wheels, in mm
+    pub track_width: f64,
+    /// The circumference of the drive wheels, in mm
+    pub wheel_circumference: f64,
+    /// The free speed of the wheels, in RPM
+    pub max_rpm: f64,
+    /// The mass of the robot, in kg
+    pub mass: f64,
+    /// How fast the wheels' RPM targets may change, in RPM per second, by
+    /// default for [`Drivetrain::new`](super::Drivetrain::new)
+    pub max_acceleration: f64,
+    /// The offset of the parallel tracking wheel from the tracking center,
+    /// in mm
+    pub parallel_offset: f64,
+    /// The offset of the perpendicular tracking wheel from the tracking
+    /// center, in mm
+    pub perpendicular_offset: f64,
+}
+
+impl ChassisConfig {
+    /// Returns the speed of the robot driving straight at the wheels' free
+    /// speed.
+    pub fn max_speed(&self) -> MillimetersPerSecond {
+        MillimetersPerSecond(self.rpm_to_speed(self.max_rpm))
+    }
+
+    /// Returns how fast the robot turns on the spot at the wheels' free
+    /// speed.
+    pub fn max_turn_rate(&self) -> RadiansPerSecond {
+        RadiansPerSecond(2.0 * self.max_speed().0 / self.track_width)
+    }
+
+    /// Returns the fastest the robot is commanded to speed up, in mm/s².
+    pub fn max_linear_acceleration(&self) -> f64 {
+        self.rpm_to_speed(self.max_acceleration)
+    }
+
+    /// Converts the wheels' RPM to their speed over the ground, in mm/s.
+    pub fn rpm_to_speed(&self, rpm: f64) -> f64 {
+        rpm / 60.0 * self.wheel_circumference
+    }
+
+    /// Converts a speed of the wheels over the ground, in mm/s, to the
+    /// wheels' RPM.
+    pub fn speed_to_rpm(&self, speed: f64) -> f64 {
+        speed / self.wheel_circumference * 60.0
+    }
+}
@@ -0,0 +1,89 @@
+//! Curvature drive mixing
+//!
+//! Arcade drive turns at the same rate whatever the throttle, which makes the
+//! robot twitchy at speed. A [`CurvatureDrive`] takes a throttle and a
+//! curvature instead, so the stick sets how tight an arc to drive and the
+//! throttle sets how fast to drive along it. Below a small throttle it
+//! quick-turns, spinning in place at the rate set by the curvature:
+//!
+//! ```ignore
+//! let curvature = CurvatureDrive::new(12.0, DrivetrainUnits::Voltage);
+//! loop {
+//!     let state = controller.state()?;
+//!     drivetrain.set_voltage(curvature.mix(state.left_stick.y(), -state.right_stick.x()));
+//!     sleep(Controller::UPDATE_INTERVAL).await;
+//! }
+//! ```
+//!
+//! [`PurePursuitAction::with_curvature_drive`] mixes its output the same way.
+//!
+//! [`PurePursuitAction::with_curvature_drive`]: super::actions::PurePursuitAction::with_curvature_drive
+
+use super::{DrivetrainPair, drivetrain_pair::DrivetrainUnits};
+
+/// Mixes a throttle and a curvature into left and right outputs. See the
+/// [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CurvatureDrive {
+    max: f64,
+    units: DrivetrainUnits,
+    quick_turn_threshold: f64,
+}
+
+impl CurvatureDrive {
+    /// Creates a mixer whose output at full throttle is `max` in `units`.
+    pub fn new(max: f64, units: DrivetrainUnits) -> Self {
+        Self {
+            max,
+            units,
+            quick_turn_threshold: 0.1,
+        }
+    }
+
+    /// Sets the throttle, as a fraction of full throttle, below which the
+    /// robot quick-turns. Defaults to `0.1`.
+    pub fn with_quick_turn_threshold(mut self, threshold: f64) -> Self {
+        self.quick_turn_threshold = threshold;
+        self
+    }
+
+    /// Returns the output at full throttle.
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    /// Returns whether `throttle` is low enough to quick-turn.
+    pub fn is_quick_turn(&self, throttle: f64) -> bool {
+        throttle.abs() < self.quick_turn_threshold
+    }
+
+    /// Mixes `throttle` and `curvature`, both from `-1.0` to `1.0`.
+    ///
+    /// A positive curvature turns counterclockwise. A curvature of `1.0`
+    /// pivots about the left wheels, or when quick-turning, spins in place at
+    /// full output.
+    pub fn mix(&self, throttle: f64, curvature: f64) -> DrivetrainPair {
+        let throttle = throttle.clamp(-1.0, 1.0);
+        let curvature = curvature.clamp(-1.0, 1.0);
+        let angular = if self.is_quick_turn(throttle) {
+            curvature
+        } else {
+            // Turning with the magnitude of the throttle keeps the arc the
+            // same at any speed, and the same way round when reversing
+            throttle.abs() * curvature
+        };
+        let mut left = throttle - angular;
+        let mut right = throttle + angular;
+        // Keep the ratio, and so the curvature, when an output saturates
+        let largest = left.abs().max(right.abs());
+        if largest > 1.0 {
+            left /= largest;
+            right /= largest;
+        }
+        DrivetrainPair {
+            left: left * self.max,
+            right: right * self.max,
+            units: self.units,
+        }
+    }
+}
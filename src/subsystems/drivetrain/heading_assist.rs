@@ -0,0 +1,164 @@
+//! Gyro-assisted tank drive
+//!
+//! Driving straight with tank controls takes both sticks at exactly the same
+//! height, and the robot still drifts when it's pushed. A [`HeadingAssist`]
+//! treats small differences between the sticks as nudges to a target heading,
+//! and holds that heading with a PID controller while driving. Larger
+//! differences turn the robot as usual, and the heading it ends up at is held
+//! once the sticks are level again:
+//!
+//! ```ignore
+//! let assist = HeadingAssist::new(tracking.clone(), ActionConfig::default());
+//! assist.bind_toggle(&input, Button::B);
+//! loop {
+//!     let state = controller.state()?;
+//!     assist.tank(&mut drivetrain, state.left_stick.y(), state.right_stick.y());
+//!     sleep(Controller::UPDATE_INTERVAL).await;
+//! }
+//! ```
+
+use core::cell::RefCell;
+use std::time::Instant;
+
+use alloc::rc::Rc;
+use vexide::math::Angle;
+
+use crate::{
+    subsystems::{
+        drivetrain::{Drivetrain, DrivetrainPair, actions::config::ActionConfig},
+        input::{BindingId, Button, InputBindings},
+        tracking::TrackingSubsystem,
+    },
+    utils::{angle, controllers::PidController},
+};
+
+/// The voltage at full stick.
+const MAX_VOLTAGE: f64 = 12.0;
+
+struct HeadingAssistInner {
+    controller: PidController,
+    deadband: f64,
+    nudge_rate: Angle,
+    enabled: bool,
+    /// The heading being held, or `None` while the driver is turning or
+    /// stopped
+    target: Option<Angle>,
+    last_update: Option<Instant>,
+}
+
+/// Holds the heading between turns in tank drive. See the
+/// [module documentation](self).
+///
+/// Clones share the same state, so one can be moved into a button binding.
+#[derive(Clone)]
+pub struct HeadingAssist {
+    tracking: TrackingSubsystem,
+    inner: Rc<RefCell<HeadingAssistInner>>,
+}
+
+impl HeadingAssist {
+    /// Creates an enabled assist which holds the heading with the turn PID
+    /// from `config`.
+    ///
+    /// Differences between the sticks of up to 0.15 nudge the heading at up
+    /// to 90° per second.
+    pub fn new(tracking: TrackingSubsystem, config: ActionConfig) -> Self {
+        Self {
+            tracking,
+            inner: Rc::new(RefCell::new(HeadingAssistInner {
+                controller: config.turn_pid(0.0),
+                deadband: 0.15,
+                nudge_rate: Angle::QUARTER_TURN,
+                enabled: true,
+                target: None,
+                last_update: None,
+            })),
+        }
+    }
+
+    /// Sets the difference between the sticks, from 0.0 to 1.0, up to which
+    /// the turn is treated as a nudge.
+    pub fn with_deadband(self, deadband: f64) -> Self {
+        self.inner.borrow_mut().deadband = deadband;
+        self
+    }
+
+    /// Sets how fast the heading is nudged, per second, when the difference
+    /// between the sticks is at the deadband.
+    pub fn with_nudge_rate(self, nudge_rate: Angle) -> Self {
+        self.inner.borrow_mut().nudge_rate = nudge_rate;
+        self
+    }
+
+    /// Returns whether the heading is being held.
+    pub fn is_enabled(&self) -> bool {
+        self.inner.borrow().enabled
+    }
+
+    /// Turns the assist on or off. While off, the sticks drive the robot
+    /// directly.
+    pub fn set_enabled(&self, enabled: bool) {
+        let mut inner = self.inner.borrow_mut();
+        inner.enabled = enabled;
+        inner.target = None;
+    }
+
+    /// Turns the assist off if it is on, and on if it is off.
+    pub fn toggle(&self) {
+        let enabled = self.is_enabled();
+        self.set_enabled(!enabled);
+    }
+
+    /// Toggles the assist whenever `button` is pressed.
+    pub fn bind_toggle(&self, input: &InputBindings, button: Button) -> BindingId {
+        let assist = self.clone();
+        input.on_press(button, move || {
+            assist.toggle();
+            log::info!(
+                "Heading assist {}",
+                if assist.is_enabled() { "on" } else { "off" }
+            );
+        })
+    }
+
+    /// Drives `drivetrain` with tank controls, where `left` and `right` are
+    /// the sticks from -1.0 to 1.0.
+    pub fn tank(&self, drivetrain: &mut Drivetrain, left: f64, right: f64) {
+        let output = self.update(left, right);
+        drivetrain.set_voltage(output);
+    }
+
+    /// Returns the output for tank controls, holding the heading if enabled.
+    pub fn update(&self, left: f64, right: f64) -> DrivetrainPair {
+        let left = left.clamp(-1.0, 1.0);
+        let right = right.clamp(-1.0, 1.0);
+        let raw = DrivetrainPair::new_voltage(left * MAX_VOLTAGE, right * MAX_VOLTAGE);
+
+        let mut inner = self.inner.borrow_mut();
+        let now = Instant::now();
+        let dt = inner
+            .last_update
+            .map_or(0.0, |last_update| (now - last_update).as_secs_f64());
+        inner.last_update = Some(now);
+
+        let throttle = (left + right) / 2.0;
+        // Counterclockwise-positive, like the heading
+        let turn = (right - left) / 2.0;
+        if !inner.enabled || turn.abs() > inner.deadband || throttle.abs() <= inner.deadband {
+            // Turning or stopped, so hold wherever the robot ends up
+            inner.target = None;
+            inner.controller.reset_integral_term();
+            return raw;
+        }
+
+        let heading = self.tracking.current().heading;
+        let nudge = inner.nudge_rate * (turn / inner.deadband * dt);
+        let target = inner.target.unwrap_or(heading) + nudge;
+        inner.target = Some(target);
+
+        let error = angle::shortest_error(target, heading).as_radians();
+        let correction = inner.controller.next_control_output(error).output;
+        let throttle = throttle * MAX_VOLTAGE;
+        DrivetrainPair::new_voltage(throttle - correction, throttle + correction).max(MAX_VOLTAGE)
+    }
+}
@@ -1,24 +1,64 @@
-use core::{cell::RefCell, future::Future, sync::atomic::AtomicBool};
+use core::{
+    cell::{Cell, RefCell},
+    future::Future,
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+use std::time::Instant;
 
 use alloc::{boxed::Box, rc::Rc};
-use vexide_motorgroup::SharedMotors;
 
 use crate::{
-    subsystems::tracking::TrackingData, utils::unwrap_expect_report::UnwrapExpectReportExt as _,
+    debug_render::Graph,
+    motorgroup::DoxaMotorGroup,
+    subsystems::{drivetrain::actions::Action as _, tracking::TrackingData},
+    utils::{
+        events::{self, EventBus},
+        logger::native_log,
+        profiling, telemetry,
+        ticker::{LOOP_PERIOD, Ticker},
+        unwrap_expect_report::UnwrapExpectReportExt as _,
+    },
 };
 
 use super::tracking::TrackingSubsystem;
+use acceleration::Ramp;
+use tip_guard::TipGuard;
 
+pub mod acceleration;
 pub mod actions;
+pub mod chassis;
+pub mod curvature;
 pub mod drivetrain_pair;
+pub mod heading_assist;
+pub mod self_test;
+pub mod tip_guard;
+pub mod trim;
 
+pub use chassis::ChassisConfig;
 pub use drivetrain_pair::DrivetrainPair;
 
-const LOOP_TIME: f64 = 10.0; // ms
+/// How a drivetrain action ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionOutcome {
+    /// The action finished by itself.
+    Settled,
+    /// The action was replaced by another one, or cancelled.
+    Cancelled,
+    /// The [timeout](DrivetrainActionFuture::with_timeout) elapsed first, so
+    /// the action was stopped.
+    TimedOut,
+}
 
+/// A future which resolves when an action settles, or when it is replaced or
+/// cancelled.
 #[allow(clippy::type_complexity)]
 pub struct DrivetrainActionFuture {
-    settled: Rc<AtomicBool>,
+    id: u32,
+    finished: Rc<AtomicU32>,
+    settled: Rc<Cell<bool>>,
+    deadline: Option<Instant>,
+    timed_out: Rc<Cell<bool>>,
     tracking: TrackingSubsystem,
     callback: Option<RefCell<Box<dyn FnMut(TrackingData)>>>,
 }
@@ -34,7 +74,21 @@ impl Future for DrivetrainActionFuture {
         self: core::pin::Pin<&mut Self>,
         cx: &mut core::task::Context<'_>,
     ) -> core::task::Poll<Self::Output> {
-        if self.settled.load(core::sync::atomic::Ordering::Acquire) {
+        if self.finished.load(Ordering::Acquire) >= self.id {
+            core::task::Poll::Ready(())
+        } else if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            native_log!(
+                warn,
+                "Drivetrain action timed out; stopping it",
+                "Drivetrain action timed out; stopping it"
+            );
+            self.timed_out.set(true);
+            // The drivetrain task stops the motors once the action is marked
+            // finished
+            self.finished.fetch_max(self.id, Ordering::SeqCst);
             core::task::Poll::Ready(())
         } else {
             cx.waker().wake_by_ref();
@@ -51,12 +105,77 @@ impl DrivetrainActionFuture {
         self.callback = Some(RefCell::new(Box::new(callback)));
         self
     }
+
+    /// Stops the action if it is still running after `timeout`, measured
+    /// from now.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.deadline = Some(Instant::now() + timeout);
+        self
+    }
+
+    /// Waits for the action to end like awaiting the future directly, and
+    /// returns how it ended.
+    pub async fn await_settled_or_cancel(self) -> ActionOutcome {
+        let settled = self.settled.clone();
+        let timed_out = self.timed_out.clone();
+        self.await;
+        if settled.get() {
+            ActionOutcome::Settled
+        } else if timed_out.get() {
+            ActionOutcome::TimedOut
+        } else {
+            ActionOutcome::Cancelled
+        }
+    }
+}
+
+/// The currently running action, its id, and whether it settled, shared with
+/// the drivetrain task.
+pub(crate) type ActionSlot = Rc<RefCell<Option<(actions::AnyAction, u32, Rc<Cell<bool>>)>>>;
+
+/// Stops the running action of a [`Drivetrain`], from
+/// [`Drivetrain::canceller`].
+#[derive(Clone)]
+pub struct ActionCanceller {
+    action: ActionSlot,
+    finished: Rc<AtomicU32>,
+}
+
+impl core::fmt::Debug for ActionCanceller {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ActionCanceller").finish_non_exhaustive()
+    }
+}
+
+impl ActionCanceller {
+    /// Stops the running action, if any. The drivetrain task stops the
+    /// motors on its next update, and the action's future resolves as
+    /// [cancelled](ActionOutcome::Cancelled).
+    pub fn cancel(&self) {
+        if let Some((_, id, _)) = self.action.borrow().as_ref() {
+            self.finished.fetch_max(*id, Ordering::SeqCst);
+        }
+    }
 }
 
-#[allow(clippy::type_complexity)]
 pub struct Drivetrain {
-    action: Rc<RefCell<Option<(Box<dyn actions::Action>, Rc<AtomicBool>)>>>,
+    pub(crate) action: ActionSlot,
+    /// The id of the last action started
+    last_id: u32,
+    /// Every action with an id up to this one has settled, or was replaced or
+    /// cancelled
+    finished: Rc<AtomicU32>,
     max_voltage: Rc<RefCell<f64>>,
+    error_graph: Rc<RefCell<Option<Graph>>>,
+    output_graph: Rc<RefCell<Option<Graph>>>,
+    pub(crate) last_output: Rc<RefCell<Option<DrivetrainPair>>>,
+    tip_guard: Rc<Cell<Option<TipGuard>>>,
+    angular_acceleration: Rc<Cell<Option<Ramp>>>,
+    trim: Rc<Cell<Option<trim::DriveTrim>>>,
+    tipping: Rc<Cell<bool>>,
+    tracing: Rc<Cell<bool>>,
+    velocity_trace: Rc<Cell<Option<u32>>>,
+    events: Rc<RefCell<Option<EventBus>>>,
     tracking: TrackingSubsystem,
     _task: vexide::task::Task<()>,
 }
@@ -67,25 +186,65 @@ unsafe impl Sync for Drivetrain {}
 
 #[allow(clippy::await_holding_refcell_ref)]
 impl Drivetrain {
+    /// Creates a drivetrain whose RPM targets change by at most the
+    /// chassis's [`max_acceleration`](ChassisConfig::max_acceleration).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the chassis's `max_acceleration` is negative or NaN.
     pub fn new(
-        mut left: SharedMotors,
-        mut right: SharedMotors,
+        mut left: DoxaMotorGroup,
+        mut right: DoxaMotorGroup,
         max_voltage: f64,
         tracking: TrackingSubsystem,
-        max_acceleration: f64, // rpm/s
+        chassis: &ChassisConfig,
     ) -> Self {
-        let max_acceleration_loop = max_acceleration * LOOP_TIME / 1000.0;
-        let action = Rc::new(RefCell::new(None));
+        let default_rpm_ramp = Ramp::symmetric(chassis.max_acceleration);
+        let action: ActionSlot = Rc::new(RefCell::new(None));
+        let finished = Rc::new(AtomicU32::new(0));
         let max_voltage = Rc::new(RefCell::new(max_voltage));
+        let error_graph: Rc<RefCell<Option<Graph>>> = Rc::new(RefCell::new(None));
+        let output_graph: Rc<RefCell<Option<Graph>>> = Rc::new(RefCell::new(None));
+        let last_output = Rc::new(RefCell::new(None));
+        let tip_guard: Rc<Cell<Option<TipGuard>>> = Rc::new(Cell::new(None));
+        let angular_acceleration: Rc<Cell<Option<Ramp>>> = Rc::new(Cell::new(None));
+        let trim: Rc<Cell<Option<trim::DriveTrim>>> = Rc::new(Cell::new(None));
+        let tipping = Rc::new(Cell::new(false));
+        let tracing = Rc::new(Cell::new(false));
+        let velocity_trace: Rc<Cell<Option<u32>>> = Rc::new(Cell::new(None));
+        let events: Rc<RefCell<Option<EventBus>>> = Rc::new(RefCell::new(None));
         Drivetrain {
             action: action.clone(),
+            last_id: 0,
+            finished: finished.clone(),
             max_voltage: max_voltage.clone(),
+            error_graph: error_graph.clone(),
+            output_graph: output_graph.clone(),
+            last_output: last_output.clone(),
+            tip_guard: tip_guard.clone(),
+            angular_acceleration: angular_acceleration.clone(),
+            trim: trim.clone(),
+            tipping: tipping.clone(),
+            tracing: tracing.clone(),
+            velocity_trace: velocity_trace.clone(),
+            events: events.clone(),
             tracking: tracking.clone(),
             _task: vexide::task::spawn(async move {
                 let last_max_voltage = 0.0;
                 let mut last_left_rpm = 0.0;
                 let mut last_right_rpm = 0.0;
+                let mut last_left_voltage = 0.0;
+                let mut last_right_voltage = 0.0;
+                // The id of the last action traced, to log each one's name once
+                let mut traced_id = 0;
+                // The loops since the velocities were last traced
+                let mut velocity_trace_count = 0;
+                let mut ticker = Ticker::new(LOOP_PERIOD);
+                // The time the last loop actually took, for the acceleration
+                // limit
+                let mut dt = LOOP_PERIOD;
                 loop {
+                    let scope = profiling::scope("drivetrain");
                     {
                         let max_voltage = max_voltage.borrow();
                         if *max_voltage != last_max_voltage {
@@ -97,6 +256,9 @@ impl Drivetrain {
                                 .expect_report("failed to set right dt max voltage");
                         }
                     }
+                    // The name of the action which settled, published once the
+                    // action is no longer borrowed
+                    let mut settled_action = None;
                     {
                         let mut action_owned = action.borrow_mut();
                         if let Some(ref mut action_ref) = *action_owned {
@@ -105,19 +267,78 @@ impl Drivetrain {
                             // Assemble the action context
                             let context = actions::ActionContext { data };
                             // Run the action
-                            if !action_ref.1.load(core::sync::atomic::Ordering::Acquire)
-                                && let Some(mut voltage) = action_ref.0.update(context)
-                            {
+                            let running = finished.load(Ordering::Acquire) < action_ref.1;
+                            if running && let Some(mut voltage) = action_ref.0.update(context) {
                                 // If the action is still running
+                                if let Some(telemetry) = action_ref.0.telemetry() {
+                                    telemetry::record("action_error", telemetry.error);
+                                    telemetry::record("action_output", telemetry.output);
+                                    if let Some(graph) = error_graph.borrow().as_ref() {
+                                        graph.push(telemetry.error);
+                                    }
+                                    if let Some(graph) = output_graph.borrow().as_ref() {
+                                        graph.push(telemetry.output);
+                                    }
+                                }
                                 if tracking.reverse() {
                                     // Rotate the robot in the opposite direction
                                     // if the tracking subsystem is reversed
                                     voltage = voltage.reverse();
                                 }
+                                if let Some(trim) = trim.get() {
+                                    // The trim is for the physical sides, so
+                                    // it goes after reversing
+                                    voltage = trim.apply(voltage);
+                                }
+                                if let Some(guard) = tip_guard.get() {
+                                    let was_tipping = tipping.get();
+                                    let is_tipping = guard.is_tipping(&data, was_tipping);
+                                    if is_tipping && !was_tipping {
+                                        native_log!(
+                                            warn,
+                                            "Tipping (pitch {=f64}°, roll {=f64}°); overriding drivetrain output",
+                                            "Tipping (pitch {:.1}°, roll {:.1}°); overriding drivetrain output",
+                                            data.pitch.as_degrees(),
+                                            data.roll.as_degrees()
+                                        );
+                                        action_ref.0.on_event(actions::ActionEvent::Tipping {
+                                            pitch: data.pitch,
+                                            roll: data.roll,
+                                        });
+                                    } else if was_tipping && !is_tipping {
+                                        native_log!(
+                                            info,
+                                            "Level again; resuming drivetrain output",
+                                            "Level again; resuming drivetrain output"
+                                        );
+                                        action_ref.0.on_event(actions::ActionEvent::Recovered);
+                                    }
+                                    tipping.set(is_tipping);
+                                    if is_tipping {
+                                        voltage = guard.apply(voltage);
+                                    }
+                                }
+                                let limit = action_ref.0.acceleration_limit().unwrap_or_default();
                                 // Scale the voltage to be under the max voltage
                                 match voltage.units {
                                     drivetrain_pair::DrivetrainUnits::Voltage => {
                                         voltage = voltage.max(*max_voltage.borrow());
+                                        if let Some(ramp) = limit.angular_voltage {
+                                            (voltage.left, voltage.right) = ramp.step_angular(
+                                                (last_left_voltage, last_right_voltage),
+                                                voltage.left,
+                                                voltage.right,
+                                                dt,
+                                            );
+                                        }
+                                        if let Some(ramp) = limit.voltage {
+                                            voltage.left =
+                                                ramp.step(last_left_voltage, voltage.left, dt);
+                                            voltage.right =
+                                                ramp.step(last_right_voltage, voltage.right, dt);
+                                        }
+                                        last_left_voltage = voltage.left;
+                                        last_right_voltage = voltage.right;
                                         // Set the voltage
                                         left.set_voltage(voltage.left).expect_report(
                                             "failed to set left voltage in drivetrain",
@@ -127,25 +348,22 @@ impl Drivetrain {
                                         );
                                     }
                                     drivetrain_pair::DrivetrainUnits::RPM => {
-                                        // Set the RPM
-                                        if voltage.left > last_left_rpm {
-                                            voltage.left = voltage
-                                                .left
-                                                .min(last_left_rpm + max_acceleration_loop);
-                                        } else if voltage.left < last_left_rpm {
-                                            voltage.left = voltage
-                                                .left
-                                                .max(last_left_rpm - max_acceleration_loop);
-                                        }
-                                        if voltage.right > last_right_rpm {
-                                            voltage.right = voltage
-                                                .right
-                                                .min(last_right_rpm + max_acceleration_loop);
-                                        } else if voltage.right < last_right_rpm {
-                                            voltage.right = voltage
-                                                .right
-                                                .max(last_right_rpm - max_acceleration_loop);
+                                        // Set the RPM, limiting the change by
+                                        // the time since the last loop
+                                        if let Some(ramp) =
+                                            limit.angular_rpm.or(angular_acceleration.get())
+                                        {
+                                            (voltage.left, voltage.right) = ramp.step_angular(
+                                                (last_left_rpm, last_right_rpm),
+                                                voltage.left,
+                                                voltage.right,
+                                                dt,
+                                            );
                                         }
+                                        let ramp = limit.rpm.unwrap_or(default_rpm_ramp);
+                                        voltage.left = ramp.step(last_left_rpm, voltage.left, dt);
+                                        voltage.right =
+                                            ramp.step(last_right_rpm, voltage.right, dt);
                                         last_left_rpm = voltage.left;
                                         last_right_rpm = voltage.right;
                                         left.set_velocity(voltage.left as i32)
@@ -155,8 +373,37 @@ impl Drivetrain {
                                             .expect_report("failed to set right RPM in drivetrain");
                                     }
                                 }
+                                *last_output.borrow_mut() = Some(voltage);
+                                if tracing.get() {
+                                    if traced_id != action_ref.1 {
+                                        traced_id = action_ref.1;
+                                        native_log!(
+                                            info,
+                                            "Trace: action {=u32} is {=str}",
+                                            "Trace: action {} is {}",
+                                            traced_id,
+                                            action_ref.0.name()
+                                        );
+                                    }
+                                    telemetry::record("trace_action", traced_id as f64);
+                                    telemetry::record("trace_left", voltage.left);
+                                    telemetry::record("trace_right", voltage.right);
+                                    telemetry::record("trace_x", data.offset.x);
+                                    telemetry::record("trace_y", data.offset.y);
+                                    telemetry::record("trace_heading", data.heading.as_degrees());
+                                }
                                 drop(action_owned);
                             } else {
+                                if running {
+                                    // The action finished by itself
+                                    action_ref.2.set(true);
+                                    settled_action = Some(action_ref.0.name());
+                                }
+                                *last_output.borrow_mut() = None;
+                                last_left_voltage = 0.0;
+                                last_right_voltage = 0.0;
+                                last_left_rpm = 0.0;
+                                last_right_rpm = 0.0;
                                 // Zero out the motors if the action is done
                                 left.set_voltage(0.0)
                                     .expect_report("failed to zero left dt voltage");
@@ -164,24 +411,130 @@ impl Drivetrain {
                                     .set_voltage(0.0)
                                     .expect_report("failed to zero right dt voltage");
                                 // Notify the main task that the action is done
-                                action_ref
-                                    .1
-                                    .store(true, core::sync::atomic::Ordering::SeqCst);
+                                finished.fetch_max(action_ref.1, Ordering::SeqCst);
                             }
                         }
                     }
-                    vexide::time::sleep(core::time::Duration::from_millis(10)).await;
+                    if let Some(name) = settled_action
+                        && let Some(bus) = events.borrow().clone()
+                    {
+                        bus.publish(events::ACTION_SETTLED, name);
+                    }
+                    if let Some(every) = velocity_trace.get() {
+                        velocity_trace_count += 1;
+                        if velocity_trace_count >= every {
+                            velocity_trace_count = 0;
+                            // Stopped is a command of zero volts
+                            let command = last_output
+                                .borrow()
+                                .unwrap_or(DrivetrainPair::new_voltage(0.0, 0.0));
+                            // The channels of the other unit are cleared, so
+                            // a stale command isn't plotted alongside
+                            let (rpm, voltage) = match command.units {
+                                drivetrain_pair::DrivetrainUnits::RPM => {
+                                    ((command.left, command.right), (f64::NAN, f64::NAN))
+                                }
+                                drivetrain_pair::DrivetrainUnits::Voltage => {
+                                    ((f64::NAN, f64::NAN), (command.left, command.right))
+                                }
+                            };
+                            telemetry::record("velocity_left_command_rpm", rpm.0);
+                            telemetry::record("velocity_right_command_rpm", rpm.1);
+                            telemetry::record("velocity_left_command_voltage", voltage.0);
+                            telemetry::record("velocity_right_command_voltage", voltage.1);
+                            // Read what the motors which answered measured,
+                            // without logging a failure every sample
+                            for (channel, reading) in [
+                                (
+                                    "velocity_left_rpm",
+                                    left.velocity().map_or_else(|err| err.result, Some),
+                                ),
+                                (
+                                    "velocity_right_rpm",
+                                    right.velocity().map_or_else(|err| err.result, Some),
+                                ),
+                                (
+                                    "velocity_left_voltage",
+                                    left.voltage().map_or_else(|err| err.result, Some),
+                                ),
+                                (
+                                    "velocity_right_voltage",
+                                    right.voltage().map_or_else(|err| err.result, Some),
+                                ),
+                            ] {
+                                if let Some(value) = reading {
+                                    telemetry::record(channel, value);
+                                }
+                            }
+                        }
+                    }
+                    drop(scope);
+                    dt = ticker.tick().await;
                 }
             }),
         }
     }
 
     pub fn set_voltage(&mut self, voltage: DrivetrainPair) {
-        let mut action = self.action.borrow_mut();
-        *action = Some((
-            Box::new(actions::VoltageAction { voltage }),
-            Rc::new(AtomicBool::new(false)),
-        ));
+        _ = self.action(actions::VoltageAction { voltage });
+    }
+
+    /// Overrides the output of actions while the robot is tipping. See
+    /// [`tip_guard`].
+    pub fn with_tip_guard(self, guard: TipGuard) -> Self {
+        self.tip_guard.set(Some(guard));
+        self
+    }
+
+    /// Limits how fast the turning part of RPM outputs, half the difference
+    /// between the sides, changes for every action, in RPM per second, or
+    /// `None` to limit only each side. An action's
+    /// [`AccelerationLimit`](acceleration::AccelerationLimit) overrides this.
+    pub fn set_angular_acceleration(&mut self, ramp: Option<Ramp>) {
+        self.angular_acceleration.set(ramp);
+    }
+
+    /// Returns whether the [`TipGuard`] is overriding the output because the
+    /// robot is tipping.
+    pub fn is_tipping(&self) -> bool {
+        self.tipping.get()
+    }
+
+    /// Turns tracing on or off. While on, the id, output and pose of the
+    /// running action are recorded to [`telemetry`] every update, as the
+    /// `trace_*` channels, next to `action_error` and `action_output`. The
+    /// name of each action is logged with its id when it is first traced.
+    pub fn set_tracing(&self, tracing: bool) {
+        self.tracing.set(tracing);
+    }
+
+    /// Records the commanded and measured velocity of each side to
+    /// [`telemetry`] every `every` updates, or `None` to stop, for tuning
+    /// feedforward and acceleration limits from plots.
+    ///
+    /// The command is the output last sent to the motors, after scaling and
+    /// acceleration limiting, as `velocity_left_command_rpm` and
+    /// `velocity_right_command_rpm` while it is in RPM, or
+    /// `velocity_left_command_voltage` and `velocity_right_command_voltage`
+    /// while it is in volts. The other unit's channels are NaN. The motors'
+    /// measured RPM and voltage are `velocity_left_rpm`,
+    /// `velocity_right_rpm`, `velocity_left_voltage` and
+    /// `velocity_right_voltage`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `every` is zero.
+    pub fn set_velocity_trace(&mut self, every: Option<u32>) {
+        assert!(
+            every != Some(0),
+            "velocity trace decimation must be positive"
+        );
+        self.velocity_trace.set(every);
+    }
+
+    /// Returns whether tracing is on. See [`set_tracing`](Self::set_tracing).
+    pub fn is_tracing(&self) -> bool {
+        self.tracing.get()
     }
 
     pub fn set_max_voltage(&mut self, max_voltage: f64) {
@@ -189,25 +542,77 @@ impl Drivetrain {
         *max_voltage_ref = max_voltage;
     }
 
+    /// Returns the tracking subsystem the drivetrain's actions use.
+    pub fn tracking(&self) -> &TrackingSubsystem {
+        &self.tracking
+    }
+
+    /// Sets the bus that an [`ACTION_SETTLED`](events::ACTION_SETTLED) event
+    /// is published to whenever an action finishes by itself, or `None` to
+    /// stop publishing.
+    pub fn set_events(&mut self, events: Option<EventBus>) {
+        *self.events.borrow_mut() = events;
+    }
+
+    /// Sets the graph that the error of the current action's primary
+    /// controller is plotted to every loop, or `None` to stop plotting.
+    pub fn set_error_graph(&mut self, graph: Option<Graph>) {
+        *self.error_graph.borrow_mut() = graph;
+    }
+
+    /// Sets the graph that the output of the current action's primary
+    /// controller is plotted to every loop, or `None` to stop plotting.
+    pub fn set_output_graph(&mut self, graph: Option<Graph>) {
+        *self.output_graph.borrow_mut() = graph;
+    }
+
+    /// Returns the output the drivetrain last sent to the motors, after
+    /// scaling and acceleration limiting, or `None` if no action is running.
+    pub fn last_output(&self) -> Option<DrivetrainPair> {
+        *self.last_output.borrow()
+    }
+
     pub fn boxed_action(&mut self, new_action: Box<dyn actions::Action>) -> DrivetrainActionFuture {
-        let mut action = self.action.borrow_mut();
-        let bool = Rc::new(AtomicBool::new(false));
-        *action = Some((new_action, bool.clone()));
-        drop(action);
+        self.action(new_action)
+    }
+
+    /// Starts an action, replacing the current one. The future of the
+    /// replaced action resolves immediately.
+    ///
+    /// Built-in actions are stored without allocating. Custom actions must be
+    /// boxed, e.g., with [`AnyAction::boxed`](actions::AnyAction::boxed).
+    pub fn action(&mut self, action: impl Into<actions::AnyAction>) -> DrivetrainActionFuture {
+        self.last_id += 1;
+        let settled = Rc::new(Cell::new(false));
+        *self.action.borrow_mut() = Some((action.into(), self.last_id, settled.clone()));
+        self.finished.fetch_max(self.last_id - 1, Ordering::SeqCst);
 
         DrivetrainActionFuture {
+            id: self.last_id,
+            finished: self.finished.clone(),
+            settled,
+            deadline: None,
+            timed_out: Rc::new(Cell::new(false)),
             callback: None,
-            settled: bool,
             tracking: self.tracking.clone(),
         }
     }
 
-    pub fn action(&mut self, action: impl actions::Action + 'static) -> DrivetrainActionFuture {
-        self.boxed_action(Box::new(action))
+    /// Returns a handle which stops the running action, for code which
+    /// doesn't own the drivetrain, e.g., a
+    /// [`RouteContext`](crate::auton::RouteContext) cutting off a step.
+    pub fn canceller(&self) -> ActionCanceller {
+        ActionCanceller {
+            action: self.action.clone(),
+            finished: self.finished.clone(),
+        }
     }
 
+    /// Stops the current action. Its future resolves immediately.
     pub fn cancel_action(&mut self) {
         let mut action = self.action.borrow_mut();
         *action = None;
+        self.finished.fetch_max(self.last_id, Ordering::SeqCst);
+        *self.last_output.borrow_mut() = None;
     }
 }
@@ -0,0 +1,289 @@
+use core::time::Duration;
+use std::time::Instant;
+
+use alloc::{format, string::String, vec::Vec};
+use nalgebra::{Rotation2, Vector2};
+use vexide::math::Angle;
+
+use crate::{subsystems::tracking::TrackingDiagnostics, utils::angle};
+
+use super::{
+    ActionOutcome, Drivetrain,
+    actions::{DriveToPointAction, RotationAction, config::ActionConfig},
+};
+
+/// How long the robot is kept still to measure the heading sensor's drift.
+const STILL_TIME: Duration = Duration::from_secs(3);
+
+/// How long each move of the pattern may take before the test gives up.
+const MOVE_TIMEOUT: Duration = Duration::from_secs(4);
+
+/// The moves of an [odometry self-test](Drivetrain::odometry_self_test).
+/// Both end where they started.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelfTestPattern {
+    /// Drives a square with sides `side` mm long, turning left at each
+    /// corner.
+    Square { side: f64 },
+    /// Turns on the spot, a quarter turn at a time, `turns` full turns to
+    /// the left.
+    Spin { turns: u32 },
+}
+
+/// How large each error of an [`OdometrySelfTest`] may be for it to pass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelfTestLimits {
+    /// In mm
+    pub position_error: f64,
+    pub heading_error: Angle,
+    /// In mm
+    pub wheel_disagreement: f64,
+    /// In degrees per second
+    pub drift_rate: f64,
+}
+
+impl Default for SelfTestLimits {
+    fn default() -> Self {
+        Self {
+            position_error: 30.0,
+            heading_error: Angle::from_degrees(2.0),
+            wheel_disagreement: 20.0,
+            drift_rate: 0.05,
+        }
+    }
+}
+
+/// The result of an [odometry self-test](Drivetrain::odometry_self_test).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OdometrySelfTest {
+    pub pattern: SelfTestPattern,
+    pub limits: SelfTestLimits,
+    /// Whether every move of the pattern settled before its timeout
+    pub completed: bool,
+    /// How far, in mm, the tracked position ended from where it started
+    pub position_error: f64,
+    /// How far the tracked heading ended from where it started
+    pub heading_error: Angle,
+    /// How much, in mm, the parallel tracking wheels disagree about how far
+    /// the center of the robot moved, after accounting for the rotation, or
+    /// `None` with fewer than two parallel wheels
+    pub wheel_disagreement: Option<f64>,
+    /// How fast the heading sensor drifted while the robot was still, in
+    /// degrees per second
+    pub drift_rate: f64,
+}
+
+impl OdometrySelfTest {
+    /// Returns the names of the checks which failed.
+    pub fn failures(&self) -> Vec<&'static str> {
+        let mut failures = Vec::new();
+        if !self.completed {
+            failures.push("pattern");
+        }
+        if self.position_error > self.limits.position_error {
+            failures.push("position");
+        }
+        if self.heading_error.as_radians().abs() > self.limits.heading_error.as_radians() {
+            failures.push("heading");
+        }
+        if self
+            .wheel_disagreement
+            .is_some_and(|disagreement| disagreement > self.limits.wheel_disagreement)
+        {
+            failures.push("wheels");
+        }
+        if self.drift_rate.abs() > self.limits.drift_rate {
+            failures.push("drift");
+        }
+        failures
+    }
+
+    /// Returns whether every check passed.
+    pub fn passed(&self) -> bool {
+        self.failures().is_empty()
+    }
+
+    /// Returns a summary of the test, one check per line, e.g., for a
+    /// [`TextPage`](crate::debug_render::TextPage).
+    pub fn lines(&self) -> Vec<String> {
+        let failures = self.failures();
+        let mark = |check: &str| {
+            if failures.contains(&check) {
+                "FAIL"
+            } else {
+                "ok"
+            }
+        };
+        let mut lines = Vec::from([
+            format!(
+                "Odometry self-test: {}",
+                if failures.is_empty() { "PASS" } else { "FAIL" }
+            ),
+            format!(
+                "pattern {:?}: {}",
+                self.pattern,
+                if self.completed { "ok" } else { "timed out" }
+            ),
+            format!(
+                "position {:.0} mm: {}",
+                self.position_error,
+                mark("position")
+            ),
+            format!(
+                "heading {:.1} deg: {}",
+                self.heading_error.as_degrees(),
+                mark("heading")
+            ),
+        ]);
+        if let Some(disagreement) = self.wheel_disagreement {
+            lines.push(format!(
+                "wheels {:.0} mm apart: {}",
+                disagreement,
+                mark("wheels")
+            ));
+        }
+        lines.push(format!(
+            "drift {:.3} deg/s: {}",
+            self.drift_rate,
+            mark("drift")
+        ));
+        lines
+    }
+
+    /// Logs the summary, as a warning if any check failed.
+    pub fn log(&self) {
+        let passed = self.passed();
+        for line in self.lines() {
+            if passed {
+                log::info!("{}", line);
+            } else {
+                log::warn!("{}", line);
+            }
+        }
+    }
+}
+
+/// Returns the distance the center of the robot moved forwards according to
+/// each parallel wheel, between two diagnostics, after taking out the part
+/// each wheel moved because the robot turned by `rotation`.
+fn implied_distances(
+    start: &TrackingDiagnostics,
+    end: &TrackingDiagnostics,
+    rotation: Angle,
+) -> Vec<f64> {
+    end.parallel
+        .iter()
+        .zip(&start.parallel)
+        .map(|(end, start)| {
+            // Turning left (counterclockwise) moves a wheel to the right of
+            // center forwards
+            end.distance - start.distance - end.mounting_offset * rotation.as_radians()
+        })
+        .collect()
+}
+
+impl Drivetrain {
+    /// Checks odometry by keeping the robot still, then driving `pattern`
+    /// from where it is and comparing where tracking thinks it ended.
+    ///
+    /// The test measures the heading sensor's drift while still, how far the
+    /// tracked pose ended from the start, and whether the parallel wheels
+    /// agree. Mark where the robot started on the field: if the tracked pose
+    /// closes but the robot is visibly elsewhere, the wheel circumference or
+    /// offsets are wrong.
+    ///
+    /// ```ignore
+    /// let report = drivetrain
+    ///     .odometry_self_test(SelfTestPattern::Spin { turns: 2 }, config, SelfTestLimits::default())
+    ///     .await;
+    /// report.log();
+    /// ```
+    pub async fn odometry_self_test(
+        &mut self,
+        pattern: SelfTestPattern,
+        config: ActionConfig,
+        limits: SelfTestLimits,
+    ) -> OdometrySelfTest {
+        let tracking = self.tracking.clone();
+
+        // Measure the drift first, while nothing is moving
+        let still_start = tracking.diagnostics().raw_heading;
+        let still_since = Instant::now();
+        vexide::time::sleep(STILL_TIME).await;
+        let drift = angle::shortest_error(tracking.diagnostics().raw_heading, still_start);
+        let drift_rate = drift.as_degrees() / still_since.elapsed().as_secs_f64();
+
+        let start = tracking.current();
+        let start_diagnostics = tracking.diagnostics();
+        let heading_after =
+            |quarter_turns: u32| start.heading + Angle::from_degrees(90.0 * quarter_turns as f64);
+
+        let mut completed = true;
+        match pattern {
+            SelfTestPattern::Square { side } => {
+                let mut corner = start.offset;
+                for side_index in 0..4 {
+                    corner = if side_index == 3 {
+                        start.offset
+                    } else {
+                        corner
+                            + Rotation2::new(heading_after(side_index).as_radians())
+                                * Vector2::new(side, 0.0)
+                    };
+                    completed &= self
+                        .self_test_move(DriveToPointAction::new(corner, config))
+                        .await;
+                    completed &= self
+                        .self_test_move(RotationAction::new(
+                            heading_after(side_index + 1).as_radians(),
+                            config,
+                        ))
+                        .await;
+                }
+            }
+            SelfTestPattern::Spin { turns } => {
+                for quarter_turn in 1..=turns * 4 {
+                    completed &= self
+                        .self_test_move(RotationAction::new(
+                            heading_after(quarter_turn).as_radians(),
+                            config,
+                        ))
+                        .await;
+                }
+            }
+        }
+
+        let end = tracking.current();
+        let implied = implied_distances(
+            &start_diagnostics,
+            &tracking.diagnostics(),
+            end.rotation - start.rotation,
+        );
+        let wheel_disagreement = (implied.len() >= 2).then(|| {
+            implied.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+                - implied.iter().copied().fold(f64::INFINITY, f64::min)
+        });
+        OdometrySelfTest {
+            pattern,
+            limits,
+            completed,
+            position_error: nalgebra::distance(&start.offset, &end.offset),
+            heading_error: angle::shortest_error(end.heading, start.heading),
+            wheel_disagreement,
+            drift_rate,
+        }
+    }
+
+    /// Runs one move of a self-test, returning whether it settled.
+    async fn self_test_move(&mut self, action: impl Into<super::actions::AnyAction>) -> bool {
+        let outcome = self
+            .action(action)
+            .with_timeout(MOVE_TIMEOUT)
+            .await_settled_or_cancel()
+            .await;
+        if outcome != ActionOutcome::Settled {
+            log::warn!("Odometry self-test: a move ended with {:?}", outcome);
+        }
+        outcome == ActionOutcome::Settled
+    }
+}
@@ -0,0 +1,104 @@
+//! Keeping the robot on its wheels
+//!
+//! Accelerating hard, or driving up onto a field element, can lift one end of
+//! the robot. A [`TipGuard`] watches the pitch and roll from tracking (see
+//! [`TrackingSubsystem::with_tilt_sensor`]) in the drivetrain task, and
+//! overrides the action's output when the robot tilts past a threshold, until
+//! it is close to level again:
+//!
+//! ```ignore
+//! let tracking = TrackingSubsystem::new(perpendicular, parallel, imu.clone()).with_tilt_sensor(imu);
+//! let drivetrain = Drivetrain::new(left, right, 12.0, tracking, &CHASSIS)
+//!     .with_tip_guard(TipGuard::new(15.0.deg(), TipResponse::Reverse(4.0)));
+//! ```
+//!
+//! The running action is told with an [`ActionEvent`] when the guard takes
+//! over and when it hands back.
+//!
+//! [`TrackingSubsystem::with_tilt_sensor`]: crate::subsystems::tracking::TrackingSubsystem::with_tilt_sensor
+//! [`ActionEvent`]: super::actions::ActionEvent
+
+use vexide::math::Angle;
+
+use crate::subsystems::{
+    drivetrain::{DrivetrainPair, drivetrain_pair::DrivetrainUnits},
+    tracking::TrackingData,
+};
+
+/// What the drivetrain does while the robot is tipping.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TipResponse {
+    /// Stops the motors.
+    Stop,
+    /// Limits the output to this voltage, so the action can carry on more
+    /// gently.
+    Clamp(f64),
+    /// Drives straight at this voltage in the opposite direction to the
+    /// action's output, which brings the raised end back down when the tip
+    /// was caused by accelerating.
+    Reverse(f64),
+}
+
+/// Overrides the drivetrain output while the robot is tipping. See the
+/// [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TipGuard {
+    threshold: Angle,
+    recovery: Angle,
+    response: TipResponse,
+}
+
+impl TipGuard {
+    /// Creates a guard which responds once the pitch or roll exceeds
+    /// `threshold`, until both are within half of it again.
+    pub fn new(threshold: Angle, response: TipResponse) -> Self {
+        Self {
+            threshold,
+            recovery: threshold / 2.0,
+            response,
+        }
+    }
+
+    /// Sets how close to level both the pitch and roll must be for the guard
+    /// to hand back to the action.
+    pub fn with_recovery(mut self, recovery: Angle) -> Self {
+        self.recovery = recovery;
+        self
+    }
+
+    /// Returns whether the robot is tipping, given whether it was tipping
+    /// before.
+    pub(crate) fn is_tipping(&self, data: &TrackingData, was_tipping: bool) -> bool {
+        let tilt = data
+            .pitch
+            .as_radians()
+            .abs()
+            .max(data.roll.as_radians().abs());
+        if was_tipping {
+            tilt > self.recovery.as_radians()
+        } else {
+            tilt > self.threshold.as_radians()
+        }
+    }
+
+    /// Returns the output to use instead of `output` while tipping.
+    pub(crate) fn apply(&self, output: DrivetrainPair) -> DrivetrainPair {
+        match self.response {
+            TipResponse::Stop => DrivetrainPair::new_voltage(0.0, 0.0),
+            TipResponse::Clamp(voltage) => match output.units {
+                DrivetrainUnits::Voltage => output.max(voltage),
+                // RPM can't be compared with a voltage, so stop instead
+                DrivetrainUnits::RPM => DrivetrainPair::new_voltage(0.0, 0.0),
+            },
+            // Turning in place doesn't tip the robot, so there is nothing to
+            // reverse
+            TipResponse::Reverse(_) if output.average() == 0.0 => {
+                DrivetrainPair::new_voltage(0.0, 0.0)
+            }
+            TipResponse::Reverse(voltage) => {
+                let voltage = -output.average().signum() * voltage;
+                DrivetrainPair::new_voltage(voltage, voltage)
+            }
+        }
+    }
+}
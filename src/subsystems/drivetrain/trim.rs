@@ -0,0 +1,179 @@
+//! Correcting for a drivetrain which pulls to one side
+//!
+//! Friction, worn motors and bent axles make one side of a drivetrain faster
+//! than the other for the same output, so open-loop moves curve before the
+//! heading controller notices. A [`DriveTrim`] scales the left and right
+//! outputs, voltage and RPM alike, so that they match.
+//!
+//! [`Drivetrain::calibrate_trim`] finds the trim by driving straight
+//! open-loop and measuring how far the robot turned. The trim is kept in the
+//! `[trim]` section of the [config file](crate::utils::config):
+//!
+//! ```ignore
+//! let mut file = ConfigFile::load_or_empty("config.toml");
+//! drivetrain.set_trim(file.trim()?);
+//! // ...
+//! let calibration = drivetrain.calibrate_trim(6.0, Duration::from_millis(1500), &CHASSIS).await;
+//! calibration.log();
+//! file.set_trim(calibration.trim);
+//! file.save("config.toml")?;
+//! ```
+
+use core::time::Duration;
+
+use vexide::math::Angle;
+
+use super::{ChassisConfig, Drivetrain, DrivetrainPair};
+
+/// The largest correction a single calibration makes to either side, as a
+/// fraction, so a run which hit something doesn't wreck the trim.
+const MAX_CORRECTION: f64 = 0.2;
+
+/// How far, in mm, the robot must drive for a calibration to be trusted.
+const MIN_DISTANCE: f64 = 100.0;
+
+/// Scales for the left and right outputs of the drivetrain. See the
+/// [module documentation](self).
+///
+/// The faster side is scaled down rather than the slower side up, so trimmed
+/// outputs never exceed the max voltage.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DriveTrim {
+    pub left: f64,
+    pub right: f64,
+}
+
+impl Default for DriveTrim {
+    /// No trim
+    fn default() -> Self {
+        Self {
+            left: 1.0,
+            right: 1.0,
+        }
+    }
+}
+
+impl DriveTrim {
+    /// Creates a trim with the given scales, normalized so that the larger
+    /// is 1.0.
+    pub fn new(left: f64, right: f64) -> Self {
+        let max = left.max(right);
+        Self {
+            left: left / max,
+            right: right / max,
+        }
+    }
+
+    /// Returns `output` with each side scaled.
+    #[must_use = "does not mutate original value"]
+    pub fn apply(&self, output: DrivetrainPair) -> DrivetrainPair {
+        DrivetrainPair {
+            left: output.left * self.left,
+            right: output.right * self.right,
+            units: output.units,
+        }
+    }
+}
+
+/// The result of a [trim calibration](Drivetrain::calibrate_trim).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrimCalibration {
+    /// The trim before the calibration
+    pub previous: DriveTrim,
+    /// The new trim, already set on the drivetrain, or the previous trim if
+    /// the calibration failed
+    pub trim: DriveTrim,
+    /// How far the robot turned while driving, counterclockwise positive
+    pub heading_change: Angle,
+    /// How far the robot drove, in mm
+    pub distance: f64,
+    /// Whether the robot drove far enough for the trim to be trusted
+    pub completed: bool,
+}
+
+impl TrimCalibration {
+    /// Logs the result, as a warning if the calibration failed.
+    pub fn log(&self) {
+        if self.completed {
+            log::info!(
+                "Trim: turned {:.1}° over {:.0} mm; trim left {:.3}, right {:.3} (was {:.3}, {:.3})",
+                self.heading_change.as_degrees(),
+                self.distance,
+                self.trim.left,
+                self.trim.right,
+                self.previous.left,
+                self.previous.right
+            );
+        } else {
+            log::warn!(
+                "Trim: only drove {:.0} mm, so the trim was not changed",
+                self.distance
+            );
+        }
+    }
+}
+
+impl Drivetrain {
+    /// Scales the left and right outputs of every action by `trim`, or
+    /// `None` for no trim. See the [trim module](self).
+    pub fn set_trim(&mut self, trim: Option<DriveTrim>) {
+        self.trim.set(trim);
+    }
+
+    /// Returns the trim applied to every output, if any.
+    pub fn trim(&self) -> Option<DriveTrim> {
+        self.trim.get()
+    }
+
+    /// Finds the trim by driving forwards open-loop at `voltage` for
+    /// `duration`, with the current trim, and measuring how far the robot
+    /// turned.
+    ///
+    /// The new trim is set on the drivetrain, but not saved. Repeat until
+    /// the heading change is small, since the motors don't respond exactly
+    /// in proportion to their output.
+    pub async fn calibrate_trim(
+        &mut self,
+        voltage: f64,
+        duration: Duration,
+        chassis: &ChassisConfig,
+    ) -> TrimCalibration {
+        let previous = self.trim().unwrap_or_default();
+        let start = self.tracking.current();
+        self.set_voltage(DrivetrainPair::new_voltage(voltage, voltage));
+        vexide::time::sleep(duration).await;
+        self.set_voltage(DrivetrainPair::new_voltage(0.0, 0.0));
+        let end = self.tracking.current();
+
+        let mut heading_change = end.rotation - start.rotation;
+        if self.tracking.reverse() {
+            // Mirroring swaps clockwise and counterclockwise
+            heading_change = -heading_change;
+        }
+        let distance = nalgebra::distance(&start.offset, &end.offset);
+        let completed = distance >= MIN_DISTANCE;
+        let trim = if completed {
+            // The sides drove arcs whose difference turned the robot:
+            // (right - left) / (right + left) = heading change * track width
+            // / (2 * distance)
+            let ratio = (heading_change.as_radians() * chassis.track_width / (2.0 * distance))
+                .clamp(-MAX_CORRECTION, MAX_CORRECTION)
+                * voltage.signum();
+            let trim = DriveTrim::new(
+                previous.left * (1.0 + ratio),
+                previous.right * (1.0 - ratio),
+            );
+            self.set_trim(Some(trim));
+            trim
+        } else {
+            previous
+        };
+        TrimCalibration {
+            previous,
+            trim,
+            heading_change,
+            distance,
+            completed,
+        }
+    }
+}
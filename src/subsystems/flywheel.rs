@@ -0,0 +1,243 @@
+use core::{cell::RefCell, future::Future, time::Duration};
+use std::time::Instant;
+
+use alloc::{boxed::Box, rc::Rc};
+use pid::Pid;
+
+use crate::{
+    motorgroup::DoxaMotorGroup,
+    utils::{
+        controllers::{BangBang, Controller, TakeBackHalf},
+        profiling,
+        ticker::{LOOP_PERIOD, Ticker},
+        units::Rpm,
+        unwrap_expect_report::UnwrapExpectReportExt as _,
+    },
+};
+
+const MAX_VOLTAGE: f64 = 12.0;
+
+/// The velocity controller used by a [`FlywheelSubsystem`].
+#[derive(Debug, Clone, Copy)]
+pub enum FlywheelControl {
+    /// A feedforward term of `kv` volts per RPM of target velocity, plus a PID
+    /// controller on the velocity error.
+    ///
+    /// This reaches the target quickly if `kv` is well-tuned, and the PID only
+    /// has to correct for disturbances like shots.
+    FeedforwardPid { kv: f64, kp: f64, ki: f64, kd: f64 },
+    /// A [`TakeBackHalf`] controller with the given gain in volts per RPM of
+    /// error per loop.
+    ///
+    /// This needs only one gain and settles without overshoot oscillation on
+    /// most flywheels.
+    TakeBackHalf { gain: f64 },
+    /// A bang-bang controller which applies `high` volts below the target
+    /// and `low` volts above it, switching only once the velocity is more
+    /// than `hysteresis` RPM past the target.
+    ///
+    /// This recovers from shots as fast as possible, at the cost of some
+    /// ripple in the velocity.
+    BangBang {
+        high: f64,
+        low: f64,
+        hysteresis: f64,
+    },
+}
+
+/// A feedforward term plus a PID controller on the velocity error.
+#[derive(Debug)]
+struct FeedforwardPid {
+    kv: f64,
+    pid: Pid<f64>,
+}
+
+impl Controller for FeedforwardPid {
+    fn update(&mut self, setpoint: f64, measurement: f64) -> f64 {
+        self.kv * setpoint + self.pid.update(setpoint, measurement)
+    }
+
+    fn reset(&mut self) {
+        self.pid.reset();
+    }
+}
+
+impl FlywheelControl {
+    fn controller(self) -> Box<dyn Controller> {
+        match self {
+            FlywheelControl::FeedforwardPid { kv, kp, ki, kd } => {
+                let mut pid = Pid::new(0.0, MAX_VOLTAGE);
+                pid.p(kp, MAX_VOLTAGE);
+                pid.i(ki, MAX_VOLTAGE);
+                pid.d(kd, MAX_VOLTAGE);
+                Box::new(FeedforwardPid { kv, pid })
+            }
+            FlywheelControl::TakeBackHalf { gain } => {
+                Box::new(TakeBackHalf::new(gain).with_max_output(MAX_VOLTAGE))
+            }
+            FlywheelControl::BangBang {
+                high,
+                low,
+                hysteresis,
+            } => Box::new(BangBang::new(high, low).with_hysteresis(hysteresis)),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct FlywheelState {
+    target: f64,
+    velocity: f64,
+    tolerance: f64,
+    at_speed: bool,
+    /// When the velocity dropped out of tolerance after being at speed
+    dip_start: Option<Instant>,
+    last_recovery_time: Option<Duration>,
+    recoveries: u32,
+}
+
+/// A flywheel spun by a motor group with closed-loop velocity control.
+///
+/// The control loop runs in a background task. Velocities are in RPM of the
+/// flywheel itself, as reported by the motor group, so any gearing between
+/// the motors and the flywheel should be set with
+/// [`DoxaMotorGroup::with_gear_ratio`].
+///
+/// Every time the velocity drops out of tolerance after being at speed (e.g.,
+/// after a shot), the time it takes to recover is measured and exposed by
+/// [`last_recovery_time`](Self::last_recovery_time).
+#[derive(Debug, Clone)]
+pub struct FlywheelSubsystem {
+    state: Rc<RefCell<FlywheelState>>,
+    _task: Rc<vexide::task::Task<()>>,
+}
+
+impl FlywheelSubsystem {
+    /// Creates a new flywheel subsystem.
+    ///
+    /// `tolerance` is how close to the target velocity, in RPM, the flywheel
+    /// must be to be at speed. The flywheel starts stopped.
+    pub fn new(mut motors: DoxaMotorGroup, control: FlywheelControl, tolerance: f64) -> Self {
+        let state = Rc::new(RefCell::new(FlywheelState {
+            tolerance,
+            ..Default::default()
+        }));
+        Self {
+            state: state.clone(),
+            _task: Rc::new(vexide::task::spawn(async move {
+                let mut controller = control.controller();
+                let mut ticker = Ticker::new(LOOP_PERIOD);
+                loop {
+                    let scope = profiling::scope("flywheel");
+                    {
+                        let mut state = state.borrow_mut();
+                        if let Some(velocity) = motors
+                            .velocity()
+                            .expect_report("failed to read flywheel velocity")
+                        {
+                            state.velocity = velocity;
+                        }
+
+                        if state.target == 0.0 {
+                            controller.reset();
+                            motors
+                                .set_voltage(0.0)
+                                .expect_report("failed to stop flywheel");
+                        } else {
+                            let voltage = controller
+                                .update(state.target, state.velocity)
+                                .clamp(-MAX_VOLTAGE, MAX_VOLTAGE);
+                            motors
+                                .set_voltage(voltage)
+                                .expect_report("failed to set flywheel voltage");
+                        }
+
+                        // Recovery time telemetry
+                        let in_tolerance = state.target != 0.0
+                            && (state.velocity - state.target).abs() < state.tolerance;
+                        if in_tolerance {
+                            if let Some(dip_start) = state.dip_start.take() {
+                                let recovery_time = dip_start.elapsed();
+                                state.last_recovery_time = Some(recovery_time);
+                                state.recoveries += 1;
+                                log::debug!(
+                                    "Flywheel recovered to {:.0} RPM in {} ms",
+                                    state.target,
+                                    recovery_time.as_millis()
+                                );
+                            }
+                            state.at_speed = true;
+                        } else if state.at_speed {
+                            state.at_speed = false;
+                            state.dip_start = Some(Instant::now());
+                        }
+                    }
+                    drop(scope);
+                    ticker.tick().await;
+                }
+            })),
+        }
+    }
+
+    /// Sets the target velocity in RPM. A target of zero lets the flywheel
+    /// coast to a stop.
+    pub fn set_target(&mut self, rpm: impl Into<Rpm>) {
+        let rpm = rpm.into().0;
+        let mut state = self.state.borrow_mut();
+        if state.target != rpm {
+            state.target = rpm;
+            // A setpoint change isn't a disturbance to recover from
+            state.at_speed = false;
+            state.dip_start = None;
+        }
+    }
+
+    /// Stops the flywheel, letting it coast.
+    pub fn stop(&mut self) {
+        self.set_target(0.0);
+    }
+
+    /// Returns the target velocity in RPM.
+    pub fn target(&self) -> f64 {
+        self.state.borrow().target
+    }
+
+    /// Returns the measured velocity in RPM.
+    pub fn velocity(&self) -> f64 {
+        self.state.borrow().velocity
+    }
+
+    /// Sets how close to the target velocity, in RPM, the flywheel must be to
+    /// be at speed.
+    pub fn set_tolerance(&mut self, tolerance: impl Into<Rpm>) {
+        self.state.borrow_mut().tolerance = tolerance.into().0;
+    }
+
+    /// Returns whether the flywheel is within tolerance of a nonzero target.
+    pub fn is_at_speed(&self) -> bool {
+        let state = self.state.borrow();
+        state.target != 0.0 && (state.velocity - state.target).abs() < state.tolerance
+    }
+
+    /// Waits until the flywheel is within tolerance of a nonzero target.
+    pub fn at_speed(&self) -> impl Future<Output = ()> + 'static {
+        let flywheel = self.clone();
+        async move {
+            while !flywheel.is_at_speed() {
+                vexide::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+    }
+
+    /// Returns how long the flywheel took to get back to speed after it last
+    /// dropped out of tolerance, if it has yet.
+    pub fn last_recovery_time(&self) -> Option<Duration> {
+        self.state.borrow().last_recovery_time
+    }
+
+    /// Returns how many times the flywheel has recovered after dropping out of
+    /// tolerance, which is roughly the number of shots fired.
+    pub fn recoveries(&self) -> u32 {
+        self.state.borrow().recoveries
+    }
+}
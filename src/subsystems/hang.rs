@@ -0,0 +1,397 @@
+//! Endgame hang/climb
+//!
+//! A climb is a sequence of steps which each have to finish before the next
+//! can start: deploy the hooks, pull until the robot's weight is on them,
+//! climb to height, and lock the ratchet. [`HangSubsystem`] runs the motors
+//! for each step in a background task, and exposes each step as a future
+//! which resolves when the step is confirmed by the sensors, or fails with a
+//! [`HangError`] if it takes too long, wrapped in a
+//! [`DoxaError`] like device errors:
+//!
+//! ```ignore
+//! hang.deploy().await?;
+//! drivetrain.action(forward(150.0)).await;
+//! hang.climb().await?;
+//! hang.wait_level().await?;
+//! hang.lock()?;
+//! ```
+
+use core::{cell::RefCell, time::Duration};
+use std::time::Instant;
+
+use alloc::rc::Rc;
+use snafu::Snafu;
+use vexide::{math::Angle, smart::motor::BrakeMode};
+
+use crate::{
+    error::DoxaError,
+    motorgroup::DoxaMotorGroup,
+    subsystems::pneumatic::PneumaticSubsystem,
+    utils::{
+        filters::{Debounce, Filter},
+        profiling,
+        ticker::{LOOP_PERIOD, Ticker},
+        traits::{HasPitch, HasRotation},
+        unwrap_expect_report::UnwrapExpectReportExt as _,
+    },
+};
+
+/// The stage of a [`HangSubsystem`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HangStage {
+    /// The hooks are stowed and the motors are off.
+    Stowed,
+    /// The hooks are being driven out to the deploy position.
+    Deploying,
+    /// The hooks are out and held in place.
+    Deployed,
+    /// The robot is being pulled up.
+    Climbing,
+    /// The robot has reached the climb position and is held there.
+    Climbed,
+    /// The ratchet is engaged and the motors are off.
+    Locked,
+}
+
+#[derive(Debug, Snafu)]
+pub enum HangError {
+    #[snafu(display("Timed out in stage {:?}", stage))]
+    Timeout { stage: HangStage },
+    #[snafu(display("Climb was interrupted in stage {:?}", stage))]
+    Interrupted { stage: HangStage },
+}
+
+/// Configuration for a [`HangSubsystem`].
+///
+/// Positions are angles of the motor group, measured from where it was when
+/// the subsystem was created.
+///
+/// The sign of each stage's voltage sets the direction it moves in: a stage
+/// has reached its position once the motors have moved past it in that
+/// direction. For example, if the motor group reads negative positions as
+/// the hooks move out, `deploy_voltage` and `deploy_position` should both be
+/// negative.
+#[derive(Debug, Clone, Copy)]
+pub struct HangConfig {
+    pub deploy_position: Angle,
+    pub deploy_voltage: f64,
+    pub climb_position: Angle,
+    pub climb_voltage: f64,
+    /// The average current in amps above which the hooks are considered
+    /// loaded with the robot's weight
+    pub loaded_current: f64,
+    /// How long the current must stay above `loaded_current`
+    pub loaded_duration: Duration,
+    /// How far the pitch may be from the pitch on the ground while level
+    pub level_tolerance: Angle,
+    /// How long the pitch must stay within `level_tolerance`
+    pub level_duration: Duration,
+    /// How long each step may take before it fails
+    pub step_timeout: Duration,
+}
+
+impl HangConfig {
+    /// Creates a new configuration with the given positions and default
+    /// voltages, thresholds, and timeouts.
+    pub fn new(deploy_position: Angle, climb_position: Angle) -> Self {
+        Self {
+            deploy_position,
+            deploy_voltage: 8.0,
+            climb_position,
+            climb_voltage: 12.0,
+            loaded_current: 1.5,
+            loaded_duration: Duration::from_millis(150),
+            level_tolerance: Angle::from_degrees(3.0),
+            level_duration: Duration::from_millis(250),
+            step_timeout: Duration::from_secs(3),
+        }
+    }
+
+    pub fn with_deploy_voltage(mut self, deploy_voltage: f64) -> Self {
+        self.deploy_voltage = deploy_voltage;
+        self
+    }
+
+    pub fn with_climb_voltage(mut self, climb_voltage: f64) -> Self {
+        self.climb_voltage = climb_voltage;
+        self
+    }
+
+    pub fn with_loaded_current(mut self, loaded_current: f64, loaded_duration: Duration) -> Self {
+        self.loaded_current = loaded_current;
+        self.loaded_duration = loaded_duration;
+        self
+    }
+
+    pub fn with_level_tolerance(
+        mut self,
+        level_tolerance: Angle,
+        level_duration: Duration,
+    ) -> Self {
+        self.level_tolerance = level_tolerance;
+        self.level_duration = level_duration;
+        self
+    }
+
+    pub fn with_step_timeout(mut self, step_timeout: Duration) -> Self {
+        self.step_timeout = step_timeout;
+        self
+    }
+}
+
+/// Returns whether `position` is at or past `target` when moving in the
+/// direction of `voltage`.
+fn reached(position: Angle, target: Angle, voltage: f64) -> bool {
+    (position - target).as_radians() * voltage.signum() >= 0.0
+}
+
+#[derive(Debug)]
+struct HangState {
+    stage: HangStage,
+    config: HangConfig,
+    position: Angle,
+    /// Whether the current has stayed above the loaded threshold
+    loading: Debounce,
+    loaded: bool,
+    /// Whether the pitch has stayed within the level tolerance. Any sample
+    /// out of tolerance restarts the wait.
+    level: Debounce,
+    pitch: Angle,
+}
+
+/// A hang mechanism with a motor group and a pneumatic ratchet.
+///
+/// The ratchet is released while deploying, so that the hooks can move out,
+/// and engaged for the rest of the climb. The robot is loaded once the motor
+/// current has stayed above [`HangConfig::loaded_current`] long enough, and
+/// level once its pitch has stayed close to the pitch it had on the ground
+/// when the subsystem was created.
+#[derive(Debug, Clone)]
+pub struct HangSubsystem {
+    state: Rc<RefCell<HangState>>,
+    ratchet: Rc<RefCell<PneumaticSubsystem<1>>>,
+    _task: Rc<vexide::task::Task<()>>,
+}
+
+impl HangSubsystem {
+    /// Creates a new hang subsystem with the hooks stowed.
+    ///
+    /// The robot must be level on the ground, since the current pitch is used
+    /// as the level reference.
+    pub fn new(
+        mut motors: DoxaMotorGroup,
+        ratchet: PneumaticSubsystem<1>,
+        imu: impl HasPitch + 'static,
+        config: HangConfig,
+    ) -> Self {
+        let ground_pitch = imu.pitch();
+        let state = Rc::new(RefCell::new(HangState {
+            stage: HangStage::Stowed,
+            config,
+            position: Angle::ZERO,
+            loading: Debounce::new(config.loaded_duration),
+            loaded: false,
+            level: Debounce::new(config.level_duration).with_falling_duration(Duration::ZERO),
+            pitch: Angle::ZERO,
+        }));
+        Self {
+            state: state.clone(),
+            ratchet: Rc::new(RefCell::new(ratchet)),
+            _task: Rc::new(vexide::task::spawn(async move {
+                let start = HasRotation::position(&motors);
+                let mut ticker = Ticker::new(LOOP_PERIOD);
+                loop {
+                    let scope = profiling::scope("hang");
+                    {
+                        let mut state = state.borrow_mut();
+                        let config = state.config;
+                        let position = HasRotation::position(&motors) - start;
+                        let current = motors.current().unwrap_or(0.0);
+                        let pitch = (imu.pitch() - ground_pitch).wrapped_half();
+                        state.position = position;
+                        state.pitch = pitch;
+
+                        state
+                            .level
+                            .update(pitch.as_radians().abs() < config.level_tolerance.as_radians());
+
+                        match state.stage {
+                            HangStage::Stowed | HangStage::Locked => {
+                                motors
+                                    .set_voltage(0.0)
+                                    .expect_report("failed to stop hang motors");
+                            }
+                            HangStage::Deploying => {
+                                if reached(position, config.deploy_position, config.deploy_voltage)
+                                {
+                                    log::info!("Hang deployed");
+                                    state.stage = HangStage::Deployed;
+                                } else {
+                                    motors
+                                        .set_voltage(config.deploy_voltage)
+                                        .expect_report("failed to deploy hang");
+                                }
+                            }
+                            HangStage::Deployed | HangStage::Climbed => {
+                                motors
+                                    .brake(BrakeMode::Hold)
+                                    .expect_report("failed to hold hang");
+                            }
+                            HangStage::Climbing => {
+                                if state.loading.update(current >= config.loaded_current)
+                                    && !state.loaded
+                                {
+                                    log::info!("Hang loaded at {:.2} A", current);
+                                    state.loaded = true;
+                                }
+                                if state.loaded
+                                    && reached(
+                                        position,
+                                        config.climb_position,
+                                        config.climb_voltage,
+                                    )
+                                {
+                                    log::info!("Hang climbed");
+                                    state.stage = HangStage::Climbed;
+                                } else {
+                                    motors
+                                        .set_voltage(config.climb_voltage)
+                                        .expect_report("failed to climb");
+                                }
+                            }
+                        }
+                    }
+                    drop(scope);
+                    ticker.tick().await;
+                }
+            })),
+        }
+    }
+
+    fn enter(&self, stage: HangStage) {
+        let mut state = self.state.borrow_mut();
+        log::info!("Hang: {:?} -> {:?}", state.stage, stage);
+        state.stage = stage;
+    }
+
+    /// Waits until `done` returns `true`, failing if the stage changes to
+    /// something other than `next` or the step timeout elapses.
+    async fn wait_step(
+        &self,
+        stage: HangStage,
+        next: HangStage,
+        mut done: impl FnMut(&HangState) -> bool,
+    ) -> Result<(), DoxaError> {
+        let start = Instant::now();
+        let timeout = self.state.borrow().config.step_timeout;
+        loop {
+            {
+                let state = self.state.borrow();
+                if done(&state) {
+                    return Ok(());
+                }
+                if state.stage != stage && state.stage != next {
+                    return Err(HangError::Interrupted { stage }.into());
+                }
+            }
+            if start.elapsed() > timeout {
+                log::error!("Hang timed out in stage {:?}", stage);
+                return Err(HangError::Timeout { stage }.into());
+            }
+            vexide::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    /// Releases the ratchet and drives the hooks out to the deploy position.
+    pub async fn deploy(&self) -> Result<(), DoxaError> {
+        self.ratchet.borrow_mut().retract()?;
+        self.enter(HangStage::Deploying);
+        self.wait_step(HangStage::Deploying, HangStage::Deployed, |state| {
+            state.stage == HangStage::Deployed
+        })
+        .await
+    }
+
+    /// Engages the ratchet and pulls the robot up, resolving once the hooks
+    /// are loaded with the robot's weight. The climb continues afterwards;
+    /// await [`climb`](Self::climb) to wait for it to finish.
+    pub async fn pull(&self) -> Result<(), DoxaError> {
+        self.start_climb()?;
+        self.wait_step(HangStage::Climbing, HangStage::Climbed, |state| {
+            state.loaded
+        })
+        .await
+    }
+
+    /// Engages the ratchet and pulls the robot up, resolving once it is
+    /// loaded and has reached the climb position.
+    ///
+    /// If the climb was already started with [`pull`](Self::pull), it is
+    /// continued rather than restarted.
+    pub async fn climb(&self) -> Result<(), DoxaError> {
+        if self.stage() != HangStage::Climbing {
+            self.start_climb()?;
+        }
+        self.wait_step(HangStage::Climbing, HangStage::Climbed, |state| {
+            state.stage == HangStage::Climbed
+        })
+        .await
+    }
+
+    fn start_climb(&self) -> Result<(), DoxaError> {
+        self.ratchet.borrow_mut().extend()?;
+        {
+            let mut state = self.state.borrow_mut();
+            state.loaded = false;
+            state.loading.reset();
+        }
+        self.enter(HangStage::Climbing);
+        Ok(())
+    }
+
+    /// Waits until the robot is level, e.g., to confirm that it is hanging
+    /// freely before locking.
+    pub async fn wait_level(&self) -> Result<(), DoxaError> {
+        let stage = self.stage();
+        self.wait_step(stage, stage, |state| state.level.value())
+            .await
+    }
+
+    /// Engages the ratchet and turns the motors off, leaving the robot hanging
+    /// on the ratchet.
+    ///
+    /// The motors are turned off even if the ratchet fails.
+    pub fn lock(&self) -> Result<(), DoxaError> {
+        let result = self.ratchet.borrow_mut().extend();
+        self.enter(HangStage::Locked);
+        result
+    }
+
+    /// Turns the motors off and releases the ratchet. Any step in progress
+    /// fails.
+    pub fn stow(&self) -> Result<(), DoxaError> {
+        let result = self.ratchet.borrow_mut().retract();
+        self.enter(HangStage::Stowed);
+        result
+    }
+
+    /// Returns the current stage.
+    pub fn stage(&self) -> HangStage {
+        self.state.borrow().stage
+    }
+
+    /// Returns whether the hooks are loaded with the robot's weight.
+    pub fn is_loaded(&self) -> bool {
+        self.state.borrow().loaded
+    }
+
+    /// Returns the position of the motors since the subsystem was created.
+    pub fn position(&self) -> Angle {
+        self.state.borrow().position
+    }
+
+    /// Returns the pitch of the robot relative to the ground.
+    pub fn pitch(&self) -> Angle {
+        self.state.borrow().pitch
+    }
+}
@@ -0,0 +1,248 @@
+//! Controller screen HUD
+//!
+//! The V5 controller's screen has three lines of 19 characters and only
+//! accepts a write every 50 ms or so; writes sent faster than that are
+//! silently dropped. A [`ControllerHud`] owns the screen, and refreshes one
+//! changed line at a time from registered [`HudField`]s, so that every field
+//! eventually shows up and nothing fights over the write budget.
+
+use core::{
+    cell::{Cell, RefCell},
+    time::Duration,
+};
+
+use alloc::{
+    boxed::Box,
+    format,
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+use vexide::controller::Controller;
+
+use crate::{
+    auton::AutonRegistry,
+    motorgroup::DoxaMotorGroup,
+    subsystems::tracking::TrackingSubsystem,
+    utils::{health::MotorHealth, ticker::Ticker, unwrap_expect_report},
+};
+
+/// A labelled value shown on a [`ControllerHud`] line.
+pub struct HudField {
+    label: &'static str,
+    value: Box<dyn FnMut() -> String>,
+}
+
+impl HudField {
+    /// Creates a new field which shows `label` followed by the result of
+    /// `value`.
+    ///
+    /// Labels and values should be short, since all fields on a line share
+    /// 19 characters.
+    pub fn new(label: &'static str, value: impl FnMut() -> String + 'static) -> Self {
+        Self {
+            label,
+            value: Box::new(value),
+        }
+    }
+
+    /// A field showing the name of the route selected in `registry`, or
+    /// `none` if no route is selected.
+    pub fn selected_auton(registry: AutonRegistry) -> Self {
+        Self::new("aut", move || {
+            registry
+                .selected()
+                .map_or_else(|| "none".to_string(), |route| route.name.to_string())
+        })
+    }
+
+    /// A field showing the robot battery's remaining capacity.
+    pub fn battery() -> Self {
+        Self::new("bat", || {
+            format!("{:.0}%", vexide::battery::capacity() * 100.0)
+        })
+    }
+
+    /// A field showing the temperature of the hottest of the given motor
+    /// groups, in degrees Celsius.
+    pub fn hottest_motor(motors: Vec<DoxaMotorGroup>) -> Self {
+        Self::new("hot", move || {
+            motors
+                .iter()
+                .filter_map(|motors| motors.temperature().ok())
+                .reduce(f64::max)
+                .map_or_else(
+                    || "?".to_string(),
+                    |temperature| format!("{:.0}C", temperature),
+                )
+        })
+    }
+
+    /// A field showing the drive motor most likely to limit its current
+    /// within about a match, e.g., `drv P3 52C 40s`, or `ok` if there is
+    /// none.
+    pub fn thermal(health: MotorHealth) -> Self {
+        Self::new("drv", move || match health.drive_warning() {
+            None => "ok".to_string(),
+            Some(motor) => match motor.time_to_limit() {
+                Some(time) if !time.is_zero() => format!(
+                    "P{} {:.0}C {}s",
+                    motor.port,
+                    motor.temperature,
+                    time.as_secs()
+                ),
+                _ => format!("P{} {:.0}C!", motor.port, motor.temperature),
+            },
+        })
+    }
+
+    /// A field showing the ports of disconnected devices, or `ok` if there
+    /// are none.
+    pub fn disconnected_ports() -> Self {
+        Self::new("dc", || {
+            let ports = unwrap_expect_report::disconnected_ports();
+            if ports.is_empty() {
+                "ok".to_string()
+            } else {
+                ports
+                    .iter()
+                    .map(|port| port.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            }
+        })
+    }
+
+    /// A field showing the position in mm and heading in degrees of the given
+    /// tracking subsystem.
+    pub fn tracking(tracking: TrackingSubsystem) -> Self {
+        Self::new("", move || {
+            let data = tracking.current();
+            format!(
+                "{:.0},{:.0},{:.0}",
+                data.offset.x,
+                data.offset.y,
+                data.heading.as_degrees()
+            )
+        })
+    }
+
+    fn render(&mut self) -> String {
+        let value = (self.value)();
+        if self.label.is_empty() {
+            value
+        } else {
+            format!("{} {}", self.label, value)
+        }
+    }
+}
+
+struct HudLine {
+    fields: Vec<HudField>,
+    /// The text last written successfully, if any
+    written: Option<String>,
+}
+
+/// Manages the V5 controller's screen, refreshing registered fields on a
+/// budget that respects the controller's slow update rate.
+///
+/// Every `interval` (50 ms by default), the next line whose text changed is
+/// written. Writes which fail because the controller is busy are retried on
+/// the next refresh.
+#[derive(Clone)]
+pub struct ControllerHud {
+    lines: Rc<RefCell<[HudLine; Controller::MAX_LINES]>>,
+    interval: Rc<Cell<Duration>>,
+    _task: Rc<vexide::task::Task<()>>,
+}
+
+impl ControllerHud {
+    /// Creates a new HUD on the given controller and starts refreshing it.
+    ///
+    /// Use [`InputBindings::controller`](super::input::InputBindings::controller)
+    /// to share a controller which is also used for input.
+    pub fn new(controller: Rc<RefCell<Controller>>) -> Self {
+        let lines = Rc::new(RefCell::new(core::array::from_fn(|_| HudLine {
+            fields: Vec::new(),
+            written: None,
+        })));
+        let interval = Rc::new(Cell::new(Duration::from_millis(50)));
+        Self {
+            lines: lines.clone(),
+            interval: interval.clone(),
+            _task: Rc::new(vexide::task::spawn(async move {
+                let mut next_line = 0;
+                let mut ticker = Ticker::new(interval.get());
+                loop {
+                    {
+                        let mut lines = lines.borrow_mut();
+                        for offset in 0..Controller::MAX_LINES {
+                            let index = (next_line + offset) % Controller::MAX_LINES;
+                            let line = &mut lines[index];
+                            let text = line
+                                .fields
+                                .iter_mut()
+                                .map(HudField::render)
+                                .collect::<Vec<_>>()
+                                .join(" ");
+                            // Pad to overwrite anything left over from before
+                            let text =
+                                format!("{:<width$.width$}", text, width = Controller::MAX_COLUMNS);
+                            if line.written.as_ref() == Some(&text) {
+                                continue;
+                            }
+                            if controller
+                                .borrow_mut()
+                                .try_set_text(&text, index as u8 + 1, 1)
+                                .is_ok()
+                            {
+                                line.written = Some(text);
+                            }
+                            next_line = index + 1;
+                            break;
+                        }
+                    }
+                    ticker.set_period(interval.get());
+                    ticker.tick().await;
+                }
+            })),
+        }
+    }
+
+    /// Adds a field to the end of the given line, from 1 to 3.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the line is out of range.
+    pub fn with_field(self, line: u8, field: HudField) -> Self {
+        assert!(
+            (1..=Controller::MAX_LINES as u8).contains(&line),
+            "controller HUD line must be from 1 to {}, got {}",
+            Controller::MAX_LINES,
+            line
+        );
+        self.lines.borrow_mut()[line as usize - 1]
+            .fields
+            .push(field);
+        self
+    }
+
+    /// Sets how often a line is written. Writing more often than every 50 ms
+    /// causes writes to be dropped by the controller.
+    pub fn with_interval(self, interval: Duration) -> Self {
+        self.interval.set(interval);
+        self
+    }
+
+    /// Removes every field from the given line, from 1 to 3, leaving it
+    /// blank.
+    pub fn clear_line(&self, line: u8) {
+        if let Some(line) = self
+            .lines
+            .borrow_mut()
+            .get_mut((line as usize).wrapping_sub(1))
+        {
+            line.fields.clear();
+        }
+    }
+}
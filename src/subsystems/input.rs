@@ -0,0 +1,311 @@
+//! Controller input bindings
+//!
+//! Instead of a long chain of `if controller.button_a.is_now_pressed()` checks
+//! in `driver()`, buttons and axes are bound to closures once, and an
+//! [`InputBindings`] background task evaluates them every loop:
+//!
+//! ```ignore
+//! let input = InputBindings::new(peripherals.primary_controller);
+//! input.on_press(Button::A, move || {
+//!     clamp.toggle().expect_report("failed to toggle clamp");
+//! });
+//! input.while_held(Button::R1, move |held| intake.set_voltage(if held { 12.0 } else { 0.0 }));
+//! input.spawn_on_press(Button::X, move || {
+//!     let mut lift = lift.clone();
+//!     let score = lift.move_to_named("score").expect_report("no score setpoint");
+//!     async move {
+//!         if let Some(score) = score {
+//!             score.await;
+//!         }
+//!     }
+//! });
+//! ```
+//!
+//! Bindings can be added and removed at any time, including from inside a
+//! binding, which allows modes such as a shift key to rebind the controller at
+//! runtime.
+
+use core::{cell::RefCell, future::Future};
+
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+use vexide::controller::{ButtonState, Controller, ControllerState};
+
+use crate::utils::{
+    profiling,
+    ticker::{LOOP_PERIOD, Ticker},
+};
+
+/// A button on the V5 controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Button {
+    A,
+    B,
+    X,
+    Y,
+    Up,
+    Down,
+    Left,
+    Right,
+    L1,
+    L2,
+    R1,
+    R2,
+    Power,
+}
+
+impl Button {
+    /// Returns the state of this button in the given controller state.
+    pub fn state(self, controller: &ControllerState) -> ButtonState {
+        match self {
+            Button::A => controller.button_a,
+            Button::B => controller.button_b,
+            Button::X => controller.button_x,
+            Button::Y => controller.button_y,
+            Button::Up => controller.button_up,
+            Button::Down => controller.button_down,
+            Button::Left => controller.button_left,
+            Button::Right => controller.button_right,
+            Button::L1 => controller.button_l1,
+            Button::L2 => controller.button_l2,
+            Button::R1 => controller.button_r1,
+            Button::R2 => controller.button_r2,
+            Button::Power => controller.button_power,
+        }
+    }
+}
+
+/// A joystick axis on the V5 controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+}
+
+impl Axis {
+    /// Returns the value of this axis from -1.0 to 1.0 in the given controller
+    /// state.
+    pub fn value(self, controller: &ControllerState) -> f64 {
+        match self {
+            Axis::LeftX => controller.left_stick.x(),
+            Axis::LeftY => controller.left_stick.y(),
+            Axis::RightX => controller.right_stick.x(),
+            Axis::RightY => controller.right_stick.y(),
+        }
+    }
+}
+
+/// Identifies a binding so that it can be removed with
+/// [`InputBindings::unbind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BindingId(u32);
+
+enum Binding {
+    OnPress(Button, Box<dyn FnMut()>),
+    OnRelease(Button, Box<dyn FnMut()>),
+    WhileHeld(Button, Box<dyn FnMut(bool)>),
+    Toggle(Button, bool, Box<dyn FnMut(bool)>),
+    Axis(Axis, Box<dyn FnMut(f64)>),
+}
+
+impl Binding {
+    fn button(&self) -> Option<Button> {
+        match self {
+            Binding::OnPress(button, _)
+            | Binding::OnRelease(button, _)
+            | Binding::WhileHeld(button, _)
+            | Binding::Toggle(button, _, _) => Some(*button),
+            Binding::Axis(_, _) => None,
+        }
+    }
+
+    fn evaluate(&mut self, state: &ControllerState) {
+        match self {
+            Binding::OnPress(button, callback) => {
+                if button.state(state).is_now_pressed() {
+                    callback();
+                }
+            }
+            Binding::OnRelease(button, callback) => {
+                if button.state(state).is_now_released() {
+                    callback();
+                }
+            }
+            Binding::WhileHeld(button, callback) => {
+                let button = button.state(state);
+                // Call once more on release so that the mechanism can stop
+                if button.is_pressed() || button.is_now_released() {
+                    callback(button.is_pressed());
+                }
+            }
+            Binding::Toggle(button, toggled, callback) => {
+                if button.state(state).is_now_pressed() {
+                    *toggled = !*toggled;
+                    callback(*toggled);
+                }
+            }
+            Binding::Axis(axis, callback) => callback(axis.value(state)),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Bindings {
+    bindings: Vec<(BindingId, Binding)>,
+    /// Bindings and buttons removed while the bindings were being evaluated
+    removed: Vec<BindingId>,
+    removed_buttons: Vec<Button>,
+    evaluating: bool,
+    next_id: u32,
+    state: ControllerState,
+}
+
+/// Button and axis bindings for a controller, evaluated by a background task.
+///
+/// The controller is read every 10 ms. Each binding is evaluated in the order
+/// it was added.
+#[derive(Clone)]
+pub struct InputBindings {
+    controller: Rc<RefCell<Controller>>,
+    bindings: Rc<RefCell<Bindings>>,
+    _task: Rc<vexide::task::Task<()>>,
+}
+
+impl InputBindings {
+    /// Creates a new set of bindings for the given controller and starts
+    /// evaluating them.
+    pub fn new(controller: Controller) -> Self {
+        let controller = Rc::new(RefCell::new(controller));
+        let bindings: Rc<RefCell<Bindings>> = Rc::new(RefCell::new(Bindings::default()));
+        Self {
+            controller: controller.clone(),
+            bindings: bindings.clone(),
+            _task: Rc::new(vexide::task::spawn(async move {
+                let mut ticker = Ticker::new(LOOP_PERIOD);
+                loop {
+                    let scope = profiling::scope("input");
+                    let state = controller.borrow().state();
+                    if let Ok(state) = state {
+                        // Take the bindings out so that they can rebind the
+                        // controller while they are being evaluated
+                        let mut evaluating = {
+                            let mut bindings = bindings.borrow_mut();
+                            bindings.state = state;
+                            bindings.evaluating = true;
+                            core::mem::take(&mut bindings.bindings)
+                        };
+                        for (_, binding) in evaluating.iter_mut() {
+                            binding.evaluate(&state);
+                        }
+                        let mut bindings = bindings.borrow_mut();
+                        bindings.evaluating = false;
+                        let removed = core::mem::take(&mut bindings.removed);
+                        let removed_buttons = core::mem::take(&mut bindings.removed_buttons);
+                        evaluating.retain(|(id, binding)| {
+                            !removed.contains(id)
+                                && binding
+                                    .button()
+                                    .is_none_or(|button| !removed_buttons.contains(&button))
+                        });
+                        // Bindings added while evaluating go after the rest
+                        evaluating.append(&mut bindings.bindings);
+                        bindings.bindings = evaluating;
+                    }
+                    drop(scope);
+                    ticker.tick().await;
+                }
+            })),
+        }
+    }
+
+    fn bind(&self, binding: Binding) -> BindingId {
+        let mut bindings = self.bindings.borrow_mut();
+        let id = BindingId(bindings.next_id);
+        bindings.next_id += 1;
+        bindings.bindings.push((id, binding));
+        id
+    }
+
+    /// Calls `callback` when `button` is pressed.
+    pub fn on_press(&self, button: Button, callback: impl FnMut() + 'static) -> BindingId {
+        self.bind(Binding::OnPress(button, Box::new(callback)))
+    }
+
+    /// Calls `callback` when `button` is released.
+    pub fn on_release(&self, button: Button, callback: impl FnMut() + 'static) -> BindingId {
+        self.bind(Binding::OnRelease(button, Box::new(callback)))
+    }
+
+    /// Calls `callback` with `true` every loop while `button` is held, and
+    /// once with `false` when it is released.
+    pub fn while_held(&self, button: Button, callback: impl FnMut(bool) + 'static) -> BindingId {
+        self.bind(Binding::WhileHeld(button, Box::new(callback)))
+    }
+
+    /// Flips a toggle every time `button` is pressed, and calls `callback`
+    /// with the new value. The toggle starts off.
+    pub fn toggle(&self, button: Button, callback: impl FnMut(bool) + 'static) -> BindingId {
+        self.bind(Binding::Toggle(button, false, Box::new(callback)))
+    }
+
+    /// Calls `callback` with the value of `axis`, from -1.0 to 1.0, every
+    /// loop.
+    pub fn axis(&self, axis: Axis, callback: impl FnMut(f64) + 'static) -> BindingId {
+        self.bind(Binding::Axis(axis, Box::new(callback)))
+    }
+
+    /// Spawns the future returned by `command` as a detached task when
+    /// `button` is pressed.
+    ///
+    /// This is useful for commands which take time, such as moving a lift to
+    /// a setpoint, without blocking other bindings.
+    pub fn spawn_on_press<F: Future<Output = ()> + 'static>(
+        &self,
+        button: Button,
+        mut command: impl FnMut() -> F + 'static,
+    ) -> BindingId {
+        self.on_press(button, move || vexide::task::spawn(command()).detach())
+    }
+
+    /// Removes a binding. Does nothing if it was already removed.
+    pub fn unbind(&self, id: BindingId) {
+        let mut bindings = self.bindings.borrow_mut();
+        bindings.bindings.retain(|(binding, _)| *binding != id);
+        if bindings.evaluating {
+            bindings.removed.push(id);
+        }
+    }
+
+    /// Removes every binding on `button`.
+    pub fn unbind_button(&self, button: Button) {
+        let mut bindings = self.bindings.borrow_mut();
+        bindings
+            .bindings
+            .retain(|(_, binding)| binding.button() != Some(button));
+        if bindings.evaluating {
+            bindings.removed_buttons.push(button);
+        }
+    }
+
+    /// Removes every binding.
+    pub fn clear(&self) {
+        let mut bindings = self.bindings.borrow_mut();
+        bindings.bindings.clear();
+        if bindings.evaluating {
+            bindings.removed = (0..bindings.next_id).map(BindingId).collect();
+        }
+    }
+
+    /// Returns the controller, e.g., for sharing it with a
+    /// [`ControllerHud`](super::hud::ControllerHud).
+    pub fn controller(&self) -> Rc<RefCell<Controller>> {
+        self.controller.clone()
+    }
+
+    /// Returns the controller state read in the last loop, e.g., for reading
+    /// the joysticks in a drive loop.
+    pub fn state(&self) -> ControllerState {
+        self.bindings.borrow().state
+    }
+}
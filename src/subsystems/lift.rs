@@ -0,0 +1,383 @@
+use core::{
+    cell::RefCell,
+    future::Future,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use std::time::Instant;
+
+use alloc::{collections::BTreeMap, rc::Rc, string::ToString};
+use pid::Pid;
+use vexide::math::Angle;
+
+use crate::{
+    error::DoxaError,
+    motorgroup::DoxaMotorGroup,
+    utils::{
+        motion_profile::TrapezoidalProfile,
+        profiling,
+        settling::Tolerances,
+        ticker::{LOOP_PERIOD, Ticker},
+        traits::HasRotation,
+        unwrap_expect_report::UnwrapExpectReportExt as _,
+    },
+};
+
+const MAX_VOLTAGE: f64 = 12.0;
+
+/// How gravity acts on a [`LiftSubsystem`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gravity {
+    /// Gravity is ignored, e.g., for a mechanism moving horizontally.
+    None,
+    /// Gravity pulls with a constant force, e.g., on a linear or four-bar
+    /// lift. `kg` volts are always applied to hold the lift up.
+    Constant { kg: f64 },
+    /// Gravity pulls with a force proportional to the cosine of the arm's
+    /// angle from horizontal, e.g., on a single-jointed arm. `kg` volts are
+    /// applied when the arm is at `horizontal`.
+    Arm { kg: f64, horizontal: Angle },
+}
+
+impl Gravity {
+    /// Returns the feedforward voltage needed to hold the mechanism at
+    /// `position`.
+    fn feedforward(&self, position: Angle) -> f64 {
+        match *self {
+            Gravity::None => 0.0,
+            Gravity::Constant { kg } => kg,
+            Gravity::Arm { kg, horizontal } => kg * (position - horizontal).cos(),
+        }
+    }
+}
+
+/// Configuration for a [`LiftSubsystem`].
+///
+/// Positions are angles measured by the lift's rotation sensor, velocities
+/// are in radians per second, and accelerations are in radians per second
+/// squared.
+#[derive(Debug, Clone, Copy)]
+pub struct LiftConfig {
+    pub kp: f64,
+    pub ki: f64,
+    pub ki_limit: f64,
+    pub kd: f64,
+    /// Volts per radian per second of profiled velocity
+    pub kv: f64,
+    pub gravity: Gravity,
+
+    pub max_velocity: f64,
+    pub max_acceleration: f64,
+
+    /// The lowest position the lift may be driven to
+    pub min_position: Angle,
+    /// The highest position the lift may be driven to
+    pub max_position: Angle,
+
+    pub error_tolerance: f64,
+    pub velocity_tolerance: f64,
+    pub tolerance_duration: Duration,
+    pub timeout: Duration,
+}
+
+impl LiftConfig {
+    fn pid(&self) -> Pid<f64> {
+        let mut pid = Pid::new(0.0, MAX_VOLTAGE);
+        pid.p(self.kp, MAX_VOLTAGE);
+        pid.i(self.ki, self.ki_limit);
+        pid.d(self.kd, MAX_VOLTAGE);
+        pid
+    }
+
+    fn tolerances(&self) -> Tolerances {
+        Tolerances::new()
+            .error_tolerance(self.error_tolerance)
+            .velocity_tolerance(self.velocity_tolerance)
+            .tolerance_duration(self.tolerance_duration)
+            .timeout(self.timeout)
+    }
+
+    // #region: Builder
+    pub fn with_kp(mut self, kp: f64) -> Self {
+        self.kp = kp;
+        self
+    }
+    pub fn with_ki(mut self, ki: f64) -> Self {
+        self.ki = ki;
+        self
+    }
+    pub fn with_ki_limit(mut self, ki_limit: f64) -> Self {
+        self.ki_limit = ki_limit;
+        self
+    }
+    pub fn with_kd(mut self, kd: f64) -> Self {
+        self.kd = kd;
+        self
+    }
+    pub fn with_kv(mut self, kv: f64) -> Self {
+        self.kv = kv;
+        self
+    }
+    pub fn with_gravity(mut self, gravity: Gravity) -> Self {
+        self.gravity = gravity;
+        self
+    }
+    pub fn with_max_velocity(mut self, max_velocity: f64) -> Self {
+        self.max_velocity = max_velocity;
+        self
+    }
+    pub fn with_max_acceleration(mut self, max_acceleration: f64) -> Self {
+        self.max_acceleration = max_acceleration;
+        self
+    }
+    pub fn with_min_position(mut self, min_position: Angle) -> Self {
+        self.min_position = min_position;
+        self
+    }
+    pub fn with_max_position(mut self, max_position: Angle) -> Self {
+        self.max_position = max_position;
+        self
+    }
+    pub fn with_error_tolerance(mut self, error_tolerance: f64) -> Self {
+        self.error_tolerance = error_tolerance;
+        self
+    }
+    pub fn with_velocity_tolerance(mut self, velocity_tolerance: f64) -> Self {
+        self.velocity_tolerance = velocity_tolerance;
+        self
+    }
+    pub fn with_tolerance_duration(mut self, tolerance_duration: Duration) -> Self {
+        self.tolerance_duration = tolerance_duration;
+        self
+    }
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    // #endregion: Builder
+}
+
+/// A profiled move towards a target position.
+#[derive(Debug)]
+struct LiftMove {
+    profile: TrapezoidalProfile,
+    start_time: Instant,
+    tolerances: Tolerances,
+    settled: Rc<AtomicBool>,
+}
+
+#[derive(Debug)]
+struct LiftState {
+    position: Angle,
+    target: Option<Angle>,
+    current_move: Option<LiftMove>,
+}
+
+/// A future which resolves when a [`LiftSubsystem`] move has settled, or has
+/// been replaced by another move.
+pub struct LiftMoveFuture {
+    settled: Rc<AtomicBool>,
+}
+
+impl Future for LiftMoveFuture {
+    type Output = ();
+
+    fn poll(
+        self: core::pin::Pin<&mut Self>,
+        cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<Self::Output> {
+        if self.settled.load(Ordering::Acquire) {
+            core::task::Poll::Ready(())
+        } else {
+            cx.waker().wake_by_ref();
+            core::task::Poll::Pending
+        }
+    }
+}
+
+/// A lift or arm driven by a motor group to positions measured by a rotation
+/// sensor.
+///
+/// Moves follow a [`TrapezoidalProfile`], with a PID controller tracking the
+/// profile plus velocity and gravity feedforward. Once a move finishes, the
+/// lift keeps holding the target until [`release`](Self::release) is called.
+/// The lift is never driven past the soft limits in the [`LiftConfig`].
+///
+/// Like [`Drivetrain`](super::drivetrain::Drivetrain), the control loop runs
+/// in a background task, and each move returns a future which resolves once
+/// the lift has settled at the target.
+pub struct LiftSubsystem {
+    state: Rc<RefCell<LiftState>>,
+    config: LiftConfig,
+    setpoints: BTreeMap<&'static str, Angle>,
+    _task: vexide::task::Task<()>,
+}
+
+impl LiftSubsystem {
+    /// Creates a lift and starts its control loop.
+    ///
+    /// Fails if the config's `max_velocity` or `max_acceleration` isn't
+    /// positive.
+    pub fn new(
+        mut motors: DoxaMotorGroup,
+        sensor: impl HasRotation + 'static,
+        config: LiftConfig,
+    ) -> Result<Self, DoxaError> {
+        TrapezoidalProfile::validate_limits(config.max_velocity, config.max_acceleration)?;
+        let state = Rc::new(RefCell::new(LiftState {
+            position: sensor.position(),
+            target: None,
+            current_move: None,
+        }));
+        Ok(Self {
+            state: state.clone(),
+            config,
+            setpoints: BTreeMap::new(),
+            _task: vexide::task::spawn(async move {
+                let mut pid = config.pid();
+                let mut last_position = sensor.position();
+                let mut last_time = Instant::now();
+                let mut ticker = Ticker::new(LOOP_PERIOD);
+                loop {
+                    let scope = profiling::scope("lift");
+                    {
+                        let position = sensor.position();
+                        let now = Instant::now();
+                        let dt = now.duration_since(last_time).as_secs_f64();
+                        let velocity = if dt > 0.0 {
+                            (position - last_position).as_radians() / dt
+                        } else {
+                            0.0
+                        };
+                        last_position = position;
+                        last_time = now;
+
+                        let mut state = state.borrow_mut();
+                        state.position = position;
+                        if let Some(target) = state.target {
+                            let (setpoint, setpoint_velocity) = match &state.current_move {
+                                Some(current_move) => current_move
+                                    .profile
+                                    .sample(current_move.start_time.elapsed().as_secs_f64()),
+                                None => (target.as_radians(), 0.0),
+                            };
+                            pid.setpoint(setpoint);
+                            let mut output = pid.next_control_output(position.as_radians()).output
+                                + config.kv * setpoint_velocity
+                                + config.gravity.feedforward(position);
+                            // Soft limits: never push further past a limit
+                            if position >= config.max_position {
+                                output = output.min(0.0);
+                            } else if position <= config.min_position {
+                                output = output.max(0.0);
+                            }
+                            motors
+                                .set_voltage(output.clamp(-MAX_VOLTAGE, MAX_VOLTAGE))
+                                .expect_report("failed to set lift voltage");
+
+                            if let Some(current_move) = &mut state.current_move
+                                && current_move.start_time.elapsed().as_secs_f64()
+                                    >= current_move.profile.duration()
+                                && current_move
+                                    .tolerances
+                                    .check((target - position).as_radians(), velocity)
+                            {
+                                // Keep holding the target, but notify waiters
+                                current_move.settled.store(true, Ordering::Release);
+                                state.current_move = None;
+                            }
+                        } else {
+                            pid.reset_integral_term();
+                            motors
+                                .set_voltage(0.0)
+                                .expect_report("failed to zero lift voltage");
+                        }
+                    }
+                    drop(scope);
+                    ticker.tick().await;
+                }
+            }),
+        })
+    }
+
+    /// Registers a named setpoint which can be moved to with
+    /// [`move_to_named`](Self::move_to_named).
+    pub fn with_setpoint(mut self, name: &'static str, position: Angle) -> Self {
+        self.setpoints.insert(name, position);
+        self
+    }
+
+    /// Starts a profiled move to `target`, which is clamped to the soft
+    /// limits.
+    ///
+    /// Any move in progress is replaced, and its future resolves immediately.
+    pub fn move_to(&mut self, target: Angle) -> LiftMoveFuture {
+        let target = Angle::from_radians(target.as_radians().clamp(
+            self.config.min_position.as_radians(),
+            self.config.max_position.as_radians(),
+        ));
+        let settled = Rc::new(AtomicBool::new(false));
+        let mut state = self.state.borrow_mut();
+        if let Some(previous) = state.current_move.take() {
+            previous.settled.store(true, Ordering::Release);
+        }
+        let start = state.position.as_radians();
+        state.current_move = Some(LiftMove {
+            profile: TrapezoidalProfile::new(
+                start,
+                target.as_radians(),
+                self.config.max_velocity,
+                self.config.max_acceleration,
+            )
+            .expect("Lift limits are validated in LiftSubsystem::new"),
+            start_time: Instant::now(),
+            tolerances: self.config.tolerances(),
+            settled: settled.clone(),
+        });
+        state.target = Some(target);
+        LiftMoveFuture { settled }
+    }
+
+    /// Starts a profiled move to a setpoint registered with
+    /// [`with_setpoint`](Self::with_setpoint).
+    ///
+    /// Fails if no setpoint with the given name was registered, without
+    /// moving the lift.
+    pub fn move_to_named(&mut self, name: &str) -> Result<LiftMoveFuture, DoxaError> {
+        let target = *self
+            .setpoints
+            .get(name)
+            .ok_or_else(|| DoxaError::NotFound {
+                kind: "lift setpoint",
+                name: name.to_string(),
+            })?;
+        Ok(self.move_to(target))
+    }
+
+    /// Stops driving the lift, letting it fall or be back-driven.
+    ///
+    /// Any move in progress is cancelled, and its future resolves
+    /// immediately.
+    pub fn release(&mut self) {
+        let mut state = self.state.borrow_mut();
+        if let Some(previous) = state.current_move.take() {
+            previous.settled.store(true, Ordering::Release);
+        }
+        state.target = None;
+    }
+
+    /// Returns the current position measured by the rotation sensor.
+    pub fn position(&self) -> Angle {
+        self.state.borrow().position
+    }
+
+    /// Returns the position the lift is moving to or holding, if any.
+    pub fn target(&self) -> Option<Angle> {
+        self.state.borrow().target
+    }
+
+    /// Returns whether a move is in progress.
+    pub fn is_moving(&self) -> bool {
+        self.state.borrow().current_move.is_some()
+    }
+}
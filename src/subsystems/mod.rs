@@ -1,3 +1,14 @@
+pub mod catapult;
 pub mod drivetrain;
+pub mod flywheel;
+pub mod hang;
+pub mod hud;
+pub mod input;
+pub mod lift;
+pub mod pid;
 pub mod pneumatic;
+pub mod state_machine;
+pub mod system_check;
 pub mod tracking;
+pub mod vision;
+pub mod wall;
@@ -0,0 +1,200 @@
+use core::{
+    cell::RefCell,
+    future::Future,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use std::time::Instant;
+
+use alloc::rc::Rc;
+use vexide::math::Angle;
+
+use crate::{
+    motorgroup::DoxaMotorGroup,
+    utils::{
+        controllers::Controller,
+        profiling,
+        settling::Tolerances,
+        ticker::{LOOP_PERIOD, Ticker},
+        traits::HasRotation,
+        unwrap_expect_report::UnwrapExpectReportExt as _,
+    },
+};
+
+#[derive(Debug)]
+struct PidState {
+    position: Angle,
+    target: Option<Angle>,
+    tolerances: Tolerances,
+    /// The tolerances for the current target, reset on every new target
+    current_tolerances: Tolerances,
+    settled: Rc<AtomicBool>,
+}
+
+/// A future which resolves when a [`PidSubsystem`] has settled at its target,
+/// or the target has been replaced.
+pub struct PidSettleFuture {
+    settled: Rc<AtomicBool>,
+}
+
+impl Future for PidSettleFuture {
+    type Output = ();
+
+    fn poll(
+        self: core::pin::Pin<&mut Self>,
+        cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<Self::Output> {
+        if self.settled.load(Ordering::Acquire) {
+            core::task::Poll::Ready(())
+        } else {
+            cx.waker().wake_by_ref();
+            core::task::Poll::Pending
+        }
+    }
+}
+
+/// A single-degree-of-freedom mechanism, such as a wall-stake arm or tilter,
+/// driven to positions by a PID controller.
+///
+/// This generalizes the drivetrain's action machinery: the control loop runs
+/// in a background task, and [`set_target`](Self::set_target) returns a
+/// future which resolves once the mechanism has settled within the
+/// [`Tolerances`]. The PID output is in volts, and errors are in radians of
+/// the feedback source.
+///
+/// After settling, the mechanism keeps holding its target until
+/// [`release`](Self::release) is called.
+#[derive(Debug, Clone)]
+pub struct PidSubsystem {
+    state: Rc<RefCell<PidState>>,
+    _task: Rc<vexide::task::Task<()>>,
+}
+
+impl PidSubsystem {
+    /// Creates a new PID subsystem. The mechanism is released until a target
+    /// is set.
+    ///
+    /// `controller` is usually a [`Pid`](pid::Pid), but any [`Controller`]
+    /// works.
+    pub fn new(
+        mut motors: DoxaMotorGroup,
+        sensor: impl HasRotation + 'static,
+        mut controller: impl Controller + 'static,
+        tolerances: Tolerances,
+    ) -> Self {
+        let state = Rc::new(RefCell::new(PidState {
+            position: sensor.position(),
+            target: None,
+            tolerances,
+            current_tolerances: tolerances,
+            settled: Rc::new(AtomicBool::new(true)),
+        }));
+        Self {
+            state: state.clone(),
+            _task: Rc::new(vexide::task::spawn(async move {
+                let mut last_position = sensor.position();
+                let mut last_time = Instant::now();
+                let mut ticker = Ticker::new(LOOP_PERIOD);
+                loop {
+                    let scope = profiling::scope("pid");
+                    {
+                        let position = sensor.position();
+                        let now = Instant::now();
+                        let dt = now.duration_since(last_time).as_secs_f64();
+                        let velocity = if dt > 0.0 {
+                            (position - last_position).as_radians() / dt
+                        } else {
+                            0.0
+                        };
+                        last_position = position;
+                        last_time = now;
+
+                        let mut state = state.borrow_mut();
+                        state.position = position;
+                        if let Some(target) = state.target {
+                            let error = (target - position).as_radians();
+                            if !state.settled.load(Ordering::Acquire)
+                                && state.current_tolerances.check(error, velocity)
+                            {
+                                state.settled.store(true, Ordering::Release);
+                            }
+                            let output = controller.update(0.0, -error);
+                            motors
+                                .set_voltage(output)
+                                .expect_report("failed to set mechanism voltage");
+                        } else {
+                            controller.reset();
+                            motors
+                                .set_voltage(0.0)
+                                .expect_report("failed to zero mechanism voltage");
+                        }
+                    }
+                    drop(scope);
+                    ticker.tick().await;
+                }
+            })),
+        }
+    }
+
+    /// Sets the target position and returns a future which resolves once the
+    /// mechanism has settled there.
+    ///
+    /// Any previous target is replaced, and its future resolves immediately.
+    pub fn set_target(&mut self, target: Angle) -> PidSettleFuture {
+        PidSettleFuture {
+            settled: self.replace_target(target),
+        }
+    }
+
+    /// Holds the mechanism at its current position.
+    pub fn hold(&mut self) {
+        let position = self.position();
+        self.replace_target(position);
+    }
+
+    /// Replaces the target, resolving any future waiting on the previous
+    /// one, and returns the flag set once the mechanism settles.
+    fn replace_target(&mut self, target: Angle) -> Rc<AtomicBool> {
+        let mut state = self.state.borrow_mut();
+        state.settled.store(true, Ordering::Release);
+        let settled = Rc::new(AtomicBool::new(false));
+        state.settled = settled.clone();
+        state.current_tolerances = state.tolerances;
+        state.target = Some(target);
+        settled
+    }
+
+    /// Stops driving the mechanism. Any future waiting on a target resolves
+    /// immediately.
+    pub fn release(&mut self) {
+        let mut state = self.state.borrow_mut();
+        state.settled.store(true, Ordering::Release);
+        state.target = None;
+    }
+
+    /// Sets the tolerances used for targets set after this call.
+    pub fn set_tolerances(&mut self, tolerances: Tolerances) {
+        self.state.borrow_mut().tolerances = tolerances;
+    }
+
+    /// Returns the tolerances used to decide whether the mechanism has
+    /// settled.
+    pub fn tolerances(&self) -> Tolerances {
+        self.state.borrow().tolerances
+    }
+
+    /// Returns the current position of the feedback source.
+    pub fn position(&self) -> Angle {
+        self.state.borrow().position
+    }
+
+    /// Returns the target position, or `None` if the mechanism is released.
+    pub fn target(&self) -> Option<Angle> {
+        self.state.borrow().target
+    }
+
+    /// Returns whether the mechanism has settled at its target.
+    pub fn is_settled(&self) -> bool {
+        let state = self.state.borrow();
+        state.target.is_some() && state.settled.load(Ordering::Acquire)
+    }
+}
@@ -1,13 +1,177 @@
-use core::cell::RefCell;
+use core::cell::{Cell, RefCell};
 
-use alloc::rc::Rc;
+use alloc::{boxed::Box, rc::Rc};
 use vexide::adi::digital::LogicLevel;
 
-use crate::utils::unwrap_expect_report::UnwrapExpectReportExt;
+pub use crate::utils::alliance::MirroredState;
+use crate::{error::DoxaError, utils::alliance::AllianceContext};
+
+struct AirBudgetInner {
+    actuations_per_fill: u32,
+    used: u32,
+    low_threshold: u32,
+    warned: bool,
+    on_low: Option<Box<dyn FnMut(u32)>>,
+}
+
+/// An estimate of how much air is left in the reservoir.
+///
+/// The budget is counted in piston actuations: a full reservoir is good for
+/// about `actuations_per_fill` of them. Every [`PneumaticSubsystem`] sharing
+/// the reservoir should be given a clone of the same budget with
+/// [`PneumaticSubsystem::with_air_budget`], and each one uses up one actuation
+/// per solenoid every time it changes state.
+///
+/// When the remaining budget first drops to or below the low threshold, a
+/// warning is logged and the callback set with
+/// [`with_on_low`](Self::with_on_low) is called.
+#[derive(Clone)]
+pub struct AirBudget {
+    inner: Rc<RefCell<AirBudgetInner>>,
+}
+
+impl AirBudget {
+    /// Creates a new, full air budget.
+    ///
+    /// The low threshold defaults to a fifth of `actuations_per_fill`.
+    pub fn new(actuations_per_fill: u32) -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(AirBudgetInner {
+                actuations_per_fill,
+                used: 0,
+                low_threshold: actuations_per_fill / 5,
+                warned: false,
+                on_low: None,
+            })),
+        }
+    }
+
+    /// Sets the number of remaining actuations at or below which the budget
+    /// is considered low.
+    pub fn with_low_threshold(self, low_threshold: u32) -> Self {
+        self.inner.borrow_mut().low_threshold = low_threshold;
+        self
+    }
+
+    /// Sets a callback which is called with the remaining actuations when the
+    /// budget first drops low, e.g., to rumble the controller.
+    pub fn with_on_low(self, on_low: impl FnMut(u32) + 'static) -> Self {
+        self.inner.borrow_mut().on_low = Some(Box::new(on_low));
+        self
+    }
+
+    fn use_actuations(&self, count: u32) {
+        let mut inner = self.inner.borrow_mut();
+        inner.used = inner.used.saturating_add(count);
+        let remaining = inner.actuations_per_fill.saturating_sub(inner.used);
+        if !inner.warned && remaining <= inner.low_threshold {
+            inner.warned = true;
+            log::warn!(
+                "Air budget low: about {} of {} actuations left",
+                remaining,
+                inner.actuations_per_fill
+            );
+            if let Some(on_low) = inner.on_low.as_mut() {
+                on_low(remaining);
+            }
+        }
+    }
+
+    /// Returns the estimated number of actuations left.
+    pub fn remaining(&self) -> u32 {
+        let inner = self.inner.borrow();
+        inner.actuations_per_fill.saturating_sub(inner.used)
+    }
+
+    /// Returns the estimated fraction of the reservoir left, from 0.0 to 1.0.
+    pub fn remaining_fraction(&self) -> f64 {
+        let inner = self.inner.borrow();
+        if inner.actuations_per_fill == 0 {
+            return 0.0;
+        }
+        self.remaining() as f64 / inner.actuations_per_fill as f64
+    }
+
+    /// Returns the number of actuations used since the last refill.
+    pub fn used(&self) -> u32 {
+        self.inner.borrow().used
+    }
+
+    /// Returns whether the remaining budget is at or below the low threshold.
+    pub fn is_low(&self) -> bool {
+        self.remaining() <= self.inner.borrow().low_threshold
+    }
+
+    /// Resets the budget after the reservoir has been refilled.
+    pub fn refill(&self) {
+        let mut inner = self.inner.borrow_mut();
+        inner.used = 0;
+        inner.warned = false;
+    }
+}
+
+impl core::fmt::Debug for AirBudget {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let inner = self.inner.borrow();
+        f.debug_struct("AirBudget")
+            .field("actuations_per_fill", &inner.actuations_per_fill)
+            .field("used", &inner.used)
+            .field("low_threshold", &inner.low_threshold)
+            .finish()
+    }
+}
+
+impl PartialEq for AirBudget {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Eq for AirBudget {}
+
+/// A state of a multi-position pneumatic mechanism.
+///
+/// Implement this on an enum to control a mechanism with several solenoids by
+/// name instead of by solenoid, e.g., two solenoids driving the four positions
+/// of a two-stage mechanism:
+///
+/// ```ignore
+/// enum Stage { Stowed, Half, Full, Hook }
+///
+/// impl PneumaticState<2> for Stage {
+///     fn extended(&self) -> [bool; 2] {
+///         match self {
+///             Stage::Stowed => [false, false],
+///             Stage::Half => [true, false],
+///             Stage::Full => [true, true],
+///             Stage::Hook => [false, true],
+///         }
+///     }
+///
+///     fn from_extended(extended: [bool; 2]) -> Option<Self> {
+///         Some(match extended {
+///             [false, false] => Stage::Stowed,
+///             [true, false] => Stage::Half,
+///             [true, true] => Stage::Full,
+///             [false, true] => Stage::Hook,
+///         })
+///     }
+/// }
+/// ```
+pub trait PneumaticState<const N: usize>: Sized {
+    /// Returns whether each solenoid is extended in this state.
+    fn extended(&self) -> [bool; N];
+
+    /// Returns the state in which the solenoids are extended as given, or
+    /// `None` if there is no such state.
+    fn from_extended(extended: [bool; N]) -> Option<Self>;
+}
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct PneumaticSubsystem<const N: usize, const LOW_IS_EXTENDED: bool = false> {
     solenoids: Rc<RefCell<[vexide::adi::digital::AdiDigitalOut; N]>>,
+    actuations: Rc<Cell<u32>>,
+    air_budget: Option<AirBudget>,
 }
 
 impl<const N: usize, const LOW_IS_EXTENDED: bool> PneumaticSubsystem<N, LOW_IS_EXTENDED> {
@@ -20,38 +184,112 @@ impl<const N: usize, const LOW_IS_EXTENDED: bool> PneumaticSubsystem<N, LOW_IS_E
         }
         Self {
             solenoids: Rc::new(RefCell::new(solenoids)),
+            actuations: Rc::new(Cell::new(0)),
+            air_budget: None,
         }
     }
 
+    /// Counts this subsystem's actuations against the given air budget.
+    pub fn with_air_budget(mut self, air_budget: AirBudget) -> Self {
+        self.air_budget = Some(air_budget);
+        self
+    }
+
+    /// Returns the air budget this subsystem uses, if any.
+    pub fn air_budget(&self) -> Option<&AirBudget> {
+        self.air_budget.as_ref()
+    }
+
+    /// Returns the number of times the piston(s) have changed state.
+    pub fn actuations(&self) -> u32 {
+        self.actuations.get()
+    }
+
+    /// Records a state change in which `solenoids` solenoids changed level.
+    fn record_actuation(&self, solenoids: u32) {
+        self.actuations.set(self.actuations.get() + 1);
+        if let Some(air_budget) = &self.air_budget {
+            air_budget.use_actuations(solenoids);
+        }
+    }
+
+    const fn extended_level(extended: bool) -> LogicLevel {
+        if extended ^ LOW_IS_EXTENDED {
+            LogicLevel::High
+        } else {
+            LogicLevel::Low
+        }
+    }
+
+    /// Sets each solenoid to the level for the given mechanism state.
+    ///
+    /// Every solenoid is set even if one fails, and the first error is
+    /// returned.
+    pub fn set_state<S: PneumaticState<N>>(&mut self, state: S) -> Result<(), DoxaError> {
+        let extended = state.extended();
+        let mut solenoids = self.solenoids.borrow_mut();
+        let changed = solenoids
+            .iter()
+            .zip(extended)
+            .filter(|(solenoid, extended)| {
+                solenoid.level().ok() != Some(Self::extended_level(*extended))
+            })
+            .count() as u32;
+        let mut result = Ok(());
+        for (solenoid, extended) in solenoids.iter_mut().zip(extended) {
+            result = result.and(solenoid.set_level(Self::extended_level(extended)));
+        }
+        drop(solenoids);
+        if changed > 0 {
+            self.record_actuation(changed);
+        }
+        Ok(result?)
+    }
+
+    /// Returns the mechanism state matching the current solenoid levels, or
+    /// `None` if no state matches.
+    pub fn state<S: PneumaticState<N>>(&self) -> Option<S> {
+        let solenoids = self.solenoids.borrow();
+        let mut extended = [false; N];
+        for (extended, solenoid) in extended.iter_mut().zip(solenoids.iter()) {
+            *extended = solenoid.level().ok()? == Self::extended_level(true);
+        }
+        S::from_extended(extended)
+    }
+
     /// Extends the piston(s).
-    pub fn extend(&mut self) {
-        for solenoid in self.solenoids.borrow_mut().iter_mut() {
-            solenoid
-                .set_level(match LOW_IS_EXTENDED {
-                    true => LogicLevel::Low,
-                    false => LogicLevel::High,
-                })
-                .expect_report("failed to extend piston");
+    pub fn extend(&mut self) -> Result<(), DoxaError> {
+        if !self.extended() {
+            self.record_actuation(N as u32);
         }
+        self.set_levels(Self::extended_level(true))
     }
 
     /// Retracts the piston(s).
-    pub fn retract(&mut self) {
+    pub fn retract(&mut self) -> Result<(), DoxaError> {
+        if !self.retracted() {
+            self.record_actuation(N as u32);
+        }
+        self.set_levels(Self::extended_level(false))
+    }
+
+    /// Sets every solenoid to `level`, returning the first error.
+    fn set_levels(&mut self, level: LogicLevel) -> Result<(), DoxaError> {
+        let mut result = Ok(());
         for solenoid in self.solenoids.borrow_mut().iter_mut() {
-            solenoid
-                .set_level(match LOW_IS_EXTENDED {
-                    true => LogicLevel::High,
-                    false => LogicLevel::Low,
-                })
-                .expect_report("failed to retract piston");
+            result = result.and(solenoid.set_level(level));
         }
+        Ok(result?)
     }
 
     /// Toggles the piston(s).
-    pub fn toggle(&mut self) {
+    pub fn toggle(&mut self) -> Result<(), DoxaError> {
+        self.record_actuation(N as u32);
+        let mut result = Ok(());
         for solenoid in self.solenoids.borrow_mut().iter_mut() {
-            solenoid.toggle().expect_report("failed to toggle piston");
+            result = result.and(solenoid.toggle());
         }
+        Ok(result?)
     }
 
     /// Returns whether the piston(s) is/are extended.
@@ -77,19 +315,24 @@ impl<const N: usize, const LOW_IS_EXTENDED: bool> PneumaticSubsystem<N, LOW_IS_E
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum MirroredState {
-    /// Like normal. Right is right, left is left.
-    Normal,
-    /// Mirrored. Right is left, left is right.
-    Mirrored,
-}
-
+/// A pair of pneumatics on either side of the robot, one of which is
+/// dominant depending on which side of the field the robot starts on.
+///
+/// Share the tracking subsystem's [`AllianceContext`] so that reversing
+/// tracking also mirrors the pneumatics, and the mirror flag lives in one
+/// place:
+///
+/// ```ignore
+/// let mut wings = MirroredPneumaticSubsystem::new([left], [right], MirroredState::Normal)
+///     .with_alliance(tracking.alliance());
+/// tracking.set_reverse(true);
+/// wings.extend_dominant(); // Extends the left wing
+/// ```
 #[derive(Debug, PartialEq, Eq)]
 pub struct MirroredPneumaticSubsystem<const N: usize, const LOW_IS_EXTENDED: bool = false> {
     pub left: PneumaticSubsystem<N, LOW_IS_EXTENDED>,
     pub right: PneumaticSubsystem<N, LOW_IS_EXTENDED>,
-    mirrored_state: MirroredState,
+    alliance: AllianceContext,
 }
 
 impl<const N: usize, const LOW_IS_EXTENDED: bool> MirroredPneumaticSubsystem<N, LOW_IS_EXTENDED> {
@@ -102,14 +345,29 @@ impl<const N: usize, const LOW_IS_EXTENDED: bool> MirroredPneumaticSubsystem<N,
         Self {
             left: PneumaticSubsystem::new(left_solenoids),
             right: PneumaticSubsystem::new(right_solenoids),
-            mirrored_state,
+            alliance: AllianceContext::new(mirrored_state),
         }
     }
 
+    /// Shares the given alliance context, e.g., the one from
+    /// [`TrackingSubsystem::alliance`], so that the mirrored state only has to
+    /// be set in one place. The initial mirrored state is discarded.
+    ///
+    /// [`TrackingSubsystem::alliance`]: crate::subsystems::tracking::TrackingSubsystem::alliance
+    pub fn with_alliance(mut self, alliance: AllianceContext) -> Self {
+        self.alliance = alliance;
+        self
+    }
+
+    /// Returns the alliance context the mirrored state is read from.
+    pub fn alliance(&self) -> AllianceContext {
+        self.alliance.clone()
+    }
+
     /// Returns the dominant side of the subsystem (i.e., normally right,
     /// mirrored left).
     pub fn dominant(&mut self) -> &mut PneumaticSubsystem<N, LOW_IS_EXTENDED> {
-        match self.mirrored_state {
+        match self.mirrored_state() {
             MirroredState::Normal => &mut self.right,
             MirroredState::Mirrored => &mut self.left,
         }
@@ -118,19 +376,49 @@ impl<const N: usize, const LOW_IS_EXTENDED: bool> MirroredPneumaticSubsystem<N,
     /// Returns the non-dominant side of the subsystem (i.e., normally left,
     /// mirrored right).
     pub fn non_dominant(&mut self) -> &mut PneumaticSubsystem<N, LOW_IS_EXTENDED> {
-        match self.mirrored_state {
+        match self.mirrored_state() {
             MirroredState::Normal => &mut self.left,
             MirroredState::Mirrored => &mut self.right,
         }
     }
 
+    /// Extends the dominant side.
+    pub fn extend_dominant(&mut self) -> Result<(), DoxaError> {
+        self.dominant().extend()
+    }
+
+    /// Retracts the dominant side.
+    pub fn retract_dominant(&mut self) -> Result<(), DoxaError> {
+        self.dominant().retract()
+    }
+
+    /// Toggles the dominant side.
+    pub fn toggle_dominant(&mut self) -> Result<(), DoxaError> {
+        self.dominant().toggle()
+    }
+
+    /// Extends both sides. Both are extended even if one fails.
+    pub fn extend_both(&mut self) -> Result<(), DoxaError> {
+        let left = self.left.extend();
+        left.and(self.right.extend())
+    }
+
+    /// Retracts both sides. Both are retracted even if one fails.
+    pub fn retract_both(&mut self) -> Result<(), DoxaError> {
+        let left = self.left.retract();
+        left.and(self.right.retract())
+    }
+
     /// Sets the mirrored state of the subsystem
+    ///
+    /// This sets the state of the shared alliance context, so it also affects
+    /// every other subsystem sharing it.
     pub fn set_mirrored_state(&mut self, mirrored_state: MirroredState) {
-        self.mirrored_state = mirrored_state;
+        self.alliance.set_state(mirrored_state);
     }
 
     /// Gets the current mirrored state of the subsystem
     pub fn mirrored_state(&self) -> MirroredState {
-        self.mirrored_state
+        self.alliance.state()
     }
 }
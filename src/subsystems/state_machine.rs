@@ -0,0 +1,218 @@
+//! Finite state machines for mechanisms
+//!
+//! Multi-step mechanisms, such as a clamp → lift → score sequence, are easy to
+//! write as a tangle of booleans and sleeps and hard to get right. A
+//! [`StateMachine`] instead describes the mechanism as a set of states, with:
+//!
+//! - a periodic closure run every loop while in a state,
+//! - guarded transitions, taken as soon as their guard returns `true`, and
+//! - timeouts, which move to a fallback state if a state lasts too long.
+//!
+//! Every transition is logged, so sequences can be debugged from the log.
+//!
+//! ```ignore
+//! #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+//! enum Score { Idle, Clamp, Lift, Release }
+//!
+//! let machine = StateMachine::new("score", Score::Idle)
+//!     .with_periodic(Score::Clamp, move || {
+//!         clamp.extend().expect_report("failed to clamp");
+//!     })
+//!     .with_transition(Score::Clamp, Score::Lift, move || clamp2.extended())
+//!     .with_periodic(Score::Lift, move || intake.run_up())
+//!     .with_transition(Score::Lift, Score::Release, move || ring_sensor.seen())
+//!     .with_timeout(Score::Lift, Duration::from_secs(2), Score::Idle);
+//! machine.transition_to(Score::Clamp);
+//! ```
+
+use core::{cell::RefCell, fmt::Debug, future::Future, time::Duration};
+use std::time::Instant;
+
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+
+use crate::utils::{
+    profiling,
+    ticker::{LOOP_PERIOD, Ticker},
+};
+
+struct Transition<S> {
+    to: S,
+    guard: Box<dyn FnMut() -> bool>,
+}
+
+struct StateConfig<S> {
+    state: S,
+    periodic: Option<Box<dyn FnMut()>>,
+    transitions: Vec<Transition<S>>,
+    timeout: Option<(Duration, S)>,
+}
+
+#[derive(Debug)]
+struct Current<S> {
+    state: S,
+    entered_at: Instant,
+}
+
+/// A finite state machine run in a background task.
+///
+/// The machine checks the current state every 10 ms: it first takes the first
+/// transition whose guard returns `true` (in the order they were added), or
+/// the timeout transition if the state has lasted too long, and then runs the
+/// periodic closure of the resulting state.
+///
+/// Closures must not call the `with_*` builder methods of their own machine,
+/// but may call [`transition_to`](Self::transition_to) and
+/// [`state`](Self::state).
+pub struct StateMachine<S: Copy + PartialEq + Debug + 'static> {
+    name: &'static str,
+    current: Rc<RefCell<Current<S>>>,
+    configs: Rc<RefCell<Vec<StateConfig<S>>>>,
+    _task: Rc<vexide::task::Task<()>>,
+}
+
+impl<S: Copy + PartialEq + Debug + 'static> Clone for StateMachine<S> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name,
+            current: self.current.clone(),
+            configs: self.configs.clone(),
+            _task: self._task.clone(),
+        }
+    }
+}
+
+impl<S: Copy + PartialEq + Debug + 'static> StateMachine<S> {
+    /// Creates a new state machine in the `initial` state and starts running
+    /// it. `name` is used when logging transitions.
+    pub fn new(name: &'static str, initial: S) -> Self {
+        let current = Rc::new(RefCell::new(Current {
+            state: initial,
+            entered_at: Instant::now(),
+        }));
+        let configs: Rc<RefCell<Vec<StateConfig<S>>>> = Rc::new(RefCell::new(Vec::new()));
+        Self {
+            name,
+            current: current.clone(),
+            configs: configs.clone(),
+            _task: Rc::new(vexide::task::spawn(async move {
+                let mut ticker = Ticker::new(LOOP_PERIOD);
+                loop {
+                    let scope = profiling::scope("state_machine");
+                    {
+                        let mut configs = configs.borrow_mut();
+                        let (state, elapsed) = {
+                            let current = current.borrow();
+                            (current.state, current.entered_at.elapsed())
+                        };
+                        if let Some(config) = configs.iter_mut().find(|c| c.state == state) {
+                            let next = config
+                                .transitions
+                                .iter_mut()
+                                .find_map(|t| (t.guard)().then_some(t.to))
+                                .map(|to| (to, "guard"))
+                                .or_else(|| {
+                                    config.timeout.and_then(|(timeout, to)| {
+                                        (elapsed >= timeout).then_some((to, "timeout"))
+                                    })
+                                });
+                            if let Some((to, reason)) = next {
+                                Self::enter(name, &current, to, reason);
+                            }
+                        }
+
+                        let state = current.borrow().state;
+                        if let Some(periodic) = configs
+                            .iter_mut()
+                            .find(|c| c.state == state)
+                            .and_then(|c| c.periodic.as_mut())
+                        {
+                            periodic();
+                        }
+                    }
+                    drop(scope);
+                    ticker.tick().await;
+                }
+            })),
+        }
+    }
+
+    fn enter(name: &str, current: &RefCell<Current<S>>, to: S, reason: &str) {
+        let mut current = current.borrow_mut();
+        log::info!(
+            "{}: {:?} -> {:?} ({}, after {} ms)",
+            name,
+            current.state,
+            to,
+            reason,
+            current.entered_at.elapsed().as_millis()
+        );
+        current.state = to;
+        current.entered_at = Instant::now();
+    }
+
+    fn config(&self, state: S) -> core::cell::RefMut<'_, StateConfig<S>> {
+        core::cell::RefMut::map(self.configs.borrow_mut(), |configs| {
+            match configs.iter().position(|c| c.state == state) {
+                Some(index) => &mut configs[index],
+                None => {
+                    configs.push(StateConfig {
+                        state,
+                        periodic: None,
+                        transitions: Vec::new(),
+                        timeout: None,
+                    });
+                    configs.last_mut().unwrap()
+                }
+            }
+        })
+    }
+
+    /// Sets the closure run every loop while in `state`, replacing any
+    /// previous one.
+    pub fn with_periodic(self, state: S, periodic: impl FnMut() + 'static) -> Self {
+        self.config(state).periodic = Some(Box::new(periodic));
+        self
+    }
+
+    /// Adds a transition from `from` to `to`, taken as soon as `guard` returns
+    /// `true`.
+    pub fn with_transition(self, from: S, to: S, guard: impl FnMut() -> bool + 'static) -> Self {
+        self.config(from).transitions.push(Transition {
+            to,
+            guard: Box::new(guard),
+        });
+        self
+    }
+
+    /// Moves from `state` to `to` if `state` lasts longer than `timeout`,
+    /// replacing any previous timeout for `state`.
+    pub fn with_timeout(self, state: S, timeout: Duration, to: S) -> Self {
+        self.config(state).timeout = Some((timeout, to));
+        self
+    }
+
+    /// Moves to `state` immediately, regardless of guards.
+    pub fn transition_to(&self, state: S) {
+        Self::enter(self.name, &self.current, state, "requested");
+    }
+
+    /// Returns the current state.
+    pub fn state(&self) -> S {
+        self.current.borrow().state
+    }
+
+    /// Returns how long the machine has been in the current state.
+    pub fn time_in_state(&self) -> Duration {
+        self.current.borrow().entered_at.elapsed()
+    }
+
+    /// Waits until the machine is in `state`.
+    pub fn wait_for(&self, state: S) -> impl Future<Output = ()> + 'static {
+        let machine = self.clone();
+        async move {
+            while machine.state() != state {
+                vexide::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+    }
+}
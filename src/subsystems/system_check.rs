@@ -0,0 +1,227 @@
+//! Pre-match system checks
+//!
+//! Before every match, someone should make sure every motor is plugged in
+//! and cool, the pneumatics fire, the sensors respond, and the SD card can
+//! be written to. Each subsystem registers a check for its own parts with a
+//! shared [`SystemCheck`], and one call runs them all:
+//!
+//! ```ignore
+//! let checks = SystemCheck::new()
+//!     .with_check("drive", SystemCheck::motors(drive_motors.clone(), 55.0))
+//!     .with_check("clamp", SystemCheck::pneumatic(clamp.clone()))
+//!     .with_check("tracking", SystemCheck::tracking(tracking.clone()))
+//!     .with_check("sd card", SystemCheck::sd_writable("check.txt"));
+//! let report = checks.system_check();
+//! report.log();
+//! ```
+//!
+//! The report can be shown as a checklist on the screen with a
+//! [`ChecklistPage`](crate::debug_render::ChecklistPage).
+
+use core::cell::RefCell;
+
+use alloc::{
+    boxed::Box,
+    format,
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::{
+    motorgroup::DoxaMotorGroup, subsystems::pneumatic::PneumaticSubsystem,
+    subsystems::tracking::TrackingSubsystem,
+};
+
+/// The result of one check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    /// The check passed, but something should be looked at, e.g., a warm
+    /// motor.
+    Warn(String),
+    Fail(String),
+}
+
+impl CheckStatus {
+    /// Returns whether the check didn't fail.
+    pub fn is_ok(&self) -> bool {
+        !matches!(self, CheckStatus::Fail(_))
+    }
+}
+
+/// The result of one named check of a [`SystemCheckReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub status: CheckStatus,
+}
+
+/// The results of every check, from [`SystemCheck::system_check`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SystemCheckReport {
+    pub results: Vec<CheckResult>,
+}
+
+impl SystemCheckReport {
+    /// Returns whether no check failed.
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|result| result.status.is_ok())
+    }
+
+    /// Returns one line per check, e.g., `ok   drive` or
+    /// `FAIL clamp: solenoid error`.
+    pub fn lines(&self) -> Vec<String> {
+        self.results
+            .iter()
+            .map(|result| match &result.status {
+                CheckStatus::Pass => format!("ok   {}", result.name),
+                CheckStatus::Warn(message) => format!("warn {}: {}", result.name, message),
+                CheckStatus::Fail(message) => format!("FAIL {}: {}", result.name, message),
+            })
+            .collect()
+    }
+
+    /// Logs every check, at the level matching its status.
+    pub fn log(&self) {
+        for (result, line) in self.results.iter().zip(self.lines()) {
+            match result.status {
+                CheckStatus::Pass => log::info!("System check: {}", line),
+                CheckStatus::Warn(_) => log::warn!("System check: {}", line),
+                CheckStatus::Fail(_) => log::error!("System check: {}", line),
+            }
+        }
+        if self.passed() {
+            log::info!("System check: all {} checks passed", self.results.len());
+        }
+    }
+}
+
+type Check = Box<dyn FnMut() -> CheckStatus>;
+
+/// A registry of pre-match checks. See the [module documentation](self).
+///
+/// Clones share the same checks, so each subsystem can register its own.
+#[derive(Clone, Default)]
+pub struct SystemCheck {
+    checks: Rc<RefCell<Vec<(&'static str, Check)>>>,
+}
+
+impl core::fmt::Debug for SystemCheck {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SystemCheck")
+            .field(
+                "checks",
+                &self
+                    .checks
+                    .borrow()
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl SystemCheck {
+    /// Creates a registry with no checks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a check, run after the existing ones.
+    pub fn with_check(
+        self,
+        name: &'static str,
+        check: impl FnMut() -> CheckStatus + 'static,
+    ) -> Self {
+        self.register(name, check);
+        self
+    }
+
+    /// Registers a check, run after the existing ones.
+    pub fn register(&self, name: &'static str, check: impl FnMut() -> CheckStatus + 'static) {
+        self.checks.borrow_mut().push((name, Box::new(check)));
+    }
+
+    /// Runs every check in order.
+    pub fn system_check(&self) -> SystemCheckReport {
+        let mut checks = self.checks.borrow_mut();
+        SystemCheckReport {
+            results: checks
+                .iter_mut()
+                .map(|(name, check)| CheckResult {
+                    name,
+                    status: check(),
+                })
+                .collect(),
+        }
+    }
+
+    /// A check that every motor of `motors` is connected and cooler than
+    /// `max_temperature` °C. Motors within 10 °C of it are a warning.
+    pub fn motors(motors: DoxaMotorGroup, max_temperature: f64) -> impl FnMut() -> CheckStatus {
+        move || {
+            let disconnected = motors.disconnected();
+            if !disconnected.is_empty() {
+                return CheckStatus::Fail(format!("ports {:?} disconnected", disconnected));
+            }
+            match motors.hottest() {
+                None => CheckStatus::Fail("no temperature".to_string()),
+                Some((port, temperature)) if temperature >= max_temperature => {
+                    CheckStatus::Fail(format!("port {} at {:.0}C", port, temperature))
+                }
+                Some((port, temperature)) if temperature >= max_temperature - 10.0 => {
+                    CheckStatus::Warn(format!("port {} at {:.0}C", port, temperature))
+                }
+                Some(_) => CheckStatus::Pass,
+            }
+        }
+    }
+
+    /// A check that `pneumatic` can be toggled and toggled back, and that
+    /// its air budget, if any, isn't low. This fires the pneumatic twice.
+    pub fn pneumatic<const N: usize, const LOW_IS_EXTENDED: bool>(
+        pneumatic: Rc<RefCell<PneumaticSubsystem<N, LOW_IS_EXTENDED>>>,
+    ) -> impl FnMut() -> CheckStatus {
+        move || {
+            let mut pneumatic = pneumatic.borrow_mut();
+            if let Err(error) = pneumatic.toggle().and_then(|_| pneumatic.toggle()) {
+                return CheckStatus::Fail(error.to_string());
+            }
+            match pneumatic.air_budget() {
+                Some(budget) if budget.is_low() => {
+                    CheckStatus::Warn(format!("{} actuations left", budget.remaining()))
+                }
+                _ => CheckStatus::Pass,
+            }
+        }
+    }
+
+    /// A check that every tracking wheel of `tracking` can be read.
+    pub fn tracking(tracking: TrackingSubsystem) -> impl FnMut() -> CheckStatus {
+        move || {
+            let diagnostics = tracking.diagnostics();
+            let errors: Vec<String> = diagnostics
+                .perpendicular
+                .iter()
+                .chain(&diagnostics.parallel)
+                .filter_map(|wheel| wheel.error)
+                .map(|error| error.to_string())
+                .collect();
+            if errors.is_empty() {
+                CheckStatus::Pass
+            } else {
+                CheckStatus::Fail(errors.join(", "))
+            }
+        }
+    }
+
+    /// A check that the file at `path` on the SD card can be written to.
+    pub fn sd_writable(path: &'static str) -> impl FnMut() -> CheckStatus {
+        move || match std::fs::write(path, "system check\n") {
+            Ok(()) => CheckStatus::Pass,
+            Err(error) => CheckStatus::Fail(error.to_string()),
+        }
+    }
+}
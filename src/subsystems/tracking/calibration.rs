@@ -0,0 +1,220 @@
+use core::time::Duration;
+use std::time::Instant;
+
+use alloc::vec::Vec;
+use vexide::{math::Angle, smart::PortError};
+
+use super::{
+    TrackingDiagnostics, TrackingSubsystem,
+    wheel::{TrackingWheelMountingDirection, WheelDiagnostics},
+};
+
+/// How long the wheels must be still before the push is considered done.
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// How far, in mm, a wheel may move in one update while still.
+const STILL_DELTA: f64 = 0.5;
+
+/// The fraction of the pushed distance a wheel must measure to count as
+/// moving with the push, and below which a perpendicular wheel counts as
+/// still.
+const MOVED_FRACTION: f64 = 0.5;
+
+/// How far, as a fraction, a parallel wheel's measured distance may be from
+/// the pushed distance before its circumference is reported as wrong.
+const SCALE_TOLERANCE: f64 = 0.1;
+
+/// What a [wheel direction check](TrackingSubsystem::check_wheel_directions)
+/// found about one tracking wheel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WheelCheck {
+    /// The wheel measured what it should have.
+    Ok,
+    /// A parallel wheel measured the push backwards, so its sensor should be
+    /// reversed.
+    Reversed,
+    /// A parallel wheel measured the push with the right sign, but `ratio`
+    /// times the distance, so its circumference or gearing is wrong.
+    Scale { ratio: f64 },
+    /// A parallel wheel barely moved, so it is probably mounted
+    /// perpendicular, or not touching the ground.
+    NotMoving,
+    /// A perpendicular wheel moved with the push, so it is probably mounted
+    /// parallel.
+    MovedWithPush,
+    /// The sensor couldn't be read.
+    SensorError(PortError),
+}
+
+/// The result of a wheel direction check for one tracking wheel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WheelFinding {
+    pub mounting_direction: TrackingWheelMountingDirection,
+    /// The index of the wheel among the wheels in its mounting direction
+    pub index: usize,
+    /// The distance the wheel measured during the push, in mm
+    pub measured: f64,
+    pub check: WheelCheck,
+}
+
+/// The result of a [wheel direction
+/// check](TrackingSubsystem::check_wheel_directions).
+#[derive(Debug, Clone, PartialEq)]
+pub struct WheelDirectionReport {
+    pub findings: Vec<WheelFinding>,
+    /// How much the heading changed during the push. If it is more than a
+    /// few degrees, the robot was pushed crooked and the check should be
+    /// repeated.
+    pub heading_change: Angle,
+    /// Whether the push was detected before the timeout
+    pub completed: bool,
+}
+
+impl WheelDirectionReport {
+    /// Returns whether every wheel measured what it should have.
+    pub fn is_ok(&self) -> bool {
+        self.completed
+            && self
+                .findings
+                .iter()
+                .all(|finding| finding.check == WheelCheck::Ok)
+    }
+
+    /// Logs every finding, with a warning for each problem.
+    pub fn log(&self) {
+        if !self.completed {
+            log::warn!("Wheel check: timed out before the push finished");
+        }
+        for finding in &self.findings {
+            match finding.check {
+                WheelCheck::Ok => log::info!(
+                    "Wheel check: {:?} wheel {} OK ({:.0} mm)",
+                    finding.mounting_direction,
+                    finding.index,
+                    finding.measured
+                ),
+                check => log::warn!(
+                    "Wheel check: {:?} wheel {}: {:?} ({:.0} mm)",
+                    finding.mounting_direction,
+                    finding.index,
+                    check,
+                    finding.measured
+                ),
+            }
+        }
+        log::info!(
+            "Wheel check: heading changed by {:.1}° during the push",
+            self.heading_change.as_degrees()
+        );
+    }
+}
+
+fn check_wheel(start: &WheelDiagnostics, end: &WheelDiagnostics, distance: f64) -> WheelCheck {
+    if let Some(error) = end.error {
+        return WheelCheck::SensorError(error);
+    }
+    let measured = end.distance - start.distance;
+    match end.mounting_direction {
+        TrackingWheelMountingDirection::Parallel => {
+            let ratio = measured / distance;
+            if ratio.abs() < MOVED_FRACTION {
+                WheelCheck::NotMoving
+            } else if ratio < 0.0 {
+                WheelCheck::Reversed
+            } else if (ratio - 1.0).abs() > SCALE_TOLERANCE {
+                WheelCheck::Scale { ratio }
+            } else {
+                WheelCheck::Ok
+            }
+        }
+        TrackingWheelMountingDirection::Perpendicular => {
+            if measured.abs() >= distance * MOVED_FRACTION {
+                WheelCheck::MovedWithPush
+            } else {
+                WheelCheck::Ok
+            }
+        }
+    }
+}
+
+impl TrackingSubsystem {
+    /// Checks the direction and mounting of every tracking wheel, while the
+    /// robot is pushed straight forwards by hand by `distance` mm.
+    ///
+    /// The check starts once any wheel has measured half the distance, and
+    /// finishes once the wheels have been still for half a second, or after
+    /// `timeout`. Parallel wheels should measure the distance, and
+    /// perpendicular wheels nothing:
+    ///
+    /// ```ignore
+    /// log::info!("Push the robot forwards 600 mm");
+    /// let report = tracking.check_wheel_directions(600.0, Duration::from_secs(15)).await;
+    /// report.log();
+    /// ```
+    pub async fn check_wheel_directions(
+        &self,
+        distance: f64,
+        timeout: Duration,
+    ) -> WheelDirectionReport {
+        let deadline = Instant::now() + timeout;
+        let start = self.diagnostics();
+        let start_rotation = self.current().rotation;
+
+        let moved = |diagnostics: &TrackingDiagnostics| {
+            diagnostics
+                .perpendicular
+                .iter()
+                .zip(&start.perpendicular)
+                .chain(diagnostics.parallel.iter().zip(&start.parallel))
+                .any(|(now, start)| {
+                    (now.distance - start.distance).abs() >= distance * MOVED_FRACTION
+                })
+        };
+        let still = |diagnostics: &TrackingDiagnostics| {
+            diagnostics
+                .perpendicular
+                .iter()
+                .chain(&diagnostics.parallel)
+                .all(|wheel| wheel.last_delta.abs() < STILL_DELTA)
+        };
+
+        let mut pushed = false;
+        let mut still_since = None;
+        let completed = loop {
+            if Instant::now() >= deadline {
+                break false;
+            }
+            let diagnostics = self.diagnostics();
+            pushed |= moved(&diagnostics);
+            if pushed && still(&diagnostics) {
+                let since = *still_since.get_or_insert_with(Instant::now);
+                if since.elapsed() >= SETTLE_TIME {
+                    break true;
+                }
+            } else {
+                still_since = None;
+            }
+            vexide::time::sleep(Duration::from_millis(10)).await;
+        };
+
+        let end = self.diagnostics();
+        let findings = end
+            .perpendicular
+            .iter()
+            .zip(&start.perpendicular)
+            .enumerate()
+            .chain(end.parallel.iter().zip(&start.parallel).enumerate())
+            .map(|(index, (end, start))| WheelFinding {
+                mounting_direction: end.mounting_direction,
+                index,
+                measured: end.distance - start.distance,
+                check: check_wheel(start, end, distance),
+            })
+            .collect();
+        WheelDirectionReport {
+            findings,
+            heading_change: self.current().rotation - start_rotation,
+            completed,
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use alloc::vec::Vec;
+use vexide::math::Angle;
+
+use super::wheel::WheelDiagnostics;
+
+/// A snapshot of the sensors of the tracking loop, from
+/// [`TrackingSubsystem::diagnostics`](super::TrackingSubsystem::diagnostics).
+///
+/// A wheel which counts the wrong way, or a perpendicular wheel which moves
+/// when driving straight, shows up here before the pose goes wrong:
+///
+/// ```ignore
+/// // Push the robot forwards by hand, then:
+/// tracking.diagnostics().log();
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TrackingDiagnostics {
+    pub perpendicular: Vec<WheelDiagnostics>,
+    pub parallel: Vec<WheelDiagnostics>,
+    /// The heading from the heading sensor, before any offset, clockwise
+    /// positive
+    pub raw_heading: Angle,
+}
+
+impl TrackingDiagnostics {
+    /// Logs every wheel and the raw heading.
+    pub fn log(&self) {
+        let wheels = self
+            .perpendicular
+            .iter()
+            .map(|wheel| ("perpendicular", wheel))
+            .chain(self.parallel.iter().map(|wheel| ("parallel", wheel)));
+        for (index, (kind, wheel)) in wheels.enumerate() {
+            match wheel.error {
+                Some(error) => log::warn!(
+                    "Tracking wheel {} ({}, offset {:.0} mm): {}",
+                    index,
+                    kind,
+                    wheel.mounting_offset,
+                    error
+                ),
+                None => log::info!(
+                    "Tracking wheel {} ({}, offset {:.0} mm): {:.1} mm total, {:.2} mm last update",
+                    index,
+                    kind,
+                    wheel.mounting_offset,
+                    wheel.distance,
+                    wheel.last_delta
+                ),
+            }
+        }
+        log::info!("Raw heading: {:.1}°", self.raw_heading.as_degrees());
+    }
+}
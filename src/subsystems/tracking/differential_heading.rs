@@ -0,0 +1,85 @@
+use core::cell::Cell;
+
+use vexide::{math::Angle, time::LowResolutionTime};
+
+use crate::utils::{
+    traits::{HasHeading, HasRotation},
+    units::Millimeters,
+};
+
+/// A heading computed from the difference between two parallel tracking
+/// wheels, for robots without a working IMU, or with the IMU needed
+/// elsewhere.
+///
+/// The same wheels can also track position, by sharing their sensors with
+/// `Rc<RefCell<_>>`:
+///
+/// ```ignore
+/// let left = Rc::new(RefCell::new(RotationSensor::new(peripherals.port_1, Direction::Forward)));
+/// let right = Rc::new(RefCell::new(RotationSensor::new(peripherals.port_2, Direction::Forward)));
+/// let tracking = TrackingSubsystem::new(
+///     [] as [TrackingWheel<()>; 0],
+///     [
+///         TrackingWheel::parallel_at(WHEEL_CIRCUMFERENCE, -120.0, left.clone()),
+///         TrackingWheel::parallel_at(WHEEL_CIRCUMFERENCE, 120.0, right.clone()),
+///     ],
+///     DifferentialHeading::new(left, right, WHEEL_CIRCUMFERENCE, 240.0),
+/// );
+/// ```
+///
+/// Any slip of either wheel turns into heading error which is never
+/// corrected, so the track width should be tuned by turning the robot
+/// several times on the spot and comparing the heading with the real one.
+pub struct DifferentialHeading<L: HasRotation, R: HasRotation> {
+    left: L,
+    right: R,
+    circumference: f64,
+    track_width: f64,
+    /// The positions of the wheels when the heading was zero
+    start: Cell<(Angle, Angle)>,
+}
+
+impl<L: HasRotation, R: HasRotation> DifferentialHeading<L, R> {
+    /// Creates a heading source from the left and right wheels, which read
+    /// positive when the robot drives forwards and are `track_width` mm
+    /// apart. The current heading is zero.
+    pub fn new(
+        left: L,
+        right: R,
+        circumference: impl Into<Millimeters>,
+        track_width: impl Into<Millimeters>,
+    ) -> Self {
+        Self {
+            start: Cell::new((left.position(), right.position())),
+            left,
+            right,
+            circumference: circumference.into().0,
+            track_width: track_width.into().0,
+        }
+    }
+
+    /// Makes the current heading zero.
+    pub fn reset(&self) {
+        self.start
+            .set((self.left.position(), self.right.position()));
+    }
+}
+
+impl<L: HasRotation, R: HasRotation> HasHeading for DifferentialHeading<L, R> {
+    fn heading(&self) -> Angle {
+        let (left_start, right_start) = self.start.get();
+        let left = (self.left.position() - left_start).as_turns() * self.circumference;
+        let right = (self.right.position() - right_start).as_turns() * self.circumference;
+        // Heading sensors are clockwise-positive, like the IMU, and the left
+        // wheel gets ahead of the right one when turning clockwise
+        Angle::from_radians((left - right) / self.track_width)
+    }
+
+    fn timestamp(&self) -> Option<LowResolutionTime> {
+        // The older of the two, since the heading needs both
+        match (self.left.timestamp(), self.right.timestamp()) {
+            (Some(left), Some(right)) => Some(left.min(right)),
+            _ => None,
+        }
+    }
+}
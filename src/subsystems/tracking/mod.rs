@@ -1,23 +1,78 @@
-use core::{cell::RefCell, f64};
+use core::{
+    cell::{Cell, RefCell},
+    f64, fmt,
+};
+use std::time::Instant;
 
-use alloc::{rc::Rc, vec::Vec};
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
 use nalgebra::{Point2, Rotation2, Vector2};
 use vexide::{
     math::Angle,
     prelude::{RotationSensor, SmartDevice},
 };
 
-use crate::utils::traits::{HasHeading, HasRotation};
+use crate::{
+    subsystems::drivetrain::ChassisConfig,
+    utils::{
+        alliance::{AllianceContext, mirror_heading, mirror_point},
+        angle,
+        filters::{Ema, Filter},
+        profiling,
+        ticker::Ticker,
+        traits::{HasHeading, HasRotation, HasTilt},
+    },
+};
+
+/// The default EMA alpha used to smooth velocities
+const DEFAULT_VELOCITY_SMOOTHING: f64 = 0.5;
 
+mod calibration;
+mod diagnostics;
+mod differential_heading;
+mod recorder;
+mod slip;
+mod timing;
+mod trace;
 mod tracking_data;
 pub mod wheel;
+pub use calibration::{WheelCheck, WheelDirectionReport, WheelFinding};
+pub use diagnostics::TrackingDiagnostics;
+pub use differential_heading::DifferentialHeading;
+pub use recorder::PoseRecorder;
+pub use slip::{SlipFilter, SlipStats};
+pub use timing::TrackingTiming;
+pub use trace::{PoseTrace, PoseTraceSample};
 pub use tracking_data::TrackingData;
 
+/// A sensor which measures pitch and roll, and its readings while level.
+struct TiltSensor {
+    sensor: Box<dyn HasTilt>,
+    level_pitch: Angle,
+    level_roll: Angle,
+}
+
+impl fmt::Debug for TiltSensor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TiltSensor")
+            .field("level_pitch", &self.level_pitch)
+            .field("level_roll", &self.level_roll)
+            .finish_non_exhaustive()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct TrackingSubsystem {
-    current: Rc<RefCell<TrackingData>>,
-    reverse: Rc<RefCell<bool>>,
-    heading_offset: Rc<RefCell<Angle>>,
+    /// The latest snapshot of the tracking data. The task computes each new
+    /// snapshot on its own and swaps it in whole, so reading it never
+    /// contends with an update in progress.
+    current: Rc<Cell<TrackingData>>,
+    alliance: AllianceContext,
+    heading_offset: Rc<Cell<Angle>>,
+    velocity_smoothing: Rc<Cell<f64>>,
+    tilt_sensor: Rc<RefCell<Option<TiltSensor>>>,
+    slip_filter: Rc<RefCell<Option<SlipFilter>>>,
+    timing: Rc<RefCell<TrackingTiming>>,
+    diagnostics: Rc<RefCell<TrackingDiagnostics>>,
     _task: Rc<vexide::task::Task<()>>,
 }
 
@@ -44,35 +99,61 @@ impl TrackingSubsystem {
         let mut parallel_tracking_wheels = parallel_tracking_wheels
             .into_iter()
             .collect::<Vec<wheel::TrackingWheel<LT>>>();
-        let current = Rc::new(RefCell::new(TrackingData::default()));
-        let heading_offset = Rc::new(RefCell::new(Angle::default()));
+        let current = Rc::new(Cell::new(TrackingData::default()));
+        let heading_offset = Rc::new(Cell::new(Angle::default()));
+        let velocity_smoothing = Rc::new(Cell::new(DEFAULT_VELOCITY_SMOOTHING));
+        let tilt_sensor: Rc<RefCell<Option<TiltSensor>>> = Rc::new(RefCell::new(None));
+        let slip_filter: Rc<RefCell<Option<SlipFilter>>> = Rc::new(RefCell::new(None));
+        let timing = Rc::new(RefCell::new(TrackingTiming::default()));
+        // Sized for the wheels now, so the task only overwrites them
+        let diagnostics = Rc::new(RefCell::new(TrackingDiagnostics {
+            perpendicular: perpendicular_tracking_wheels
+                .iter()
+                .map(|wheel| wheel.diagnostics())
+                .collect(),
+            parallel: parallel_tracking_wheels
+                .iter()
+                .map(|wheel| wheel.diagnostics())
+                .collect(),
+            raw_heading: Angle::default(),
+        }));
         Self {
+            diagnostics: diagnostics.clone(),
+            timing: timing.clone(),
             current: current.clone(),
-            reverse: Rc::new(RefCell::new(false)),
+            alliance: AllianceContext::default(),
             heading_offset: heading_offset.clone(),
+            velocity_smoothing: velocity_smoothing.clone(),
+            tilt_sensor: tilt_sensor.clone(),
+            slip_filter: slip_filter.clone(),
             _task: Rc::new(vexide::task::spawn(async move {
                 // The raw heading is the heading from the heading sensor,
                 // before any transformations.
                 let mut last_raw_heading = heading_sensor.heading();
+                // Velocities are differentiated from positions, so they are
+                // noisy without smoothing
+                let mut velocity_filters = [Ema::new(DEFAULT_VELOCITY_SMOOTHING); 3];
+                let mut ticker = Ticker::new(RotationSensor::UPDATE_INTERVAL);
+                // Each parallel wheel's estimate of the forward movement
+                let mut parallel_displacements = Vec::with_capacity(parallel_tracking_wheels.len());
                 loop {
+                    let scope = profiling::scope("tracking");
+                    let read_at = Instant::now();
                     let raw_heading = heading_sensor.heading();
-                    // opposite because of CCW vs CW
-                    let heading_delta = last_raw_heading - raw_heading;
+                    // opposite because of CCW vs CW, and the short way round in
+                    // case the sensor wraps
+                    let heading_delta = angle::shortest_error(last_raw_heading, raw_heading);
                     last_raw_heading = raw_heading;
 
-                    let last_heading = {
-                        let current = current.borrow();
-                        current.heading
-                        // current is implicitly dropped here
-                    };
+                    let last = current.get();
                     // TODO: avoid adding the delta to avoid precision issues
                     // We subtract the heading delta to get the new heading
                     // because we use mathematical positive rotation (CCW) but
                     // the heading sensor uses CW as positive rotation.
-                    let heading = -(raw_heading + *heading_offset.borrow());
+                    let heading = -(raw_heading + heading_offset.get());
 
                     // Average the heading and displacement of the tracking wheels
-                    let average_heading = (heading + last_heading) / 2.0;
+                    let average_heading = (heading + last.heading) / 2.0;
                     let average_displacement: Vector2<_> =
                         if perpendicular_tracking_wheels.is_empty() {
                             Vector2::zeros()
@@ -82,22 +163,90 @@ impl TrackingSubsystem {
                                 .map(|wheel| wheel.local_delta(heading_delta))
                                 .sum::<Vector2<_>>()
                                 / perpendicular_tracking_wheels.len() as f64
-                        } + parallel_tracking_wheels
-                            .iter_mut()
-                            .map(|wheel| wheel.local_delta(heading_delta))
-                            .sum::<Vector2<_>>()
-                            / parallel_tracking_wheels.len() as f64;
+                        } + {
+                            parallel_displacements.clear();
+                            parallel_displacements.extend(
+                                parallel_tracking_wheels
+                                    .iter_mut()
+                                    .map(|wheel| wheel.local_delta(heading_delta).y),
+                            );
+                            let displacement = match slip_filter.borrow_mut().as_mut() {
+                                Some(filter) => filter.filter(&parallel_displacements, read_at),
+                                None => {
+                                    parallel_displacements.iter().sum::<f64>()
+                                        / parallel_displacements.len() as f64
+                                }
+                            };
+                            Vector2::new(0.0, displacement)
+                        };
                     // Update the current pose with the new tracking data.
                     // This is in the original coordinate system.
                     {
-                        let mut current = current.borrow_mut();
                         let rotation_matrix =
                             Rotation2::new((average_heading + Angle::QUARTER_TURN).as_radians());
-                        *current = current.advance(
-                            current.offset + rotation_matrix * average_displacement,
+                        // Differentiate over when the sensors measured, not
+                        // when this task got to run, so a late update doesn't
+                        // look like a burst of speed
+                        let measured_at = heading_sensor
+                            .timestamp()
+                            .into_iter()
+                            .chain(
+                                perpendicular_tracking_wheels
+                                    .iter()
+                                    .filter_map(|wheel| wheel.timestamp()),
+                            )
+                            .chain(
+                                parallel_tracking_wheels
+                                    .iter()
+                                    .filter_map(|wheel| wheel.timestamp()),
+                            )
+                            .max()
+                            .map_or(read_at, |timestamp| Instant::now() - timestamp.elapsed());
+                        let mut next = last.advance(
+                            last.offset + rotation_matrix * average_displacement,
                             average_heading,
                             raw_heading,
+                            measured_at,
                         );
+                        if last.timestamp.is_none() {
+                            // The first update has nothing to differentiate
+                        } else if next.timestamp == last.timestamp {
+                            timing.borrow_mut().stale += 1;
+                        } else {
+                            timing.borrow_mut().record(next.dt, measured_at.elapsed());
+                            for filter in &mut velocity_filters {
+                                filter.set_alpha(velocity_smoothing.get());
+                            }
+                            let [x, y, angular] = &mut velocity_filters;
+                            next.velocity =
+                                Vector2::new(x.update(next.velocity.x), y.update(next.velocity.y));
+                            next.angular_velocity = Angle::from_radians(
+                                angular.update(next.angular_velocity.as_radians()),
+                            );
+                        }
+                        if let Some(tilt) = tilt_sensor.borrow().as_ref() {
+                            next.pitch = tilt.sensor.pitch() - tilt.level_pitch;
+                            next.roll = tilt.sensor.roll() - tilt.level_roll;
+                        }
+                        current.set(next);
+                    }
+                    {
+                        let mut diagnostics = diagnostics.borrow_mut();
+                        diagnostics.raw_heading = raw_heading;
+                        for (snapshot, wheel) in diagnostics
+                            .perpendicular
+                            .iter_mut()
+                            .zip(&perpendicular_tracking_wheels)
+                        {
+                            *snapshot = wheel.diagnostics();
+                        }
+                        for (snapshot, wheel) in diagnostics
+                            .parallel
+                            .iter_mut()
+                            .zip(&parallel_tracking_wheels)
+                        {
+                            *snapshot = wheel.diagnostics();
+                        }
                     }
                     // TODO: add a way to pass a debug renderer directly to the
                     // tracking subsystem
@@ -116,7 +265,7 @@ impl TrackingSubsystem {
                     ) {
                         // SAFETY: This is not safe.
                         let mut display = unsafe { vexide::display::Display::new() };
-                        let current = { *current.borrow() };
+                        let current = current.get();
                         let shape = vexide::display::Circle::new(
                             vexide::math::Point2 {
                                 x: (current.offset.x * 0.066666667 + 120.0) as i16,
@@ -165,61 +314,123 @@ impl TrackingSubsystem {
                         display.render();
                     }
 
-                    vexide::time::sleep(RotationSensor::UPDATE_INTERVAL).await;
+                    drop(scope);
+                    ticker.tick().await;
                 }
             })),
         }
     }
 
+    /// Creates a TrackingSubsystem with one parallel and one perpendicular
+    /// tracking wheel, e.g., from
+    /// [`TrackingWheel::parallel_at`](wheel::TrackingWheel::parallel_at) and
+    /// [`TrackingWheel::perpendicular_at`](wheel::TrackingWheel::perpendicular_at),
+    /// and a heading sensor.
+    pub fn two_wheel<
+        PT: HasRotation + 'static,
+        LT: HasRotation + 'static,
+        HT: HasHeading + 'static,
+    >(
+        parallel: wheel::TrackingWheel<LT>,
+        perpendicular: wheel::TrackingWheel<PT>,
+        heading_sensor: HT,
+    ) -> Self {
+        if !matches!(
+            parallel.mounting_direction(),
+            wheel::TrackingWheelMountingDirection::Parallel
+        ) || !matches!(
+            perpendicular.mounting_direction(),
+            wheel::TrackingWheelMountingDirection::Perpendicular
+        ) {
+            log::warn!("Tracking: two_wheel was given wheels in the wrong mounting directions");
+        }
+        Self::new([perpendicular], [parallel], heading_sensor)
+    }
+
+    /// Creates a TrackingSubsystem which tracks with the drivetrain motors'
+    /// encoders and a heading sensor, without tracking sideways movement.
+    ///
+    /// The wheels are half the chassis's track width either side of the
+    /// tracking center, and travel its
+    /// [`wheel_circumference`](ChassisConfig::wheel_circumference) per turn
+    /// of the motor groups' position, so any gearing must be set on the
+    /// groups with
+    /// [`with_gear_ratio`](crate::motorgroup::DoxaMotorGroup::with_gear_ratio).
+    ///
+    /// The drivetrain wheels slip far more than tracking wheels, so this is
+    /// usually combined with a [slip filter](Self::with_slip_filter).
+    pub fn drive_encoders<M: HasRotation + 'static, HT: HasHeading + 'static>(
+        left_motors: M,
+        right_motors: M,
+        chassis: &ChassisConfig,
+        heading_sensor: HT,
+    ) -> Self {
+        let half_track = chassis.track_width * 0.5;
+        let circumference = chassis.wheel_circumference;
+        Self::new(
+            [] as [wheel::TrackingWheel<()>; 0],
+            [
+                wheel::TrackingWheel::parallel_at(circumference, -half_track, left_motors),
+                wheel::TrackingWheel::parallel_at(circumference, half_track, right_motors),
+            ],
+            heading_sensor,
+        )
+    }
+
     /// The current pose of the robot
     ///
     /// This is the pose of the robot in the transformed coordinate system
     /// used by the `reverse` function. This means that autonomous routes should
     /// be written in the original coordinate system, and then the `reverse`
     /// function can be used to add genericity, if that's a word.
+    ///
+    /// This never blocks or panics, even from a callback which runs while
+    /// the tracking task is updating.
     pub fn current(&self) -> TrackingData {
-        let data = *self.current.borrow();
-        if *self.reverse.borrow() {
-            TrackingData {
-                offset: Point2::new(data.offset.x, -data.offset.y),
-                heading: Angle::FULL_TURN - data.heading,
-                ..data
-            }
+        let data = self.current.get();
+        if self.alliance.is_mirrored() {
+            mirror(data)
         } else {
             data
         }
     }
 
+    /// Like [`current`](Self::current), which can no longer fail, so this
+    /// always returns `Some`.
+    #[deprecated(note = "`current` never panics; use it instead")]
+    pub fn try_current(&self) -> Option<TrackingData> {
+        Some(self.current())
+    }
+
     /// Reset the initial pose of the robot
     ///
     /// Note that this in the transformed coordinate system used by the
     /// `reverse` function.
     pub fn set_current(&mut self, offset: Point2<f64>, heading: Angle) {
-        let current_raw_heading = {
-            let current = self.current.borrow();
-            current.raw_heading.unwrap_or_default()
-            // current is implicitly dropped here
-        };
+        let current_raw_heading = self.current.get().raw_heading.unwrap_or_default();
         // Adjust the heading offset to make the new heading correct
-        *self.heading_offset.borrow_mut() = (-heading) - current_raw_heading;
+        self.heading_offset.set((-heading) - current_raw_heading);
         let data = TrackingData {
             offset,
             heading,
+            rotation: Angle::default(),
             velocity: Vector2::default(),
             angular_velocity: Angle::default(),
             timestamp: Some(std::time::Instant::now()),
             dt: std::time::Duration::default(),
+            pitch: self.current.get().pitch,
+            roll: self.current.get().roll,
             raw_heading: Some(current_raw_heading),
         };
-        *self.current.borrow_mut() = if *self.reverse.borrow() {
-            TrackingData {
-                offset: Point2::new(data.offset.x, -data.offset.y),
-                heading: Angle::FULL_TURN - data.heading,
-                ..data
+        self.current.set(TrackingData {
+            // The rotation keeps counting across resets
+            rotation: self.current.get().rotation,
+            ..if self.alliance.is_mirrored() {
+                mirror(data)
+            } else {
+                data
             }
-        } else {
-            data
-        };
+        });
     }
 
     /// The reverse state of the tracking subsystem
@@ -227,7 +438,7 @@ impl TrackingSubsystem {
     /// This will mirror the pose of the robot over the central line, inverting
     /// the pose heading and y-coordinate.
     pub fn reverse(&self) -> bool {
-        *self.reverse.borrow()
+        self.alliance.is_mirrored()
     }
 
     /// Sets the reverse state of the tracking subsystem
@@ -240,7 +451,94 @@ impl TrackingSubsystem {
     /// to reverse the direction of the robot. You probably don't want that, so
     /// you should set the reverse state to be `false` at the beginning of
     /// driver control.
+    ///
+    /// This sets the state of the shared [`AllianceContext`], so it also
+    /// affects every other subsystem sharing it.
     pub fn set_reverse(&mut self, reverse: bool) {
-        *self.reverse.borrow_mut() = reverse;
+        self.alliance.set_mirrored(reverse);
+    }
+
+    /// Shares the given alliance context, which then decides whether the
+    /// tracking subsystem is reversed.
+    pub fn with_alliance(mut self, alliance: AllianceContext) -> Self {
+        self.alliance = alliance;
+        self
+    }
+
+    /// Sets how much velocities are smoothed, as the alpha of an [`Ema`]: 1.0
+    /// doesn't smooth at all, and smaller values smooth more but lag more.
+    /// The default is 0.5.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alpha` isn't greater than zero and at most one.
+    pub fn with_velocity_smoothing(self, alpha: f64) -> Self {
+        // Validate now rather than in the task
+        Ema::new(alpha);
+        self.velocity_smoothing.set(alpha);
+        self
+    }
+
+    /// Tracks pitch and roll with `sensor`, usually the IMU, so that they are
+    /// in [`TrackingData`].
+    ///
+    /// The robot must be level on the ground, since the current pitch and
+    /// roll are taken as level.
+    pub fn with_tilt_sensor(self, sensor: impl HasTilt + 'static) -> Self {
+        *self.tilt_sensor.borrow_mut() = Some(TiltSensor {
+            level_pitch: sensor.pitch(),
+            level_roll: sensor.roll(),
+            sensor: Box::new(sensor),
+        });
+        self
+    }
+
+    /// Discards parallel wheel samples which slipped with `filter`, e.g., for
+    /// [drive encoder](Self::drive_encoders) tracking. See [`SlipFilter`].
+    pub fn with_slip_filter(self, filter: SlipFilter) -> Self {
+        *self.slip_filter.borrow_mut() = Some(filter);
+        self
+    }
+
+    /// Returns statistics on the wheel samples discarded by the
+    /// [slip filter](Self::with_slip_filter), or `None` if there is none.
+    pub fn slip_stats(&self) -> Option<SlipStats> {
+        self.slip_filter.borrow().as_ref().map(SlipFilter::stats)
+    }
+
+    /// Returns a snapshot of the tracking wheels and heading sensor as of the
+    /// last update.
+    pub fn diagnostics(&self) -> TrackingDiagnostics {
+        self.diagnostics.borrow().clone()
+    }
+
+    /// Returns statistics on the timing of updates since the subsystem was
+    /// created or [`reset_timing`](Self::reset_timing) was called.
+    pub fn timing(&self) -> TrackingTiming {
+        *self.timing.borrow()
+    }
+
+    /// Clears the timing statistics, e.g., at the start of a route.
+    pub fn reset_timing(&self) {
+        *self.timing.borrow_mut() = TrackingTiming::default();
+    }
+
+    /// Returns the alliance context which decides whether the tracking
+    /// subsystem is reversed, for sharing with other subsystems.
+    pub fn alliance(&self) -> AllianceContext {
+        self.alliance.clone()
+    }
+}
+
+/// Mirrors tracking data over the central line, inverting the heading and
+/// y-coordinate.
+fn mirror(data: TrackingData) -> TrackingData {
+    TrackingData {
+        offset: mirror_point(data.offset),
+        heading: mirror_heading(data.heading),
+        // Mirroring swaps clockwise and counterclockwise, and left and right
+        rotation: -data.rotation,
+        roll: -data.roll,
+        ..data
     }
 }
@@ -0,0 +1,164 @@
+use core::{cell::RefCell, time::Duration};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    time::Instant,
+};
+
+use alloc::rc::Rc;
+
+use super::TrackingSubsystem;
+use crate::{
+    error::DoxaError,
+    subsystems::drivetrain::{Drivetrain, DrivetrainPair},
+    utils::{logger, ticker::Ticker},
+};
+
+/// How often a recording is flushed to the SD card.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+struct Recording {
+    file: BufWriter<File>,
+    start_time: Instant,
+    last_flush: Instant,
+    outputs: Option<Rc<RefCell<Option<DrivetrainPair>>>>,
+}
+
+impl Recording {
+    /// Writes a row with the current pose from `tracking`.
+    fn write_row(&mut self, tracking: &TrackingSubsystem) -> std::io::Result<()> {
+        let data = tracking.current();
+        let time = data
+            .timestamp
+            .map_or(self.start_time.elapsed(), |timestamp| {
+                timestamp.saturating_duration_since(self.start_time)
+            });
+        write!(
+            self.file,
+            "{},{:.2},{:.2},{:.4},{:.2},{:.2},{:.4}",
+            time.as_millis(),
+            data.offset.x,
+            data.offset.y,
+            data.heading.as_radians(),
+            data.velocity.x,
+            data.velocity.y,
+            data.angular_velocity.as_radians(),
+        )?;
+        if let Some(last_output) = &self.outputs {
+            match *last_output.borrow() {
+                Some(output) => write!(
+                    self.file,
+                    ",{:.2},{:.2},{:?}",
+                    output.left, output.right, output.units
+                )?,
+                None => write!(self.file, ",,,")?,
+            }
+        }
+        writeln!(self.file)
+    }
+}
+
+/// Records the pose of the robot to a CSV file on the SD card.
+///
+/// Each row contains the time since the recording was started in ms, the
+/// position in mm, the heading in radians, and their derivatives. If
+/// [`with_outputs`](Self::with_outputs) was used, the output the drivetrain
+/// sent to the motors is also recorded.
+///
+/// Recording happens in a background task at the configured interval, so the
+/// recorder only needs to be started and stopped. The file is flushed every
+/// second and whenever an error is logged, so little is lost if the program
+/// crashes. If writing fails, the error is logged and recording stops.
+/// Recordings can be loaded
+/// back with [`PoseTrace::load`](super::PoseTrace::load), e.g., to replay a
+/// practice run in autonomous.
+pub struct PoseRecorder {
+    recording: Rc<RefCell<Option<Recording>>>,
+    outputs: Option<Rc<RefCell<Option<DrivetrainPair>>>>,
+    _task: vexide::task::Task<()>,
+}
+
+impl PoseRecorder {
+    /// Creates a new recorder that samples the given tracking subsystem every
+    /// `interval`.
+    pub fn new(tracking: TrackingSubsystem, interval: Duration) -> Self {
+        let recording: Rc<RefCell<Option<Recording>>> = Rc::new(RefCell::new(None));
+        Self {
+            recording: recording.clone(),
+            outputs: None,
+            _task: vexide::task::spawn(async move {
+                let mut ticker = Ticker::new(interval);
+                let mut errors = logger::error_count();
+                loop {
+                    let result = recording.borrow_mut().as_mut().map(|current| {
+                        current.write_row(&tracking)?;
+                        // Flush now and then so a crash or brownout loses
+                        // little, and straight away when an error is logged,
+                        // since the robot may be about to stop
+                        let error_count = logger::error_count();
+                        if current.last_flush.elapsed() >= FLUSH_INTERVAL || error_count != errors {
+                            errors = error_count;
+                            current.last_flush = Instant::now();
+                            current.file.flush()?;
+                        }
+                        Ok::<_, std::io::Error>(())
+                    });
+                    if let Some(Err(err)) = result {
+                        log::error!("Failed to write pose trace, stopping: {}", err);
+                        *recording.borrow_mut() = None;
+                    }
+                    ticker.tick().await;
+                }
+            }),
+        }
+    }
+
+    /// Also records the output the given drivetrain sent to the motors.
+    ///
+    /// This only affects recordings started after this call.
+    pub fn with_outputs(mut self, drivetrain: &Drivetrain) -> Self {
+        self.outputs = Some(drivetrain.last_output.clone());
+        self
+    }
+
+    /// Starts recording to the file at `path`, overwriting it if it exists.
+    ///
+    /// If a recording is already in progress, it is stopped first, and any
+    /// error flushing it is returned without starting the new recording.
+    pub fn start(&mut self, path: &str) -> Result<(), DoxaError> {
+        self.stop()?;
+        let mut file = BufWriter::new(File::create(path)?);
+        write!(file, "time,x,y,heading,vx,vy,angular_velocity")?;
+        if self.outputs.is_some() {
+            write!(file, ",left,right,units")?;
+        }
+        writeln!(file)?;
+        *self.recording.borrow_mut() = Some(Recording {
+            file,
+            start_time: Instant::now(),
+            last_flush: Instant::now(),
+            outputs: self.outputs.clone(),
+        });
+        log::info!("Recording pose trace to {}", path);
+        Ok(())
+    }
+
+    /// Stops recording and flushes the file to the SD card.
+    ///
+    /// Does nothing if the recorder is not recording.
+    pub fn stop(&mut self) -> Result<(), DoxaError> {
+        if let Some(mut recording) = self.recording.borrow_mut().take() {
+            log::info!(
+                "Stopped recording pose trace after {:.1}s",
+                recording.start_time.elapsed().as_secs_f64()
+            );
+            recording.file.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Returns whether the recorder is currently recording.
+    pub fn is_recording(&self) -> bool {
+        self.recording.borrow().is_some()
+    }
+}
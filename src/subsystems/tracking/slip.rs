@@ -0,0 +1,177 @@
+use std::time::Instant;
+
+use crate::subsystems::drivetrain::ChassisConfig;
+
+/// How much faster than the commanded acceleration the wheels may speed up
+/// or slow down before they count as slipping, for
+/// [`SlipFilter::for_chassis`].
+const ACCELERATION_MARGIN: f64 = 1.5;
+
+/// The default disagreement, in mm per update, between what each parallel
+/// wheel says the tracking center moved.
+const DEFAULT_MAX_DISAGREEMENT: f64 = 2.0;
+
+/// Statistics on the samples a [`SlipFilter`] discarded, from
+/// [`TrackingSubsystem::slip_stats`](super::TrackingSubsystem::slip_stats).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SlipStats {
+    /// The number of updates filtered
+    pub samples: u32,
+    /// The number of updates where at least one wheel was discarded
+    pub slipping: u32,
+    /// The number of wheel samples discarded
+    pub discarded: u32,
+}
+
+impl SlipStats {
+    /// Returns the fraction of updates where a wheel slipped.
+    pub fn slip_fraction(&self) -> f64 {
+        if self.samples == 0 {
+            0.0
+        } else {
+            self.slipping as f64 / self.samples as f64
+        }
+    }
+}
+
+/// Discards parallel wheel samples which can't be real movement, for
+/// tracking with the drivetrain motors'
+/// [encoders](super::TrackingSubsystem::drive_encoders), which slip when the
+/// robot accelerates hard, turns fast or pushes against something.
+///
+/// Each update, every parallel wheel gives its own estimate of how far the
+/// tracking center moved, using the heading change from the heading sensor.
+/// A wheel is discarded if:
+///
+/// - its estimate would change the robot's speed faster than
+///   `max_acceleration`, i.e., it spun up or locked up faster than the
+///   drivetrain is commanded to accelerate, or
+/// - it disagrees with the other wheels by more than `max_disagreement`, so the
+///   wheels' differential doesn't match the heading change, in which case the
+///   wheel closest to the robot's last speed is kept.
+///
+/// If every wheel is discarded, the robot is assumed to keep its speed,
+/// changing it towards the wheels by at most `max_acceleration`.
+///
+/// ```ignore
+/// let tracking = TrackingSubsystem::drive_encoders(left, right, &CHASSIS, imu)
+///     .with_slip_filter(SlipFilter::for_chassis(&CHASSIS));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlipFilter {
+    /// The fastest the robot can really change speed, in mm/s²
+    pub max_acceleration: f64,
+    /// The most the wheels' estimates of the tracking center's movement may
+    /// differ in one update, in mm
+    pub max_disagreement: f64,
+    /// The speed of the tracking center from the last update, in mm/s
+    velocity: f64,
+    last_update: Option<Instant>,
+    stats: SlipStats,
+}
+
+impl SlipFilter {
+    /// Creates a filter which discards wheels changing speed faster than
+    /// `max_acceleration` mm/s².
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_acceleration` is negative or NaN.
+    pub fn new(max_acceleration: f64) -> Self {
+        assert!(
+            max_acceleration >= 0.0,
+            "Invalid slip filter acceleration: {}",
+            max_acceleration
+        );
+        Self {
+            max_acceleration,
+            max_disagreement: DEFAULT_MAX_DISAGREEMENT,
+            velocity: 0.0,
+            last_update: None,
+            stats: SlipStats::default(),
+        }
+    }
+
+    /// Creates a filter for a drivetrain limited to the chassis's
+    /// [`max_linear_acceleration`](ChassisConfig::max_linear_acceleration).
+    ///
+    /// The wheels may change speed somewhat faster than commanded, since the
+    /// motors overshoot the ramp.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the chassis's acceleration is negative or NaN.
+    pub fn for_chassis(chassis: &ChassisConfig) -> Self {
+        Self::new(chassis.max_linear_acceleration() * ACCELERATION_MARGIN)
+    }
+
+    /// Sets how far, in mm, the wheels' estimates of the tracking center's
+    /// movement may differ in one update. The default is 2 mm.
+    pub fn with_max_disagreement(mut self, max_disagreement: f64) -> Self {
+        self.max_disagreement = max_disagreement;
+        self
+    }
+
+    /// Returns statistics on the samples discarded so far.
+    pub fn stats(&self) -> SlipStats {
+        self.stats
+    }
+
+    /// Returns how far the tracking center moved forwards, in mm, given each
+    /// parallel wheel's estimate of it, measured at `at`.
+    pub(crate) fn filter(&mut self, displacements: &[f64], at: Instant) -> f64 {
+        let dt = self
+            .last_update
+            .map_or(0.0, |last| at.saturating_duration_since(last).as_secs_f64());
+        self.last_update = Some(at);
+        if displacements.is_empty() {
+            return 0.0;
+        }
+        let mean = displacements.iter().sum::<f64>() / displacements.len() as f64;
+        if dt <= 0.0 {
+            // Nothing to compare the speed against yet
+            return mean;
+        }
+        self.stats.samples += 1;
+
+        let max_change = self.max_acceleration * dt;
+        let predicted = self.velocity * dt;
+        let plausible =
+            |displacement: &&f64| (**displacement / dt - self.velocity).abs() <= max_change;
+        let (min, max) = displacements
+            .iter()
+            .filter(plausible)
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &d| {
+                (min.min(d), max.max(d))
+            });
+        let (displacement, kept) = if min > max {
+            // Every wheel slipped, so keep going, changing speed towards the
+            // wheels as fast as the robot could
+            let displacement = mean
+                .max(predicted - max_change * dt)
+                .min(predicted + max_change * dt);
+            (displacement, 0)
+        } else if max - min > self.max_disagreement {
+            // The wheels disagree about the heading change, so trust the one
+            // closest to the last speed
+            let closest = displacements
+                .iter()
+                .filter(plausible)
+                .copied()
+                .min_by(|a, b| (a - predicted).abs().total_cmp(&(b - predicted).abs()))
+                .unwrap_or(mean);
+            (closest, 1)
+        } else {
+            let kept = displacements.iter().filter(plausible).copied();
+            let count = kept.clone().count();
+            (kept.sum::<f64>() / count as f64, count)
+        };
+        let discarded = (displacements.len() - kept) as u32;
+        if discarded > 0 {
+            self.stats.slipping += 1;
+            self.stats.discarded += discarded;
+        }
+        self.velocity = displacement / dt;
+        displacement
+    }
+}
@@ -0,0 +1,48 @@
+use core::time::Duration;
+
+/// Statistics on the timing of tracking updates, from
+/// [`TrackingSubsystem::timing`](super::TrackingSubsystem::timing).
+///
+/// Velocities are computed from when the sensors measured, so a late update
+/// doesn't corrupt them, but a lot of jitter still means the tracking task is
+/// being starved, e.g., by rendering or logging.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TrackingTiming {
+    /// The number of updates with new sensor data
+    pub samples: u32,
+    /// The number of updates where the sensors had no new data
+    pub stale: u32,
+    /// The mean time between sensor measurements
+    pub mean_dt: Duration,
+    /// The longest time between sensor measurements
+    pub max_dt: Duration,
+    /// The longest time from a sensor measurement until it was processed
+    pub max_latency: Duration,
+    /// The running sum of squared differences from the mean, in seconds
+    /// squared
+    m2: f64,
+}
+
+impl TrackingTiming {
+    /// Returns the standard deviation of the time between sensor
+    /// measurements.
+    pub fn jitter(&self) -> Duration {
+        if self.samples < 2 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64((self.m2 / (self.samples - 1) as f64).sqrt())
+    }
+
+    /// Adds an update with new sensor data.
+    pub(crate) fn record(&mut self, dt: Duration, latency: Duration) {
+        self.samples += 1;
+        let dt_secs = dt.as_secs_f64();
+        let mean = self.mean_dt.as_secs_f64();
+        let delta = dt_secs - mean;
+        let mean = mean + delta / self.samples as f64;
+        self.m2 += delta * (dt_secs - mean);
+        self.mean_dt = Duration::from_secs_f64(mean);
+        self.max_dt = self.max_dt.max(dt);
+        self.max_latency = self.max_latency.max(latency);
+    }
+}
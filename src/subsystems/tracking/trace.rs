@@ -0,0 +1,142 @@
+use core::time::Duration;
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+};
+
+use alloc::{string::String, vec::Vec};
+use nalgebra::Point2;
+use vexide::math::Angle;
+
+use crate::{
+    path_planner::sampled::SampledPath,
+    subsystems::drivetrain::{
+        DrivetrainPair, actions::ReplayAction, drivetrain_pair::DrivetrainUnits,
+    },
+};
+
+/// A single row of a [`PoseTrace`].
+#[derive(Debug, Clone, Copy)]
+pub struct PoseTraceSample {
+    /// Time since the start of the recording
+    pub time: Duration,
+    pub offset: Point2<f64>,
+    pub heading: Angle,
+    /// The output the drivetrain sent to the motors, if it was recorded and
+    /// an action was running
+    pub output: Option<DrivetrainPair>,
+}
+
+/// A pose trace recorded by [`PoseRecorder`](super::PoseRecorder), loaded
+/// back from the SD card.
+///
+/// Traces recorded during a practice run in driver control can be replayed in
+/// autonomous, either open-loop with [`replay_action`](Self::replay_action)
+/// (if the recorder was created
+/// [`with_outputs`](super::PoseRecorder::with_outputs)), or closed-loop by
+/// following the path from [`to_path`](Self::to_path) with a path follower.
+#[derive(Debug, Clone, Default)]
+pub struct PoseTrace {
+    samples: Vec<PoseTraceSample>,
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl PoseTrace {
+    /// Loads a trace from the CSV file at `path`.
+    pub fn load(path: &str) -> io::Result<Self> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        let header = lines
+            .next()
+            .ok_or_else(|| invalid_data(format!("{}: empty pose trace", path)))??;
+        let has_outputs = header.split(',').count() >= 10;
+
+        let mut samples = Vec::new();
+        for (index, line) in lines.enumerate() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let row = index + 2;
+            let fields: Vec<&str> = line.split(',').collect();
+            let number = |column: usize| -> io::Result<f64> {
+                fields
+                    .get(column)
+                    .and_then(|field| field.parse().ok())
+                    .ok_or_else(|| {
+                        invalid_data(format!("{}:{}: bad value in column {}", path, row, column))
+                    })
+            };
+            let output = if has_outputs && fields.get(9).is_some_and(|units| !units.is_empty()) {
+                let units = match fields[9] {
+                    "Voltage" => DrivetrainUnits::Voltage,
+                    "RPM" => DrivetrainUnits::RPM,
+                    other => {
+                        return Err(invalid_data(format!(
+                            "{}:{}: unknown units {:?}",
+                            path, row, other
+                        )));
+                    }
+                };
+                Some(DrivetrainPair {
+                    left: number(7)?,
+                    right: number(8)?,
+                    units,
+                })
+            } else {
+                None
+            };
+            samples.push(PoseTraceSample {
+                time: Duration::from_millis(number(0)? as u64),
+                offset: Point2::new(number(1)?, number(2)?),
+                heading: Angle::from_radians(number(3)?),
+                output,
+            });
+        }
+        log::info!("Loaded {} pose trace samples from {}", samples.len(), path);
+        Ok(Self { samples })
+    }
+
+    /// Returns the samples in the trace.
+    pub fn samples(&self) -> &[PoseTraceSample] {
+        &self.samples
+    }
+
+    /// Returns the first sample, which is where replays should start.
+    pub fn start(&self) -> Option<&PoseTraceSample> {
+        self.samples.first()
+    }
+
+    /// Returns an action which replays the recorded drivetrain outputs
+    /// open-loop. Samples without an output are replayed as stopped.
+    pub fn replay_action(&self) -> ReplayAction {
+        let start = self.start().map_or(Duration::ZERO, |sample| sample.time);
+        ReplayAction::new(
+            self.samples
+                .iter()
+                .map(|sample| {
+                    (
+                        sample.time.saturating_sub(start),
+                        sample.output.unwrap_or(DrivetrainPair::from(0.0)),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// Converts the recorded positions into a smoothed path for closed-loop
+    /// replay.
+    ///
+    /// See [`SampledPath::smoothed`] for `window` and `spacing`. Returns
+    /// `None` if the robot didn't move.
+    pub fn to_path(&self, window: usize, spacing: f64) -> Option<SampledPath> {
+        let points: Vec<Point2<f64>> = self.samples.iter().map(|sample| sample.offset).collect();
+        let first = points.first()?;
+        if points.iter().all(|point| point == first) {
+            return None;
+        }
+        Some(SampledPath::smoothed(&points, window, spacing))
+    }
+}
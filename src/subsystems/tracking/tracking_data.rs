@@ -1,63 +1,105 @@
 use nalgebra::{Point2, Vector2};
 use vexide::math::Angle;
 
+use crate::utils::angle;
+
 /// Struct representing tracking data from the tracking subsystem, containing
 /// the current pose and its derivative.
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub struct TrackingData {
     pub offset: Point2<f64>,
-    /// The normalized heading angle in radians.
+    /// The heading angle, counterclockwise-positive. Compare headings with
+    /// [`angle::shortest_error`](crate::utils::angle::shortest_error), since
+    /// they may be a turn apart.
     pub heading: Angle,
+    /// The total rotation since tracking started, counterclockwise-positive.
+    /// Unlike the heading, this is never reset by
+    /// [`set_current`](super::TrackingSubsystem::set_current), so it counts
+    /// the turns the robot has made.
+    pub rotation: Angle,
 
     pub velocity: Vector2<f64>,
     pub angular_velocity: Angle,
 
+    /// When the sensors measured this pose, if they report it, or else when
+    /// they were read.
     pub timestamp: Option<std::time::Instant>,
+    /// The time between the measurements of the previous pose and this one,
+    /// which the velocities are computed over.
     pub dt: std::time::Duration,
 
+    /// The pitch relative to level, as measured by the tilt sensor, or zero
+    /// if tracking has no tilt sensor.
+    pub pitch: Angle,
+    /// The roll relative to level, as measured by the tilt sensor, or zero if
+    /// tracking has no tilt sensor.
+    pub roll: Angle,
+
     pub(crate) raw_heading: Option<Angle>,
 }
 
 impl TrackingData {
     /// Creates a new `TrackingData` instance based on the current one,
-    /// computing velocity and angular velocity from the change in pose over
-    /// time.
+    /// computing velocity and angular velocity from the change in pose since
+    /// the previous measurement, at `measured_at`.
     ///
     /// If the old timestamp is `None`, `velocity` and `angular_velocity` will
-    /// be set to zero.
+    /// be set to zero. If the sensors haven't measured since the old
+    /// timestamp, the velocities and timestamp are kept.
     pub(crate) fn advance(
         &self,
         new_offset: Point2<f64>,
         new_heading: Angle,
         new_raw_heading: Angle,
+        measured_at: std::time::Instant,
     ) -> Self {
-        if let Some(old_timestamp) = self.timestamp {
-            let now = std::time::Instant::now();
-            let dt = now.duration_since(old_timestamp).as_secs_f64();
-            let velocity = (new_offset - self.offset) / dt;
-            let angular_velocity = (new_heading - self.heading) / dt;
-            Self {
+        // Difference the headings the short way round, so a heading which
+        // wraps doesn't look like a full turn in one update
+        let rotation_delta = angle::shortest_error(new_heading, self.heading);
+        match self.timestamp {
+            Some(old_timestamp) if measured_at > old_timestamp => {
+                let dt = measured_at.duration_since(old_timestamp);
+                Self {
+                    offset: new_offset,
+                    heading: new_heading,
+                    rotation: self.rotation + rotation_delta,
+                    velocity: (new_offset - self.offset) / dt.as_secs_f64(),
+                    angular_velocity: rotation_delta / dt.as_secs_f64(),
+                    timestamp: Some(measured_at),
+                    dt,
+                    pitch: self.pitch,
+                    roll: self.roll,
+                    raw_heading: Some(new_raw_heading),
+                }
+            }
+            Some(_) => Self {
                 offset: new_offset,
                 heading: new_heading,
-                velocity,
-                angular_velocity,
-                timestamp: Some(now),
-                dt: now.duration_since(old_timestamp),
+                rotation: self.rotation + rotation_delta,
                 raw_heading: Some(new_raw_heading),
-            }
-        } else {
-            Self {
+                ..*self
+            },
+            None => Self {
                 offset: new_offset,
                 heading: new_heading,
+                rotation: self.rotation + rotation_delta,
                 velocity: Vector2::default(),
                 angular_velocity: Angle::default(),
-                timestamp: Some(std::time::Instant::now()),
+                timestamp: Some(measured_at),
                 dt: std::time::Duration::default(),
+                pitch: self.pitch,
+                roll: self.roll,
                 raw_heading: Some(new_raw_heading),
-            }
+            },
         }
     }
 
+    /// Returns the number of turns the robot has made since tracking started,
+    /// counterclockwise-positive.
+    pub fn turns(&self) -> f64 {
+        self.rotation.as_turns()
+    }
+
     /// Computes the linear velocity in the direction of movement (the heading).
     ///
     /// This is typically the signed magnitude of the velocity vector, but may
@@ -2,9 +2,9 @@ use core::{error::Error, f64, fmt::Debug};
 
 use nalgebra::Vector2;
 use snafu::Snafu;
-use vexide::math::Angle;
+use vexide::{math::Angle, smart::PortError, time::LowResolutionTime};
 
-use crate::utils::traits::HasRotation;
+use crate::utils::{traits::HasRotation, units::Millimeters};
 
 #[derive(Debug, Snafu)]
 pub enum TrackingWheelError<T: Debug + Error + 'static> {
@@ -12,7 +12,7 @@ pub enum TrackingWheelError<T: Debug + Error + 'static> {
     Sensor { source: T },
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TrackingWheelMountingDirection {
     /// The tracking wheel is mounted parallel to the robot's forward direction.
     Parallel,
@@ -21,31 +21,59 @@ pub enum TrackingWheelMountingDirection {
     Perpendicular,
 }
 
+/// A snapshot of a tracking wheel, for finding wiring and direction
+/// mistakes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WheelDiagnostics {
+    pub mounting_direction: TrackingWheelMountingDirection,
+    pub mounting_offset: f64,
+    /// The distance travelled in the last update, in mm
+    pub last_delta: f64,
+    /// The distance travelled since tracking started, in mm
+    pub distance: f64,
+    /// The error from the last reading of the sensor, if it failed
+    pub error: Option<PortError>,
+}
+
 pub struct TrackingWheel<T: HasRotation> {
     circumference: f64,
     mounting_offset: f64,
     sensor: T,
     last_angle: Angle,
     mounting_direction: TrackingWheelMountingDirection,
+    last_delta: f64,
+    distance: f64,
+    /// The error from the last reading of the sensor, if it failed
+    error: Option<PortError>,
 }
 
 impl<T: HasRotation> TrackingWheel<T> {
     pub fn new(
-        circumference: f64,
-        mounting_offset: f64,
+        circumference: impl Into<Millimeters>,
+        mounting_offset: impl Into<Millimeters>,
         mounting_direction: TrackingWheelMountingDirection,
         sensor: T,
     ) -> TrackingWheel<T> {
         Self {
-            circumference,
-            mounting_offset,
+            circumference: circumference.into().0,
+            mounting_offset: mounting_offset.into().0,
             mounting_direction,
             last_angle: sensor.position(),
             sensor,
+            last_delta: 0.0,
+            distance: 0.0,
+            error: None,
         }
     }
 
-    pub fn new_parallel(circumference: f64, mounting_offset: f64, sensor: T) -> TrackingWheel<T> {
+    /// Creates a parallel tracking wheel. The `mounting_offset` is positive
+    /// to the right of the tracking center; see
+    /// [`parallel_at`](Self::parallel_at).
+    pub fn new_parallel(
+        circumference: impl Into<Millimeters>,
+        mounting_offset: impl Into<Millimeters>,
+        sensor: T,
+    ) -> TrackingWheel<T> {
         Self::new(
             circumference,
             mounting_offset,
@@ -54,9 +82,12 @@ impl<T: HasRotation> TrackingWheel<T> {
         )
     }
 
+    /// Creates a perpendicular tracking wheel. The `mounting_offset` is
+    /// positive *behind* the tracking center; see
+    /// [`perpendicular_at`](Self::perpendicular_at).
     pub fn new_perpendicular(
-        circumference: f64,
-        mounting_offset: f64,
+        circumference: impl Into<Millimeters>,
+        mounting_offset: impl Into<Millimeters>,
         sensor: T,
     ) -> TrackingWheel<T> {
         Self::new(
@@ -70,10 +101,55 @@ impl<T: HasRotation> TrackingWheel<T> {
     /// Returns the difference between the last reported position and the
     /// current position.
     pub fn delta(&mut self) -> f64 {
-        let position = self.sensor.position();
-        let delta = position - self.last_angle;
+        let (position, error) = self.sensor.read_position();
+        self.error = error;
+        let delta = (position - self.last_angle).as_turns() * self.circumference;
         self.last_angle = position;
-        delta.as_turns() * self.circumference
+        self.last_delta = delta;
+        self.distance += delta;
+        delta
+    }
+
+    /// Returns a snapshot of the wheel's last update. This doesn't read the
+    /// sensor.
+    pub fn diagnostics(&self) -> WheelDiagnostics {
+        WheelDiagnostics {
+            mounting_direction: self.mounting_direction,
+            mounting_offset: self.mounting_offset,
+            last_delta: self.last_delta,
+            distance: self.distance,
+            error: self.error,
+        }
+    }
+
+    /// Creates a parallel tracking wheel `right_of_center` mm to the right of
+    /// the tracking center, or to the left if negative.
+    ///
+    /// The sensor should read positive when the robot drives forwards.
+    pub fn parallel_at(
+        circumference: impl Into<Millimeters>,
+        right_of_center: impl Into<Millimeters>,
+        sensor: T,
+    ) -> TrackingWheel<T> {
+        Self::new_parallel(circumference, right_of_center, sensor)
+    }
+
+    /// Creates a perpendicular tracking wheel `ahead_of_center` mm in front
+    /// of the tracking center, or behind it if negative.
+    ///
+    /// The sensor should read positive when the robot moves to the right.
+    pub fn perpendicular_at(
+        circumference: impl Into<Millimeters>,
+        ahead_of_center: impl Into<Millimeters>,
+        sensor: T,
+    ) -> TrackingWheel<T> {
+        // The mounting offset of a perpendicular wheel is measured backwards
+        Self::new_perpendicular(circumference, -ahead_of_center.into(), sensor)
+    }
+
+    /// Returns when the sensor last measured its position, if it knows.
+    pub fn timestamp(&self) -> Option<LowResolutionTime> {
+        self.sensor.timestamp()
     }
 
     pub fn mounting_offset(&self) -> f64 {
@@ -0,0 +1,427 @@
+//! Localizing game objects with a vision sensor
+//!
+//! The [`VisionSubsystem`] reads detections from an AI Vision or legacy Vision
+//! sensor, estimates the bearing and distance of every detection it has a
+//! [`VisionTarget`] for, and converts them into field coordinates using the
+//! current pose from the tracking subsystem and the [`CameraMount`]. Other
+//! actions can then target the localized objects without caring about pixels.
+//!
+//! ```ignore
+//! let vision = VisionSubsystem::new(
+//!     AiVisionSensor::new(peripherals.port_5),
+//!     tracking.clone(),
+//!     CameraMount::new(Vector2::new(150.0, 0.0), Angle::ZERO),
+//! )
+//! .with_target(VisionTarget::new("red ring", DetectionKind::Color, 1, 50.0));
+//!
+//! if let Some(ring) = vision.nearest("red ring") {
+//!     drivetrain.action(seek_to(ring.position)).await;
+//! }
+//! ```
+
+use core::{cell::RefCell, f64::consts::FRAC_PI_2};
+use std::time::Instant;
+
+use alloc::{rc::Rc, vec::Vec};
+use nalgebra::{Point2, Rotation2, Vector2};
+use vexide::{
+    math::Angle,
+    smart::{
+        ai_vision::{AiVisionObject, AiVisionSensor},
+        vision::{DetectionSource, VisionSensor},
+    },
+};
+
+use crate::{
+    subsystems::tracking::TrackingSubsystem,
+    utils::{
+        profiling,
+        ticker::{LOOP_PERIOD, Ticker},
+        unwrap_expect_report::UnwrapExpectReportExt,
+    },
+};
+
+/// How an object was detected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DetectionKind {
+    /// A color signature
+    Color,
+    /// A color code
+    Code,
+    /// An AprilTag
+    AprilTag,
+    /// An object recognized by the AI Vision sensor's onboard model
+    Model,
+}
+
+/// An object detected by a [`VisionSource`], in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Detection {
+    pub kind: DetectionKind,
+    /// The ID of the signature, code, tag, or model class
+    pub id: u16,
+    /// The center of the bounding box, from the top-left of the image
+    pub center: Point2<f64>,
+    pub width: f64,
+    pub height: f64,
+    /// The confidence from 0.0 to 1.0, if the sensor reports one
+    pub confidence: Option<f64>,
+}
+
+/// A sensor which detects objects in an image.
+pub trait VisionSource {
+    /// The size of the image in pixels, as (width, height).
+    const RESOLUTION: (f64, f64);
+    /// The field of view, as (horizontal, vertical).
+    const FOV: (Angle, Angle);
+
+    /// Returns the objects currently detected, or `None` if the sensor can't
+    /// be read.
+    fn detections(&self) -> Option<Vec<Detection>>;
+}
+
+impl VisionSource for AiVisionSensor {
+    const RESOLUTION: (f64, f64) = (
+        AiVisionSensor::HORIZONTAL_RESOLUTION as f64,
+        AiVisionSensor::VERTICAL_RESOLUTION as f64,
+    );
+    const FOV: (Angle, Angle) = (
+        Angle::from_degrees(AiVisionSensor::HORIZONTAL_FOV as f64),
+        Angle::from_degrees(AiVisionSensor::VERTICAL_FOV as f64),
+    );
+
+    fn detections(&self) -> Option<Vec<Detection>> {
+        let objects = self
+            .objects()
+            .expect_report("failed to read AI vision sensor")?;
+        Some(
+            objects
+                .into_iter()
+                .map(|object| match object {
+                    AiVisionObject::Color {
+                        id,
+                        position,
+                        width,
+                        height,
+                    } => box_detection(DetectionKind::Color, id, position, width, height, None),
+                    AiVisionObject::Code {
+                        id,
+                        position,
+                        width,
+                        height,
+                        ..
+                    } => box_detection(DetectionKind::Code, id, position, width, height, None),
+                    AiVisionObject::Model {
+                        id,
+                        position,
+                        width,
+                        height,
+                        confidence,
+                        ..
+                    } => box_detection(
+                        DetectionKind::Model,
+                        id,
+                        position,
+                        width,
+                        height,
+                        Some(confidence as f64 / 100.0),
+                    ),
+                    AiVisionObject::AprilTag {
+                        id,
+                        top_left,
+                        top_right,
+                        bottom_right,
+                        bottom_left,
+                    } => {
+                        let corners = [top_left, top_right, bottom_right, bottom_left];
+                        let xs = corners.map(|corner| corner.x as f64);
+                        let ys = corners.map(|corner| corner.y as f64);
+                        let (min_x, max_x) = (
+                            xs.iter().copied().fold(f64::MAX, f64::min),
+                            xs.iter().copied().fold(f64::MIN, f64::max),
+                        );
+                        let (min_y, max_y) = (
+                            ys.iter().copied().fold(f64::MAX, f64::min),
+                            ys.iter().copied().fold(f64::MIN, f64::max),
+                        );
+                        Detection {
+                            kind: DetectionKind::AprilTag,
+                            id: id as u16,
+                            center: Point2::new((min_x + max_x) / 2.0, (min_y + max_y) / 2.0),
+                            width: max_x - min_x,
+                            height: max_y - min_y,
+                            confidence: None,
+                        }
+                    }
+                })
+                .collect(),
+        )
+    }
+}
+
+fn box_detection(
+    kind: DetectionKind,
+    id: u8,
+    top_left: vexide::math::Point2<u16>,
+    width: u16,
+    height: u16,
+    confidence: Option<f64>,
+) -> Detection {
+    Detection {
+        kind,
+        id: id as u16,
+        center: Point2::new(
+            top_left.x as f64 + width as f64 / 2.0,
+            top_left.y as f64 + height as f64 / 2.0,
+        ),
+        width: width as f64,
+        height: height as f64,
+        confidence,
+    }
+}
+
+impl VisionSource for VisionSensor {
+    const RESOLUTION: (f64, f64) = (
+        VisionSensor::HORIZONTAL_RESOLUTION as f64,
+        VisionSensor::VERTICAL_RESOLUTION as f64,
+    );
+    const FOV: (Angle, Angle) = (
+        Angle::from_degrees(VisionSensor::HORIZONTAL_FOV as f64),
+        Angle::from_degrees(VisionSensor::VERTICAL_FOV as f64),
+    );
+
+    fn detections(&self) -> Option<Vec<Detection>> {
+        let objects = self
+            .objects()
+            .expect_report("failed to read vision sensor")?;
+        Some(
+            objects
+                .into_iter()
+                .filter_map(|object| {
+                    let (kind, id) = match object.source {
+                        DetectionSource::Signature(id) => (DetectionKind::Color, id as u16),
+                        DetectionSource::Code(code) => (DetectionKind::Code, code.id()),
+                        // Lines can't be localized
+                        DetectionSource::Line => return None,
+                    };
+                    Some(Detection {
+                        kind,
+                        id,
+                        center: Point2::new(object.center.x as f64, object.center.y as f64),
+                        width: object.width as f64,
+                        height: object.height as f64,
+                        confidence: None,
+                    })
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Where the camera is mounted on the robot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraMount {
+    /// The position of the camera relative to the tracking center in mm, with
+    /// x forwards and y to the left
+    pub offset: Vector2<f64>,
+    /// The direction the camera faces relative to the front of the robot,
+    /// counterclockwise positive
+    pub heading: Angle,
+}
+
+impl CameraMount {
+    pub fn new(offset: Vector2<f64>, heading: Angle) -> Self {
+        Self { offset, heading }
+    }
+}
+
+/// A kind of game object to localize.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VisionTarget {
+    /// The name localized objects are published under
+    pub label: &'static str,
+    pub kind: DetectionKind,
+    pub id: u16,
+    /// The real height of the object in mm, used to estimate its distance
+    pub height: f64,
+    /// Detections less confident than this are ignored
+    pub min_confidence: f64,
+}
+
+impl VisionTarget {
+    pub fn new(label: &'static str, kind: DetectionKind, id: u16, height: f64) -> Self {
+        Self {
+            label,
+            kind,
+            id,
+            height,
+            min_confidence: 0.0,
+        }
+    }
+
+    pub fn with_min_confidence(mut self, min_confidence: f64) -> Self {
+        self.min_confidence = min_confidence;
+        self
+    }
+}
+
+/// A game object localized on the field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LocalizedObject {
+    /// The label of the [`VisionTarget`] which matched the object
+    pub label: &'static str,
+    /// The position of the object on the field in mm, in the same coordinate
+    /// system as [`TrackingSubsystem::current`]
+    pub position: Point2<f64>,
+    /// The estimated distance from the camera in mm
+    pub distance: f64,
+    /// The bearing from the camera, counterclockwise positive
+    pub bearing: Angle,
+    pub confidence: Option<f64>,
+    /// When the object was seen
+    pub timestamp: Instant,
+}
+
+#[derive(Debug, Default)]
+struct VisionState {
+    targets: Vec<VisionTarget>,
+    objects: Vec<LocalizedObject>,
+}
+
+/// Localizes game objects seen by a vision sensor, in a background task.
+///
+/// Distances are estimated from the apparent height of each object with a
+/// pinhole camera model, so they are only as good as the
+/// [`VisionTarget::height`] and assume the object isn't partially hidden.
+#[derive(Debug, Clone)]
+pub struct VisionSubsystem {
+    state: Rc<RefCell<VisionState>>,
+    _task: Rc<vexide::task::Task<()>>,
+}
+
+impl VisionSubsystem {
+    /// Creates a new vision subsystem and starts localizing objects.
+    pub fn new<S: VisionSource + 'static>(
+        sensor: S,
+        tracking: TrackingSubsystem,
+        mount: CameraMount,
+    ) -> Self {
+        let state = Rc::new(RefCell::new(VisionState::default()));
+        Self {
+            state: state.clone(),
+            _task: Rc::new(vexide::task::spawn(async move {
+                let mut failing = false;
+                let mut ticker = Ticker::new(LOOP_PERIOD);
+                loop {
+                    let scope = profiling::scope("vision");
+                    match sensor.detections() {
+                        Some(detections) => {
+                            if failing {
+                                log::info!("Vision sensor recovered");
+                                failing = false;
+                            }
+                            let pose = tracking.current();
+                            let mut state = state.borrow_mut();
+                            let objects = detections
+                                .iter()
+                                .filter_map(|detection| {
+                                    let target = state.targets.iter().find(|target| {
+                                        target.kind == detection.kind
+                                            && target.id == detection.id
+                                            && detection
+                                                .confidence
+                                                .is_none_or(|c| c >= target.min_confidence)
+                                    })?;
+                                    localize::<S>(
+                                        detection,
+                                        target,
+                                        &mount,
+                                        pose.offset,
+                                        pose.heading,
+                                    )
+                                })
+                                .collect();
+                            state.objects = objects;
+                        }
+                        None => {
+                            if !failing {
+                                log::warn!("Failed to read vision sensor");
+                                failing = true;
+                            }
+                            state.borrow_mut().objects.clear();
+                        }
+                    }
+                    drop(scope);
+                    ticker.tick().await;
+                }
+            })),
+        }
+    }
+
+    /// Adds a kind of game object to localize.
+    pub fn with_target(self, target: VisionTarget) -> Self {
+        self.state.borrow_mut().targets.push(target);
+        self
+    }
+
+    /// Returns every object localized in the last update.
+    pub fn objects(&self) -> Vec<LocalizedObject> {
+        self.state.borrow().objects.clone()
+    }
+
+    /// Returns the objects with the given label localized in the last update.
+    pub fn objects_labeled(&self, label: &str) -> Vec<LocalizedObject> {
+        self.state
+            .borrow()
+            .objects
+            .iter()
+            .filter(|object| object.label == label)
+            .copied()
+            .collect()
+    }
+
+    /// Returns the closest object to the camera with the given label.
+    pub fn nearest(&self, label: &str) -> Option<LocalizedObject> {
+        self.objects_labeled(label)
+            .into_iter()
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+}
+
+/// Converts a detection into field coordinates.
+fn localize<S: VisionSource>(
+    detection: &Detection,
+    target: &VisionTarget,
+    mount: &CameraMount,
+    robot_offset: Point2<f64>,
+    robot_heading: Angle,
+) -> Option<LocalizedObject> {
+    if detection.height <= 0.0 {
+        return None;
+    }
+    let (width, height) = S::RESOLUTION;
+    let (horizontal_fov, vertical_fov) = S::FOV;
+    // Focal lengths in pixels
+    let horizontal_focal = (width / 2.0) / (horizontal_fov.as_radians() / 2.0).tan();
+    let vertical_focal = (height / 2.0) / (vertical_fov.as_radians() / 2.0).tan();
+
+    // Image x grows to the right, but bearings are counterclockwise positive
+    let bearing = -((detection.center.x - width / 2.0) / horizontal_focal).atan();
+    if bearing.abs() >= FRAC_PI_2 {
+        return None;
+    }
+    // The distance along the optical axis, converted to the distance along
+    // the ray to the object
+    let distance = target.height * vertical_focal / detection.height / bearing.cos();
+
+    let camera = robot_offset + Rotation2::new(robot_heading.as_radians()) * mount.offset;
+    let direction = (robot_heading + mount.heading).as_radians() + bearing;
+    Some(LocalizedObject {
+        label: target.label,
+        position: camera + Vector2::new(direction.cos(), direction.sin()) * distance,
+        distance,
+        bearing: Angle::from_radians(bearing),
+        confidence: detection.confidence,
+        timestamp: Instant::now(),
+    })
+}
@@ -0,0 +1,172 @@
+//! Wall alignment with a pair of distance sensors
+//!
+//! Two distance sensors side by side on the same face of the robot measure
+//! both how far the robot is from a wall and how crooked it is: if the left
+//! sensor reads further than the right one, the robot is turned
+//! counterclockwise from square. [`WallSensors`] turns the two readings into a
+//! [`WallMeasurement`], which can be used to drive square to a wall with
+//! [`align_to_wall`](WallSensors::align_to_wall), or to correct the tracking
+//! pose against a known wall with [`correct_pose`](WallSensors::correct_pose).
+
+use core::cell::RefCell;
+
+use alloc::rc::Rc;
+use nalgebra::Vector2;
+use vexide::{math::Angle, smart::distance::DistanceSensor};
+
+use crate::{
+    subsystems::{
+        drivetrain::actions::{AlignToWallAction, config::ActionConfig},
+        tracking::TrackingSubsystem,
+    },
+    utils::{
+        filters::{Filter, Median},
+        profiling,
+        ticker::{LOOP_PERIOD, Ticker},
+        unwrap_expect_report::UnwrapExpectReportExt,
+    },
+};
+
+/// The distance and angle to a wall.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WallMeasurement {
+    /// The perpendicular distance from the tracking center to the wall in mm
+    pub distance: f64,
+    /// How far the sensor face is turned from square to the wall,
+    /// counterclockwise positive
+    pub angle: Angle,
+}
+
+/// A wall on the field, for correcting the tracking pose.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FieldWall {
+    /// The direction the sensor face points when square to the wall
+    pub facing: Angle,
+    /// The position of the wall along `facing` in mm, i.e., the dot product
+    /// of any point on the wall with the unit vector in the `facing`
+    /// direction
+    pub position: f64,
+}
+
+#[derive(Debug, Default)]
+struct WallState {
+    measurement: Option<WallMeasurement>,
+}
+
+/// Two distance sensors on the same face of the robot, read in a background
+/// task.
+#[derive(Debug, Clone)]
+pub struct WallSensors {
+    state: Rc<RefCell<WallState>>,
+    face: Angle,
+    _task: Rc<vexide::task::Task<()>>,
+}
+
+impl WallSensors {
+    /// Creates a new pair of wall sensors.
+    ///
+    /// `spacing` is the distance between the sensors in mm, and `offset` is
+    /// the distance from the tracking center to the sensors along the
+    /// direction they face. `face` is the direction the sensors face relative
+    /// to the front of the robot, and must be either forwards
+    /// ([`Angle::ZERO`]) or backwards ([`Angle::HALF_TURN`]) for
+    /// [`align_to_wall`](Self::align_to_wall) to work. `left` is the sensor on
+    /// the left when looking in the direction the sensors face.
+    ///
+    /// Readings with a confidence below `min_confidence` (from 0.0 to 1.0) are
+    /// ignored, and the rest are median filtered to reject the occasional
+    /// reading which misses the wall.
+    pub fn new(
+        left: DistanceSensor,
+        right: DistanceSensor,
+        spacing: f64,
+        offset: f64,
+        face: Angle,
+        min_confidence: f64,
+    ) -> Self {
+        let state = Rc::new(RefCell::new(WallState::default()));
+        Self {
+            state: state.clone(),
+            face,
+            _task: Rc::new(vexide::task::spawn(async move {
+                let read = |sensor: &DistanceSensor, filter: &mut Median<5>| match sensor
+                    .object()
+                    .expect_report("failed to read wall distance sensor")
+                {
+                    Some(Some(object)) if object.confidence >= min_confidence => {
+                        Some(filter.update(object.distance as f64))
+                    }
+                    _ => {
+                        filter.reset();
+                        None
+                    }
+                };
+                let mut left_filter = Median::new();
+                let mut right_filter = Median::new();
+                let mut ticker = Ticker::new(LOOP_PERIOD);
+                loop {
+                    let scope = profiling::scope("wall");
+                    let left_distance = read(&left, &mut left_filter);
+                    let right_distance = read(&right, &mut right_filter);
+                    let measurement = left_distance.zip(right_distance).map(|(left, right)| {
+                        // See the module documentation: the difference between
+                        // the two beams is `spacing * tan(angle)`
+                        let angle = ((left - right) / spacing).atan();
+                        WallMeasurement {
+                            distance: ((left + right) / 2.0 + offset) * angle.cos(),
+                            angle: Angle::from_radians(angle),
+                        }
+                    });
+                    state.borrow_mut().measurement = measurement;
+                    drop(scope);
+                    ticker.tick().await;
+                }
+            })),
+        }
+    }
+
+    /// Returns the latest measurement, or `None` if either sensor can't see
+    /// the wall.
+    pub fn measurement(&self) -> Option<WallMeasurement> {
+        self.state.borrow().measurement
+    }
+
+    /// Returns the direction the sensors face relative to the front of the
+    /// robot.
+    pub fn face(&self) -> Angle {
+        self.face
+    }
+
+    /// Returns an action which drives square to the wall, `distance` mm from
+    /// the tracking center.
+    pub fn align_to_wall(&self, distance: f64, config: ActionConfig) -> AlignToWallAction {
+        AlignToWallAction::new(self.clone(), distance, config)
+    }
+
+    /// Corrects the heading and the position along the wall's normal of the
+    /// tracking pose from the latest measurement of the given wall.
+    ///
+    /// The position along the wall is kept. Returns the measurement used, or
+    /// `None` if there isn't one, in which case the pose isn't changed.
+    pub fn correct_pose(
+        &self,
+        tracking: &mut TrackingSubsystem,
+        wall: FieldWall,
+    ) -> Option<WallMeasurement> {
+        let measurement = self.measurement()?;
+        let current = tracking.current();
+        let heading = wall.facing + measurement.angle - self.face;
+        let normal = Vector2::new(wall.facing.cos(), wall.facing.sin());
+        let along = wall.position - measurement.distance;
+        let offset = current.offset + normal * (along - current.offset.coords.dot(&normal));
+        log::info!(
+            "Corrected pose from wall: {:?} -> {:?}, heading {:.1}° -> {:.1}°",
+            current.offset,
+            offset,
+            current.heading.as_degrees(),
+            heading.as_degrees()
+        );
+        tracking.set_current(offset, heading);
+        Some(measurement)
+    }
+}
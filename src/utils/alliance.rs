@@ -0,0 +1,102 @@
+//! Robot-wide field mirroring
+//!
+//! Most games are mirrored across the central line, so the same route can run
+//! on either side by mirroring the robot's idea of the field. An
+//! [`AllianceContext`] is the single source of truth for whether the robot is
+//! mirrored: the tracking subsystem, mirrored pneumatics, paths, and the
+//! autonomous registry all share one context, so they can't get out of sync.
+//!
+//! ```ignore
+//! let alliance = tracking.alliance();
+//! let doinker = MirroredPneumaticSubsystem::new(left, right, MirroredState::Normal)
+//!     .with_alliance(alliance.clone());
+//! let autons = AutonRegistry::new().with_alliance(alliance.clone());
+//! ```
+
+use core::cell::Cell;
+
+use alloc::rc::Rc;
+use nalgebra::Point2;
+use vexide::math::Angle;
+
+/// Whether the field is mirrored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MirroredState {
+    /// Like normal. Right is right, left is left.
+    #[default]
+    Normal,
+    /// Mirrored. Right is left, left is right.
+    Mirrored,
+}
+
+/// A shared mirrored state.
+///
+/// Clones share the same state, so setting it through one clone affects every
+/// subsystem holding another.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AllianceContext {
+    state: Rc<Cell<MirroredState>>,
+}
+
+impl AllianceContext {
+    /// Creates a new context in the given state.
+    pub fn new(state: MirroredState) -> Self {
+        Self {
+            state: Rc::new(Cell::new(state)),
+        }
+    }
+
+    /// Returns the current state.
+    pub fn state(&self) -> MirroredState {
+        self.state.get()
+    }
+
+    /// Sets the state, logging it if it changed.
+    pub fn set_state(&self, state: MirroredState) {
+        if self.state.replace(state) != state {
+            log::info!("Alliance context is now {:?}", state);
+        }
+    }
+
+    /// Returns whether the field is mirrored.
+    pub fn is_mirrored(&self) -> bool {
+        self.state() == MirroredState::Mirrored
+    }
+
+    /// Sets whether the field is mirrored.
+    pub fn set_mirrored(&self, mirrored: bool) {
+        self.set_state(if mirrored {
+            MirroredState::Mirrored
+        } else {
+            MirroredState::Normal
+        });
+    }
+
+    /// Mirrors a point over the central line if the field is mirrored.
+    pub fn point(&self, point: Point2<f64>) -> Point2<f64> {
+        if self.is_mirrored() {
+            mirror_point(point)
+        } else {
+            point
+        }
+    }
+
+    /// Mirrors a heading over the central line if the field is mirrored.
+    pub fn heading(&self, heading: Angle) -> Angle {
+        if self.is_mirrored() {
+            mirror_heading(heading)
+        } else {
+            heading
+        }
+    }
+}
+
+/// Mirrors a point over the central line, inverting the y-coordinate.
+pub fn mirror_point(point: Point2<f64>) -> Point2<f64> {
+    Point2::new(point.x, -point.y)
+}
+
+/// Mirrors a heading over the central line.
+pub fn mirror_heading(heading: Angle) -> Angle {
+    Angle::FULL_TURN - heading
+}
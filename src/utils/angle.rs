@@ -0,0 +1,87 @@
+//! Angle helpers
+//!
+//! Headings wrap, so the difference between two of them is only meaningful
+//! once it is wrapped to the turn it describes. These helpers do the wrapping
+//! in one place:
+//!
+//! - [`shortest_error`] is the error for turning the short way round.
+//! - [`directed_error`] is the error for turning a given way round.
+//! - [`lerp`] interpolates along the short way round.
+//! - [`angle_to`] is the heading from one point to another.
+//!
+//! Angles are counterclockwise-positive, like the tracking heading. For
+//! literals, [`AngleExt`] reads better than the constructors:
+//!
+//! ```ignore
+//! use libdoxa::utils::angle::AngleExt as _;
+//!
+//! let action = RotationAction::new(90.deg().as_radians(), config);
+//! ```
+
+use nalgebra::Point2;
+use vexide::math::Angle;
+
+/// The way round to turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TurnDirection {
+    Clockwise,
+    Counterclockwise,
+}
+
+/// Returns the error from `current` to `target` when turning the short way
+/// round, in [-180°, 180°).
+pub fn shortest_error(target: Angle, current: Angle) -> Angle {
+    (target - current).wrapped_half()
+}
+
+/// Returns the error from `current` to `target` when turning in `direction`,
+/// even if the other way is shorter.
+///
+/// The error is in [0°, 360°) counterclockwise and (-360°, 0°] clockwise.
+pub fn directed_error(target: Angle, current: Angle, direction: TurnDirection) -> Angle {
+    let counterclockwise = (target - current).wrapped_full();
+    match direction {
+        TurnDirection::Counterclockwise => counterclockwise,
+        TurnDirection::Clockwise if counterclockwise == Angle::ZERO => Angle::ZERO,
+        TurnDirection::Clockwise => counterclockwise - Angle::FULL_TURN,
+    }
+}
+
+/// Interpolates from `from` to `to` the short way round, where a `t` of 0.0
+/// is `from` and 1.0 is `to`.
+pub fn lerp(from: Angle, to: Angle, t: f64) -> Angle {
+    from + shortest_error(to, from) * t
+}
+
+/// Returns the heading which points from `from` to `to`.
+pub fn angle_to(from: Point2<f64>, to: Point2<f64>) -> Angle {
+    Angle::atan2(to.y - from.y, to.x - from.x)
+}
+
+/// Constructors for angle literals, e.g., `90.deg()`.
+pub trait AngleExt {
+    /// Returns an angle of this many degrees.
+    fn deg(self) -> Angle;
+    /// Returns an angle of this many radians.
+    fn rad(self) -> Angle;
+}
+
+impl AngleExt for f64 {
+    fn deg(self) -> Angle {
+        Angle::from_degrees(self)
+    }
+
+    fn rad(self) -> Angle {
+        Angle::from_radians(self)
+    }
+}
+
+impl AngleExt for i32 {
+    fn deg(self) -> Angle {
+        Angle::from_degrees(self as f64)
+    }
+
+    fn rad(self) -> Angle {
+        Angle::from_radians(self as f64)
+    }
+}
@@ -0,0 +1,506 @@
+//! Configuration loaded from the SD card
+//!
+//! Tuning constants change far more often than code. [`ConfigFile`] reads a
+//! small TOML file from the SD card at startup and overlays it on the
+//! compiled-in defaults, so gains and geometry can be changed by swapping the
+//! file instead of recompiling.
+//!
+//! Only a subset of TOML is supported: `[section]` headers, and `key = value`
+//! pairs where the value is a number, `true`/`false`, or a double-quoted
+//! string. Comments start with `#`.
+//!
+//! ```toml
+//! [action]
+//! linear_kp = 0.05
+//! turn_kp = 12.0
+//! linear_timeout = 2000 # ms
+//!
+//! [chassis]
+//! track_width = 290.0
+//! wheel_circumference = 219.4
+//!
+//! [features]
+//! debug_render = true
+//!
+//! [trim]
+//! left = 1.0
+//! right = 0.97
+//!
+//! [log]
+//! level = "debug"
+//! tracking = "warn"
+//! ```
+
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    fmt::{self, Write as _},
+    time::Duration,
+};
+
+use snafu::Snafu;
+
+use log::LevelFilter;
+
+use crate::{
+    subsystems::drivetrain::{
+        ChassisConfig,
+        actions::config::{ActionConfig, ActionConfigError},
+        trim::DriveTrim,
+    },
+    utils::logger,
+};
+
+#[derive(Debug, Snafu)]
+pub enum ConfigError {
+    #[snafu(display("Failed to read {}: {}", path, source))]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[snafu(display("{}:{}: {}", path, line, message))]
+    Syntax {
+        path: String,
+        line: usize,
+        message: String,
+    },
+    #[snafu(display("Invalid value for {}: {}", key, message))]
+    Invalid { key: String, message: String },
+    #[snafu(display("Invalid action config: {}", source))]
+    ActionConfig { source: ActionConfigError },
+}
+
+/// Calls `$callback!` with the names of the [`ActionConfig`] fields which
+/// can be configured: first the numbers, then the durations, which are
+/// configured in ms.
+macro_rules! action_fields {
+    ($callback:ident) => {
+        $callback!(
+            [
+                linear_kp,
+                linear_kp_limit,
+                linear_ki,
+                linear_ki_limit,
+                linear_kd,
+                linear_kd_limit,
+                linear_limit,
+                linear_integral_zone,
+                linear_min_output,
+                turn_kp,
+                turn_kp_limit,
+                turn_ki,
+                turn_ki_limit,
+                turn_kd,
+                turn_kd_limit,
+                turn_limit,
+                turn_integral_zone,
+                turn_min_output,
+                pursuit_turn_kp,
+                pursuit_turn_kp_limit,
+                pursuit_turn_ki,
+                pursuit_turn_ki_limit,
+                pursuit_turn_kd,
+                pursuit_turn_kd_limit,
+                pursuit_turn_limit,
+                pursuit_lookahead,
+                pursuit_radius_tolerance,
+                boomerang_lead,
+                boomerang_close,
+                boomerang_heading_tolerance,
+                linear_error_tolerance,
+                linear_velocity_tolerance,
+                turn_error_tolerance,
+                turn_velocity_tolerance,
+                linear_min_progress,
+                turn_min_progress,
+            ],
+            [
+                linear_tolerance_duration,
+                linear_timeout,
+                turn_tolerance_duration,
+                turn_timeout,
+                linear_progress_window,
+                turn_progress_window,
+            ]
+        )
+    };
+}
+
+macro_rules! action_keys {
+    ([$($number:ident),* $(,)?], [$($duration:ident),* $(,)?]) => {
+        &[$(stringify!($number),)* $(stringify!($duration),)*]
+    };
+}
+
+/// The keys of the `[action]` section, which are the names of the
+/// [`ActionConfig`] fields.
+pub const ACTION_KEYS: &[&str] = action_fields!(action_keys);
+
+/// The keys of the `[chassis]` section, which are the names of the
+/// [`ChassisConfig`] fields.
+pub const CHASSIS_KEYS: &[&str] = &[
+    "track_width",
+    "wheel_circumference",
+    "parallel_offset",
+    "perpendicular_offset",
+    "max_rpm",
+    "mass",
+    "max_acceleration",
+];
+
+/// A value in a [`ConfigFile`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigValue {
+    Number(f64),
+    Bool(bool),
+    String(String),
+}
+
+impl fmt::Display for ConfigValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Number(number) => write!(f, "{}", number),
+            Self::Bool(bool) => write!(f, "{}", bool),
+            Self::String(string) => write!(f, "\"{}\"", string),
+        }
+    }
+}
+
+/// A configuration file of `section.key` values.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigFile {
+    values: BTreeMap<String, ConfigValue>,
+}
+
+impl ConfigFile {
+    /// Loads and parses the configuration file at `path`.
+    pub fn load(path: &str) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_string(),
+            source,
+        })?;
+        let config = Self::parse(path, &text)?;
+        log::info!("Loaded {} config values from {}", config.values.len(), path);
+        Ok(config)
+    }
+
+    /// Like [`load`](Self::load), but logs the error and returns an empty
+    /// configuration if the file can't be loaded, so that the robot still
+    /// runs with its compiled-in defaults.
+    pub fn load_or_empty(path: &str) -> Self {
+        Self::load(path).unwrap_or_else(|err| {
+            log::error!("{}; using default configuration", err);
+            Self::default()
+        })
+    }
+
+    /// Writes the configuration to `path`, replacing the file.
+    ///
+    /// See [`to_toml`](Self::to_toml) for what is written.
+    pub fn save(&self, path: &str) -> Result<(), ConfigError> {
+        std::fs::write(path, self.to_toml()).map_err(|source| ConfigError::Io {
+            path: path.to_string(),
+            source,
+        })?;
+        log::info!("Saved {} config values to {}", self.values.len(), path);
+        Ok(())
+    }
+
+    /// Parses configuration text. `path` is only used in error messages.
+    pub fn parse(path: &str, text: &str) -> Result<Self, ConfigError> {
+        let mut values = BTreeMap::new();
+        let mut section = String::new();
+        for (index, line) in text.lines().enumerate() {
+            let syntax = |message: &str| ConfigError::Syntax {
+                path: path.to_string(),
+                line: index + 1,
+                message: message.to_string(),
+            };
+            // Strip comments, but not inside strings
+            let line = match line.find('#') {
+                Some(hash) if line[..hash].matches('"').count() % 2 == 0 => &line[..hash],
+                _ => line,
+            }
+            .trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('[') {
+                section = header
+                    .strip_suffix(']')
+                    .ok_or_else(|| syntax("unterminated section header"))?
+                    .trim()
+                    .to_string();
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| syntax("expected `key = value`"))?;
+            let key = key.trim();
+            let value = value.trim();
+            let value = if let Some(string) = value.strip_prefix('"') {
+                ConfigValue::String(
+                    string
+                        .strip_suffix('"')
+                        .ok_or_else(|| syntax("unterminated string"))?
+                        .to_string(),
+                )
+            } else if value == "true" || value == "false" {
+                ConfigValue::Bool(value == "true")
+            } else {
+                ConfigValue::Number(
+                    value
+                        .replace('_', "")
+                        .parse()
+                        .map_err(|_| syntax("expected a number, boolean, or string"))?,
+                )
+            };
+            let key = if section.is_empty() {
+                key.to_string()
+            } else {
+                format!("{}.{}", section, key)
+            };
+            values.insert(key, value);
+        }
+        Ok(Self { values })
+    }
+
+    /// Formats the configuration as TOML which [`parse`](Self::parse) reads
+    /// back. Comments and the order of the original file are not kept.
+    pub fn to_toml(&self) -> String {
+        let mut toml = String::new();
+        let mut section = "";
+        // Keys outside any section have to come before the first header
+        let (unsectioned, sectioned): (Vec<_>, Vec<_>) =
+            self.values.iter().partition(|(key, _)| !key.contains('.'));
+        for (key, value) in unsectioned {
+            _ = writeln!(toml, "{} = {}", key, value);
+        }
+        for (key, value) in sectioned {
+            let (key_section, name) = key.split_once('.').unwrap_or(("", key));
+            if key_section != section {
+                if !toml.is_empty() {
+                    toml.push('\n');
+                }
+                _ = writeln!(toml, "[{}]", key_section);
+                section = key_section;
+            }
+            _ = writeln!(toml, "{} = {}", name, value);
+        }
+        toml
+    }
+
+    /// Returns the value for `key`, written `section.key`.
+    pub fn get(&self, key: &str) -> Option<&ConfigValue> {
+        self.values.get(key)
+    }
+
+    /// Sets the value for `key`, written `section.key`.
+    pub fn set(&mut self, key: &str, value: ConfigValue) {
+        self.values.insert(key.to_string(), value);
+    }
+
+    /// Returns the number for `key`, or an error if it isn't a number.
+    pub fn number(&self, key: &str) -> Result<Option<f64>, ConfigError> {
+        match self.values.get(key) {
+            None => Ok(None),
+            Some(ConfigValue::Number(number)) => Ok(Some(*number)),
+            Some(_) => Err(ConfigError::Invalid {
+                key: key.to_string(),
+                message: "expected a number".to_string(),
+            }),
+        }
+    }
+
+    /// Returns whether the feature `name` is enabled in the `[features]`
+    /// section. Missing or non-boolean features are disabled.
+    pub fn feature(&self, name: &str) -> bool {
+        matches!(
+            self.values.get(&format!("features.{}", name)),
+            Some(ConfigValue::Bool(true))
+        )
+    }
+
+    /// Returns every boolean in the `[features]` section.
+    pub fn features(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.values.iter().filter_map(
+            |(key, value)| match (key.strip_prefix("features."), value) {
+                (Some(name), ConfigValue::Bool(enabled)) => Some((name, *enabled)),
+                _ => None,
+            },
+        )
+    }
+
+    /// Logs a warning for each key in `section` which isn't in `known`, since
+    /// they are most likely typos.
+    fn warn_unknown(&self, section: &str, known: &[&str]) {
+        let prefix = format!("{}.", section);
+        for key in self.values.keys() {
+            if let Some(name) = key.strip_prefix(&prefix)
+                && !known.contains(&name)
+            {
+                log::warn!("Unknown config key {:?} ignored", key);
+            }
+        }
+    }
+
+    /// Overlays the `[action]` section on `base`.
+    ///
+    /// Keys are the names of the [`ActionConfig`] fields. Durations are in ms.
+    /// Gains, limits, and tolerances must not be negative, and the resulting
+    /// config must be [valid](ActionConfig::validate).
+    pub fn action_config(&self, base: ActionConfig) -> Result<ActionConfig, ConfigError> {
+        let mut config = base;
+        macro_rules! overlay {
+            ([$($number:ident),* $(,)?], [$($duration:ident),* $(,)?]) => {
+                $(
+                    let key = concat!("action.", stringify!($number));
+                    if let Some(value) = self.non_negative(key)? {
+                        config.$number = value;
+                    }
+                )*
+                $(
+                    let key = concat!("action.", stringify!($duration));
+                    if let Some(value) = self.non_negative(key)? {
+                        config.$duration = Duration::from_millis(value as u64);
+                    }
+                )*
+            };
+        }
+        action_fields!(overlay);
+        self.warn_unknown("action", ACTION_KEYS);
+        config
+            .validate()
+            .map_err(|source| ConfigError::ActionConfig { source })?;
+        Ok(config)
+    }
+
+    /// Sets every key of the `[action]` section from `config`, so that
+    /// [`action_config`](Self::action_config) returns it.
+    pub fn set_action_config(&mut self, config: &ActionConfig) {
+        macro_rules! set {
+            ([$($number:ident),* $(,)?], [$($duration:ident),* $(,)?]) => {
+                $(
+                    self.set(
+                        concat!("action.", stringify!($number)),
+                        ConfigValue::Number(config.$number),
+                    );
+                )*
+                $(
+                    self.set(
+                        concat!("action.", stringify!($duration)),
+                        ConfigValue::Number(config.$duration.as_millis() as f64),
+                    );
+                )*
+            };
+        }
+        action_fields!(set);
+    }
+
+    /// Overlays the `[chassis]` section on `base`.
+    ///
+    /// Keys are the names of the [`ChassisConfig`] fields. All but the
+    /// tracking wheel offsets must be positive.
+    pub fn chassis(&self, base: ChassisConfig) -> Result<ChassisConfig, ConfigError> {
+        let mut chassis = base;
+        for (key, field) in [
+            ("chassis.track_width", &mut chassis.track_width),
+            (
+                "chassis.wheel_circumference",
+                &mut chassis.wheel_circumference,
+            ),
+            ("chassis.max_rpm", &mut chassis.max_rpm),
+            ("chassis.mass", &mut chassis.mass),
+            ("chassis.max_acceleration", &mut chassis.max_acceleration),
+        ] {
+            if let Some(value) = self.positive(key)? {
+                *field = value;
+            }
+        }
+        for (key, field) in [
+            ("chassis.parallel_offset", &mut chassis.parallel_offset),
+            (
+                "chassis.perpendicular_offset",
+                &mut chassis.perpendicular_offset,
+            ),
+        ] {
+            if let Some(value) = self.number(key)? {
+                *field = value;
+            }
+        }
+        self.warn_unknown("chassis", CHASSIS_KEYS);
+        Ok(chassis)
+    }
+
+    /// Returns the trim in the `[trim]` section, from its `left` and `right`
+    /// keys, or `None` if there is neither. A missing side is 1.0.
+    pub fn trim(&self) -> Result<Option<DriveTrim>, ConfigError> {
+        let left = self.positive("trim.left")?;
+        let right = self.positive("trim.right")?;
+        self.warn_unknown("trim", &["left", "right"]);
+        if left.is_none() && right.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(DriveTrim::new(
+            left.unwrap_or(1.0),
+            right.unwrap_or(1.0),
+        )))
+    }
+
+    /// Sets the `[trim]` section from `trim`, so that [`trim`](Self::trim)
+    /// returns it.
+    pub fn set_trim(&mut self, trim: DriveTrim) {
+        self.set("trim.left", ConfigValue::Number(trim.left));
+        self.set("trim.right", ConfigValue::Number(trim.right));
+    }
+
+    /// Applies the `[log]` section to the logger.
+    ///
+    /// `level` sets the default level, and every other key sets the level of
+    /// the module it names, e.g., `tracking = "warn"`. Levels are names like
+    /// `"debug"`.
+    pub fn apply_log_levels(&self) -> Result<(), ConfigError> {
+        for (key, value) in &self.values {
+            let Some(module) = key.strip_prefix("log.") else {
+                continue;
+            };
+            let level = match value {
+                ConfigValue::String(level) => level.parse::<LevelFilter>().ok(),
+                _ => None,
+            }
+            .ok_or_else(|| ConfigError::Invalid {
+                key: key.clone(),
+                message: "expected a level like \"debug\"".to_string(),
+            })?;
+            if module == "level" {
+                logger::set_level(level);
+            } else {
+                logger::set_module_level(module, level);
+            }
+        }
+        Ok(())
+    }
+
+    fn positive(&self, key: &str) -> Result<Option<f64>, ConfigError> {
+        match self.number(key)? {
+            Some(value) if value <= 0.0 || !value.is_finite() => Err(ConfigError::Invalid {
+                key: key.to_string(),
+                message: format!("must be positive, got {}", value),
+            }),
+            value => Ok(value),
+        }
+    }
+
+    fn non_negative(&self, key: &str) -> Result<Option<f64>, ConfigError> {
+        match self.number(key)? {
+            Some(value) if value < 0.0 || !value.is_finite() => Err(ConfigError::Invalid {
+                key: key.to_string(),
+                message: format!("must be a non-negative number, got {}", value),
+            }),
+            value => Ok(value),
+        }
+    }
+}
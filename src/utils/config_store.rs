@@ -0,0 +1,187 @@
+//! One shared source of configuration
+//!
+//! Configuration can change at runtime: it is loaded from the SD card, and
+//! changed by the [serial tuner](super::tuner) or an on-screen tuner. A
+//! [`ConfigStore`] holds the current [`ActionConfig`], [`ChassisConfig`],
+//! and feature toggles, so all of them change the same values. Clones share
+//! the store, and listeners added with [`on_change`](ConfigStore::on_change)
+//! are told about every change.
+//!
+//! Actions copy their config when they are created. To pick up changes,
+//! create them with [`ConfigStore::action`], which reads the config when the
+//! action starts instead:
+//!
+//! ```ignore
+//! let file = ConfigFile::load_or_empty("config.toml");
+//! let store = ConfigStore::from_file(&file, ActionConfig::default(), CHASSIS)?;
+//! tuner::init(store.clone(), "config.toml");
+//! // ...
+//! drivetrain.action(store.action(|config| ForwardAction::new(600.0, config))).await;
+//! ```
+
+use alloc::{
+    collections::BTreeMap,
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{cell::RefCell, fmt};
+
+use crate::{
+    subsystems::drivetrain::{
+        ChassisConfig,
+        actions::{
+            Action, LazyAction,
+            config::{ActionConfig, ActionConfigError},
+        },
+    },
+    utils::config::{ConfigError, ConfigFile},
+};
+
+/// What changed in a [`ConfigStore`]. Listeners read the new values from the
+/// store.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigChange {
+    ActionConfig,
+    Chassis,
+    Feature { name: String, enabled: bool },
+}
+
+type Listener = Rc<dyn Fn(&ConfigChange)>;
+
+struct ConfigValues {
+    action: ActionConfig,
+    chassis: ChassisConfig,
+    features: BTreeMap<String, bool>,
+}
+
+/// Shared, mutable configuration. See the [module documentation](self).
+#[derive(Clone)]
+pub struct ConfigStore {
+    values: Rc<RefCell<ConfigValues>>,
+    listeners: Rc<RefCell<Vec<Listener>>>,
+}
+
+impl fmt::Debug for ConfigStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let values = self.values.borrow();
+        f.debug_struct("ConfigStore")
+            .field("action", &values.action)
+            .field("chassis", &values.chassis)
+            .field("features", &values.features)
+            .finish()
+    }
+}
+
+impl ConfigStore {
+    /// Creates a store with no features enabled.
+    pub fn new(action: ActionConfig, chassis: ChassisConfig) -> Self {
+        Self {
+            values: Rc::new(RefCell::new(ConfigValues {
+                action,
+                chassis,
+                features: BTreeMap::new(),
+            })),
+            listeners: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    /// Creates a store from a config file overlaid on the compiled-in
+    /// defaults, with the features from its `[features]` section.
+    pub fn from_file(
+        file: &ConfigFile,
+        action: ActionConfig,
+        chassis: ChassisConfig,
+    ) -> Result<Self, ConfigError> {
+        let store = Self::new(file.action_config(action)?, file.chassis(chassis)?);
+        store.values.borrow_mut().features = file
+            .features()
+            .map(|(name, enabled)| (name.to_string(), enabled))
+            .collect();
+        Ok(store)
+    }
+
+    /// Returns the current action config.
+    pub fn action_config(&self) -> ActionConfig {
+        self.values.borrow().action
+    }
+
+    /// Replaces the action config, if it is [valid](ActionConfig::validate).
+    pub fn set_action_config(&self, config: ActionConfig) -> Result<(), ActionConfigError> {
+        config.validate()?;
+        self.values.borrow_mut().action = config;
+        self.notify(ConfigChange::ActionConfig);
+        Ok(())
+    }
+
+    /// Changes the action config with `update`, if the result is
+    /// [valid](ActionConfig::validate).
+    pub fn update_action_config(
+        &self,
+        update: impl FnOnce(&mut ActionConfig),
+    ) -> Result<(), ActionConfigError> {
+        let mut config = self.action_config();
+        update(&mut config);
+        self.set_action_config(config)
+    }
+
+    /// Returns the current chassis config.
+    pub fn chassis(&self) -> ChassisConfig {
+        self.values.borrow().chassis
+    }
+
+    /// Replaces the chassis config.
+    pub fn set_chassis(&self, chassis: ChassisConfig) {
+        self.values.borrow_mut().chassis = chassis;
+        self.notify(ConfigChange::Chassis);
+    }
+
+    /// Returns whether the feature `name` is enabled. Features which were
+    /// never set are disabled.
+    pub fn feature(&self, name: &str) -> bool {
+        self.values
+            .borrow()
+            .features
+            .get(name)
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Enables or disables the feature `name`.
+    pub fn set_feature(&self, name: &str, enabled: bool) {
+        self.values
+            .borrow_mut()
+            .features
+            .insert(name.to_string(), enabled);
+        self.notify(ConfigChange::Feature {
+            name: name.to_string(),
+            enabled,
+        });
+    }
+
+    /// Calls `listener` after every change.
+    ///
+    /// Listeners may read the store, but changing it from a listener calls
+    /// the listeners again.
+    pub fn on_change(&self, listener: impl Fn(&ConfigChange) + 'static) {
+        self.listeners.borrow_mut().push(Rc::new(listener));
+    }
+
+    /// Returns an action which is created with the action config current when
+    /// it starts, rather than when this is called.
+    pub fn action<T: Action>(
+        &self,
+        create: impl FnOnce(ActionConfig) -> T + 'static,
+    ) -> LazyAction<T> {
+        let store = self.clone();
+        LazyAction::new(move |_| create(store.action_config()))
+    }
+
+    fn notify(&self, change: ConfigChange) {
+        // Listeners are cloned out so that they can add listeners themselves
+        let listeners: Vec<Listener> = self.listeners.borrow().clone();
+        for listener in listeners {
+            listener(&change);
+        }
+    }
+}
@@ -0,0 +1,344 @@
+//! Feedback controllers
+//!
+//! PID is the right tool for most mechanisms, but not all of them. A flywheel
+//! only needs to reach a velocity and stay there, and it can't be slowed down
+//! by driving it backwards, so simpler controllers often work better:
+//!
+//! - [`TakeBackHalf`] needs a single gain and settles without oscillating.
+//! - [`BangBang`] drives at full power until the target is reached, which
+//!   recovers fastest after a shot.
+//!
+//! For everything else, [`PidController`] extends [`pid::Pid`] with the
+//! usual fixes for integral windup and derivative noise.
+//!
+//! Every controller implements [`Controller`], as does [`pid::Pid`], so a
+//! mechanism can take any of them.
+
+use pid::{ControlOutput, Pid};
+
+use crate::utils::filters::{Ema, Filter};
+
+/// The default maximum output, in volts.
+const MAX_VOLTAGE: f64 = 12.0;
+
+/// A feedback controller which drives a measurement towards a setpoint.
+pub trait Controller {
+    /// Returns the output for the given setpoint and measurement.
+    ///
+    /// This should be called once per loop.
+    fn update(&mut self, setpoint: f64, measurement: f64) -> f64;
+
+    /// Clears any accumulated state, e.g., when the mechanism is stopped.
+    fn reset(&mut self);
+}
+
+impl Controller for Pid<f64> {
+    fn update(&mut self, setpoint: f64, measurement: f64) -> f64 {
+        self.setpoint(setpoint);
+        self.next_control_output(measurement).output
+    }
+
+    fn reset(&mut self) {
+        self.reset_integral_term();
+    }
+}
+
+impl<T: Controller + ?Sized> Controller for alloc::boxed::Box<T> {
+    fn update(&mut self, setpoint: f64, measurement: f64) -> f64 {
+        (**self).update(setpoint, measurement)
+    }
+
+    fn reset(&mut self) {
+        (**self).reset()
+    }
+}
+
+/// A Take-Back-Half velocity controller.
+///
+/// The output integrates the error with the given gain, and is cut to halfway
+/// between the current output and the output at the last zero crossing
+/// whenever the error changes sign. Over a few crossings, the output
+/// converges on the one which holds the target velocity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TakeBackHalf {
+    gain: f64,
+    max_output: f64,
+    output: f64,
+    /// The output at the last zero crossing
+    tbh: f64,
+    last_error: f64,
+}
+
+impl TakeBackHalf {
+    /// Creates a new Take-Back-Half controller with the given gain, in output
+    /// units per unit of error per loop, and a maximum output of 12 volts.
+    pub fn new(gain: f64) -> Self {
+        Self {
+            gain,
+            max_output: MAX_VOLTAGE,
+            output: 0.0,
+            tbh: 0.0,
+            last_error: 0.0,
+        }
+    }
+
+    /// Sets the maximum magnitude of the output.
+    pub fn with_max_output(mut self, max_output: f64) -> Self {
+        self.max_output = max_output;
+        self
+    }
+}
+
+impl Controller for TakeBackHalf {
+    fn update(&mut self, setpoint: f64, measurement: f64) -> f64 {
+        let error = setpoint - measurement;
+        self.output = (self.output + self.gain * error).clamp(-self.max_output, self.max_output);
+        // Take back half on every zero crossing
+        if error.signum() != self.last_error.signum() {
+            self.output = (self.output + self.tbh) / 2.0;
+            self.tbh = self.output;
+        }
+        self.last_error = error;
+        self.output
+    }
+
+    fn reset(&mut self) {
+        self.output = 0.0;
+        self.tbh = 0.0;
+        self.last_error = 0.0;
+    }
+}
+
+/// A bang-bang controller with hysteresis.
+///
+/// The output is `high` while the measurement is below the setpoint and
+/// `low` once it is above it. To keep the output from chattering around the
+/// setpoint, it only switches once the measurement is more than `hysteresis`
+/// past the setpoint.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BangBang {
+    high: f64,
+    low: f64,
+    hysteresis: f64,
+    /// Whether the output is `high`
+    on: bool,
+}
+
+impl BangBang {
+    /// Creates a new bang-bang controller without hysteresis.
+    ///
+    /// For a flywheel, `high` is typically 12 volts and `low` a little under
+    /// the voltage which holds the target, so that it coasts down slowly.
+    pub fn new(high: f64, low: f64) -> Self {
+        Self {
+            high,
+            low,
+            hysteresis: 0.0,
+            on: false,
+        }
+    }
+
+    /// Sets how far past the setpoint the measurement must be to switch the
+    /// output.
+    pub fn with_hysteresis(mut self, hysteresis: f64) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+}
+
+impl Controller for BangBang {
+    fn update(&mut self, setpoint: f64, measurement: f64) -> f64 {
+        if measurement < setpoint - self.hysteresis {
+            self.on = true;
+        } else if measurement > setpoint + self.hysteresis {
+            self.on = false;
+        }
+        if self.on { self.high } else { self.low }
+    }
+
+    fn reset(&mut self) {
+        self.on = false;
+    }
+}
+
+/// A PID controller with integral zone, integral resets, and derivative
+/// filtering.
+///
+/// The gains and limits are those of a [`pid::Pid`] and work the same way,
+/// but the plain PID's integral keeps winding up while far from the setpoint
+/// and keeps pushing after an overshoot. This controller:
+///
+/// - only integrates while the error is within the integral zone, if set, and
+///   clears the integral outside of it;
+/// - clears the integral when the error changes sign, i.e., on overshoot;
+/// - clears the integral when the setpoint changes;
+/// - takes the derivative of the measurement rather than the error, so that
+///   setpoint changes don't kick the output; and
+/// - optionally smooths the derivative with an [`Ema`], since differentiating a
+///   noisy measurement amplifies the noise.
+///
+/// The integral resets can be turned off individually, e.g., for a setpoint
+/// which moves every loop along a motion profile.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PidController {
+    gains: Pid<f64>,
+    integral_zone: Option<f64>,
+    reset_on_sign_flip: bool,
+    reset_on_setpoint_change: bool,
+    derivative_filter: Option<Ema>,
+
+    integral: f64,
+    last_error: Option<f64>,
+    last_measurement: Option<f64>,
+}
+
+impl PidController {
+    /// Creates a new controller with no gains, like [`Pid::new`].
+    pub fn new(setpoint: f64, output_limit: f64) -> Self {
+        Self::from(Pid::new(setpoint, output_limit))
+    }
+
+    /// Sets the proportional gain and the limit of its contribution.
+    pub fn p(&mut self, gain: f64, limit: f64) -> &mut Self {
+        self.gains.p(gain, limit);
+        self
+    }
+
+    /// Sets the integral gain and the limit of its contribution.
+    pub fn i(&mut self, gain: f64, limit: f64) -> &mut Self {
+        self.gains.i(gain, limit);
+        self
+    }
+
+    /// Sets the derivative gain and the limit of its contribution.
+    pub fn d(&mut self, gain: f64, limit: f64) -> &mut Self {
+        self.gains.d(gain, limit);
+        self
+    }
+
+    /// Only integrates while the error is at most `zone` in magnitude.
+    pub fn with_integral_zone(mut self, zone: f64) -> Self {
+        self.integral_zone = Some(zone);
+        self
+    }
+
+    /// Sets whether the integral is cleared when the error changes sign. This
+    /// is on by default.
+    pub fn with_sign_flip_reset(mut self, reset: bool) -> Self {
+        self.reset_on_sign_flip = reset;
+        self
+    }
+
+    /// Sets whether the integral is cleared when the setpoint changes. This is
+    /// on by default.
+    pub fn with_setpoint_reset(mut self, reset: bool) -> Self {
+        self.reset_on_setpoint_change = reset;
+        self
+    }
+
+    /// Smooths the derivative with an [`Ema`] with the given alpha.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alpha` isn't greater than zero and at most one.
+    pub fn with_derivative_filter(mut self, alpha: f64) -> Self {
+        self.derivative_filter = Some(Ema::new(alpha));
+        self
+    }
+
+    /// Returns the gains, limits, and setpoint.
+    pub fn gains(&self) -> &Pid<f64> {
+        &self.gains
+    }
+
+    /// Changes the setpoint, clearing the integral if it changed and setpoint
+    /// resets are on.
+    pub fn setpoint(&mut self, setpoint: f64) -> &mut Self {
+        if self.reset_on_setpoint_change && setpoint != self.gains.setpoint {
+            self.integral = 0.0;
+        }
+        self.gains.setpoint(setpoint);
+        self
+    }
+
+    /// Returns the output for the given measurement, with each term's
+    /// contribution.
+    pub fn next_control_output(&mut self, measurement: f64) -> ControlOutput<f64> {
+        let gains = &self.gains;
+        let error = gains.setpoint - measurement;
+
+        let p = (error * gains.kp).clamp(-gains.p_limit.abs(), gains.p_limit.abs());
+
+        let outside_zone = self.integral_zone.is_some_and(|zone| error.abs() > zone);
+        let sign_flipped = self.reset_on_sign_flip
+            && self
+                .last_error
+                .is_some_and(|last_error| last_error * error < 0.0);
+        if outside_zone || sign_flipped {
+            self.integral = 0.0;
+        } else {
+            self.integral += error * gains.ki;
+        }
+        self.integral = self
+            .integral
+            .clamp(-gains.i_limit.abs(), gains.i_limit.abs());
+        self.last_error = Some(error);
+
+        let derivative = match self.last_measurement {
+            Some(last_measurement) => -(measurement - last_measurement),
+            None => 0.0,
+        };
+        let derivative = match &mut self.derivative_filter {
+            Some(filter) => filter.update(derivative),
+            None => derivative,
+        };
+        self.last_measurement = Some(measurement);
+        let d = (derivative * gains.kd).clamp(-gains.d_limit.abs(), gains.d_limit.abs());
+
+        let output =
+            (p + self.integral + d).clamp(-gains.output_limit.abs(), gains.output_limit.abs());
+        ControlOutput {
+            p,
+            i: self.integral,
+            d,
+            output,
+        }
+    }
+
+    /// Clears the integral.
+    pub fn reset_integral_term(&mut self) {
+        self.integral = 0.0;
+    }
+}
+
+impl From<Pid<f64>> for PidController {
+    fn from(gains: Pid<f64>) -> Self {
+        Self {
+            gains,
+            integral_zone: None,
+            reset_on_sign_flip: true,
+            reset_on_setpoint_change: true,
+            derivative_filter: None,
+            integral: 0.0,
+            last_error: None,
+            last_measurement: None,
+        }
+    }
+}
+
+impl Controller for PidController {
+    fn update(&mut self, setpoint: f64, measurement: f64) -> f64 {
+        self.setpoint(setpoint);
+        self.next_control_output(measurement).output
+    }
+
+    /// Clears the integral and the derivative history.
+    fn reset(&mut self) {
+        self.integral = 0.0;
+        self.last_error = None;
+        self.last_measurement = None;
+        if let Some(filter) = &mut self.derivative_filter {
+            filter.reset();
+        }
+    }
+}
@@ -0,0 +1,275 @@
+//! Timestamped events shared between subsystems
+//!
+//! Instead of sharing a boolean for every "has this happened yet" between
+//! subsystems, a subsystem publishes an [`Event`] to an [`EventBus`], and
+//! anything interested subscribes to it or waits for it:
+//!
+//! ```ignore
+//! const PIECE_INTAKED: &str = "piece intaked";
+//!
+//! let events = EventBus::new().with_tracking(tracking.clone()).with_logging();
+//! drivetrain.set_events(Some(events.clone()));
+//!
+//! // In the intake's task
+//! events.publish(PIECE_INTAKED, "intake");
+//!
+//! // In a route
+//! events.wait_for(PIECE_INTAKED).await;
+//! clamp.set_state(true);
+//! ```
+//!
+//! Each event records when it happened and, with
+//! [`with_tracking`](EventBus::with_tracking), where the robot was, so an
+//! intake can be matched up with the position it happened at afterwards.
+//!
+//! Events are identified by name. The names published by libdoxa are
+//! constants in this module; robot code defines its own the same way.
+
+use core::cell::RefCell;
+use std::time::Instant;
+
+use alloc::{boxed::Box, collections::VecDeque, rc::Rc, vec::Vec};
+use nalgebra::Point2;
+use vexide::math::Angle;
+
+use super::sync::Signal;
+use crate::subsystems::tracking::TrackingSubsystem;
+
+/// A drivetrain action finished by itself. The source is the action's name.
+/// Published by the [`Drivetrain`](crate::subsystems::drivetrain::Drivetrain).
+pub const ACTION_SETTLED: &str = "action settled";
+
+/// The number of recent events kept for [`EventBus::recent`] and
+/// [`EventBus::wait_for`].
+const HISTORY_LEN: usize = 32;
+
+/// Something that happened, published to an [`EventBus`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Event {
+    /// What happened, e.g., [`ACTION_SETTLED`]
+    pub name: &'static str,
+    /// What published the event, e.g., the name of a subsystem
+    pub source: &'static str,
+    /// A number describing the event, e.g., the number of pieces held
+    pub value: Option<f64>,
+    pub at: Instant,
+    /// Where the robot was, if the bus has a tracking subsystem
+    pub offset: Option<Point2<f64>>,
+    pub heading: Option<Angle>,
+    /// The number of events published on the bus before this one
+    pub sequence: u64,
+}
+
+/// The id of a subscription, used to unsubscribe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscriptionId(u64);
+
+struct Subscriber {
+    id: SubscriptionId,
+    /// The name of the events to receive, or `None` for every event
+    name: Option<&'static str>,
+    callback: Box<dyn FnMut(&Event)>,
+}
+
+#[derive(Default)]
+struct EventBusInner {
+    subscribers: Vec<Subscriber>,
+    history: VecDeque<Event>,
+    /// Events published while dispatching, waiting to be dispatched
+    queue: VecDeque<Event>,
+    dispatching: bool,
+    /// Subscriptions removed while dispatching
+    unsubscribed: Vec<SubscriptionId>,
+    tracking: Option<TrackingSubsystem>,
+    /// Notified whenever an event is published
+    published_signal: Signal,
+    published: u64,
+    next_id: u64,
+}
+
+/// A publish/subscribe bus for [events](Event). See the
+/// [module documentation](self).
+///
+/// Clones share the same subscribers and history, so every subsystem can
+/// hold one.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    inner: Rc<RefCell<EventBusInner>>,
+}
+
+impl core::fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let inner = self.inner.borrow();
+        f.debug_struct("EventBus")
+            .field("subscribers", &inner.subscribers.len())
+            .field("published", &inner.published)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EventBus {
+    /// Creates a new bus with no subscribers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records where the robot was in every event published from now on.
+    pub fn with_tracking(self, tracking: TrackingSubsystem) -> Self {
+        self.inner.borrow_mut().tracking = Some(tracking);
+        self
+    }
+
+    /// Logs every event published from now on at debug level.
+    pub fn with_logging(self) -> Self {
+        self.subscribe_all(|event| match event.offset {
+            Some(offset) => log::debug!(
+                "Event: {} from {} at ({:.0}, {:.0})",
+                event.name,
+                event.source,
+                offset.x,
+                offset.y
+            ),
+            None => log::debug!("Event: {} from {}", event.name, event.source),
+        });
+        self
+    }
+
+    /// Publishes an event named `name` from `source`.
+    pub fn publish(&self, name: &'static str, source: &'static str) {
+        self.publish_with_value(name, source, None);
+    }
+
+    /// Publishes an event named `name` from `source`, with a number
+    /// describing it.
+    pub fn publish_with_value(&self, name: &'static str, source: &'static str, value: Option<f64>) {
+        let mut inner = self.inner.borrow_mut();
+        let data = inner.tracking.as_ref().map(|tracking| tracking.current());
+        let event = Event {
+            name,
+            source,
+            value,
+            at: Instant::now(),
+            offset: data.map(|data| data.offset),
+            heading: data.map(|data| data.heading),
+            sequence: inner.published,
+        };
+        inner.published += 1;
+        if inner.history.len() == HISTORY_LEN {
+            inner.history.pop_front();
+        }
+        inner.history.push_back(event);
+        inner.queue.push_back(event);
+        let signal = inner.published_signal.clone();
+        if inner.dispatching {
+            // The dispatch further up the stack will get to it
+            drop(inner);
+            signal.notify();
+            return;
+        }
+        inner.dispatching = true;
+        drop(inner);
+        self.dispatch();
+        signal.notify();
+    }
+
+    /// Calls the subscribers for every queued event. The bus isn't borrowed
+    /// while they are called, so they may publish and subscribe themselves.
+    fn dispatch(&self) {
+        loop {
+            let (event, mut subscribers) = {
+                let mut inner = self.inner.borrow_mut();
+                let Some(event) = inner.queue.pop_front() else {
+                    inner.dispatching = false;
+                    inner.unsubscribed.clear();
+                    return;
+                };
+                (event, core::mem::take(&mut inner.subscribers))
+            };
+            for subscriber in &mut subscribers {
+                if subscriber.name.is_none_or(|name| name == event.name) {
+                    (subscriber.callback)(&event);
+                }
+            }
+            let mut inner = self.inner.borrow_mut();
+            // Keep the subscriptions added by the callbacks, after the
+            // existing ones
+            subscribers.append(&mut inner.subscribers);
+            let unsubscribed = core::mem::take(&mut inner.unsubscribed);
+            subscribers.retain(|subscriber| !unsubscribed.contains(&subscriber.id));
+            inner.unsubscribed = unsubscribed;
+            inner.subscribers = subscribers;
+        }
+    }
+
+    /// Calls `callback` with every event named `name` published from now on.
+    pub fn subscribe(
+        &self,
+        name: &'static str,
+        callback: impl FnMut(&Event) + 'static,
+    ) -> SubscriptionId {
+        self.add_subscriber(Some(name), Box::new(callback))
+    }
+
+    /// Calls `callback` with every event published from now on.
+    pub fn subscribe_all(&self, callback: impl FnMut(&Event) + 'static) -> SubscriptionId {
+        self.add_subscriber(None, Box::new(callback))
+    }
+
+    fn add_subscriber(
+        &self,
+        name: Option<&'static str>,
+        callback: Box<dyn FnMut(&Event)>,
+    ) -> SubscriptionId {
+        let mut inner = self.inner.borrow_mut();
+        let id = SubscriptionId(inner.next_id);
+        inner.next_id += 1;
+        inner.subscribers.push(Subscriber { id, name, callback });
+        id
+    }
+
+    /// Stops calling the callback of a subscription.
+    pub fn unsubscribe(&self, id: SubscriptionId) {
+        let mut inner = self.inner.borrow_mut();
+        inner.subscribers.retain(|subscriber| subscriber.id != id);
+        if inner.dispatching {
+            // The subscriber may be taken out for the dispatch
+            inner.unsubscribed.push(id);
+        }
+    }
+
+    /// Returns the most recent event named `name`, if it is still in the
+    /// history of the last 32 events.
+    pub fn last(&self, name: &str) -> Option<Event> {
+        self.inner
+            .borrow()
+            .history
+            .iter()
+            .rev()
+            .find(|event| event.name == name)
+            .copied()
+    }
+
+    /// Returns up to the last 32 events, oldest first.
+    pub fn recent(&self) -> Vec<Event> {
+        self.inner.borrow().history.iter().copied().collect()
+    }
+
+    /// Waits for the next event named `name`, published after this is
+    /// called.
+    pub async fn wait_for(&self, name: &str) -> Event {
+        let after = self.inner.borrow().published;
+        loop {
+            let published = self.inner.borrow().published_signal.notified();
+            if let Some(event) = self
+                .inner
+                .borrow()
+                .history
+                .iter()
+                .find(|event| event.sequence >= after && event.name == name)
+            {
+                return *event;
+            }
+            published.await;
+        }
+    }
+}
@@ -0,0 +1,277 @@
+//! Filters for noisy readings
+//!
+//! Sensor readings and values derived from them, like velocities computed from
+//! changing positions, are noisy. Each filter here takes one sample at a time
+//! and returns the filtered value, and they all implement [`Filter`] so that
+//! one can be swapped for another while tuning:
+//!
+//! - [`Ema`] smooths steady noise with little lag.
+//! - [`MovingAverage`] smooths over a fixed window of samples.
+//! - [`Median`] rejects occasional outliers, e.g., a distance sensor briefly
+//!   seeing past its target.
+//! - [`Debounce`] only lets a condition change once it has held for a while.
+
+use core::time::Duration;
+use std::time::Instant;
+
+/// A filter which takes one sample at a time.
+pub trait Filter<T> {
+    /// Adds a sample and returns the filtered value.
+    fn update(&mut self, sample: T) -> T;
+
+    /// Forgets every sample, e.g., after the reading was lost.
+    fn reset(&mut self);
+}
+
+/// An exponential moving average.
+///
+/// Each sample moves the value `alpha` of the way towards it, so an `alpha`
+/// of 1.0 doesn't filter at all and smaller values smooth more but lag more.
+/// The first sample is taken as is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ema {
+    alpha: f64,
+    value: Option<f64>,
+}
+
+impl Ema {
+    /// Creates a new exponential moving average.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alpha` isn't greater than zero and at most one.
+    pub fn new(alpha: f64) -> Self {
+        let mut ema = Self { alpha, value: None };
+        ema.set_alpha(alpha);
+        ema
+    }
+
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+
+    /// Changes `alpha`, keeping the current value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alpha` isn't greater than zero and at most one.
+    pub fn set_alpha(&mut self, alpha: f64) {
+        assert!(
+            alpha > 0.0 && alpha <= 1.0,
+            "EMA alpha must be in (0, 1], got {}",
+            alpha
+        );
+        self.alpha = alpha;
+    }
+
+    /// Returns the filtered value, or `None` if there haven't been any
+    /// samples.
+    pub fn value(&self) -> Option<f64> {
+        self.value
+    }
+}
+
+impl Filter<f64> for Ema {
+    fn update(&mut self, sample: f64) -> f64 {
+        let value = match self.value {
+            Some(value) => value + self.alpha * (sample - value),
+            None => sample,
+        };
+        self.value = Some(value);
+        value
+    }
+
+    fn reset(&mut self) {
+        self.value = None;
+    }
+}
+
+/// The last `N` samples, oldest overwritten first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Window<const N: usize> {
+    samples: [f64; N],
+    len: usize,
+    next: usize,
+}
+
+impl<const N: usize> Window<N> {
+    fn new() -> Self {
+        assert!(N > 0, "A filter window must hold at least one sample");
+        Self {
+            samples: [0.0; N],
+            len: 0,
+            next: 0,
+        }
+    }
+
+    fn push(&mut self, sample: f64) {
+        self.samples[self.next] = sample;
+        self.next = (self.next + 1) % N;
+        self.len = (self.len + 1).min(N);
+    }
+
+    /// Returns the samples in the window, in no particular order.
+    fn samples(&self) -> &[f64] {
+        &self.samples[..self.len]
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+        self.next = 0;
+    }
+}
+
+/// The average of the last `N` samples.
+///
+/// Until `N` samples have been added, the average is over the samples so far.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MovingAverage<const N: usize> {
+    window: Window<N>,
+}
+
+impl<const N: usize> MovingAverage<N> {
+    /// Creates a new moving average.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is zero.
+    pub fn new() -> Self {
+        Self {
+            window: Window::new(),
+        }
+    }
+}
+
+impl<const N: usize> Default for MovingAverage<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Filter<f64> for MovingAverage<N> {
+    fn update(&mut self, sample: f64) -> f64 {
+        self.window.push(sample);
+        let samples = self.window.samples();
+        samples.iter().sum::<f64>() / samples.len() as f64
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+    }
+}
+
+/// The median of the last `N` samples.
+///
+/// Outliers are ignored entirely as long as fewer than half of the samples
+/// are outliers. `N` should be odd; with an even number of samples, the two
+/// middle samples are averaged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Median<const N: usize> {
+    window: Window<N>,
+}
+
+impl<const N: usize> Median<N> {
+    /// Creates a new median filter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is zero.
+    pub fn new() -> Self {
+        Self {
+            window: Window::new(),
+        }
+    }
+}
+
+impl<const N: usize> Default for Median<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Filter<f64> for Median<N> {
+    fn update(&mut self, sample: f64) -> f64 {
+        self.window.push(sample);
+        let mut sorted = [0.0; N];
+        let sorted = &mut sorted[..self.window.len];
+        sorted.copy_from_slice(self.window.samples());
+        sorted.sort_unstable_by(f64::total_cmp);
+        let middle = sorted.len() / 2;
+        if sorted.len() % 2 == 1 {
+            sorted[middle]
+        } else {
+            (sorted[middle - 1] + sorted[middle]) / 2.0
+        }
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+    }
+}
+
+/// A condition which only changes once the new value has held for a while.
+///
+/// For example, a current being above a threshold for 150 ms, rather than
+/// for a single noisy sample. The condition can be made to fall sooner than
+/// it rises with [`with_falling_duration`](Self::with_falling_duration).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Debounce {
+    duration: Duration,
+    /// How long `false` must hold for the condition to fall
+    falling_duration: Duration,
+    value: bool,
+    /// When the samples first differed from `value`
+    changing_since: Option<Instant>,
+}
+
+impl Debounce {
+    /// Creates a new debounced condition which starts `false`.
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            falling_duration: duration,
+            value: false,
+            changing_since: None,
+        }
+    }
+
+    /// Sets how long `false` must hold for the condition to fall, instead of
+    /// the same duration as it takes to rise. With [`Duration::ZERO`], the
+    /// condition falls on the first `false` sample, so it is only true after
+    /// the whole duration of continuous `true` samples.
+    pub fn with_falling_duration(mut self, falling_duration: Duration) -> Self {
+        self.falling_duration = falling_duration;
+        self
+    }
+
+    /// Returns the debounced value.
+    pub fn value(&self) -> bool {
+        self.value
+    }
+}
+
+impl Filter<bool> for Debounce {
+    fn update(&mut self, sample: bool) -> bool {
+        if sample == self.value {
+            self.changing_since = None;
+        } else {
+            let since = *self.changing_since.get_or_insert_with(Instant::now);
+            let duration = if sample {
+                self.duration
+            } else {
+                self.falling_duration
+            };
+            if since.elapsed() >= duration {
+                self.value = sample;
+                self.changing_since = None;
+            }
+        }
+        self.value
+    }
+
+    /// Returns the condition to `false`.
+    fn reset(&mut self) {
+        self.value = false;
+        self.changing_since = None;
+    }
+}
@@ -0,0 +1,100 @@
+//! 2D geometry helpers
+//!
+//! Line segments are given by their endpoints, and positions along a segment
+//! by a parameter `t`, where 0.0 is the start and 1.0 is the end. This is
+//! the same convention as [`Path`](crate::path_planner::Path), so a segment's
+//! `t` maps directly onto a piecewise-linear path.
+
+use nalgebra::Point2;
+
+/// Returns the parameter of the point on the infinite line through `start`
+/// and `end` closest to `point`. It is outside [0, 1] when the closest point
+/// is beyond either end.
+///
+/// If `start` and `end` are the same point, this returns 0.0.
+pub fn project_onto_line(point: Point2<f64>, start: Point2<f64>, end: Point2<f64>) -> f64 {
+    let direction = end - start;
+    let length_squared = direction.norm_squared();
+    if length_squared == 0.0 {
+        return 0.0;
+    }
+    (point - start).dot(&direction) / length_squared
+}
+
+/// Returns the parameter of the point on the segment closest to `point`.
+pub fn project_onto_segment(point: Point2<f64>, start: Point2<f64>, end: Point2<f64>) -> f64 {
+    project_onto_line(point, start, end).clamp(0.0, 1.0)
+}
+
+/// Returns the point at parameter `t` along the segment.
+pub fn point_on_segment(start: Point2<f64>, end: Point2<f64>, t: f64) -> Point2<f64> {
+    start + (end - start) * t
+}
+
+/// Returns the point on the segment closest to `point`.
+pub fn closest_point_on_segment(
+    point: Point2<f64>,
+    start: Point2<f64>,
+    end: Point2<f64>,
+) -> Point2<f64> {
+    point_on_segment(start, end, project_onto_segment(point, start, end))
+}
+
+/// Returns the distance from `point` to the closest point on the segment.
+pub fn distance_to_segment(point: Point2<f64>, start: Point2<f64>, end: Point2<f64>) -> f64 {
+    nalgebra::distance(&point, &closest_point_on_segment(point, start, end))
+}
+
+/// Returns where two segments cross, or `None` if they don't. Parallel
+/// segments are never considered to cross, even if they overlap.
+pub fn segment_intersection(
+    a_start: Point2<f64>,
+    a_end: Point2<f64>,
+    b_start: Point2<f64>,
+    b_end: Point2<f64>,
+) -> Option<Point2<f64>> {
+    let a = a_end - a_start;
+    let b = b_end - b_start;
+    let denominator = a.perp(&b);
+    if denominator == 0.0 {
+        return None;
+    }
+    let offset = b_start - a_start;
+    let t = offset.perp(&b) / denominator;
+    let u = offset.perp(&a) / denominator;
+    ((0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u))
+        .then(|| point_on_segment(a_start, a_end, t))
+}
+
+/// Returns the parameters of the points where the segment crosses the circle,
+/// in order from `start` to `end`.
+///
+/// A segment which only touches the circle crosses it once, and a segment
+/// which is entirely inside or outside it doesn't cross it at all.
+pub fn circle_segment_intersections(
+    center: Point2<f64>,
+    radius: f64,
+    start: Point2<f64>,
+    end: Point2<f64>,
+) -> impl Iterator<Item = f64> {
+    // Solve |start + t * direction - center| = radius for t
+    let direction = end - start;
+    let offset = start - center;
+    let a = direction.norm_squared();
+    let b = 2.0 * offset.dot(&direction);
+    let c = offset.norm_squared() - radius * radius;
+    let discriminant = b * b - 4.0 * a * c;
+
+    let roots = if a == 0.0 || discriminant < 0.0 {
+        [None, None]
+    } else if discriminant == 0.0 {
+        [Some(-b / (2.0 * a)), None]
+    } else {
+        let root = discriminant.sqrt();
+        [Some((-b - root) / (2.0 * a)), Some((-b + root) / (2.0 * a))]
+    };
+    roots
+        .into_iter()
+        .flatten()
+        .filter(|t| (0.0..=1.0).contains(t))
+}
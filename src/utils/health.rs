@@ -0,0 +1,245 @@
+//! Motor temperature trends
+//!
+//! V5 motors start limiting their current at 55 °C, so a drivetrain which
+//! was run hard before the match may not have the power for a long route.
+//! A [`MotorHealth`] samples the temperatures of motor groups over time and
+//! estimates when each motor will reach the limit, so the HUD and the auton
+//! selector can warn the drive team in time to pick a shorter route:
+//!
+//! ```ignore
+//! let health = MotorHealth::new()
+//!     .with_drive_group("left", left.clone())
+//!     .with_drive_group("right", right.clone())
+//!     .with_group("intake", intake.clone());
+//! hud = hud.with_field(2, HudField::thermal(health.clone()));
+//! screen = screen.with_page(AutonPreview::new(autons, field).with_health(health));
+//! ```
+//!
+//! Temperatures are sampled whenever the model is read, at most every few
+//! seconds, so anything showing it keeps it up to date.
+
+use core::{cell::RefCell, time::Duration};
+use std::time::Instant;
+
+use alloc::{collections::VecDeque, format, rc::Rc, string::String, vec::Vec};
+
+use crate::motorgroup::DoxaMotorGroup;
+
+/// The temperature, in °C, at which V5 motors start limiting their current.
+pub const THERMAL_LIMIT: f64 = 55.0;
+
+/// How often temperatures are sampled.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How many samples are kept per motor, i.e., the window of the trend.
+const SAMPLES: usize = 12;
+
+/// How soon a drive motor must be expected to reach the limit to be warned
+/// about: about the length of a match.
+const WARNING_HORIZON: Duration = Duration::from_secs(120);
+
+/// The temperature and trend of one motor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotorThermal {
+    /// The label of the motor's group
+    pub group: &'static str,
+    pub port: u8,
+    /// Whether the motor is part of the drivetrain
+    pub drive: bool,
+    /// In °C
+    pub temperature: f64,
+    /// How fast the temperature is rising, in °C per minute, or `None`
+    /// before there are two samples
+    pub rate: Option<f64>,
+}
+
+impl MotorThermal {
+    /// Returns how long until the motor reaches [`THERMAL_LIMIT`] at its
+    /// current rate: zero if it already has, or `None` if it isn't warming
+    /// up.
+    pub fn time_to_limit(&self) -> Option<Duration> {
+        if self.temperature >= THERMAL_LIMIT {
+            return Some(Duration::ZERO);
+        }
+        let rate = self.rate.filter(|rate| *rate > 0.0)?;
+        Some(Duration::from_secs_f64(
+            (THERMAL_LIMIT - self.temperature) / rate * 60.0,
+        ))
+    }
+
+    /// Returns whether the motor is likely to limit its current within
+    /// about a match.
+    pub fn at_risk(&self) -> bool {
+        self.time_to_limit()
+            .is_some_and(|time| time <= WARNING_HORIZON)
+    }
+}
+
+struct Group {
+    label: &'static str,
+    motors: DoxaMotorGroup,
+    drive: bool,
+}
+
+/// Temperature samples of one motor, oldest first.
+type Samples = VecDeque<(Instant, f64)>;
+
+#[derive(Default)]
+struct Inner {
+    groups: Vec<Group>,
+    /// The index of each motor's group, its port, and its samples
+    samples: Vec<(usize, u8, Samples)>,
+    last_sample: Option<Instant>,
+}
+
+/// A shared model of the temperatures of motor groups. See the
+/// [module documentation](self).
+///
+/// Clones share the same groups and samples.
+#[derive(Clone, Default)]
+pub struct MotorHealth {
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl core::fmt::Debug for MotorHealth {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let inner = self.inner.borrow();
+        f.debug_struct("MotorHealth")
+            .field(
+                "groups",
+                &inner
+                    .groups
+                    .iter()
+                    .map(|group| group.label)
+                    .collect::<Vec<_>>(),
+            )
+            .field("last_sample", &inner.last_sample)
+            .finish()
+    }
+}
+
+impl MotorHealth {
+    /// Creates a model with no groups.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a group of drivetrain motors.
+    pub fn with_drive_group(self, label: &'static str, motors: DoxaMotorGroup) -> Self {
+        self.add_group(label, motors, true);
+        self
+    }
+
+    /// Adds a group of other motors, which are shown but not warned about.
+    pub fn with_group(self, label: &'static str, motors: DoxaMotorGroup) -> Self {
+        self.add_group(label, motors, false);
+        self
+    }
+
+    fn add_group(&self, label: &'static str, motors: DoxaMotorGroup, drive: bool) {
+        let mut inner = self.inner.borrow_mut();
+        inner.groups.push(Group {
+            label,
+            motors,
+            drive,
+        });
+        // Sample the new group straight away
+        inner.last_sample = None;
+    }
+
+    /// Samples the temperatures if the last sample is old enough.
+    fn sample(&self) {
+        let mut inner = self.inner.borrow_mut();
+        let now = Instant::now();
+        if inner
+            .last_sample
+            .is_some_and(|last| now.duration_since(last) < SAMPLE_INTERVAL)
+        {
+            return;
+        }
+        inner.last_sample = Some(now);
+        let readings: Vec<(usize, u8, f64)> = inner
+            .groups
+            .iter()
+            .enumerate()
+            .flat_map(|(index, group)| {
+                group
+                    .motors
+                    .telemetry()
+                    .into_iter()
+                    .map(move |motor| (index, motor))
+            })
+            .filter_map(|(index, motor)| Some((index, motor.port, motor.temperature?)))
+            .collect();
+        for (group, port, temperature) in readings {
+            let samples = match inner
+                .samples
+                .iter()
+                .position(|(g, p, _)| *g == group && *p == port)
+            {
+                Some(index) => &mut inner.samples[index].2,
+                None => {
+                    inner.samples.push((group, port, VecDeque::new()));
+                    &mut inner.samples.last_mut().unwrap().2
+                }
+            };
+            if samples.len() == SAMPLES {
+                samples.pop_front();
+            }
+            samples.push_back((now, temperature));
+        }
+    }
+
+    /// Returns the temperature and trend of every motor which could be read.
+    pub fn motors(&self) -> Vec<MotorThermal> {
+        self.sample();
+        let inner = self.inner.borrow();
+        inner
+            .samples
+            .iter()
+            .filter_map(|(group, port, samples)| {
+                let group = &inner.groups[*group];
+                let &(last_at, temperature) = samples.back()?;
+                let rate = samples.front().and_then(|&(first_at, first)| {
+                    let minutes = last_at.duration_since(first_at).as_secs_f64() / 60.0;
+                    (minutes > 0.0).then(|| (temperature - first) / minutes)
+                });
+                Some(MotorThermal {
+                    group: group.label,
+                    port: *port,
+                    drive: group.drive,
+                    temperature,
+                    rate,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the drive motor which will reach the limit soonest, if any is
+    /// likely to within about a match.
+    pub fn drive_warning(&self) -> Option<MotorThermal> {
+        self.motors()
+            .into_iter()
+            .filter(|motor| motor.drive && motor.at_risk())
+            .min_by_key(|motor| motor.time_to_limit())
+    }
+
+    /// Returns a short warning about the drive motor at most risk, e.g.,
+    /// `left P3 52C, limit in 40s`, or `None` if there is none.
+    pub fn drive_warning_text(&self) -> Option<String> {
+        let motor = self.drive_warning()?;
+        Some(match motor.time_to_limit() {
+            Some(time) if !time.is_zero() => format!(
+                "{} P{} {:.0}C, limit in {}s",
+                motor.group,
+                motor.port,
+                motor.temperature,
+                time.as_secs()
+            ),
+            _ => format!(
+                "{} P{} {:.0}C, limiting",
+                motor.group, motor.port, motor.temperature
+            ),
+        })
+    }
+}
@@ -1,87 +1,690 @@
-use alloc::{boxed::Box, sync::Arc};
-use core::time::Duration;
+//! Logging to stdout and the SD card
+//!
+//! Each run logs to a new file, so that runs aren't interleaved in one file
+//! which grows forever. The brain can't delete files, so the last few runs
+//! are kept by writing to a fixed set of numbered files in turn, e.g.,
+//! `log-0.txt` to `log-4.txt` for a path of `log.txt`, overwriting the
+//! oldest. A file next to them, `log.seq`, records the run number; each file
+//! starts with the run it belongs to.
+//!
+//! # Levels
+//!
+//! Besides the default level, each module can have its own, e.g., to silence
+//! tracking while tuning actions at debug level. A module's level applies to
+//! it and its submodules, and the most specific module wins. Modules are
+//! matched on whole path segments, so `tracking` matches
+//! `libdoxa::subsystems::tracking` and `libdoxa::subsystems::tracking::wheel`.
+//! Levels can be set in [`LoggerConfig`] or changed at any time with
+//! [`set_level`] and [`set_module_level`].
+//!
+//! # defmt
+//!
+//! With the `defmt` feature, records go over the serial link as
+//! [defmt](https://defmt.ferrous-systems.com) frames instead of text, for
+//! host tools like `defmt-print`. Like text, `log` records are queued and
+//! encoded by the logger task, so logging never waits on the serial link;
+//! their messages are still formatted, and are sent as strings. The
+//! library's own control loop messages and [telemetry](super::telemetry) use
+//! native defmt format strings instead, so the host only receives an
+//! interned index and the raw arguments. Frames are queued too, and dropped
+//! whole if the queue is full or a frame is started while another is being
+//! encoded. The log files on the SD card are still text. The feature
+//! installs the defmt global logger, so the program must link with
+//! `-Tdefmt.x` and must not install another one.
+
+use alloc::{boxed::Box, collections::VecDeque, format, string::String, vec::Vec};
+use core::{
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Duration,
+};
 use std::{
     fs::File,
     io::{Write, stdout},
 };
 
-use log::{Level, Metadata, Record, SetLoggerError, error};
-use vexide::{prelude::spawn, sync::Mutex};
+use log::{Level, LevelFilter, Metadata, Record, SetLoggerError};
+use vexide::prelude::spawn;
 
-struct SimpleLogger {
-    file: Arc<Mutex<Option<File>>>,
-    start_time: std::time::Instant,
+/// The number of recent log lines kept in memory for [`recent_lines`] and
+/// [`recent_records`].
+const HISTORY_LEN: usize = 64;
+
+/// The number of records which can wait to be written before the oldest are
+/// dropped.
+const PENDING_LEN: usize = 256;
+
+/// How often pending records are written out.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The default number of log files kept.
+const DEFAULT_KEEP: usize = 5;
+
+/// The most recent log lines and their levels, oldest first.
+static HISTORY: std::sync::Mutex<VecDeque<(Level, String)>> =
+    std::sync::Mutex::new(VecDeque::new());
+
+/// The default level and the level of each module.
+static LEVELS: std::sync::Mutex<Levels> = std::sync::Mutex::new(Levels {
+    default: LevelFilter::Debug,
+    modules: Vec::new(),
+});
+
+/// Records waiting to be written, oldest first.
+static PENDING: std::sync::Mutex<VecDeque<Pending>> = std::sync::Mutex::new(VecDeque::new());
+
+/// The number of records dropped since the last flush.
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether records are only for the log file, because they were already sent
+/// over the serial link as native defmt frames by [`native_log`].
+static FILE_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// The number of error-level records logged.
+static ERRORS: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether a failed write to stdout has been logged, so that a broken serial
+/// link is only reported once.
+static CONSOLE_ERROR_LOGGED: AtomicBool = AtomicBool::new(false);
+
+/// Returns up to `count` of the most recent log lines, oldest first.
+///
+/// At most 64 lines are kept. This never blocks; if the history is in use
+/// (e.g., when called from a panic inside the logger), it returns nothing.
+pub fn recent_lines(count: usize) -> Vec<String> {
+    recent_records(count)
+        .into_iter()
+        .map(|(_, line)| line)
+        .collect()
 }
-// Safety: The brain is single-threaded. Hopefully.
-unsafe impl core::marker::Sync for SimpleLogger {}
-unsafe impl core::marker::Send for SimpleLogger {}
 
-impl SimpleLogger {
-    pub fn new(file: &str) -> Self {
-        Self {
-            // We're being very unsafe here.
-            #[allow(clippy::arc_with_non_send_sync)]
-            file: Arc::new(Mutex::new(
-                File::options().append(true).create(true).open(file).ok(),
-            )),
-            start_time: std::time::Instant::now(),
+/// Returns up to `count` of the most recent log lines with their levels,
+/// oldest first, e.g., to color them on a screen.
+///
+/// Like [`recent_lines`], this never blocks.
+pub fn recent_records(count: usize) -> Vec<(Level, String)> {
+    match HISTORY.try_lock() {
+        Ok(history) => history
+            .iter()
+            .skip(history.len().saturating_sub(count))
+            .cloned()
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Returns the number of error-level records logged so far, e.g., to flush
+/// other files to the SD card when something goes wrong.
+pub(crate) fn error_count() -> usize {
+    ERRORS.load(Ordering::Relaxed)
+}
+
+/// Logs a record through `log` which only goes to the log file, not the
+/// serial link. Used by [`native_log`].
+#[cfg(feature = "defmt")]
+pub(crate) fn file_only(log: impl FnOnce()) {
+    FILE_ONLY.store(true, Ordering::Relaxed);
+    log();
+    FILE_ONLY.store(false, Ordering::Relaxed);
+}
+
+/// Logs from a control loop with a native defmt format string, so that with
+/// the `defmt` feature the message is interned and its arguments are sent
+/// unformatted. The `log` format string is used for the log file, and for
+/// the console without the feature:
+///
+/// ```ignore
+/// native_log!(warn, "Obstacle {=f64} mm ahead", "Obstacle {:.0} mm ahead", distance);
+/// ```
+///
+/// Native frames are filtered by defmt's `DEFMT_LOG` at compile time rather
+/// than by the logger's levels, which still apply to the log file.
+macro_rules! native_log {
+    ($level:ident, $defmt:literal, $log:literal $(, $arg:expr)* $(,)?) => {{
+        #[cfg(feature = "defmt")]
+        {
+            defmt::$level!($defmt $(, $arg)*);
+            $crate::utils::logger::file_only(|| log::$level!($log $(, $arg)*));
+        }
+        #[cfg(not(feature = "defmt"))]
+        log::$level!($log $(, $arg)*);
+    }};
+}
+pub(crate) use native_log;
+
+/// Sets the level of modules without their own level.
+pub fn set_level(level: LevelFilter) {
+    if let Ok(mut levels) = LEVELS.lock() {
+        levels.default = level;
+        levels.apply();
+    }
+}
+
+/// Sets the level of `module` and its submodules.
+pub fn set_module_level(module: &str, level: LevelFilter) {
+    if let Ok(mut levels) = LEVELS.lock() {
+        match levels.modules.iter_mut().find(|(name, _)| name == module) {
+            Some((_, existing)) => *existing = level,
+            None => levels.modules.push((String::from(module), level)),
         }
+        levels.apply();
     }
 }
 
+/// Returns `module` to the default level.
+pub fn clear_module_level(module: &str) {
+    if let Ok(mut levels) = LEVELS.lock() {
+        levels.modules.retain(|(name, _)| name != module);
+        levels.apply();
+    }
+}
+
+struct Levels {
+    default: LevelFilter,
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl Levels {
+    /// Returns the level for a record from `target`.
+    fn level(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .filter(|(module, _)| matches_module(target, module))
+            .max_by_key(|(module, _)| module.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    /// Lets through records up to the most verbose level in use, so that
+    /// the `log` macros can skip the rest without calling the logger.
+    fn apply(&self) {
+        let max = self
+            .modules
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max);
+        log::set_max_level(max);
+    }
+}
+
+/// Returns whether `target` is `module` or one of its submodules, where
+/// `module` may leave out leading path segments.
+fn matches_module(target: &str, module: &str) -> bool {
+    let starts = core::iter::once(0).chain(target.match_indices("::").map(|(index, _)| index + 2));
+    for start in starts {
+        if let Some(rest) = target[start..].strip_prefix(module)
+            && (rest.is_empty() || rest.starts_with("::"))
+        {
+            return true;
+        }
+    }
+    false
+}
+
+/// A formatted record waiting to be written.
+struct Pending {
+    level: Level,
+    #[cfg(feature = "defmt")]
+    module: &'static str,
+    message: String,
+    file: String,
+    /// Whether the record goes to the serial link as well as the file
+    serial: bool,
+}
+
+/// A logger which only formats records in [`log`](log::Log::log), leaving the
+/// I/O to a background task so that logging from a control loop is cheap.
+///
+/// Records are queued in a ring buffer of [`PENDING_LEN`] records. If the
+/// task falls behind, the oldest are dropped, and the task logs how many.
+struct SimpleLogger {
+    start_time: std::time::Instant,
+}
+
 impl log::Log for SimpleLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= Level::Debug
+        match LEVELS.try_lock() {
+            Ok(levels) => metadata.level() <= levels.level(metadata.target()),
+            Err(_) => false,
+        }
     }
 
     fn log(&self, record: &Record) {
         if self.enabled(record.metadata()) {
-            // Only write to stdout if we're not connected to the competition field control
-            // If we're connected to the field control, writing to stdout doesn't go
-            // anywhere and is a waste of time.
-            if !matches!(
-                vexide::competition::system(),
-                Some(vexide::competition::CompetitionSystem::FieldControl)
-            ) {
-                println!("{:<5} - {}", record.level(), record.args());
+            if record.level() == Level::Error {
+                ERRORS.fetch_add(1, Ordering::Relaxed);
+            }
+            let elapsed = self.start_time.elapsed();
+            let timestamp = format!("{:>3}.{:03}", elapsed.as_secs(), elapsed.subsec_millis());
+            let message = format!("{}", record.args());
+            if let Ok(mut history) = HISTORY.try_lock() {
+                if history.len() == HISTORY_LEN {
+                    history.pop_front();
+                }
+                history.push_back((
+                    record.level(),
+                    format!("{} {:<5} {}", timestamp, record.level(), message),
+                ));
             }
-            if let Some(mut guard) = self.file.try_lock() {
-                if let Some(file) = guard.as_mut() {
-                    _ = writeln!(
-                        file,
-                        "{:>3}.{:03} {:<5} {:<52} - {}",
-                        self.start_time.elapsed().as_secs(),
-                        self.start_time.elapsed().subsec_millis(),
-                        record.level(),
-                        record.module_path().unwrap_or("<unknown>"),
-                        record.args()
-                    )
+            let module = record.module_path_static().unwrap_or("<unknown>");
+            let pending = Pending {
+                level: record.level(),
+                #[cfg(feature = "defmt")]
+                module,
+                file: format!(
+                    "{} {:<5} {:<52} - {}",
+                    timestamp,
+                    record.level(),
+                    module,
+                    message
+                ),
+                message,
+                serial: !FILE_ONLY.load(Ordering::Relaxed),
+            };
+            match PENDING.try_lock() {
+                Ok(mut queue) => {
+                    if queue.len() == PENDING_LEN {
+                        queue.pop_front();
+                        DROPPED.fetch_add(1, Ordering::Relaxed);
+                    }
+                    queue.push_back(pending);
+                }
+                Err(_) => {
+                    DROPPED.fetch_add(1, Ordering::Relaxed);
                 }
-            } else {
-                spawn(async {
-                    vexide::time::sleep(Duration::from_millis(100)).await;
-                    error!("Failed to lock log file (slept 100ms). 1 message was ignored.");
-                })
-                .detach();
             }
+        }
+    }
+
+    /// Does nothing; records are written by the background task.
+    fn flush(&self) {}
+}
+
+/// The log files on the SD card. See the [module documentation](self).
+///
+/// [`telemetry`](super::telemetry) uses these for its CSV files too, without
+/// the banner line.
+pub(crate) struct LogFiles {
+    stem: String,
+    extension: Option<String>,
+    keep: usize,
+    max_size: Option<u64>,
+    sequence: u64,
+    file: Option<File>,
+    /// The path of the current file
+    path: String,
+    written: u64,
+    /// Whether each file starts with the run number
+    banner: bool,
+    /// Whether an I/O error has been logged, so that a failing SD card is
+    /// only reported once rather than on every write
+    error_logged: bool,
+}
+
+impl LogFiles {
+    pub(crate) fn new(path: &str, keep: usize, max_size: Option<u64>, banner: bool) -> Self {
+        let (stem, extension) = match path.rsplit_once('.') {
+            Some((stem, extension)) if !stem.is_empty() && !extension.contains('/') => {
+                (String::from(stem), Some(String::from(extension)))
+            }
+            _ => (String::from(path), None),
+        };
+        let sequence = std::fs::read_to_string(format!("{}.seq", stem))
+            .ok()
+            .and_then(|sequence| sequence.trim().parse().ok())
+            .unwrap_or(0);
+        let mut files = Self {
+            stem,
+            extension,
+            keep: keep.max(1),
+            max_size,
+            sequence,
+            file: None,
+            path: String::new(),
+            written: 0,
+            banner,
+            error_logged: false,
+        };
+        files.open_next();
+        files
+    }
+
+    /// Moves on to the next file, overwriting it.
+    fn open_next(&mut self) {
+        self.sequence += 1;
+        let seq_path = format!("{}.seq", self.stem);
+        let result = std::fs::write(&seq_path, format!("{}", self.sequence));
+        self.check(&seq_path, result);
+        let slot = self.sequence % self.keep as u64;
+        self.path = match &self.extension {
+            Some(extension) => format!("{}-{}.{}", self.stem, slot, extension),
+            None => format!("{}-{}", self.stem, slot),
+        };
+        self.file = match File::create(&self.path) {
+            Ok(file) => Some(file),
+            Err(err) => {
+                self.check_current(Err(err));
+                None
+            }
+        };
+        self.written = 0;
+        if self.banner {
+            self.write_line(&format!("=== Log {} ===", self.sequence));
+        }
+    }
+
+    fn write_line(&mut self, line: &str) {
+        let Some(file) = self.file.as_mut() else {
+            return;
+        };
+        let result = writeln!(file, "{}", line);
+        if result.is_ok() {
+            self.written += line.len() as u64 + 1;
+        }
+        self.check_current(result);
+    }
+
+    /// Logs the first I/O error, on `path`. Later errors are ignored, since
+    /// the card usually stays broken.
+    fn check(&mut self, path: &str, result: std::io::Result<()>) {
+        if let Err(err) = result
+            && !self.error_logged
+        {
+            self.error_logged = true;
+            log::error!("Failed to write {}: {}", path, err);
+        }
+    }
+
+    /// Like [`check`](Self::check), for the current file.
+    fn check_current(&mut self, result: std::io::Result<()>) {
+        if result.is_err() {
+            let path = self.path.clone();
+            self.check(&path, result);
+        }
+    }
+
+    /// Writes a line, moving on to the next file first if this one is full.
+    pub(crate) fn write(&mut self, line: &str) {
+        if self
+            .max_size
+            .is_some_and(|max_size| self.written + line.len() as u64 + 1 > max_size)
+        {
             self.flush();
+            self.open_next();
+        }
+        self.write_line(line);
+    }
+
+    pub(crate) fn flush(&mut self) {
+        if let Some(file) = self.file.as_mut() {
+            let result = file.flush();
+            self.check_current(result);
+        }
+    }
+}
+
+/// Writes every pending record to stdout and `files`.
+fn write_pending(files: &mut LogFiles) {
+    let queue = match PENDING.lock() {
+        Ok(mut queue) => core::mem::replace(&mut *queue, VecDeque::with_capacity(PENDING_LEN)),
+        Err(_) => return,
+    };
+    let dropped = DROPPED.swap(0, Ordering::Relaxed);
+    // Telemetry and native defmt frames may be waiting even without records
+    if queue.is_empty() && dropped == 0 && !cfg!(feature = "defmt") {
+        return;
+    }
+
+    // Only write to stdout if we're not connected to the competition field control
+    // If we're connected to the field control, writing to stdout doesn't go
+    // anywhere and is a waste of time.
+    let console = !matches!(
+        vexide::competition::system(),
+        Some(vexide::competition::CompetitionSystem::FieldControl)
+    );
+    // With defmt, records go over serial as frames, and text would corrupt
+    // the stream
+    let text = console && !cfg!(feature = "defmt");
+    let mut out = stdout();
+    let mut console_result = Ok(());
+    if dropped > 0 {
+        let message = format!(
+            "WARN  - Logger fell behind; {} messages were dropped",
+            dropped
+        );
+        if text {
+            console_result = console_result.and_then(|_| writeln!(out, "{}", message));
+        }
+        #[cfg(feature = "defmt")]
+        defmt::warn!(
+            "Logger fell behind; {=usize} messages were dropped",
+            dropped
+        );
+        files.write(&message);
+    }
+    for pending in queue {
+        if text && pending.serial {
+            console_result = console_result
+                .and_then(|_| writeln!(out, "{:<5} - {}", pending.level, pending.message));
+        }
+        #[cfg(feature = "defmt")]
+        if pending.serial {
+            serial::encode(pending.level, pending.module, &pending.message);
         }
+        files.write(&pending.file);
     }
+    #[cfg(feature = "defmt")]
+    {
+        console_result =
+            console_result.and_then(|_| serial::write_queued(console.then_some(&mut out)));
+    }
+    if console {
+        console_result = console_result.and_then(|_| out.flush());
+    }
+    files.flush();
+    if let Err(err) = console_result
+        && !CONSOLE_ERROR_LOGGED.swap(true, Ordering::Relaxed)
+    {
+        log::error!("Failed to write the log to stdout: {}", err);
+    }
+}
+
+/// The defmt global logger, which queues frames for the logger task to write
+/// to the serial link. See the [module documentation](self).
+#[cfg(feature = "defmt")]
+pub(crate) mod serial {
+    use core::{
+        cell::UnsafeCell,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+    use std::{collections::VecDeque, io::Write, sync::OnceLock, time::Instant};
+
+    use alloc::vec::Vec;
+    use log::Level;
+
+    /// The most bytes of encoded frames waiting to be written. Frames which
+    /// don't fit are dropped whole.
+    const QUEUE_CAPACITY: usize = 8 * 1024;
+
+    /// When the first frame was timestamped
+    static START: OnceLock<Instant> = OnceLock::new();
+
+    /// How many times the logger is acquired. Only the outermost frame is
+    /// encoded; a frame started while another is being written, e.g., from a
+    /// panic inside a `Format` implementation, is dropped.
+    static DEPTH: AtomicUsize = AtomicUsize::new(0);
 
-    fn flush(&self) {
-        let file = self.file.clone();
+    /// Encoded frames waiting to be written, oldest first.
+    static QUEUE: std::sync::Mutex<VecDeque<u8>> = std::sync::Mutex::new(VecDeque::new());
+
+    /// The number of frames dropped since the last write.
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    /// The encoder and the frame being encoded.
+    struct FrameCell(UnsafeCell<(defmt::Encoder, Vec<u8>)>);
+
+    // SAFETY: the frame is only used by the outermost acquisition, between
+    // `acquire` and `release`, and vexide is single-threaded
+    unsafe impl Sync for FrameCell {}
+
+    static FRAME: FrameCell = FrameCell(UnsafeCell::new((defmt::Encoder::new(), Vec::new())));
+
+    defmt::timestamp!(
+        "{=u64:ms}",
+        START.get_or_init(Instant::now).elapsed().as_millis() as u64
+    );
+
+    #[defmt::global_logger]
+    struct SerialLogger;
+
+    unsafe impl defmt::Logger for SerialLogger {
+        fn acquire() {
+            if DEPTH.fetch_add(1, Ordering::Acquire) > 0 {
+                return;
+            }
+            // SAFETY: this is the outermost acquisition
+            let (encoder, frame) = unsafe { &mut *FRAME.0.get() };
+            frame.clear();
+            encoder.start_frame(|bytes| frame.extend_from_slice(bytes));
+        }
+
+        /// Does nothing; frames are written by the logger task.
+        unsafe fn flush() {}
+
+        unsafe fn release() {
+            if DEPTH.fetch_sub(1, Ordering::Release) > 1 {
+                return;
+            }
+            // SAFETY: this is the outermost acquisition
+            let (encoder, frame) = unsafe { &mut *FRAME.0.get() };
+            encoder.end_frame(|bytes| frame.extend_from_slice(bytes));
+            match QUEUE.try_lock() {
+                Ok(mut queue) if queue.len() + frame.len() <= QUEUE_CAPACITY => {
+                    queue.extend(frame.iter());
+                }
+                _ => {
+                    DROPPED.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+
+        unsafe fn write(bytes: &[u8]) {
+            if DEPTH.load(Ordering::Relaxed) > 1 {
+                return;
+            }
+            // SAFETY: the caller acquired the logger, and this is the
+            // outermost acquisition
+            let (encoder, frame) = unsafe { &mut *FRAME.0.get() };
+            encoder.write(bytes, |bytes| frame.extend_from_slice(bytes));
+        }
+    }
+
+    /// Encodes a queued `log` record as a defmt frame.
+    pub(super) fn encode(level: Level, module: &str, message: &str) {
+        match level {
+            Level::Error => defmt::error!("{=str}: {=str}", module, message),
+            Level::Warn => defmt::warn!("{=str}: {=str}", module, message),
+            Level::Info => defmt::info!("{=str}: {=str}", module, message),
+            Level::Debug => defmt::debug!("{=str}: {=str}", module, message),
+            Level::Trace => defmt::trace!("{=str}: {=str}", module, message),
+        }
+    }
+
+    /// Writes every queued frame to `out`, or discards them if `out` is
+    /// `None`.
+    pub(super) fn write_queued(out: Option<&mut impl Write>) -> std::io::Result<()> {
+        let dropped = DROPPED.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            defmt::warn!(
+                "Serial log fell behind; {=usize} frames were dropped",
+                dropped
+            );
+        }
+        let bytes = match QUEUE.lock() {
+            Ok(mut queue) => core::mem::take(&mut *queue),
+            Err(_) => return Ok(()),
+        };
+        let Some(out) = out else {
+            return Ok(());
+        };
+        let (front, back) = bytes.as_slices();
+        out.write_all(front)?;
+        out.write_all(back)
+    }
+}
+
+/// Configuration for the logger.
+#[derive(Debug, Clone)]
+pub struct LoggerConfig {
+    path: String,
+    max_level: LevelFilter,
+    keep: usize,
+    max_file_size: Option<u64>,
+    module_levels: Vec<(String, LevelFilter)>,
+}
+
+impl LoggerConfig {
+    /// Creates a new configuration which logs to files named after `path`,
+    /// keeping the last 5 without a size limit. `max_level` is the default
+    /// level.
+    pub fn new(path: &str, max_level: LevelFilter) -> Self {
+        Self {
+            path: String::from(path),
+            max_level,
+            keep: DEFAULT_KEEP,
+            max_file_size: None,
+            module_levels: Vec::new(),
+        }
+    }
+
+    /// Sets the level of `module` and its submodules. See the
+    /// [module documentation](self) for how modules are matched.
+    pub fn with_module_level(mut self, module: &str, level: LevelFilter) -> Self {
+        self.module_levels.push((String::from(module), level));
+        self
+    }
+
+    /// Sets how many log files are kept.
+    pub fn with_keep(mut self, keep: usize) -> Self {
+        self.keep = keep;
+        self
+    }
+
+    /// Moves on to the next file, within the same run, once a file reaches
+    /// `bytes`.
+    pub fn with_max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = Some(bytes);
+        self
+    }
+
+    /// Sets up the logger and starts the task which writes the records out.
+    ///
+    /// This must be called from within the vexide runtime.
+    pub fn init(self) -> Result<(), SetLoggerError> {
+        let logger = SimpleLogger {
+            start_time: std::time::Instant::now(),
+        };
+        let logger_ref = Box::leak(Box::new(logger));
+        log::set_logger(logger_ref)?;
+        set_level(self.max_level);
+        for (module, level) in &self.module_levels {
+            set_module_level(module, *level);
+        }
+
+        if let Ok(mut queue) = PENDING.lock() {
+            queue.reserve(PENDING_LEN);
+        }
+        let mut files = LogFiles::new(&self.path, self.keep, self.max_file_size, true);
         spawn(async move {
-            _ = stdout().flush();
-            if let Some(file) = file.lock().await.as_mut() {
-                _ = file.flush();
+            loop {
+                write_pending(&mut files);
+                vexide::time::sleep(FLUSH_INTERVAL).await;
             }
         })
         .detach();
+        Ok(())
     }
 }
 
-pub fn init(file: &str, max_level: log::LevelFilter) -> Result<(), SetLoggerError> {
-    let logger = SimpleLogger::new(file);
-    let logger_ref = Box::leak(Box::new(logger));
-    log::set_logger(logger_ref).map(|()| log::set_max_level(max_level))
+/// Sets up the logger with the default [`LoggerConfig`].
+pub fn init(path: &str, max_level: LevelFilter) -> Result<(), SetLoggerError> {
+    LoggerConfig::new(path, max_level).init()
 }
@@ -0,0 +1,219 @@
+//! Match timing
+//!
+//! [`MatchTimer`] follows the competition status reported by the field
+//! controller and keeps track of how far into the current period the match
+//! is. Callbacks can be scheduled at times into driver control, e.g., to
+//! rumble the controller when endgame starts:
+//!
+//! ```ignore
+//! let timer = MatchTimer::new()
+//!     .with_event_before_end("endgame", Duration::from_secs(20), move || {
+//!         _ = controller.borrow_mut().try_rumble("---");
+//!     });
+//! ```
+
+use core::{cell::RefCell, time::Duration};
+use std::time::Instant;
+
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+use vexide::competition::{self, CompetitionMode};
+
+use crate::utils::{
+    profiling,
+    ticker::{LOOP_PERIOD, Ticker},
+};
+
+/// The length of the autonomous period in a standard VRC match.
+pub const AUTONOMOUS_DURATION: Duration = Duration::from_secs(15);
+/// The length of the driver control period in a standard VRC match.
+pub const DRIVER_DURATION: Duration = Duration::from_secs(105);
+
+#[derive(Debug, Clone, Copy)]
+enum EventTime {
+    /// After the start of driver control
+    After(Duration),
+    /// Before the end of driver control
+    BeforeEnd(Duration),
+}
+
+struct MatchEvent {
+    name: &'static str,
+    time: EventTime,
+    fired: bool,
+    callback: Box<dyn FnMut()>,
+}
+
+struct MatchTimerInner {
+    mode: CompetitionMode,
+    period_start: Instant,
+    autonomous_duration: Duration,
+    driver_duration: Duration,
+    events: Vec<MatchEvent>,
+}
+
+impl MatchTimerInner {
+    fn period_duration(&self) -> Option<Duration> {
+        match self.mode {
+            CompetitionMode::Autonomous => Some(self.autonomous_duration),
+            CompetitionMode::Driver => Some(self.driver_duration),
+            CompetitionMode::Disabled => None,
+        }
+    }
+}
+
+/// A competition-aware match timer with scheduled callbacks.
+///
+/// The competition status is polled every 10 ms in a background task. Every
+/// time the robot enters driver control, the period timer restarts and every
+/// event is armed again, so the timer works the same in practice runs started
+/// from a competition switch.
+#[derive(Clone)]
+pub struct MatchTimer {
+    inner: Rc<RefCell<MatchTimerInner>>,
+    _task: Rc<vexide::task::Task<()>>,
+}
+
+impl MatchTimer {
+    /// Creates a new match timer with the standard VRC period lengths.
+    pub fn new() -> Self {
+        let inner = Rc::new(RefCell::new(MatchTimerInner {
+            mode: competition::mode(),
+            period_start: Instant::now(),
+            autonomous_duration: AUTONOMOUS_DURATION,
+            driver_duration: DRIVER_DURATION,
+            events: Vec::new(),
+        }));
+        Self {
+            inner: inner.clone(),
+            _task: Rc::new(vexide::task::spawn(async move {
+                let mut ticker = Ticker::new(LOOP_PERIOD);
+                loop {
+                    let scope = profiling::scope("match_timer");
+                    // Take the events out so that callbacks can use the timer
+                    let (elapsed, driver_duration, mut events) = {
+                        let mut inner = inner.borrow_mut();
+                        let mode = competition::mode();
+                        if mode != inner.mode {
+                            log::info!(
+                                "Match: {:?} -> {:?} after {:.1}s",
+                                inner.mode,
+                                mode,
+                                inner.period_start.elapsed().as_secs_f64()
+                            );
+                            inner.mode = mode;
+                            inner.period_start = Instant::now();
+                            if mode == CompetitionMode::Driver {
+                                for event in inner.events.iter_mut() {
+                                    event.fired = false;
+                                }
+                            }
+                        }
+                        let elapsed = (inner.mode == CompetitionMode::Driver)
+                            .then(|| inner.period_start.elapsed());
+                        (
+                            elapsed,
+                            inner.driver_duration,
+                            core::mem::take(&mut inner.events),
+                        )
+                    };
+                    if let Some(elapsed) = elapsed {
+                        for event in events.iter_mut().filter(|event| !event.fired) {
+                            let at = match event.time {
+                                EventTime::After(after) => after,
+                                EventTime::BeforeEnd(before) => {
+                                    driver_duration.saturating_sub(before)
+                                }
+                            };
+                            if elapsed >= at {
+                                event.fired = true;
+                                log::info!(
+                                    "Match event {:?} at {:.1}s",
+                                    event.name,
+                                    elapsed.as_secs_f64()
+                                );
+                                (event.callback)();
+                            }
+                        }
+                    }
+                    {
+                        let mut inner = inner.borrow_mut();
+                        // Events added by callbacks go after the rest
+                        events.append(&mut inner.events);
+                        inner.events = events;
+                    }
+                    drop(scope);
+                    ticker.tick().await;
+                }
+            })),
+        }
+    }
+
+    /// Sets the length of the autonomous period, e.g., for skills runs.
+    pub fn with_autonomous_duration(self, duration: Duration) -> Self {
+        self.inner.borrow_mut().autonomous_duration = duration;
+        self
+    }
+
+    /// Sets the length of the driver control period, e.g., for skills runs.
+    pub fn with_driver_duration(self, duration: Duration) -> Self {
+        self.inner.borrow_mut().driver_duration = duration;
+        self
+    }
+
+    /// Calls `callback` once `at` into driver control.
+    pub fn with_event(
+        self,
+        name: &'static str,
+        at: Duration,
+        callback: impl FnMut() + 'static,
+    ) -> Self {
+        self.add_event(name, EventTime::After(at), callback);
+        self
+    }
+
+    /// Calls `callback` once `before` the end of driver control, e.g., for an
+    /// endgame warning.
+    pub fn with_event_before_end(
+        self,
+        name: &'static str,
+        before: Duration,
+        callback: impl FnMut() + 'static,
+    ) -> Self {
+        self.add_event(name, EventTime::BeforeEnd(before), callback);
+        self
+    }
+
+    fn add_event(&self, name: &'static str, time: EventTime, callback: impl FnMut() + 'static) {
+        self.inner.borrow_mut().events.push(MatchEvent {
+            name,
+            time,
+            fired: false,
+            callback: Box::new(callback),
+        });
+    }
+
+    /// Returns the current competition mode, as of the last poll.
+    pub fn mode(&self) -> CompetitionMode {
+        self.inner.borrow().mode
+    }
+
+    /// Returns how long the robot has been in the current mode.
+    pub fn elapsed(&self) -> Duration {
+        self.inner.borrow().period_start.elapsed()
+    }
+
+    /// Returns the time left in the current autonomous or driver control
+    /// period, or `None` while disabled.
+    pub fn remaining(&self) -> Option<Duration> {
+        let inner = self.inner.borrow();
+        inner
+            .period_duration()
+            .map(|duration| duration.saturating_sub(inner.period_start.elapsed()))
+    }
+}
+
+impl Default for MatchTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
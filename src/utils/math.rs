@@ -0,0 +1,50 @@
+//! Numerical helpers
+
+/// The nodes of 5-point Gauss–Legendre quadrature on [-1, 1].
+const NODES: [f64; 5] = [
+    0.0,
+    -0.538_469_310_105_683,
+    0.538_469_310_105_683,
+    -0.906_179_845_938_664,
+    0.906_179_845_938_664,
+];
+
+/// The weights of 5-point Gauss–Legendre quadrature, matching [`NODES`].
+const WEIGHTS: [f64; 5] = [
+    0.568_888_888_888_889,
+    0.478_628_670_499_366,
+    0.478_628_670_499_366,
+    0.236_926_885_056_189,
+    0.236_926_885_056_189,
+];
+
+/// Integrates `f` from `a` to `b` with 5-point Gauss–Legendre quadrature.
+///
+/// This is exact for polynomials up to degree 9 and takes five evaluations of
+/// `f`. If `b` is less than `a`, the result is negated, as usual.
+pub fn gauss_legendre(f: impl Fn(f64) -> f64, a: f64, b: f64) -> f64 {
+    let half_width = (b - a) / 2.0;
+    let center = (a + b) / 2.0;
+    NODES
+        .iter()
+        .zip(WEIGHTS)
+        .map(|(node, weight)| weight * f(center + half_width * node))
+        .sum::<f64>()
+        * half_width
+}
+
+/// Integrates `f` from `a` to `b` by splitting the interval into `segments`
+/// equal parts and applying [`gauss_legendre`] to each.
+///
+/// A few segments are enough for smooth functions which aren't polynomials,
+/// like the speed along a curve.
+pub fn integrate(f: impl Fn(f64) -> f64, a: f64, b: f64, segments: usize) -> f64 {
+    let segments = segments.max(1);
+    let width = (b - a) / segments as f64;
+    (0..segments)
+        .map(|segment| {
+            let start = a + width * segment as f64;
+            gauss_legendre(&f, start, start + width)
+        })
+        .sum()
+}
@@ -1,5 +1,25 @@
+pub mod alliance;
+pub mod angle;
+pub mod config;
+pub mod config_store;
+pub mod controllers;
+pub mod events;
+pub mod filters;
+pub mod geometry;
+pub mod health;
+pub mod json;
 pub mod logger;
+pub mod match_timer;
+pub mod math;
+pub mod motion_profile;
+pub mod panic_hook;
 pub mod pose;
+pub mod profiling;
 pub mod settling;
+pub mod sync;
+pub mod telemetry;
+pub mod ticker;
 pub mod traits;
+pub mod tuner;
+pub mod units;
 pub mod unwrap_expect_report;
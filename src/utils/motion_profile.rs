@@ -0,0 +1,132 @@
+//! Motion profiles
+//!
+//! A motion profile plans how a mechanism moves from one position to another
+//! over time without exceeding a maximum velocity and acceleration. Following
+//! the profile's setpoints instead of jumping straight to the target keeps
+//! controllers out of saturation and makes moves smooth and repeatable.
+
+use snafu::Snafu;
+
+#[derive(Debug, Snafu)]
+pub enum MotionProfileError {
+    #[snafu(display("The maximum {} must be positive, got {}", name, value))]
+    NotPositive { name: &'static str, value: f64 },
+}
+
+/// A trapezoidal motion profile.
+///
+/// The profile accelerates at the maximum acceleration until it reaches the
+/// maximum velocity, cruises, and then decelerates to a stop at the end
+/// position. If the move is too short to reach the maximum velocity, the
+/// profile is triangular instead.
+///
+/// Units are arbitrary but must be consistent, e.g., radians, radians per
+/// second, and radians per second squared.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrapezoidalProfile {
+    start: f64,
+    /// +1.0 or -1.0
+    direction: f64,
+    distance: f64,
+    acceleration: f64,
+    peak_velocity: f64,
+    acceleration_time: f64,
+    cruise_time: f64,
+}
+
+impl TrapezoidalProfile {
+    /// Creates a new profile from `start` to `end` at rest.
+    ///
+    /// Fails if `max_velocity` or `max_acceleration` isn't positive.
+    pub fn new(
+        start: f64,
+        end: f64,
+        max_velocity: f64,
+        max_acceleration: f64,
+    ) -> Result<Self, MotionProfileError> {
+        Self::validate_limits(max_velocity, max_acceleration)?;
+        let distance = (end - start).abs();
+        let acceleration_time = max_velocity / max_acceleration;
+        let acceleration_distance = 0.5 * max_acceleration * acceleration_time.powi(2);
+        let (acceleration_time, peak_velocity, cruise_time) =
+            if 2.0 * acceleration_distance > distance {
+                // Triangular: we never reach the max velocity
+                let acceleration_time = (distance / max_acceleration).sqrt();
+                (acceleration_time, max_acceleration * acceleration_time, 0.0)
+            } else {
+                (
+                    acceleration_time,
+                    max_velocity,
+                    (distance - 2.0 * acceleration_distance) / max_velocity,
+                )
+            };
+        Ok(Self {
+            start,
+            direction: if end >= start { 1.0 } else { -1.0 },
+            distance,
+            acceleration: max_acceleration,
+            peak_velocity,
+            acceleration_time,
+            cruise_time,
+        })
+    }
+
+    /// Checks that the limits can be used to create a profile, i.e., that
+    /// both are positive.
+    pub fn validate_limits(
+        max_velocity: f64,
+        max_acceleration: f64,
+    ) -> Result<(), MotionProfileError> {
+        for (name, value) in [
+            ("velocity", max_velocity),
+            ("acceleration", max_acceleration),
+        ] {
+            if value <= 0.0 || value.is_nan() {
+                return Err(MotionProfileError::NotPositive { name, value });
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the total duration of the profile in seconds.
+    pub fn duration(&self) -> f64 {
+        2.0 * self.acceleration_time + self.cruise_time
+    }
+
+    /// Returns the position at the end of the profile.
+    pub fn end(&self) -> f64 {
+        self.start + self.direction * self.distance
+    }
+
+    /// Returns the position and velocity setpoints `t` seconds after the start
+    /// of the profile.
+    ///
+    /// Before the start, this is the start position at rest, and after the
+    /// end, the end position at rest.
+    pub fn sample(&self, t: f64) -> (f64, f64) {
+        let duration = self.duration();
+        let (position, velocity) = if t <= 0.0 {
+            (0.0, 0.0)
+        } else if t < self.acceleration_time {
+            (0.5 * self.acceleration * t.powi(2), self.acceleration * t)
+        } else if t < self.acceleration_time + self.cruise_time {
+            (
+                0.5 * self.acceleration * self.acceleration_time.powi(2)
+                    + self.peak_velocity * (t - self.acceleration_time),
+                self.peak_velocity,
+            )
+        } else if t < duration {
+            let remaining = duration - t;
+            (
+                self.distance - 0.5 * self.acceleration * remaining.powi(2),
+                self.acceleration * remaining,
+            )
+        } else {
+            (self.distance, 0.0)
+        };
+        (
+            self.start + self.direction * position,
+            self.direction * velocity,
+        )
+    }
+}
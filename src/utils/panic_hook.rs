@@ -0,0 +1,112 @@
+//! Crash reports on panic
+//!
+//! A panic mid-match halts the robot and leaves nothing behind but a frozen
+//! screen. The hook installed by [`PanicHook`] writes a crash report to the
+//! SD card before the default panic handler runs, containing:
+//!
+//! - the panic message and location,
+//! - the current pose, if a [`TrackingSubsystem`] was provided,
+//! - the debug representation of the running drivetrain action, if a
+//!   [`Drivetrain`] was provided, and
+//! - the last few lines logged through [`logger`](super::logger).
+
+use alloc::boxed::Box;
+use std::{fs::File, io::Write};
+
+use crate::subsystems::{
+    drivetrain::{ActionSlot, Drivetrain},
+    tracking::TrackingSubsystem,
+};
+
+/// Builder for the crash-reporting panic hook.
+pub struct PanicHook {
+    path: &'static str,
+    log_lines: usize,
+    tracking: Option<TrackingSubsystem>,
+    action: Option<ActionSlot>,
+}
+
+// SAFETY: The brain is single-threaded, and the hook only runs on the thread
+// that panicked.
+unsafe impl Send for PanicHook {}
+unsafe impl Sync for PanicHook {}
+
+impl PanicHook {
+    /// Creates a new panic hook which writes crash reports to `path`,
+    /// overwriting any previous report.
+    pub fn new(path: &'static str) -> Self {
+        Self {
+            path,
+            log_lines: 20,
+            tracking: None,
+            action: None,
+        }
+    }
+
+    /// Sets the number of recent log lines included in the report. Defaults
+    /// to 20.
+    pub fn with_log_lines(mut self, log_lines: usize) -> Self {
+        self.log_lines = log_lines;
+        self
+    }
+
+    /// Includes the current pose from the given tracking subsystem in the
+    /// report.
+    pub fn with_tracking(mut self, tracking: TrackingSubsystem) -> Self {
+        self.tracking = Some(tracking);
+        self
+    }
+
+    /// Includes the running action of the given drivetrain in the report.
+    pub fn with_drivetrain(mut self, drivetrain: &Drivetrain) -> Self {
+        self.action = Some(drivetrain.action.clone());
+        self
+    }
+
+    /// Installs the hook. The previously installed hook still runs after the
+    /// report is written.
+    pub fn install(self) {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if let Err(err) = self.write_report(info) {
+                println!("Failed to write crash report to {}: {}", self.path, err);
+            }
+            previous(info);
+        }));
+    }
+
+    fn write_report(&self, info: &std::panic::PanicHookInfo<'_>) -> std::io::Result<()> {
+        let mut file = File::create(self.path)?;
+        writeln!(file, "libdoxa v{} crash report", env!("CARGO_PKG_VERSION"))?;
+        writeln!(file, "{}", info)?;
+
+        if let Some(tracking) = &self.tracking {
+            let data = tracking.current();
+            writeln!(
+                file,
+                "pose: ({:.1}, {:.1}) {:.3} rad, velocity ({:.1}, {:.1})",
+                data.offset.x,
+                data.offset.y,
+                data.heading.as_radians(),
+                data.velocity.x,
+                data.velocity.y
+            )?;
+        }
+
+        if let Some(action) = &self.action {
+            match action.try_borrow() {
+                Ok(action) => match action.as_ref() {
+                    Some((action, _, _)) => writeln!(file, "action: {:#?}", action)?,
+                    None => writeln!(file, "action: <none>")?,
+                },
+                Err(_) => writeln!(file, "action: <unavailable, action in use>")?,
+            }
+        }
+
+        writeln!(file, "--- last {} log lines ---", self.log_lines)?;
+        for line in super::logger::recent_lines(self.log_lines) {
+            writeln!(file, "{}", line)?;
+        }
+        file.flush()
+    }
+}
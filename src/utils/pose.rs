@@ -6,10 +6,20 @@ use core::{
     ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign},
 };
 
-use nalgebra::Vector2;
+use nalgebra::{Rotation2, Vector2};
+use vexide::math::Angle;
+
+use crate::utils::angle;
 
 /// A struct representing a 2D pose with x, y coordinates and a heading
 /// in radians.
+///
+/// The arithmetic operators work on each component, headings included, so
+/// `a - b` is the change from `b` to `a` in field coordinates,
+/// `b + (a - b) == a`, and `b + (a - b) * 0.5` is halfway between them. To move
+/// between frames, e.g., from a sensor's mounting offset on the robot to the
+/// field, use [`transform_by`](Self::transform_by)
+/// and [`relative_to`](Self::relative_to) instead.
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[deprecated(note = "Use a separate position vector and heading instead")]
 pub struct Pose {
@@ -118,6 +128,48 @@ impl Pose {
     pub fn angle_to(&self, other: Pose) -> f64 {
         (other.offset.y - self.offset.y).atan2(other.offset.x - self.offset.x)
     }
+
+    /// Returns this pose with the given heading in radians.
+    pub fn with_heading(mut self, heading: f64) -> Self {
+        self.heading = heading;
+        self
+    }
+
+    /// Treats this pose as relative to `frame` and returns it in the frame
+    /// `frame` is relative to.
+    ///
+    /// For example, a camera mounted 100 mm ahead of the tracking center is at
+    /// `Pose::new(100.0, 0.0, 0.0).transform_by(robot)` on the field.
+    pub fn transform_by(&self, frame: Pose) -> Self {
+        Self {
+            offset: frame.offset + Rotation2::new(frame.heading) * self.offset,
+            heading: frame.heading + self.heading,
+        }
+    }
+
+    /// Returns this pose relative to `frame`, i.e., as seen from `frame`
+    /// facing along its heading. This is the inverse of
+    /// [`transform_by`](Self::transform_by).
+    pub fn relative_to(&self, frame: Pose) -> Self {
+        Self {
+            offset: Rotation2::new(-frame.heading) * (self.offset - frame.offset),
+            heading: self.heading - frame.heading,
+        }
+    }
+
+    /// Interpolates from this pose to `other`, where an `s` of 0.0 is this
+    /// pose and 1.0 is `other`. The heading turns the short way round.
+    pub fn lerp(&self, other: Pose, s: f64) -> Self {
+        Self {
+            offset: self.offset.lerp(&other.offset, s),
+            heading: angle::lerp(
+                Angle::from_radians(self.heading),
+                Angle::from_radians(other.heading),
+                s,
+            )
+            .as_radians(),
+        }
+    }
 }
 
 impl From<Pose> for vexide::math::Point2<i16> {
@@ -138,7 +190,7 @@ impl Add for Pose {
                 self.offset.x + other.offset.x,
                 self.offset.y + other.offset.y,
             ),
-            heading: (self.heading + other.heading) / 2.0,
+            heading: self.heading + other.heading,
         }
     }
 }
@@ -158,7 +210,7 @@ impl Sub for Pose {
                 self.offset.x - other.offset.x,
                 self.offset.y - other.offset.y,
             ),
-            heading: (self.heading + other.heading) / 2.0,
+            heading: self.heading - other.heading,
         }
     }
 }
@@ -169,7 +221,7 @@ impl Mul<f64> for Pose {
     fn mul(self, scalar: f64) -> Self {
         Self {
             offset: Vector2::new(self.offset.x * scalar, self.offset.y * scalar),
-            heading: self.heading,
+            heading: self.heading * scalar,
         }
     }
 }
@@ -180,7 +232,7 @@ impl Div<f64> for Pose {
     fn div(self, scalar: f64) -> Self {
         Self {
             offset: Vector2::new(self.offset.x / scalar, self.offset.y / scalar),
-            heading: self.heading,
+            heading: self.heading / scalar,
         }
     }
 }
@@ -200,7 +252,7 @@ impl AddAssign for Pose {
     fn add_assign(&mut self, other: Pose) {
         self.offset.x += other.offset.x;
         self.offset.y += other.offset.y;
-        self.heading = (self.heading + other.heading) / 2.0;
+        self.heading += other.heading;
     }
 }
 
@@ -208,6 +260,6 @@ impl SubAssign for Pose {
     fn sub_assign(&mut self, other: Pose) {
         self.offset.x -= other.offset.x;
         self.offset.y -= other.offset.y;
-        self.heading = (self.heading + other.heading) / 2.0;
+        self.heading -= other.heading;
     }
 }
@@ -0,0 +1,160 @@
+//! Loop timing
+//!
+//! Every subsystem task runs a loop which is meant to finish well within
+//! 10 ms. When actions start stuttering, one of them usually isn't. Each task
+//! times its loop body with a [`scope`]:
+//!
+//! ```ignore
+//! let mut ticker = Ticker::new(LOOP_PERIOD);
+//! loop {
+//!     {
+//!         let _scope = profiling::scope("flywheel");
+//!         // ...
+//!     }
+//!     ticker.tick().await;
+//! }
+//! ```
+//!
+//! The minimum, mean, and maximum time of each scope, and how often it went
+//! over its budget, are logged every few seconds and then reset.
+//!
+//! Timing is only done with the `profiling` feature. Without it, scopes do
+//! nothing and there are never any [`Stats`].
+
+use core::time::Duration;
+#[cfg(feature = "profiling")]
+use std::time::Instant;
+
+#[cfg(feature = "profiling")]
+use alloc::collections::BTreeMap;
+
+/// The default budget for a scope, i.e., one loop iteration.
+pub const LOOP_BUDGET: Duration = Duration::from_millis(10);
+
+/// How often the statistics are logged.
+#[cfg(feature = "profiling")]
+const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+#[cfg(feature = "profiling")]
+static STATS: std::sync::Mutex<BTreeMap<&'static str, Stats>> =
+    std::sync::Mutex::new(BTreeMap::new());
+
+#[cfg(feature = "profiling")]
+static LAST_REPORT: std::sync::Mutex<Option<Instant>> = std::sync::Mutex::new(None);
+
+/// Timing statistics for a scope since they were last reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    pub count: u32,
+    pub min: Duration,
+    pub max: Duration,
+    pub total: Duration,
+    /// How many times the scope took longer than its budget
+    pub overruns: u32,
+}
+
+impl Stats {
+    /// Returns the mean time of the scope.
+    pub fn mean(&self) -> Duration {
+        self.total / self.count.max(1)
+    }
+}
+
+/// Times a scope until it is dropped. See the [module documentation](self).
+#[derive(Debug)]
+#[must_use = "the scope is timed until it is dropped"]
+pub struct Scope {
+    #[cfg(feature = "profiling")]
+    name: &'static str,
+    #[cfg(feature = "profiling")]
+    budget: Duration,
+    #[cfg(feature = "profiling")]
+    start: Instant,
+}
+
+/// Times a scope against the default budget of 10 ms.
+pub fn scope(name: &'static str) -> Scope {
+    scope_with_budget(name, LOOP_BUDGET)
+}
+
+/// Times a scope against the given budget.
+#[cfg_attr(not(feature = "profiling"), allow(unused_variables))]
+pub fn scope_with_budget(name: &'static str, budget: Duration) -> Scope {
+    Scope {
+        #[cfg(feature = "profiling")]
+        name,
+        #[cfg(feature = "profiling")]
+        budget,
+        #[cfg(feature = "profiling")]
+        start: Instant::now(),
+    }
+}
+
+#[cfg(feature = "profiling")]
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        // Never block; a sample missed while reporting doesn't matter
+        if let Ok(mut stats) = STATS.try_lock() {
+            let stats = stats.entry(self.name).or_insert(Stats {
+                count: 0,
+                min: Duration::MAX,
+                max: Duration::ZERO,
+                total: Duration::ZERO,
+                overruns: 0,
+            });
+            stats.count += 1;
+            stats.min = stats.min.min(elapsed);
+            stats.max = stats.max.max(elapsed);
+            stats.total += elapsed;
+            if elapsed > self.budget {
+                stats.overruns += 1;
+            }
+        }
+        if let Ok(mut last_report) = LAST_REPORT.try_lock() {
+            let last_report = last_report.get_or_insert_with(Instant::now);
+            if last_report.elapsed() >= REPORT_INTERVAL {
+                *last_report = Instant::now();
+                report();
+            }
+        }
+    }
+}
+
+/// Returns the statistics of a scope since they were last reported, or
+/// `None` if it hasn't run since.
+pub fn stats(name: &str) -> Option<Stats> {
+    #[cfg(feature = "profiling")]
+    return STATS.lock().ok()?.get(name).copied();
+    #[cfg(not(feature = "profiling"))]
+    {
+        _ = name;
+        None
+    }
+}
+
+/// Logs the statistics of every scope and resets them.
+///
+/// This is called every few seconds while scopes are running.
+pub fn report() {
+    #[cfg(feature = "profiling")]
+    if let Ok(mut stats) = STATS.lock() {
+        for (name, stats) in core::mem::take(&mut *stats) {
+            let level = if stats.overruns > 0 {
+                log::Level::Warn
+            } else {
+                log::Level::Debug
+            };
+            log::log!(
+                level,
+                "{:<16} n={:<5} min={:?} mean={:?} max={:?} overruns={}",
+                name,
+                stats.count,
+                stats.min,
+                stats.mean(),
+                stats.max,
+                stats.overruns
+            );
+        }
+    }
+}
@@ -17,10 +17,26 @@
 //! mechanical limitations. Under the [`Tolerances`] struct, a system is
 //! considered "settled" when it meets specified error and velocity tolerances
 //! for a given duration, after when a timeout is reached.
+//!
+//! # Lack of progress
+//!
+//! A system which is blocked, e.g., a robot pushing against an opponent, will
+//! never reach its tolerances, and waiting for the timeout wastes time. A
+//! [`Tolerances`] can also settle once the error hasn't improved by a minimum
+//! amount for a while; see [`Tolerances::no_progress`].
+//! [`Tolerances::check_reason`] tells this apart from settling normally.
+//!
+//! # Tuning
+//!
+//! [`Tolerances::validate`] catches configurations which can never settle the
+//! way they were meant to, and [`Tolerances::status`] explains why a system
+//! hasn't settled yet, e.g., whether the error or the velocity is too high.
 
-use core::time::Duration;
+use core::{fmt, time::Duration};
 use std::time::Instant;
 
+use snafu::Snafu;
+
 /// A utility for determining when a control system has stabilized reasonably
 /// near its setpoint.
 ///
@@ -41,14 +57,85 @@ use std::time::Instant;
 ///
 /// If the system leaves the tolerance window before the duration is met, the
 /// tolerance timer resets.
+///
+/// If a no-progress condition is set, the system is also considered settled
+/// once the error hasn't improved by `min_progress` within `progress_window`.
 #[derive(Default, Debug, Copy, Clone, PartialEq, PartialOrd)]
 pub struct Tolerances {
     start_timestamp: Option<Instant>,
     tolerance_timestamp: Option<Instant>,
+    /// The smallest error magnitude so far, and when it was reached
+    progress_reference: Option<(Instant, f64)>,
+    /// Why the system wasn't settled at the last check
+    status: Option<Unsettled>,
     pub tolerance_duration: Option<Duration>,
     pub error_tolerance: Option<f64>,
     pub velocity_tolerance: Option<f64>,
     pub timeout: Option<Duration>,
+    pub min_progress: Option<f64>,
+    pub progress_window: Option<Duration>,
+}
+
+/// Why a [`Tolerances`] settled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettleReason {
+    /// The error and velocity stayed within their tolerances for long enough.
+    InTolerance,
+    /// The timeout elapsed.
+    Timeout,
+    /// The error stopped improving, e.g., because the system is blocked.
+    NoProgress,
+}
+
+/// Why a [`Tolerances`] hasn't settled yet.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Unsettled {
+    /// The error is outside the error tolerance.
+    Error { error: f64, tolerance: f64 },
+    /// The error is within tolerance, but the velocity isn't.
+    Velocity { velocity: f64, tolerance: f64 },
+    /// Both are within tolerance, but haven't been for the tolerance
+    /// duration yet.
+    Waiting { remaining: Duration },
+}
+
+impl fmt::Display for Unsettled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Error { error, tolerance } => {
+                write!(f, "error {:.3} outside tolerance {:.3}", error, tolerance)
+            }
+            Self::Velocity {
+                velocity,
+                tolerance,
+            } => write!(
+                f,
+                "velocity {:.3} outside tolerance {:.3}",
+                velocity, tolerance
+            ),
+            Self::Waiting { remaining } => write!(f, "in tolerance, {:?} remaining", remaining),
+        }
+    }
+}
+
+/// A [`Tolerances`] configuration which can't settle as intended.
+#[derive(Debug, Clone, Copy, PartialEq, Snafu)]
+pub enum TolerancesError {
+    #[snafu(display("The {} must be positive, got {}", name, value))]
+    NotPositive { name: &'static str, value: f64 },
+    #[snafu(display("A tolerance duration is set without any tolerance to hold"))]
+    DurationWithoutTolerance,
+    #[snafu(display(
+        "The timeout ({:?}) is shorter than the tolerance duration ({:?})",
+        timeout,
+        duration
+    ))]
+    TimeoutTooShort {
+        timeout: Duration,
+        duration: Duration,
+    },
+    #[snafu(display("A minimum progress and a progress window must be set together"))]
+    IncompleteProgress,
 }
 
 impl Tolerances {
@@ -62,11 +149,15 @@ impl Tolerances {
         Self {
             start_timestamp: None,
             tolerance_timestamp: None,
+            progress_reference: None,
+            status: None,
 
             tolerance_duration: None,
             error_tolerance: None,
             velocity_tolerance: None,
             timeout: None,
+            min_progress: None,
+            progress_window: None,
         }
     }
 
@@ -114,6 +205,78 @@ impl Tolerances {
         *self
     }
 
+    /// Settles once the error magnitude hasn't improved by at least
+    /// `min_progress` within `window`.
+    ///
+    /// This catches a blocked system much sooner than the timeout does.
+    /// `window` should be long enough for the system to get moving from rest.
+    #[must_use]
+    pub const fn no_progress(&mut self, min_progress: f64, window: Duration) -> Self {
+        self.min_progress = Some(min_progress);
+        self.progress_window = Some(window);
+        *self
+    }
+
+    /// Checks that the configuration can settle as intended.
+    ///
+    /// A configuration without any tolerances is valid, and settles on the
+    /// first check.
+    pub fn validate(&self) -> Result<(), TolerancesError> {
+        for (name, value) in [
+            ("error tolerance", self.error_tolerance),
+            ("velocity tolerance", self.velocity_tolerance),
+            ("minimum progress", self.min_progress),
+        ] {
+            if let Some(value) = value
+                && (value <= 0.0 || value.is_nan())
+            {
+                return Err(TolerancesError::NotPositive { name, value });
+            }
+        }
+        if self.tolerance_duration.is_some()
+            && self.error_tolerance.is_none()
+            && self.velocity_tolerance.is_none()
+        {
+            return Err(TolerancesError::DurationWithoutTolerance);
+        }
+        if let (Some(timeout), Some(duration)) = (self.timeout, self.tolerance_duration)
+            && timeout < duration
+        {
+            return Err(TolerancesError::TimeoutTooShort { timeout, duration });
+        }
+        match (self.min_progress, self.progress_window) {
+            (Some(_), None) | (None, Some(_)) => Err(TolerancesError::IncompleteProgress),
+            (_, Some(window)) if window.is_zero() => Err(TolerancesError::NotPositive {
+                name: "progress window",
+                value: 0.0,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns why the system wasn't settled at the last check, or `None` if
+    /// it hasn't been checked or has settled.
+    pub fn status(&self) -> Option<Unsettled> {
+        self.status
+    }
+
+    /// Returns how long is left until the timeout, or `None` if there is no
+    /// timeout or the system hasn't been checked.
+    pub fn time_remaining(&self) -> Option<Duration> {
+        Some(
+            self.timeout?
+                .saturating_sub(self.start_timestamp?.elapsed()),
+        )
+    }
+
+    /// Clears the timers after settling, so that the next check starts over.
+    fn restart(&mut self) {
+        self.tolerance_timestamp = None;
+        self.start_timestamp = None;
+        self.progress_reference = None;
+        self.status = None;
+    }
+
     /// Checks if the system has settled based on current error and velocity.
     ///
     /// This method should be called periodically (typically in a control loop)
@@ -132,6 +295,12 @@ impl Tolerances {
     /// * `velocity` - Measurement of how fast the system response is changing
     ///   over time.
     pub fn check(&mut self, error: f64, velocity: f64) -> bool {
+        self.check_reason(error, velocity).is_some()
+    }
+
+    /// Like [`check`](Self::check), but returns why the system settled, or
+    /// `None` if it hasn't.
+    pub fn check_reason(&mut self, error: f64, velocity: f64) -> Option<SettleReason> {
         // Initialize timing on first call.
         if self.start_timestamp.is_none() {
             self.start_timestamp = Some(Instant::now());
@@ -141,20 +310,48 @@ impl Tolerances {
         if let Some(timeout) = self.timeout
             && self.start_timestamp.unwrap().elapsed() > timeout
         {
-            self.tolerance_timestamp = None;
-            self.start_timestamp = None;
-            return true;
+            match self.status {
+                Some(status) => log::warn!("Timed out: {}", status),
+                None => log::warn!("Timed out"),
+            }
+            self.restart();
+            return Some(SettleReason::Timeout);
+        }
+
+        // If the error hasn't improved enough for too long, we are blocked.
+        if let (Some(min_progress), Some(window)) = (self.min_progress, self.progress_window) {
+            match self.progress_reference {
+                Some((_, best)) if error.abs() > best - min_progress => {}
+                // First call, or enough progress: measure from here
+                _ => self.progress_reference = Some((Instant::now(), error.abs())),
+            }
+            if self
+                .progress_reference
+                .is_some_and(|(since, _)| since.elapsed() > window)
+            {
+                log::warn!(
+                    "No progress in {:?}, giving up with error {:.2}",
+                    window,
+                    error
+                );
+                self.restart();
+                return Some(SettleReason::NoProgress);
+            }
         }
 
         // Check if we are within the tolerance range for either error and velocity.
-        let in_tolerances = self
-            .error_tolerance
-            .is_none_or(|tolerance| error.abs() < tolerance)
-            && self
-                .velocity_tolerance
-                .is_none_or(|tolerance| velocity.abs() < tolerance);
-
-        if in_tolerances {
+        self.status = match (self.error_tolerance, self.velocity_tolerance) {
+            (Some(tolerance), _) if error.abs() >= tolerance => {
+                Some(Unsettled::Error { error, tolerance })
+            }
+            (_, Some(tolerance)) if velocity.abs() >= tolerance => Some(Unsettled::Velocity {
+                velocity,
+                tolerance,
+            }),
+            _ => None,
+        };
+
+        if self.status.is_none() {
             // We are now within tolerance, so we record the timestamp that this occurred if
             // we previously weren't in tolerance.
             if self.tolerance_timestamp.is_none() {
@@ -168,14 +365,19 @@ impl Tolerances {
                 .tolerance_duration
                 .is_none_or(|time| self.tolerance_timestamp.unwrap().elapsed() > time)
             {
-                self.tolerance_timestamp = None;
-                self.start_timestamp = None;
-                return true;
+                self.restart();
+                return Some(SettleReason::InTolerance);
             }
+            self.status = Some(Unsettled::Waiting {
+                remaining: self
+                    .tolerance_duration
+                    .unwrap_or_default()
+                    .saturating_sub(self.tolerance_timestamp.unwrap().elapsed()),
+            });
         } else if self.tolerance_timestamp.is_some() {
             self.tolerance_timestamp = None;
         }
 
-        false
+        None
     }
 }
@@ -0,0 +1,233 @@
+//! Async signals for coordinating tasks
+//!
+//! vexide runs every task on one thread, so waiting for another task to do
+//! something only needs to park the waiting task until it is woken, rather
+//! than polling in a loop with a sleep. A [`Signal`] wakes everything waiting
+//! on it, and a [`Watch`] holds a value and wakes everything waiting for it to
+//! change:
+//!
+//! ```ignore
+//! let height = Watch::new(0.0);
+//! // In the lift's task
+//! height.set(lift.height());
+//! // In a route
+//! height.wait_until(|height| *height > 400.0).await;
+//! intake.run_for(Duration::from_secs(1)).await;
+//! ```
+//!
+//! These are not thread-safe, and don't need to be on the brain.
+
+use core::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+
+#[derive(Debug, Default)]
+struct SignalInner {
+    /// The number of times the signal has been notified
+    generation: u64,
+    wakers: Vec<Waker>,
+}
+
+/// Wakes every task waiting on it when notified. See the
+/// [module documentation](self).
+///
+/// Clones share the same waiters, so one clone can be notified by the task
+/// doing something while others are waited on.
+#[derive(Debug, Clone, Default)]
+pub struct Signal {
+    inner: Rc<RefCell<SignalInner>>,
+}
+
+impl Signal {
+    /// Creates a new signal with nothing waiting on it.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wakes every task waiting on the signal.
+    pub fn notify(&self) {
+        let wakers = {
+            let mut inner = self.inner.borrow_mut();
+            inner.generation += 1;
+            core::mem::take(&mut inner.wakers)
+        };
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Waits until the signal is next notified after this is called.
+    pub fn notified(&self) -> Notified {
+        Notified {
+            signal: self.clone(),
+            generation: self.inner.borrow().generation,
+        }
+    }
+}
+
+/// A future which resolves once its [`Signal`] is notified.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless awaited"]
+pub struct Notified {
+    signal: Signal,
+    /// The generation of the signal when the future was created
+    generation: u64,
+}
+
+impl Future for Notified {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut inner = self.signal.inner.borrow_mut();
+        if inner.generation != self.generation {
+            return Poll::Ready(());
+        }
+        if !inner.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            inner.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+#[derive(Debug)]
+struct WatchInner<T> {
+    value: T,
+    changed: Signal,
+}
+
+/// A value which tasks can wait to change. See the
+/// [module documentation](self).
+///
+/// Clones share the same value.
+#[derive(Debug)]
+pub struct Watch<T> {
+    inner: Rc<RefCell<WatchInner<T>>>,
+}
+
+impl<T> Clone for Watch<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: Clone> Watch<T> {
+    /// Creates a new watch holding `value`.
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(WatchInner {
+                value,
+                changed: Signal::new(),
+            })),
+        }
+    }
+
+    /// Returns a copy of the value.
+    pub fn get(&self) -> T {
+        self.inner.borrow().value.clone()
+    }
+
+    /// Sets the value, waking every task waiting for it to change.
+    pub fn set(&self, value: T) {
+        let changed = {
+            let mut inner = self.inner.borrow_mut();
+            inner.value = value;
+            inner.changed.clone()
+        };
+        changed.notify();
+    }
+
+    /// Changes the value in place, waking every task waiting for it to
+    /// change.
+    pub fn update(&self, update: impl FnOnce(&mut T)) {
+        let changed = {
+            let mut inner = self.inner.borrow_mut();
+            update(&mut inner.value);
+            inner.changed.clone()
+        };
+        changed.notify();
+    }
+
+    /// Waits until the value is next set, and returns it.
+    pub async fn changed(&self) -> T {
+        let notified = self.inner.borrow().changed.notified();
+        notified.await;
+        self.get()
+    }
+
+    /// Waits until `predicate` is true of the value, and returns it. Returns
+    /// straight away if it already is.
+    pub async fn wait_until(&self, mut predicate: impl FnMut(&T) -> bool) -> T {
+        loop {
+            // Start waiting before checking, so a change in between isn't
+            // missed
+            let notified = self.inner.borrow().changed.notified();
+            let value = self.get();
+            if predicate(&value) {
+                return value;
+            }
+            notified.await;
+        }
+    }
+}
+
+impl<T: Clone + PartialEq> Watch<T> {
+    /// Sets the value, only waking waiting tasks if it is different from
+    /// the current one.
+    pub fn set_if_changed(&self, value: T) {
+        if self.inner.borrow().value != value {
+            self.set(value);
+        }
+    }
+}
+
+/// Runs every future in `futures` at the same time, until they have all
+/// finished.
+pub async fn join_all<F: Future<Output = ()>>(futures: impl IntoIterator<Item = F>) {
+    let mut futures: Vec<Option<Pin<Box<F>>>> = futures
+        .into_iter()
+        .map(|future| Some(Box::pin(future)))
+        .collect();
+    core::future::poll_fn(|cx| {
+        let mut pending = false;
+        for slot in &mut futures {
+            if let Some(future) = slot {
+                if future.as_mut().poll(cx).is_ready() {
+                    *slot = None;
+                } else {
+                    pending = true;
+                }
+            }
+        }
+        if pending {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    })
+    .await
+}
+
+/// Runs `future` for at most `duration`. Returns its output, or `None` if it
+/// didn't finish in time, in which case it is dropped.
+pub async fn timeout<T>(duration: Duration, future: impl Future<Output = T>) -> Option<T> {
+    let mut future = core::pin::pin!(future);
+    let mut sleep = core::pin::pin!(vexide::time::sleep(duration));
+    core::future::poll_fn(|cx| {
+        if let Poll::Ready(output) = future.as_mut().poll(cx) {
+            Poll::Ready(Some(output))
+        } else if sleep.as_mut().poll(cx).is_ready() {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
@@ -0,0 +1,96 @@
+//! Numeric telemetry as CSV
+//!
+//! Text logs are hard to graph. Telemetry channels are named numbers which
+//! are sampled at a fixed rate and written to the SD card as one wide CSV
+//! file per run, with a column per channel, ready for a spreadsheet:
+//!
+//! ```ignore
+//! telemetry::init("telemetry.csv", Duration::from_millis(20));
+//! // ...in a control loop
+//! telemetry::record("linear_error", error);
+//! ```
+//!
+//! Each row holds the latest value of every channel, so channels can be
+//! recorded at any rate. Channels are added as they are first recorded; the
+//! header row is written again whenever a channel is added, so columns never
+//! shift under earlier rows without a header saying so. Like the
+//! [`logger`](super::logger), the last few files are kept as `telemetry-0.csv`
+//! and so on.
+//!
+//! With the `defmt` feature, each row is also sent over the serial link as
+//! one defmt frame per channel, without formatting the numbers.
+
+use alloc::{format, vec::Vec};
+use core::time::Duration;
+use std::time::Instant;
+
+use vexide::prelude::spawn;
+
+use crate::utils::{logger::LogFiles, ticker::Ticker};
+
+/// The number of telemetry files kept.
+const KEEP: usize = 5;
+
+/// The latest value of each channel, in column order.
+static CHANNELS: std::sync::Mutex<Vec<(&'static str, f64)>> = std::sync::Mutex::new(Vec::new());
+
+/// Records the latest value of a channel.
+///
+/// This is cheap enough to call every loop. Before [`init`], values are kept
+/// but never written.
+pub fn record(channel: &'static str, value: f64) {
+    if let Ok(mut channels) = CHANNELS.try_lock() {
+        match channels.iter_mut().find(|(name, _)| *name == channel) {
+            Some((_, latest)) => *latest = value,
+            None => channels.push((channel, value)),
+        }
+    }
+}
+
+/// Returns the latest value of a channel.
+pub fn latest(channel: &str) -> Option<f64> {
+    CHANNELS
+        .try_lock()
+        .ok()?
+        .iter()
+        .find(|(name, _)| *name == channel)
+        .map(|(_, value)| *value)
+}
+
+/// Starts writing a row every `interval` to a new CSV file named after
+/// `path`.
+///
+/// This must be called from within the vexide runtime.
+pub fn init(path: &str, interval: Duration) {
+    let mut files = LogFiles::new(path, KEEP, None, false);
+    let start = Instant::now();
+    spawn(async move {
+        // The number of channels in the last header row
+        let mut columns = 0;
+        let mut ticker = Ticker::new(interval);
+        loop {
+            let channels = CHANNELS.lock().map(|channels| channels.clone());
+            if let Ok(channels) = channels
+                && !channels.is_empty()
+            {
+                if channels.len() != columns {
+                    columns = channels.len();
+                    let names: Vec<&str> = channels.iter().map(|(name, _)| *name).collect();
+                    files.write(&format!("time,{}", names.join(",")));
+                }
+                let row = core::iter::once(format!("{:.3}", start.elapsed().as_secs_f64()))
+                    .chain(channels.iter().map(|(_, value)| value.to_string()))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                files.write(&row);
+                files.flush();
+                #[cfg(feature = "defmt")]
+                for (name, value) in &channels {
+                    defmt::info!("telemetry {=str} {=f64}", name, *value);
+                }
+            }
+            ticker.tick().await;
+        }
+    })
+    .detach();
+}
@@ -0,0 +1,91 @@
+//! Fixed-rate loops
+//!
+//! A loop which sleeps for 10 ms after doing its work runs slower than every
+//! 10 ms, by however long the work took, and by more under load. Anything
+//! which assumes a fixed loop time, like an acceleration limit per loop,
+//! drifts with it. A [`Ticker`] sleeps until the next deadline instead, so
+//! the loop runs at a fixed rate no matter how long the work takes:
+//!
+//! ```ignore
+//! let mut ticker = Ticker::new(Duration::from_millis(10));
+//! loop {
+//!     // ...
+//!     ticker.tick().await;
+//! }
+//! ```
+//!
+//! If the work takes longer than a period, the deadlines which were missed
+//! are skipped rather than run back to back, and the overrun is counted.
+
+use core::time::Duration;
+use std::time::Instant;
+
+/// The period of most subsystem loops.
+pub const LOOP_PERIOD: Duration = Duration::from_millis(10);
+
+/// Wakes a loop at a fixed rate. See the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct Ticker {
+    period: Duration,
+    deadline: Instant,
+    last_tick: Instant,
+    overruns: u32,
+    missed: u32,
+}
+
+impl Ticker {
+    /// Creates a ticker whose first deadline is one period from now.
+    pub fn new(period: Duration) -> Self {
+        let now = Instant::now();
+        Self {
+            period,
+            deadline: now + period,
+            last_tick: now,
+            overruns: 0,
+            missed: 0,
+        }
+    }
+
+    /// Waits until the next deadline and returns the time since the last
+    /// tick, i.e., the actual loop time.
+    ///
+    /// If the deadline has already passed, this returns immediately, and the
+    /// next deadline is one period from now.
+    pub async fn tick(&mut self) -> Duration {
+        let now = Instant::now();
+        if now < self.deadline {
+            vexide::time::sleep_until(self.deadline).await;
+            self.deadline += self.period;
+        } else {
+            let late = now - self.deadline;
+            self.overruns += 1;
+            self.missed += (late.as_nanos() / self.period.as_nanos().max(1)) as u32;
+            self.deadline = now + self.period;
+        }
+        let now = Instant::now();
+        let elapsed = now - self.last_tick;
+        self.last_tick = now;
+        elapsed
+    }
+
+    /// Returns the period of the ticker.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Changes the period, starting from the next deadline.
+    pub fn set_period(&mut self, period: Duration) {
+        self.deadline = self.deadline - self.period + period;
+        self.period = period;
+    }
+
+    /// Returns how many times the loop was still running at its deadline.
+    pub fn overruns(&self) -> u32 {
+        self.overruns
+    }
+
+    /// Returns how many whole periods were skipped because of overruns.
+    pub fn missed(&self) -> u32 {
+        self.missed
+    }
+}
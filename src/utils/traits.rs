@@ -1,30 +1,98 @@
 use alloc::rc::Rc;
 use core::cell::RefCell;
-use vexide::{math::Angle, prelude::*};
+use vexide::{math::Angle, prelude::*, smart::PortError, time::LowResolutionTime};
 use vexide_motorgroup::{MotorGroup, SharedMotors};
 
+use crate::{
+    motorgroup::DoxaMotorGroup,
+    utils::{angle, unwrap_expect_report::UnwrapExpectReportExt},
+};
+
 /// Trait for objects that have a rotational position.
 pub trait HasRotation {
     /// Returns the position of the object.
     fn position(&self) -> Angle;
+
+    /// Returns when the position was measured, if the object knows. The
+    /// default implementation returns `None`.
+    fn timestamp(&self) -> Option<LowResolutionTime> {
+        None
+    }
+
+    /// Returns the error from reading the position, if it can't be read. The
+    /// default implementation returns `None`.
+    fn error(&self) -> Option<PortError> {
+        None
+    }
+
+    /// Returns the position and the error from reading it, like
+    /// [`position`](Self::position) and [`error`](Self::error), for callers
+    /// which need both every update. The default implementation calls both;
+    /// sensors override it to read the position once.
+    fn read_position(&self) -> (Angle, Option<PortError>) {
+        (HasRotation::position(self), self.error())
+    }
 }
 
 impl HasRotation for RotationSensor {
     fn position(&self) -> Angle {
         self.position().unwrap_or_default()
     }
+
+    fn error(&self) -> Option<PortError> {
+        self.position().err()
+    }
+
+    fn read_position(&self) -> (Angle, Option<PortError>) {
+        match self.position() {
+            Ok(position) => (position, None),
+            Err(err) => (Angle::default(), Some(err)),
+        }
+    }
+
+    fn timestamp(&self) -> Option<LowResolutionTime> {
+        SmartDevice::timestamp(self).ok()
+    }
 }
 
 impl HasRotation for Motor {
     fn position(&self) -> Angle {
         self.position().unwrap_or_default()
     }
+
+    fn error(&self) -> Option<PortError> {
+        self.position().err()
+    }
+
+    fn read_position(&self) -> (Angle, Option<PortError>) {
+        match self.position() {
+            Ok(position) => (position, None),
+            Err(err) => (Angle::default(), Some(err)),
+        }
+    }
+
+    fn timestamp(&self) -> Option<LowResolutionTime> {
+        SmartDevice::timestamp(self).ok()
+    }
 }
 
 impl HasRotation for MotorGroup {
     fn position(&self) -> Angle {
         self.position().unwrap_or_default()
     }
+
+    fn error(&self) -> Option<PortError> {
+        self.position()
+            .err()
+            .and_then(|err| err.errors.first().copied())
+    }
+
+    fn read_position(&self) -> (Angle, Option<PortError>) {
+        match self.position() {
+            Ok(position) => (position, None),
+            Err(err) => (Angle::default(), err.errors.first().copied()),
+        }
+    }
 }
 
 impl HasRotation for () {
@@ -37,18 +105,70 @@ impl HasRotation for SharedMotors {
     fn position(&self) -> Angle {
         self.position().unwrap_or_default()
     }
+
+    fn error(&self) -> Option<PortError> {
+        self.position()
+            .err()
+            .and_then(|err| err.errors.first().copied())
+    }
+
+    fn read_position(&self) -> (Angle, Option<PortError>) {
+        match self.position() {
+            Ok(position) => (position, None),
+            Err(err) => (Angle::default(), err.errors.first().copied()),
+        }
+    }
+}
+
+impl HasRotation for DoxaMotorGroup {
+    fn position(&self) -> Angle {
+        // Use the motors which could be read rather than jumping to zero
+        self.position()
+            .unwrap_or_else(|err| err.result.unwrap_or_default())
+    }
+
+    fn error(&self) -> Option<PortError> {
+        self.position()
+            .err()
+            .and_then(|err| err.errors.first().copied())
+    }
+
+    fn read_position(&self) -> (Angle, Option<PortError>) {
+        match self.position() {
+            Ok(position) => (position, None),
+            Err(err) => (err.result.unwrap_or_default(), err.errors.first().copied()),
+        }
+    }
 }
 
 impl<T: HasRotation> HasRotation for Rc<RefCell<T>> {
     fn position(&self) -> Angle {
         self.borrow().position()
     }
+
+    fn timestamp(&self) -> Option<LowResolutionTime> {
+        self.borrow().timestamp()
+    }
+
+    fn error(&self) -> Option<PortError> {
+        self.borrow().error()
+    }
+
+    fn read_position(&self) -> (Angle, Option<PortError>) {
+        self.borrow().read_position()
+    }
 }
 
 /// Trait for objects that have a heading.
 pub trait HasHeading {
     /// Returns the heading of the object. This value does not wrap around.
     fn heading(&self) -> Angle;
+
+    /// Returns when the heading was measured, if the object knows. The
+    /// default implementation returns `None`.
+    fn timestamp(&self) -> Option<LowResolutionTime> {
+        None
+    }
 }
 
 /// Trait for objects that have a wrapping heading.
@@ -78,14 +198,7 @@ impl<T: HasWrappingHeading> HasHeading for WrappingHeadingCorrector<T> {
         let last_heading = *self.last_heading.borrow();
         let heading_offset = *self.heading_offset.borrow();
 
-        let delta = current_heading - last_heading;
-        let delta = if delta > Angle::from_radians(core::f64::consts::PI) {
-            delta - Angle::from_radians(2.0 * core::f64::consts::PI)
-        } else if delta < -Angle::from_radians(core::f64::consts::PI) {
-            delta + Angle::from_radians(2.0 * core::f64::consts::PI)
-        } else {
-            delta
-        };
+        let delta = angle::shortest_error(current_heading, last_heading);
 
         *self.last_heading.borrow_mut() = current_heading;
         *self.heading_offset.borrow_mut() += delta;
@@ -96,7 +209,13 @@ impl<T: HasWrappingHeading> HasHeading for WrappingHeadingCorrector<T> {
 
 impl HasHeading for InertialSensor {
     fn heading(&self) -> Angle {
-        self.rotation().unwrap_or_default()
+        self.rotation()
+            .expect_report("failed to read inertial sensor")
+            .unwrap_or_default()
+    }
+
+    fn timestamp(&self) -> Option<LowResolutionTime> {
+        SmartDevice::timestamp(self).ok()
     }
 }
 
@@ -110,4 +229,51 @@ impl<T: HasHeading> HasHeading for Rc<RefCell<T>> {
     fn heading(&self) -> Angle {
         self.try_borrow().map_or(Angle::default(), |f| f.heading())
     }
+
+    fn timestamp(&self) -> Option<LowResolutionTime> {
+        self.try_borrow().ok().and_then(|f| f.timestamp())
+    }
+}
+
+/// Trait for objects that measure pitch, i.e., the tilt of the robot forwards
+/// or backwards.
+pub trait HasPitch {
+    /// Returns the pitch of the object.
+    fn pitch(&self) -> Angle;
+}
+
+impl HasPitch for InertialSensor {
+    fn pitch(&self) -> Angle {
+        self.euler().map(|angles| angles.a).unwrap_or_default()
+    }
+}
+
+impl<T: HasPitch> HasPitch for Rc<RefCell<T>> {
+    fn pitch(&self) -> Angle {
+        self.try_borrow().map_or(Angle::default(), |f| f.pitch())
+    }
+}
+
+/// Trait for objects that measure roll, i.e., the tilt of the robot to the
+/// left or right.
+pub trait HasRoll {
+    /// Returns the roll of the object.
+    fn roll(&self) -> Angle;
+}
+
+impl HasRoll for InertialSensor {
+    fn roll(&self) -> Angle {
+        self.euler().map(|angles| angles.c).unwrap_or_default()
+    }
+}
+
+impl<T: HasRoll> HasRoll for Rc<RefCell<T>> {
+    fn roll(&self) -> Angle {
+        self.try_borrow().map_or(Angle::default(), |f| f.roll())
+    }
 }
+
+/// Trait for objects that measure both pitch and roll, like an IMU.
+pub trait HasTilt: HasPitch + HasRoll {}
+
+impl<T: HasPitch + HasRoll> HasTilt for T {}
@@ -0,0 +1,145 @@
+//! Live gain tuning over USB serial
+//!
+//! Reflashing to try a new gain takes a minute; typing it takes a second.
+//! [`init`] starts a task which reads commands, one per line, from the USB
+//! serial connection (e.g., `cargo v5 terminal`), and applies them to the
+//! action config in a [`ConfigStore`]:
+//!
+//! ```text
+//! set linear_kp 0.08     set a value; durations are in ms
+//! get linear_kp          print a value
+//! get config             print every value
+//! save                   write the [action] section to the config file
+//! help                   list the commands
+//! ```
+//!
+//! Keys are the names of the [`ActionConfig`] fields, as in the
+//! [config file](super::config). Every change is
+//! [validated] before it is applied, and applies to
+//! actions created with [`ConfigStore::action`] from when they next start.
+//!
+//! [`ActionConfig`]: crate::subsystems::drivetrain::actions::config::ActionConfig
+//! [validated]: crate::subsystems::drivetrain::actions::config::ActionConfig::validate
+//!
+//! ```ignore
+//! tuner::init(store.clone(), "config.toml");
+//! ```
+
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+use std::io::Read;
+
+use vexide::prelude::spawn;
+
+use crate::utils::{
+    config::{ACTION_KEYS, ConfigError, ConfigFile, ConfigValue},
+    config_store::ConfigStore,
+    ticker::{LOOP_PERIOD, Ticker},
+};
+
+/// The longest line accepted; anything longer is discarded.
+const MAX_LINE: usize = 128;
+
+const HELP: &str = "commands: set <key> <value>, get <key>, get config, save, help";
+
+/// Starts reading tuning commands from USB serial into `store`. `save`
+/// writes to the config file at `path`.
+///
+/// This must be called from within the vexide runtime.
+pub fn init(store: ConfigStore, path: &str) {
+    let path = path.to_string();
+    spawn(async move {
+        let mut stdin = std::io::stdin();
+        let mut buffer = [0; 64];
+        let mut line = String::new();
+        let mut ticker = Ticker::new(LOOP_PERIOD);
+        loop {
+            // Reading doesn't block on the Brain; it returns whatever has
+            // arrived since the last read
+            let read = stdin.read(&mut buffer).unwrap_or(0);
+            for &byte in &buffer[..read] {
+                match byte {
+                    b'\n' | b'\r' => {
+                        if !line.trim().is_empty() {
+                            println!("{}", execute(&store, &path, line.trim()));
+                        }
+                        line.clear();
+                    }
+                    _ if line.len() >= MAX_LINE => {}
+                    _ => line.push(byte as char),
+                }
+            }
+            ticker.tick().await;
+        }
+    })
+    .detach();
+}
+
+/// Runs one command and returns the response.
+pub fn execute(store: &ConfigStore, path: &str, command: &str) -> String {
+    let mut words = command.split_whitespace();
+    match (words.next(), words.next(), words.next(), words.next()) {
+        (Some("set"), Some(key), Some(value), None) => set(store, key, value),
+        (Some("get"), Some("config"), None, None) => current(store).to_toml(),
+        (Some("get"), Some(key), None, None) => match current(store).get(&action_key(key)) {
+            Some(value) => format!("{} = {}", key, value),
+            None => unknown_key(key),
+        },
+        (Some("save"), None, None, None) => save(store, path),
+        (Some("help"), None, None, None) => HELP.to_string(),
+        _ => format!("error: unknown command {:?}; {}", command, HELP),
+    }
+}
+
+fn action_key(key: &str) -> String {
+    format!("action.{}", key)
+}
+
+fn unknown_key(key: &str) -> String {
+    format!("error: unknown key {:?}", key)
+}
+
+/// Returns a config file with the `[action]` section set from `store`.
+fn current(store: &ConfigStore) -> ConfigFile {
+    let mut file = ConfigFile::default();
+    file.set_action_config(&store.action_config());
+    file
+}
+
+fn set(store: &ConfigStore, key: &str, value: &str) -> String {
+    if !ACTION_KEYS.contains(&key) {
+        return unknown_key(key);
+    }
+    let Ok(number) = value.parse() else {
+        return format!("error: expected a number, got {:?}", value);
+    };
+    let mut file = ConfigFile::default();
+    file.set(&action_key(key), ConfigValue::Number(number));
+    let updated = file
+        .action_config(store.action_config())
+        .map(|updated| store.set_action_config(updated));
+    match updated {
+        Ok(Ok(())) => {
+            log::info!("Tuner: set {} = {}", key, number);
+            format!("{} = {}", key, number)
+        }
+        Ok(Err(err)) => format!("error: {}", err),
+        Err(err) => format!("error: {}", err),
+    }
+}
+
+fn save(store: &ConfigStore, path: &str) -> String {
+    // Keep the other sections of the file, unless there is no file yet
+    let mut file = match ConfigFile::load(path) {
+        Ok(file) => file,
+        Err(ConfigError::Io { .. }) => ConfigFile::default(),
+        Err(err) => return format!("error: not overwriting {}: {}", path, err),
+    };
+    file.set_action_config(&store.action_config());
+    match file.save(path) {
+        Ok(()) => format!("saved to {}", path),
+        Err(err) => format!("error: {}", err),
+    }
+}
@@ -0,0 +1,192 @@
+//! Typed distances and velocities
+//!
+//! The library works in millimeters, radians per second, and RPM, but field
+//! measurements and tuning notes are often in inches or degrees. The
+//! newtypes here make the unit explicit where a value is passed in, and
+//! convert into the library's units through [`From`]:
+//!
+//! ```ignore
+//! use libdoxa::utils::units::UnitExt as _;
+//!
+//! let wheel = TrackingWheel::new_parallel(2.75.inches() * PI, 0.0, sensor);
+//! let config = config.with_linear_error_tolerance(0.5.inches());
+//! ```
+//!
+//! Plain `f64`s convert into the library's own units, so they can still be
+//! passed where a [`Millimeters`], [`MillimetersPerSecond`],
+//! [`RadiansPerSecond`], or [`Rpm`] is expected.
+
+use core::{
+    f64::consts::TAU,
+    ops::{Mul, Neg},
+};
+
+use nalgebra::Point2;
+
+const MM_PER_INCH: f64 = 25.4;
+
+macro_rules! unit {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+        pub struct $name(pub f64);
+
+        impl Mul<f64> for $name {
+            type Output = Self;
+
+            fn mul(self, rhs: f64) -> Self {
+                Self(self.0 * rhs)
+            }
+        }
+
+        impl Neg for $name {
+            type Output = Self;
+
+            fn neg(self) -> Self {
+                Self(-self.0)
+            }
+        }
+    };
+}
+
+/// Converts between two units, where `1 $from = $factor $to`.
+macro_rules! convert {
+    ($from:ident => $to:ident, $factor:expr) => {
+        impl From<$from> for $to {
+            fn from(value: $from) -> Self {
+                Self(value.0 * $factor)
+            }
+        }
+
+        impl From<$to> for $from {
+            fn from(value: $to) -> Self {
+                Self(value.0 / $factor)
+            }
+        }
+    };
+}
+
+/// Plain numbers are in the library's units.
+macro_rules! native {
+    ($($name:ident),*) => {
+        $(
+            impl From<f64> for $name {
+                fn from(value: f64) -> Self {
+                    Self(value)
+                }
+            }
+        )*
+    };
+}
+
+unit!(
+    /// A distance in millimeters, the library's unit of distance.
+    Millimeters
+);
+unit!(
+    /// A distance in inches.
+    Inches
+);
+unit!(
+    /// A linear velocity in millimeters per second.
+    MillimetersPerSecond
+);
+unit!(
+    /// A linear velocity in inches per second.
+    InchesPerSecond
+);
+unit!(
+    /// An angular velocity in radians per second.
+    RadiansPerSecond
+);
+unit!(
+    /// An angular velocity in degrees per second.
+    DegreesPerSecond
+);
+unit!(
+    /// An angular velocity in revolutions per minute, e.g., of a motor.
+    Rpm
+);
+
+convert!(Inches => Millimeters, MM_PER_INCH);
+convert!(InchesPerSecond => MillimetersPerSecond, MM_PER_INCH);
+convert!(DegreesPerSecond => RadiansPerSecond, TAU / 360.0);
+convert!(Rpm => RadiansPerSecond, TAU / 60.0);
+convert!(Rpm => DegreesPerSecond, 6.0);
+native!(Millimeters, MillimetersPerSecond, RadiansPerSecond, Rpm);
+
+/// Returns a point in millimeters from coordinates in any distance unit.
+pub fn point(x: impl Into<Millimeters>, y: impl Into<Millimeters>) -> Point2<f64> {
+    Point2::new(x.into().0, y.into().0)
+}
+
+/// Constructors for unit literals, e.g., `24.inches()`.
+pub trait UnitExt {
+    fn mm(self) -> Millimeters;
+    fn inches(self) -> Inches;
+    fn mm_per_sec(self) -> MillimetersPerSecond;
+    fn inches_per_sec(self) -> InchesPerSecond;
+    fn rad_per_sec(self) -> RadiansPerSecond;
+    fn deg_per_sec(self) -> DegreesPerSecond;
+    fn rpm(self) -> Rpm;
+}
+
+impl UnitExt for f64 {
+    fn mm(self) -> Millimeters {
+        Millimeters(self)
+    }
+
+    fn inches(self) -> Inches {
+        Inches(self)
+    }
+
+    fn mm_per_sec(self) -> MillimetersPerSecond {
+        MillimetersPerSecond(self)
+    }
+
+    fn inches_per_sec(self) -> InchesPerSecond {
+        InchesPerSecond(self)
+    }
+
+    fn rad_per_sec(self) -> RadiansPerSecond {
+        RadiansPerSecond(self)
+    }
+
+    fn deg_per_sec(self) -> DegreesPerSecond {
+        DegreesPerSecond(self)
+    }
+
+    fn rpm(self) -> Rpm {
+        Rpm(self)
+    }
+}
+
+impl UnitExt for i32 {
+    fn mm(self) -> Millimeters {
+        (self as f64).mm()
+    }
+
+    fn inches(self) -> Inches {
+        (self as f64).inches()
+    }
+
+    fn mm_per_sec(self) -> MillimetersPerSecond {
+        (self as f64).mm_per_sec()
+    }
+
+    fn inches_per_sec(self) -> InchesPerSecond {
+        (self as f64).inches_per_sec()
+    }
+
+    fn rad_per_sec(self) -> RadiansPerSecond {
+        (self as f64).rad_per_sec()
+    }
+
+    fn deg_per_sec(self) -> DegreesPerSecond {
+        (self as f64).deg_per_sec()
+    }
+
+    fn rpm(self) -> Rpm {
+        (self as f64).rpm()
+    }
+}
@@ -1,10 +1,79 @@
-use vexide::smart::PortError;
+use alloc::{
+    collections::{BTreeMap, BTreeSet, btree_map::Entry},
+    format,
+    string::String,
+    vec::Vec,
+};
+
+use vexide::{
+    adi::gyroscope::YawError,
+    smart::{
+        PortError,
+        ai_vision::AiVisionObjectError,
+        distance::DistanceObjectError,
+        imu::{CalibrateError, InertialError},
+        vision::{VisionObjectError, VisionSignatureError},
+    },
+};
 use vexide_motorgroup::MotorGroupError;
 
-/// A global store to hold ports which have had a disconnect error reported.
-/// This is used to avoid spamming the logs with repeated disconnect errors.
-static DEVICE_DISCONNECTED_PORTS: std::sync::Mutex<Option<std::collections::HashSet<u8>>> =
-    std::sync::Mutex::new(None);
+use crate::motorgroup::DoxaMotorGroupError;
+
+/// A global store to hold ports which have had a disconnect error reported,
+/// with the message they were reported with.
+/// This is used to avoid spamming the logs with repeated disconnect errors,
+/// and to report the port as reconnected once the same call succeeds again.
+static DEVICE_DISCONNECTED_PORTS: std::sync::Mutex<BTreeMap<u8, String>> =
+    std::sync::Mutex::new(BTreeMap::new());
+
+/// Transient errors which have been reported, with their messages, so that
+/// each is only logged once.
+static REPORTED_ERRORS: std::sync::Mutex<BTreeSet<String>> = std::sync::Mutex::new(BTreeSet::new());
+
+/// Returns the ports which are currently reported as disconnected, in order.
+pub fn disconnected_ports() -> Vec<u8> {
+    DEVICE_DISCONNECTED_PORTS
+        .lock()
+        .expect("could not lock mutex. this should never happen.")
+        .keys()
+        .copied()
+        .collect()
+}
+
+/// Reports `port` as disconnected, logging only the first time.
+fn report_disconnected(port: u8, msg: &dyn std::fmt::Display) {
+    let mut ports = DEVICE_DISCONNECTED_PORTS
+        .lock()
+        .expect("could not lock mutex. this should never happen.");
+    if let Entry::Vacant(entry) = ports.entry(port) {
+        entry.insert(msg.to_string());
+        log::error!(
+            "{}: device disconnected on port {} (report-only)",
+            msg,
+            port
+        );
+    }
+}
+
+/// Reports every port which was disconnected with `msg` as reconnected,
+/// except for those in `failing`, since the call they failed in succeeded.
+fn report_connected(msg: &dyn std::fmt::Display, failing: &[u8]) {
+    let mut ports = DEVICE_DISCONNECTED_PORTS
+        .lock()
+        .expect("could not lock mutex. this should never happen.");
+    // Avoid formatting the message on every successful call
+    if ports.is_empty() {
+        return;
+    }
+    let msg = msg.to_string();
+    ports.retain(|port, reported| {
+        let reconnected = *reported == msg && !failing.contains(port);
+        if reconnected {
+            log::info!("{}: device reconnected on port {}", msg, port);
+        }
+        !reconnected
+    });
+}
 
 pub trait UnwrapExpectReportExt<T> {
     /// Reports a disconnect if there is an error, otherwise returns the value.
@@ -29,44 +98,140 @@ pub trait UnwrapExpectReportExt<T> {
     fn expect_report<M: std::fmt::Display>(self, msg: M) -> Option<T>;
 }
 
-impl<T> UnwrapExpectReportExt<T> for Result<T, PortError> {
+/// An error from a device which can be reported with
+/// [`UnwrapExpectReportExt`].
+///
+/// Disconnects and transient errors, like a sensor which is still
+/// calibrating, are reported. Errors which mean the robot is configured
+/// wrong, like the wrong device on a port, panic.
+pub trait DeviceError: core::fmt::Display {
+    /// Returns the underlying port error, if this is one.
+    fn port_error(&self) -> Option<PortError>;
+
+    /// Returns whether this error means the robot is configured wrong.
+    fn is_configuration_error(&self) -> bool {
+        false
+    }
+}
+
+impl DeviceError for PortError {
+    fn port_error(&self) -> Option<PortError> {
+        Some(*self)
+    }
+}
+
+impl DeviceError for InertialError {
+    fn port_error(&self) -> Option<PortError> {
+        match self {
+            Self::Port { source } => Some(*source),
+            Self::Calibrating => None,
+        }
+    }
+}
+
+impl DeviceError for CalibrateError {
+    fn port_error(&self) -> Option<PortError> {
+        match self {
+            Self::Port { source } => Some(*source),
+            Self::Timeout => None,
+        }
+    }
+}
+
+impl DeviceError for DistanceObjectError {
+    fn port_error(&self) -> Option<PortError> {
+        match self {
+            Self::Port { source } => Some(*source),
+            Self::StillInitializing | Self::BadStatusCode { .. } => None,
+        }
+    }
+}
+
+impl DeviceError for YawError {
+    fn port_error(&self) -> Option<PortError> {
+        match self {
+            Self::Port { source } => Some(*source),
+            Self::StillCalibrating => None,
+        }
+    }
+}
+
+impl DeviceError for VisionObjectError {
+    fn port_error(&self) -> Option<PortError> {
+        match self {
+            Self::Port { source } => Some(*source),
+            Self::WifiMode | Self::InvalidObject => None,
+        }
+    }
+
+    /// The sensor can't be read over its port while in Wi-Fi mode.
+    fn is_configuration_error(&self) -> bool {
+        matches!(self, Self::WifiMode)
+    }
+}
+
+impl DeviceError for VisionSignatureError {
+    fn port_error(&self) -> Option<PortError> {
+        match self {
+            Self::Port { source } => Some(*source),
+            Self::ReadingFailed => None,
+        }
+    }
+}
+
+impl DeviceError for AiVisionObjectError {
+    fn port_error(&self) -> Option<PortError> {
+        match self {
+            Self::Port { source } => Some(*source),
+            Self::InvalidObject | Self::InvalidClassName { .. } => None,
+        }
+    }
+}
+
+/// Logs a transient error, only the first time it happens with `msg`.
+fn report_once(msg: &dyn std::fmt::Display, err: &dyn std::fmt::Display) {
+    let report = format!("{}: {}", msg, err);
+    let mut reported = REPORTED_ERRORS
+        .lock()
+        .expect("could not lock mutex. this should never happen.");
+    if !reported.contains(&report) {
+        log::error!("{} (report-only)", report);
+        reported.insert(report);
+    }
+}
+
+impl<T, E: DeviceError> UnwrapExpectReportExt<T> for Result<T, E> {
     fn unwrap_report(self) -> Option<T> {
-        self.expect_report("called `unwrap_report` on a `Err(PortError)` value")
+        self.expect_report("called `unwrap_report` on an `Err` value")
     }
 
     fn expect_report<M: std::fmt::Display>(self, msg: M) -> Option<T> {
         match self {
-            Err(err) => match err {
-                PortError::Disconnected { port } => {
-                    let mut locked = DEVICE_DISCONNECTED_PORTS
-                        .lock()
-                        .expect("could not lock mutex. this should never happen.");
-                    if locked.is_none() {
-                        *locked = Some(std::collections::HashSet::new());
-                    }
-                    let ports = locked.as_mut().unwrap();
-                    if !ports.contains(&port) {
-                        ports.insert(port);
-                        log::error!(
-                            "{}: device disconnected on port {} (report-only)",
-                            msg,
-                            port
-                        );
-                    }
+            Err(err) => match err.port_error() {
+                Some(PortError::Disconnected { port }) => {
+                    report_disconnected(port, &msg);
                     None
                 }
-                PortError::IncorrectDevice {
+                Some(PortError::IncorrectDevice {
                     port,
                     expected,
                     actual,
-                } => {
+                }) => {
                     panic!(
                         "{}: mismatched device type on port {}: expected {:?}, found {:?}",
                         msg, port, expected, actual
                     )
                 }
+                None if err.is_configuration_error() => panic!("{}: {}", msg, err),
+                None => {
+                    report_once(&msg, &err);
+                    None
+                }
             },
-            Ok(value) => Some(value),
+            Ok(value) => {
+                report_connected(&msg, &[]);
+                Some(value)
+            }
         }
     }
 }
@@ -95,12 +260,54 @@ impl<T> UnwrapExpectReportExt<T> for Result<T, MotorGroupError<PortError, T>> {
     fn expect_report<M: std::fmt::Display>(self, msg: M) -> Option<T> {
         match self {
             Err(err) => {
+                let failing: Vec<u8> = err
+                    .errors
+                    .iter()
+                    .filter_map(|error| match error {
+                        PortError::Disconnected { port } => Some(*port),
+                        PortError::IncorrectDevice { .. } => None,
+                    })
+                    .collect();
                 for error in err.errors {
                     Result::<T, PortError>::Err(error).expect_report(&msg);
                 }
+                report_connected(&msg, &failing);
                 err.result
             }
-            Ok(value) => Some(value),
+            Ok(value) => {
+                report_connected(&msg, &[]);
+                Some(value)
+            }
+        }
+    }
+}
+
+impl<T> UnwrapExpectReportExt<T> for Result<T, DoxaMotorGroupError<T>> {
+    fn unwrap_report(self) -> Option<T> {
+        self.expect_report("called `unwrap_report` on a `DoxaMotorGroupError` value")
+    }
+
+    fn expect_report<M: std::fmt::Display>(self, msg: M) -> Option<T> {
+        match self {
+            Err(err) => {
+                let failing: Vec<u8> = err
+                    .errors
+                    .iter()
+                    .filter_map(|error| match error {
+                        PortError::Disconnected { port } => Some(*port),
+                        PortError::IncorrectDevice { .. } => None,
+                    })
+                    .collect();
+                for error in err.errors {
+                    Result::<T, PortError>::Err(error).expect_report(&msg);
+                }
+                report_connected(&msg, &failing);
+                err.result
+            }
+            Ok(value) => {
+                report_connected(&msg, &[]);
+                Some(value)
+            }
         }
     }
 }